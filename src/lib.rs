@@ -33,9 +33,29 @@ pub mod server {
     #[derive(Debug, Clone, PartialEq)]
     pub struct Tool {
         pub name: &'static str,
+        pub title: &'static str,
         pub description: &'static str,
+        pub annotations: ToolAnnotations,
     }
 
+    /// Behaviour hints advertised as MCP tool `annotations`.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ToolAnnotations {
+        pub read_only: bool,
+        pub destructive: bool,
+        pub idempotent: bool,
+        pub open_world: bool,
+    }
+
+    /// Every tool only reads the configured cluster (CQL, REST API and metrics endpoints),
+    /// so repeating a call has no side effects and nothing outside the cluster is touched.
+    pub const READ_ONLY: ToolAnnotations = ToolAnnotations {
+        read_only: true,
+        destructive: false,
+        idempotent: true,
+        open_world: false,
+    };
+
     pub fn server_info() -> ServerInfo {
         ServerInfo {
             name: "scylla-rust-mcp",
@@ -48,68 +68,189 @@ pub mod server {
         vec![
             Tool {
                 name: "list_keyspaces",
-                description: "List keyspaces in the cluster",
+                title: "List Keyspaces",
+                description: "List keyspaces in the cluster. Set accessible_only to hide keyspaces the configured role cannot SELECT from.\n\nExample: {\"accessible_only\": true}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "list_tables",
-                description: "List tables for a keyspace",
+                title: "List Tables",
+                description: "List tables for a keyspace.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "describe_table",
-                description: "Describe table schema and types",
+                title: "Describe Table",
+                description: "Describe table schema and types: partition keys, clustering keys and every column with its CQL type.\n\nExample: {\"keyspace\": \"app\", \"table\": \"users\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "sample_rows",
-                description: "Sample rows with LIMIT and filters",
+                title: "Sample Rows",
+                description: "Sample rows with LIMIT and optional equality filters on key columns.\n\nExample: {\"keyspace\": \"app\", \"table\": \"users\", \"limit\": 10}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "partition_rows",
-                description: "Fetch rows by full partition key (prepared)",
+                title: "Partition Rows",
+                description: "Fetch rows by full partition key (prepared).\n\nExample: {\"keyspace\": \"app\", \"table\": \"events\", \"partition\": {\"user_id\": 42}, \"limit\": 50}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "select",
-                description: "Execute read-only SELECT queries",
+                title: "Select Rows",
+                description: "Execute read-only SELECT queries built from columns, filters and clustering-key order_by.\n\nExample: {\"keyspace\": \"app\", \"table\": \"events\", \"columns\": [\"ts\", \"kind\"], \"filters\": {\"user_id\": 42}, \"order_by\": [{\"column\": \"ts\", \"direction\": \"desc\"}], \"limit\": 20}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "paged_select",
-                description: "Paged SELECT with cursor support",
+                title: "Paged Select",
+                description: "Paged SELECT with cursor support. Pass next_cursor back as cursor to read the following page; it is null on the last page.\n\nExample: {\"keyspace\": \"app\", \"table\": \"users\", \"columns\": [\"id\", \"name\"], \"page_size\": 100}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "cluster_topology",
-                description: "Get cluster nodes, datacenters, racks",
+                title: "Cluster Topology",
+                description: "Get cluster nodes, datacenters, racks, versions, tokens, shards and ping latency. Set `include_tokens` to false to keep only `token_count`.\n\nExample: {\"include_tokens\": false}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "list_indexes",
-                description: "List secondary indexes for a table",
+                title: "List Indexes",
+                description: "List secondary indexes for a table.\n\nExample: {\"keyspace\": \"app\", \"table\": \"users\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "keyspace_replication",
-                description: "Show keyspace replication settings",
+                title: "Keyspace Replication",
+                description: "Show keyspace replication settings.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "list_views",
-                description: "List materialized views in a keyspace",
+                title: "List Materialized Views",
+                description: "List materialized views in a keyspace.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "list_udts",
-                description: "List user-defined types in a keyspace",
+                title: "List User-Defined Types",
+                description: "List user-defined types in a keyspace.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "list_functions",
-                description: "List user-defined functions in a keyspace",
+                title: "List Functions",
+                description: "List user-defined functions in a keyspace.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "list_aggregates",
-                description: "List user-defined aggregates in a keyspace",
+                title: "List Aggregates",
+                description: "List user-defined aggregates in a keyspace.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "size_estimates",
-                description: "Approximate size estimates for a table",
+                title: "Size Estimates",
+                description: "Approximate size estimates for a table: the token ranges from every node's system.size_estimates, or from one node with `node`. Set `summary` for deduplicated totals.\n\nExample: {\"keyspace\": \"app\", \"table\": \"events\", \"summary\": true}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "table_stats",
+                title: "Table Statistics",
+                description: "Estimated partitions, partition size and bytes per DC for a table.\n\nExample: {\"keyspace\": \"app\", \"table\": \"events\"}",
+                annotations: READ_ONLY,
             },
             Tool {
                 name: "search_schema",
-                description:
-                    "Search tables, columns, UDTs, views, functions, aggregates by pattern",
+                title: "Search Schema",
+                description: "Search tables, columns, UDTs, views, functions, aggregates by pattern.\n\nExample: {\"pattern\": \"user*\", \"mode\": \"glob\", \"kinds\": [\"table\", \"column\"]}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "lint_schema",
+                title: "Lint Schema",
+                description: "Flag data-model anti-patterns with severity and rationale.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "whoami",
+                title: "Who Am I",
+                description: "Show the configured role, its inherited roles and SELECT access.\n\nExample: {}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "list_roles",
+                title: "List Roles",
+                description: "List roles with login, superuser and membership flags.\n\nExample: {}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "list_permissions",
+                title: "List Permissions",
+                description: "List direct and inherited permissions of a role.\n\nExample: {\"role\": \"analytics\"}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "large_data",
+                title: "Large Partitions, Rows and Cells",
+                description: "Largest partitions, rows and cells recorded on every node, ranked per table.\n\nExample: {\"keyspace\": \"app\", \"kinds\": [\"partition\"], \"limit\": 20}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "replication_report",
+                title: "Replication Report",
+                description: "Check keyspace replication against datacenters and live nodes, with satisfiable consistency levels.\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "locate_partition",
+                title: "Locate Partition",
+                description: "Token and replica nodes per datacenter for a full partition key.\n\nExample: {\"keyspace\": \"app\", \"table\": \"events\", \"partition\": {\"user_id\": 42}}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "token_ring",
+                title: "Token Ring",
+                description: "Vnode token and tablet ownership per node with imbalance warnings.\n\nExample: {\"keyspace\": \"app\", \"include_tablets\": true}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "node_config",
+                title: "Node Configuration",
+                description: "Node configuration, versions, runtime info and connected clients.\n\nExample: {\"name\": \"compaction\", \"sections\": [\"config\"], \"node\": \"all\"}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "compaction_status",
+                title: "Compaction Status",
+                description: "Active compactions and pending compaction tasks per node (Scylla REST API).\n\nExample: {\"keyspace\": \"app\"}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "table_sstables",
+                title: "Table SSTables",
+                description: "SSTable counts, levels and disk usage of a table per node (Scylla REST API).\n\nExample: {\"keyspace\": \"app\", \"table\": \"events\"}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "node_status",
+                title: "Node Status",
+                description: "Operation mode, load, gossip/CQL state and active repairs per node (Scylla REST API).\n\nExample: {\"node\": \"all\"}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "node_metrics",
+                title: "Node Metrics",
+                description: "Prometheus metrics by name prefix/labels with rates and latency percentiles across two scrapes.\n\nExample: {\"prefix\": \"scylla_storage_proxy_coordinator_read_latency\", \"labels\": {\"scheduling_group_name\": \"statement\"}, \"interval_ms\": 2000}",
+                annotations: READ_ONLY,
+            },
+            Tool {
+                name: "cdc_changes",
+                title: "CDC Changes",
+                description: "Read decoded CDC log changes for a time window, or list CDC-enabled tables.\n\nExample: {\"keyspace\": \"app\", \"table\": \"orders\", \"since\": \"2024-05-01T00:00:00Z\", \"page_size\": 100}",
+                annotations: READ_ONLY,
             },
         ]
     }
@@ -132,15 +273,59 @@ pub mod server {
             })
         };
 
+        let node_field = string_field(
+            "Node to read node-local system tables from (host id or address), or \"all\" to fan out to every node",
+        );
+
+        let rest_node_field = string_field(
+            "Node from SCYLLA_REST_URL (host or host:port), or \"all\" (default) for every configured node",
+        );
+
+        let accessible_only_field = json!({
+            "type": "boolean",
+            "description": "Only return objects the configured SCYLLA_USER can SELECT",
+        });
+
         match name {
+            "list_keyspaces" => {
+                props.insert("accessible_only".into(), accessible_only_field);
+            }
+            "cluster_topology" => {
+                props.insert(
+                    "include_tokens".into(),
+                    json!({
+                        "type": "boolean",
+                        "description": "Include each node's full `tokens` list next to `token_count` (default true)",
+                    }),
+                );
+            }
             "list_tables" => {
                 required.push("keyspace".into());
                 props.insert("keyspace".into(), string_field("Keyspace name"));
+                props.insert("accessible_only".into(), accessible_only_field);
+            }
+            "describe_table" | "list_indexes" | "table_stats" => {
+                required.extend(["keyspace".into(), "table".into()]);
+                props.insert("keyspace".into(), string_field("Keyspace name"));
+                props.insert("table".into(), string_field("Table name"));
             }
-            "describe_table" | "list_indexes" | "size_estimates" => {
+            "size_estimates" => {
                 required.extend(["keyspace".into(), "table".into()]);
                 props.insert("keyspace".into(), string_field("Keyspace name"));
                 props.insert("table".into(), string_field("Table name"));
+                props.insert(
+                    "node".into(),
+                    string_field(
+                        "Read one node's estimates (host id or address) instead of every node (default \"all\")",
+                    ),
+                );
+                props.insert(
+                    "summary".into(),
+                    json!({
+                        "type": "boolean",
+                        "description": "Return {summary, ranges} with cluster and per-node totals instead of the bare ranges array (default false)",
+                    }),
+                );
             }
             "sample_rows" => {
                 required.extend(["keyspace".into(), "table".into(), "limit".into()]);
@@ -210,7 +395,7 @@ pub mod server {
                     "pattern".into(),
                     json!({
                         "type": "string",
-                        "description": "Substring to search (case-insensitive)",
+                        "description": "Text to search for (case-insensitive); interpreted per 'mode'. In substring and fuzzy modes a multi-word question is split into terms, ranked by how many match",
                     }),
                 );
                 props.insert(
@@ -220,6 +405,212 @@ pub mod server {
                         "description": "Optional keyspace to scope search",
                     }),
                 );
+                props.insert(
+                    "mode".into(),
+                    json!({
+                        "type": "string",
+                        "enum": ["substring", "glob", "regex", "fuzzy"],
+                        "description": "Match mode (default substring). Results are ranked by relevance",
+                    }),
+                );
+                props.insert(
+                    "fields".into(),
+                    json!({
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": ["name", "type", "comment", "index_target", "body"],
+                        },
+                        "description": "Fields to search (default [\"name\"])",
+                    }),
+                );
+                props.insert(
+                    "kinds".into(),
+                    json!({
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": ["table", "column", "udt", "view", "index", "function", "aggregate"],
+                        },
+                        "description": "Only return these object kinds (default all)",
+                    }),
+                );
+            }
+            "lint_schema" => {
+                props.insert(
+                    "keyspace".into(),
+                    string_field("Optional keyspace to lint (default: all non-system keyspaces)"),
+                );
+            }
+            "list_permissions" => {
+                props.insert(
+                    "role".into(),
+                    string_field("Role to inspect (default: the configured SCYLLA_USER)"),
+                );
+            }
+            "large_data" => {
+                props.insert("keyspace".into(), string_field("Optional keyspace filter"));
+                props.insert(
+                    "table".into(),
+                    string_field("Optional table filter (requires keyspace)"),
+                );
+                props.insert(
+                    "kinds".into(),
+                    json!({
+                        "type": "array",
+                        "items": { "type": "string", "enum": ["partition", "row", "cell"] },
+                        "description": "Record kinds to include (default all)",
+                    }),
+                );
+                props.insert("limit".into(), integer_field(1, 500));
+                props.insert(
+                    "node".into(),
+                    string_field("Node to read from (host id or address); default every node"),
+                );
+            }
+            "replication_report" => {
+                props.insert(
+                    "keyspace".into(),
+                    string_field(
+                        "Optional keyspace (default: every keyspace, including system ones)",
+                    ),
+                );
+            }
+            "locate_partition" => {
+                required.extend(["keyspace".into(), "table".into(), "partition".into()]);
+                props.insert("keyspace".into(), string_field("Keyspace name"));
+                props.insert("table".into(), string_field("Table name"));
+                props.insert(
+                    "partition".into(),
+                    json!({
+                        "type": "object",
+                        "description": "Map of partition key column -> value",
+                    }),
+                );
+            }
+            "token_ring" => {
+                props.insert(
+                    "keyspace".into(),
+                    string_field("Optional keyspace to scope the tablet map"),
+                );
+                props.insert(
+                    "table".into(),
+                    string_field("Optional table to scope the tablet map (requires keyspace)"),
+                );
+                props.insert(
+                    "include_tablets".into(),
+                    json!({
+                        "type": "boolean",
+                        "description": "List every tablet with its last token and replicas",
+                    }),
+                );
+            }
+            "compaction_status" => {
+                props.insert(
+                    "keyspace".into(),
+                    string_field("Only show compactions of this keyspace"),
+                );
+                props.insert(
+                    "table".into(),
+                    string_field("Only show compactions of this table"),
+                );
+                props.insert("node".into(), rest_node_field.clone());
+            }
+            "table_sstables" => {
+                required.extend(["keyspace".into(), "table".into()]);
+                props.insert("keyspace".into(), string_field("Keyspace name"));
+                props.insert("table".into(), string_field("Table name"));
+                props.insert("node".into(), rest_node_field.clone());
+            }
+            "node_status" => {
+                props.insert("node".into(), rest_node_field.clone());
+            }
+            "node_metrics" => {
+                required.push("prefix".into());
+                props.insert(
+                    "prefix".into(),
+                    string_field(
+                        "Metric name prefix, e.g. scylla_storage_proxy_coordinator_read_latency",
+                    ),
+                );
+                props.insert(
+                    "labels".into(),
+                    json!({
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Only keep series whose labels equal these values",
+                    }),
+                );
+                props.insert(
+                    "interval_ms".into(),
+                    json!({
+                        "type": "integer",
+                        "minimum": 0,
+                        "maximum": 20000,
+                        "description": "Delay between the two scrapes used for rates (default 2000; 0 = single scrape)",
+                    }),
+                );
+                props.insert(
+                    "per_shard".into(),
+                    json!({
+                        "type": "boolean",
+                        "description": "Keep per-shard series instead of summing them (default false)",
+                    }),
+                );
+                props.insert("limit".into(), integer_field(1, 1000));
+                props.insert(
+                    "node".into(),
+                    string_field(
+                        "Node from SCYLLA_METRICS_URL (host or host:port), or \"all\" (default) for every configured node",
+                    ),
+                );
+            }
+            "node_config" => {
+                props.insert(
+                    "name".into(),
+                    string_field(
+                        "Case-insensitive substring filter on option (or runtime item) name",
+                    ),
+                );
+                props.insert(
+                    "sections".into(),
+                    json!({
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": ["config", "versions", "runtime_info", "clients"],
+                        },
+                        "description": "Sections to read (default all)",
+                    }),
+                );
+                props.insert("node".into(), node_field.clone());
+            }
+            "cdc_changes" => {
+                required.push("keyspace".into());
+                props.insert("keyspace".into(), string_field("Keyspace name"));
+                props.insert(
+                    "table".into(),
+                    string_field("Base table with CDC enabled (omit to list CDC-enabled tables)"),
+                );
+                let timestamp_field = |description: &str| {
+                    json!({
+                        "type": ["string", "integer"],
+                        "description": description,
+                    })
+                };
+                props.insert(
+                    "since".into(),
+                    timestamp_field("Window start: RFC 3339 or Unix ms (default: until - 1h)"),
+                );
+                props.insert(
+                    "until".into(),
+                    timestamp_field("Window end: RFC 3339 or Unix ms (default: now)"),
+                );
+                props.insert("page_size".into(), integer_field(1, 500));
+                props.insert(
+                    "cursor".into(),
+                    string_field("next_cursor from a previous page; continues its window"),
+                );
             }
             _ => {}
         }
@@ -230,3004 +621,14494 @@ pub mod server {
             "required": required,
         })
     }
-}
-
-pub mod logging {
-    use std::sync::OnceLock;
-    use tracing_subscriber::EnvFilter;
-
-    static LOGGING_INIT: OnceLock<()> = OnceLock::new();
 
-    pub fn init_tracing() {
-        LOGGING_INIT.get_or_init(|| {
-            let filter = EnvFilter::try_from_default_env().ok().or_else(|| {
-                std::env::var("MCP_SERVER_LOG")
-                    .ok()
-                    .and_then(|value| EnvFilter::try_new(value).ok())
-            });
+    /// JSON Schema of a tool's successful result, for tools that always return one JSON
+    /// object. Those results are also sent as `structuredContent`.
+    pub fn tool_output_schema(name: &str) -> Option<Value> {
+        let object = |required: &[&str], props: Value| {
+            json!({
+                "type": "object",
+                "properties": props,
+                "required": required,
+            })
+        };
+        let nodes = json!({ "type": "array", "items": { "type": "object" } });
+        let findings = json!({
+            "summary": {
+                "type": "object",
+                "properties": {
+                    "error": { "type": "integer" },
+                    "warning": { "type": "integer" },
+                    "info": { "type": "integer" },
+                },
+            },
+            "findings": { "type": "array", "items": { "type": "object" } },
+        });
 
-            if let Some(filter) = filter {
-                let _ = tracing_subscriber::fmt()
-                    .with_env_filter(filter)
-                    .with_writer(std::io::stderr)
-                    .try_init();
+        let schema = match name {
+            "describe_table" => object(
+                &[
+                    "keyspace",
+                    "table",
+                    "partition_keys",
+                    "clustering_keys",
+                    "columns",
+                ],
+                json!({
+                    "keyspace": { "type": "string" },
+                    "table": { "type": "string" },
+                    "partition_keys": { "type": "array", "items": { "type": "string" } },
+                    "clustering_keys": { "type": "array", "items": { "type": "string" } },
+                    "columns": { "type": "array", "items": { "type": "object" } },
+                }),
+            ),
+            "paged_select" => object(
+                &["items", "next_cursor"],
+                json!({
+                    "items": { "type": "array", "items": { "type": "object" } },
+                    "next_cursor": {
+                        "type": ["string", "null"],
+                        "description": "Pass as 'cursor' to read the next page; null on the last page",
+                    },
+                }),
+            ),
+            "keyspace_replication" => object(
+                &["replication"],
+                json!({
+                    "replication": { "type": "object" },
+                    "durable_writes": { "type": "boolean" },
+                }),
+            ),
+            "table_stats" => object(
+                &["keyspace", "table"],
+                json!({
+                    "keyspace": { "type": "string" },
+                    "table": { "type": "string" },
+                }),
+            ),
+            "lint_schema" => {
+                let mut props = findings;
+                props["notes"] = json!({
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Checks that could not read their source and why",
+                });
+                object(&["summary", "findings"], props)
             }
-        });
+            "replication_report" => {
+                let mut props = findings;
+                props["datacenters"] = json!({ "type": "object" });
+                props["keyspaces"] = json!({ "type": "array", "items": { "type": "object" } });
+                object(&["summary", "findings", "datacenters", "keyspaces"], props)
+            }
+            "whoami" => object(
+                &["user", "restricted", "superuser", "roles", "select_access"],
+                json!({
+                    "user": { "type": ["string", "null"] },
+                    "auth_keyspace": { "type": ["string", "null"] },
+                    "restricted": {
+                        "type": "boolean",
+                        "description": "false when no user is configured or role metadata is unreadable, so nothing is filtered",
+                    },
+                    "superuser": { "type": "boolean" },
+                    "roles": { "type": "array", "items": { "type": "string" } },
+                    "select_access": { "type": "object" },
+                    "notes": { "type": "array", "items": { "type": "string" } },
+                }),
+            ),
+            "large_data" => object(
+                &["nodes_queried", "tables"],
+                json!({
+                    "nodes_queried": { "type": "array" },
+                    "tables": { "type": "array", "items": { "type": "object" } },
+                    "errors": { "type": "array", "items": { "type": "object" } },
+                }),
+            ),
+            "locate_partition" => object(
+                &[
+                    "keyspace",
+                    "table",
+                    "partition",
+                    "token",
+                    "replica_count",
+                    "datacenters",
+                ],
+                json!({
+                    "keyspace": { "type": "string" },
+                    "table": { "type": "string" },
+                    "partition": { "type": "object" },
+                    "token": { "type": "integer" },
+                    "replica_count": { "type": "integer" },
+                    "datacenters": { "type": "object" },
+                }),
+            ),
+            "token_ring" => object(
+                &["vnodes", "tablets"],
+                json!({
+                    "vnodes": { "type": "object" },
+                    "tablets": {
+                        "type": ["array", "null"],
+                        "description": "null when the cluster has no system.tablets",
+                    },
+                    "notes": { "type": "array", "items": { "type": "string" } },
+                }),
+            ),
+            "node_config" => object(
+                &["nodes"],
+                json!({
+                    "nodes": nodes,
+                    "unavailable": { "type": "object" },
+                }),
+            ),
+            "compaction_status" => object(
+                &["active_compactions", "nodes"],
+                json!({
+                    "active_compactions": { "type": "integer" },
+                    "nodes": nodes,
+                }),
+            ),
+            "table_sstables" => object(
+                &[
+                    "keyspace",
+                    "table",
+                    "live_sstable_count",
+                    "live_disk_space_used",
+                    "nodes",
+                ],
+                json!({
+                    "keyspace": { "type": "string" },
+                    "table": { "type": "string" },
+                    "live_sstable_count": { "type": "integer" },
+                    "live_disk_space_used": { "type": "integer" },
+                    "live_disk_space_used_human": { "type": "string" },
+                    "nodes": nodes,
+                }),
+            ),
+            "node_status" => object(
+                &["nodes", "warnings"],
+                json!({
+                    "nodes": nodes,
+                    "warnings": { "type": "array", "items": { "type": "string" } },
+                }),
+            ),
+            "node_metrics" => object(
+                &["prefix", "interval_ms", "nodes"],
+                json!({
+                    "prefix": { "type": "string" },
+                    "interval_ms": { "type": "integer" },
+                    "nodes": nodes,
+                }),
+            ),
+            // The rest return arrays, or objects whose shape depends on the arguments.
+            _ => return None,
+        };
+        Some(schema)
     }
-}
-
-pub mod mcp {
-    use anyhow::{Context, Result};
-    use base64::Engine;
-    use rust_mcp_sdk::error::SdkResult;
-    use std::sync::Arc;
-    use tokio::sync::OnceCell;
-    use tracing::{error, info};
-
-    const MAX_SERVER_CURSORS: usize = 1024;
 
-    pub async fn run_stdio_server() -> SdkResult<()> {
-        crate::logging::init_tracing();
+    /// MCP `Tool` definition shared by every transport.
+    pub fn tool_definition(tool: &Tool) -> Value {
+        let mut definition = json!({
+            "name": tool.name,
+            "title": tool.title,
+            "description": tool.description,
+            "inputSchema": tool_input_schema(tool.name),
+            "annotations": {
+                "title": tool.title,
+                "readOnlyHint": tool.annotations.read_only,
+                "destructiveHint": tool.annotations.destructive,
+                "idempotentHint": tool.annotations.idempotent,
+                "openWorldHint": tool.annotations.open_world,
+            },
+        });
+        if let Some(schema) = tool_output_schema(tool.name) {
+            definition["outputSchema"] = schema;
+        }
+        definition
+    }
 
-        info!("starting MCP stdio server (rust-mcp-sdk)");
-        use rust_mcp_schema::{
-            Implementation, InitializeResult, ServerCapabilities, ServerCapabilitiesTools,
-            LATEST_PROTOCOL_VERSION,
-        };
-        use rust_mcp_sdk::mcp_server::server_runtime;
-        use rust_mcp_sdk::{MCPServer, StdioTransport, TransportOptions};
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct ResourceTemplate {
+        pub uri_template: &'static str,
+        pub name: &'static str,
+        pub description: &'static str,
+        pub mime_type: &'static str,
+    }
 
-        let server_details = InitializeResult {
-            server_info: Implementation {
-                name: "scylla-rust-mcp".to_string(),
-                version: env!("CARGO_PKG_VERSION").to_string(),
+    pub fn resource_templates() -> Vec<ResourceTemplate> {
+        vec![
+            ResourceTemplate {
+                uri_template: "scylla://{keyspace}/{table}/schema",
+                name: "table_schema",
+                description: "Columns, partition and clustering keys of a table as JSON",
+                mime_type: "application/json",
             },
-            capabilities: ServerCapabilities {
-                tools: Some(ServerCapabilitiesTools { list_changed: None }),
-                ..Default::default()
+            ResourceTemplate {
+                uri_template: "scylla://{keyspace}/ddl",
+                name: "keyspace_ddl",
+                description: "CQL statements that recreate a keyspace and its tables",
+                mime_type: "text/x-cql",
             },
-            meta: None,
-            instructions: Some("Read-only ScyllaDB access for AI agents".to_string()),
-            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
-        };
+        ]
+    }
 
-        let transport = StdioTransport::new(TransportOptions::default())?;
-        let handler = ToolExecutor::from_env();
-        handler.warmup_connection();
-        let server = server_runtime::create_server(server_details, transport, handler);
-        server.start().await
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct PromptArgument {
+        pub name: &'static str,
+        pub description: &'static str,
+        pub required: bool,
     }
 
-    // Basic handler – extend as we implement tools
-    use std::collections::HashMap as StdHashMap;
-    use tokio::sync::RwLock;
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Prompt {
+        pub name: &'static str,
+        pub description: &'static str,
+        pub arguments: Vec<PromptArgument>,
+    }
 
-    #[derive(Debug, Clone)]
-    pub struct ToolOutput {
-        pub text: String,
-        pub is_error: bool,
+    pub fn list_prompts() -> Vec<Prompt> {
+        let keyspace = PromptArgument {
+            name: "keyspace",
+            description: "Keyspace name",
+            required: true,
+        };
+        let table = PromptArgument {
+            name: "table",
+            description: "Table name",
+            required: true,
+        };
+        vec![
+            Prompt {
+                name: "explore_keyspace",
+                description: "Survey a keyspace: replication, tables and their keys",
+                arguments: vec![keyspace.clone()],
+            },
+            Prompt {
+                name: "explain_data_model",
+                description: "Explain a table's data model and the queries it supports",
+                arguments: vec![keyspace.clone(), table.clone()],
+            },
+            Prompt {
+                name: "find_entity_rows",
+                description: "Find the rows belonging to an entity id",
+                arguments: vec![
+                    keyspace.clone(),
+                    table.clone(),
+                    PromptArgument {
+                        name: "entity_id",
+                        description: "Value identifying the entity",
+                        required: true,
+                    },
+                    PromptArgument {
+                        name: "column",
+                        description:
+                            "Column holding the id (default: the first partition key column)",
+                        required: false,
+                    },
+                ],
+            },
+            Prompt {
+                name: "investigate_slow_partition",
+                description: "Diagnose a slow or oversized partition",
+                arguments: vec![
+                    keyspace,
+                    table,
+                    PromptArgument {
+                        name: "partition_key",
+                        description: "Partition key as a JSON object of column -> value, if known",
+                        required: false,
+                    },
+                ],
+            },
+        ]
     }
 
-    impl ToolOutput {
-        fn ok(text: String) -> Self {
-            Self {
-                text,
-                is_error: false,
+    fn is_uri_unreserved(byte: u8) -> bool {
+        byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b'~')
+    }
+
+    fn encode_uri_segment(name: &str) -> String {
+        name.bytes()
+            .map(|b| {
+                if is_uri_unreserved(b) {
+                    (b as char).to_string()
+                } else {
+                    format!("%{b:02X}")
+                }
+            })
+            .collect()
+    }
+
+    /// Decode a percent-encoded name segment; `None` for empty segments, stray
+    /// reserved characters, bad escapes or non-UTF-8 results.
+    fn decode_uri_segment(segment: &str) -> Option<String> {
+        let bytes = segment.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'%' => {
+                    let hex = bytes.get(i + 1..i + 3)?;
+                    if !hex.iter().all(u8::is_ascii_hexdigit) {
+                        return None;
+                    }
+                    out.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                    i += 3;
+                }
+                b if is_uri_unreserved(b) => {
+                    out.push(b);
+                    i += 1;
+                }
+                _ => return None,
             }
         }
+        let name = String::from_utf8(out).ok()?;
+        (!name.is_empty()).then_some(name)
+    }
 
-        fn error(text: String) -> Self {
-            Self {
-                text,
-                is_error: true,
+    /// A parsed `scylla://` resource URI.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ResourceUri {
+        TableSchema { keyspace: String, table: String },
+        KeyspaceDdl { keyspace: String },
+    }
+
+    impl ResourceUri {
+        /// Parse a resource URI; name segments may be percent-encoded (see [`ResourceUri::uri`]).
+        pub fn parse(uri: &str) -> Result<Self, String> {
+            let path = uri
+                .strip_prefix("scylla://")
+                .ok_or_else(|| format!("unsupported resource URI '{uri}': expected scylla://"))?;
+            let shape_error = || {
+                format!(
+                    "unsupported resource URI '{uri}': expected scylla://{{keyspace}}/ddl or scylla://{{keyspace}}/{{table}}/schema"
+                )
+            };
+            let name = |segment: &str| decode_uri_segment(segment).ok_or_else(shape_error);
+            match path.split('/').collect::<Vec<_>>().as_slice() {
+                [keyspace, "ddl"] => Ok(Self::KeyspaceDdl {
+                    keyspace: name(keyspace)?,
+                }),
+                [keyspace, table, "schema"] => Ok(Self::TableSchema {
+                    keyspace: name(keyspace)?,
+                    table: name(table)?,
+                }),
+                _ => Err(shape_error()),
             }
         }
 
-        fn text_content(text: String, _annotations: Option<serde_json::Value>) -> Self {
-            let trimmed = text.trim_start();
-            let looks_like_json = trimmed.starts_with('{') || trimmed.starts_with('[');
-            let is_error = !looks_like_json
-                && (text.starts_with("missing required")
-                    || text.starts_with("invalid ")
-                    || text.starts_with("partition keys mismatch")
-                    || text.starts_with("schema fetch failed")
-                    || text.contains(" failed")
-                    || text.contains("not yet implemented"));
-            if is_error {
-                Self::error(text)
-            } else {
-                Self::ok(text)
+        /// The URI, with any name byte outside `[A-Za-z0-9_.~-]` percent-encoded.
+        pub fn uri(&self) -> String {
+            match self {
+                Self::TableSchema { keyspace, table } => format!(
+                    "scylla://{}/{}/schema",
+                    encode_uri_segment(keyspace),
+                    encode_uri_segment(table)
+                ),
+                Self::KeyspaceDdl { keyspace } => {
+                    format!("scylla://{}/ddl", encode_uri_segment(keyspace))
+                }
+            }
+        }
+
+        pub fn name(&self) -> String {
+            match self {
+                Self::TableSchema { keyspace, table } => format!("{keyspace}.{table} schema"),
+                Self::KeyspaceDdl { keyspace } => format!("{keyspace} DDL"),
+            }
+        }
+
+        pub fn mime_type(&self) -> &'static str {
+            match self {
+                Self::TableSchema { .. } => "application/json",
+                Self::KeyspaceDdl { .. } => "text/x-cql",
             }
         }
     }
+}
 
-    #[derive(Debug, Clone)]
-    struct ExecuteRequest {
-        params: ExecuteRequestParams,
+pub mod logging {
+    use serde_json::{json, Map, Value};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use tracing::level_filters::LevelFilter;
+    use tracing::Level;
+    use tracing_subscriber::filter::Targets;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+
+    static LOGGING_INIT: OnceLock<()> = OnceLock::new();
+    static CLIENT_FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+    static CLIENT_SINKS: Mutex<Vec<ClientSink>> = Mutex::new(Vec::new());
+    static NEXT_SINK_ID: AtomicU64 = AtomicU64::new(0);
+    static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(0);
+
+    type SendLog = dyn Fn(Value) + Send + Sync;
+
+    struct ClientSink {
+        id: u64,
+        connection: u64,
+        level: LevelFilter,
+        send: Arc<SendLog>,
     }
 
-    #[derive(Debug, Clone)]
-    struct ExecuteRequestParams {
-        name: String,
-        arguments: Option<serde_json::Map<String, serde_json::Value>>,
+    /// A fresh id tying one client connection's work to its log sink.
+    pub fn next_connection_id() -> u64 {
+        NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed)
     }
 
-    pub struct ToolExecutor {
-        session_state: Arc<SessionState>,
-        schema_cache: Arc<RwLock<StdHashMap<(String, String), crate::db::DescribeTable>>>,
-        paging_cursors: Arc<RwLock<StdHashMap<String, StoredPagingCursor>>>,
-        tool_timeout: std::time::Duration,
+    /// Events inside this span go only to `connection`'s client. It is an
+    /// ERROR span so that every client filter short of `off` keeps it.
+    pub fn connection_span(connection: u64) -> tracing::Span {
+        tracing::error_span!("connection", mcp_connection = connection)
     }
 
-    #[derive(Clone, Debug, PartialEq, Eq)]
-    struct PagingCursorBinding {
-        keyspace: String,
-        table: String,
-        columns: Vec<String>,
-        page_size: i32,
-        filters_json: Option<String>,
-        order_by: Option<Vec<(String, String)>>,
+    /// Stderr logging follows `RUST_LOG`/`MCP_SERVER_LOG` and stays quiet without
+    /// them; events forwarded to MCP clients follow `logging/setLevel` instead.
+    pub fn init_tracing() {
+        LOGGING_INIT.get_or_init(|| {
+            let filter = EnvFilter::try_from_default_env().ok().or_else(|| {
+                std::env::var("MCP_SERVER_LOG")
+                    .ok()
+                    .and_then(|value| EnvFilter::try_new(value).ok())
+            });
+
+            let (client_filter, handle) = reload::Layer::new(client_targets(LevelFilter::OFF));
+            let _ = CLIENT_FILTER.set(handle);
+            let mut layers = vec![ClientLogLayer.with_filter(client_filter).boxed()];
+            if let Some(filter) = filter {
+                layers.push(
+                    tracing_subscriber::fmt::layer()
+                        .with_writer(std::io::stderr)
+                        .with_filter(filter)
+                        .boxed(),
+                );
+            }
+            let _ = tracing_subscriber::registry().with(layers).try_init();
+        });
     }
 
-    #[derive(Clone, Debug)]
-    struct StoredPagingCursor {
-        binding: PagingCursorBinding,
-        raw_cursor: String,
+    /// This crate's events at `level`; dependencies never below `warn`, which also
+    /// keeps transport-internal logging from feeding back into the client.
+    fn client_targets(level: LevelFilter) -> Targets {
+        Targets::new()
+            .with_default(level.min(LevelFilter::WARN))
+            .with_target(env!("CARGO_CRATE_NAME"), level)
     }
 
-    impl ToolExecutor {
-        fn new(session_state: Arc<SessionState>) -> Self {
-            Self {
-                session_state,
-                schema_cache: Arc::new(RwLock::new(StdHashMap::new())),
-                paging_cursors: Arc::new(RwLock::new(StdHashMap::new())),
-                tool_timeout: timeout_from_env("MCP_TOOL_TIMEOUT_MS", 30_000),
-            }
+    /// Widen or narrow the shared filter to the most verbose connected client.
+    fn apply_client_levels(sinks: &[ClientSink]) {
+        let level = sinks
+            .iter()
+            .map(|sink| sink.level)
+            .max()
+            .unwrap_or(LevelFilter::OFF);
+        if let Some(handle) = CLIENT_FILTER.get() {
+            let _ = handle.reload(client_targets(level));
         }
+    }
 
-        pub(crate) fn from_env() -> Self {
-            let session_config = SessionConfig::from_env();
-            let session_state = Arc::new(SessionState::new(session_config));
-            Self::new(session_state)
+    /// One client connection's `notifications/message` feed; silent until
+    /// `set_level` and removed on drop.
+    pub struct ClientLogs {
+        id: u64,
+    }
+
+    impl ClientLogs {
+        /// `send` receives `notifications/message` params (`level`, `logger`, `data`)
+        /// for events inside `connection_span(connection)`.
+        pub fn subscribe(connection: u64, send: impl Fn(Value) + Send + Sync + 'static) -> Self {
+            let id = NEXT_SINK_ID.fetch_add(1, Ordering::Relaxed);
+            CLIENT_SINKS.lock().unwrap().push(ClientSink {
+                id,
+                connection,
+                level: LevelFilter::OFF,
+                send: Arc::new(send),
+            });
+            Self { id }
         }
 
-        pub(crate) fn warmup_connection(&self) {
-            let enabled = bool_from_env("SCYLLA_WARMUP_ON_START", false);
-            if !enabled {
-                return;
+        pub fn set_level(&self, level: LevelFilter) {
+            let mut sinks = CLIENT_SINKS.lock().unwrap();
+            if let Some(sink) = sinks.iter_mut().find(|sink| sink.id == self.id) {
+                sink.level = level;
             }
-            let session_state = Arc::clone(&self.session_state);
-            tokio::spawn(async move {
-                if let Err(err) = session_state.session().await {
-                    error!("failed to establish initial Scylla connection: {err}");
-                }
-            });
+            apply_client_levels(&sinks);
         }
+    }
 
-        async fn session(&self) -> Result<Arc<scylla::Session>> {
-            self.session_state.session().await
+    impl Drop for ClientLogs {
+        fn drop(&mut self) {
+            let mut sinks = CLIENT_SINKS.lock().unwrap();
+            sinks.retain(|sink| sink.id != self.id);
+            apply_client_levels(&sinks);
         }
+    }
 
-        async fn get_schema(
-            &self,
-            keyspace: &str,
-            table: &str,
-        ) -> anyhow::Result<crate::db::DescribeTable> {
-            let key = (keyspace.to_string(), table.to_string());
-            if let Some(found) = self.schema_cache.read().await.get(&key).cloned() {
-                return Ok(found);
-            }
-            // Use the shared session and add a small retry for transient connection errors
-            use tokio::time::{sleep, Duration};
-            let mut last_err: Option<anyhow::Error> = None;
-            for backoff_ms in [0u64, 50, 150] {
-                if backoff_ms > 0 {
-                    sleep(Duration::from_millis(backoff_ms)).await;
-                }
-                let session = match self.session().await {
-                    Ok(session) => session,
-                    Err(err) => {
-                        last_err = Some(err);
-                        continue;
-                    }
-                };
-                match crate::db::describe_table_with(&session, keyspace, table).await {
-                    Ok(schema) => {
-                        self.schema_cache
-                            .write()
-                            .await
-                            .insert(key.clone(), schema.clone());
-                        return Ok(schema);
-                    }
-                    Err(e) => {
-                        last_err = Some(e);
-                    }
-                }
-            }
-            let err = last_err.unwrap_or_else(|| anyhow::anyhow!("unknown schema error"));
-            Err(err)
+    /// Map an MCP (syslog) level name onto the closest tracing level.
+    pub fn parse_mcp_level(name: &str) -> Option<LevelFilter> {
+        match name {
+            "debug" => Some(LevelFilter::DEBUG),
+            "info" | "notice" => Some(LevelFilter::INFO),
+            "warning" => Some(LevelFilter::WARN),
+            "error" | "critical" | "alert" | "emergency" => Some(LevelFilter::ERROR),
+            _ => None,
         }
+    }
 
-        fn paging_cursor_binding(
-            keyspace: &str,
-            table: &str,
-            columns: &[String],
-            page_size: i32,
-            filters: Option<&serde_json::Map<String, serde_json::Value>>,
-            order_by: Option<&Vec<(String, String)>>,
-        ) -> Result<PagingCursorBinding> {
-            let filters_json = filters
-                .map(serde_json::to_string)
-                .transpose()
-                .context("failed to encode paging cursor filter binding")?;
-            Ok(PagingCursorBinding {
-                keyspace: keyspace.to_string(),
-                table: table.to_string(),
-                columns: columns.to_vec(),
-                page_size,
-                filters_json,
-                order_by: order_by.cloned(),
-            })
+    fn mcp_level(level: &Level) -> &'static str {
+        match *level {
+            Level::ERROR => "error",
+            Level::WARN => "warning",
+            Level::INFO => "info",
+            _ => "debug",
         }
+    }
 
-        async fn store_paging_cursor(
-            &self,
-            binding: PagingCursorBinding,
-            raw_cursor: String,
-        ) -> Result<String> {
-            let mut random = [0_u8; 32];
-            openssl::rand::rand_bytes(&mut random).context("failed to generate cursor token")?;
-            let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(random);
+    fn is_secret_name(name: &str) -> bool {
+        let lowered = name.to_ascii_lowercase();
+        [
+            "password",
+            "passwd",
+            "secret",
+            "token",
+            "credential",
+            "authorization",
+        ]
+        .iter()
+        .any(|word| lowered.contains(word))
+            || lowered == "pass"
+            || lowered.ends_with("_pass")
+    }
 
-            let mut cursors = self.paging_cursors.write().await;
-            if cursors.len() >= MAX_SERVER_CURSORS {
-                if let Some(oldest) = cursors.keys().next().cloned() {
-                    cursors.remove(&oldest);
-                }
-            }
-            cursors.insert(
-                token.clone(),
-                StoredPagingCursor {
-                    binding,
-                    raw_cursor,
-                },
-            );
-            Ok(token)
+    /// Mask credentials in log text: `Bearer`/`Basic` header values, values of
+    /// `password=`/`token:`-style pairs, and the configured secrets themselves.
+    pub fn redact(text: &str) -> String {
+        static PATTERNS: OnceLock<[regex::Regex; 2]> = OnceLock::new();
+        static SECRETS: OnceLock<Vec<String>> = OnceLock::new();
+        let [scheme, pair] = PATTERNS.get_or_init(|| {
+            [
+                regex::Regex::new(r"(?i)\b(bearer|basic)\s+[A-Za-z0-9._~+/=-]+")
+                    .expect("static regex"),
+                regex::Regex::new(
+                    r#"(?i)("?[a-z_-]*(?:password|passwd|secret|token|api[_-]?key)[a-z_-]*"?\s*[:=]\s*)("[^"]*"|'[^']*'|[^,;}\s]+)"#,
+                )
+                .expect("static regex"),
+            ]
+        });
+        let mut text = scheme.replace_all(text, "$1 <redacted>").into_owned();
+        text = pair.replace_all(&text, "${1}<redacted>").into_owned();
+        let secrets = SECRETS.get_or_init(|| {
+            ["SCYLLA_PASS", "MCP_HTTP_BEARER_TOKEN"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .filter(|value| value.len() >= 4)
+                .collect()
+        });
+        for secret in secrets {
+            text = text.replace(secret.as_str(), "<redacted>");
         }
+        text
+    }
 
-        async fn take_paging_cursor(
-            &self,
-            token: &str,
-            binding: &PagingCursorBinding,
-        ) -> Result<String> {
-            let stored = self
-                .paging_cursors
-                .write()
-                .await
-                .remove(token)
-                .ok_or_else(|| anyhow::anyhow!("unknown or expired paging cursor"))?;
-            if stored.binding != *binding {
-                anyhow::bail!("paging cursor does not match the current query");
+    #[derive(Default)]
+    struct EventFields {
+        message: Option<String>,
+        fields: Map<String, Value>,
+    }
+
+    impl EventFields {
+        fn record_text(&mut self, field: &tracing::field::Field, text: String) {
+            let text = if is_secret_name(field.name()) {
+                "<redacted>".to_string()
+            } else {
+                redact(&text)
+            };
+            if field.name() == "message" {
+                self.message = Some(text);
+            } else {
+                self.fields
+                    .insert(field.name().to_string(), Value::from(text));
             }
-            Ok(stored.raw_cursor)
         }
 
-        async fn replace_next_cursor(
-            &self,
-            obj: &mut serde_json::Map<String, serde_json::Value>,
-            binding: &PagingCursorBinding,
-        ) -> Result<()> {
-            if let Some(serde_json::Value::String(raw_cursor)) = obj.get("next_cursor").cloned() {
-                let token = self
-                    .store_paging_cursor(binding.clone(), raw_cursor)
-                    .await?;
-                obj.insert("next_cursor".into(), serde_json::Value::String(token));
+        /// A bare message becomes a string; anything else an object.
+        fn into_data(self) -> Value {
+            if self.fields.is_empty() {
+                return Value::from(self.message.unwrap_or_default());
+            }
+            let mut data = self.fields;
+            if let Some(message) = self.message {
+                data.insert("message".into(), Value::from(message));
+            }
+            Value::Object(data)
+        }
+    }
+
+    impl tracing::field::Visit for EventFields {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.record_text(field, value.to_string());
+        }
+
+        fn record_i64(&mut self, field: &tracing::field::Field, value: i64) {
+            self.fields
+                .insert(field.name().to_string(), Value::from(value));
+        }
+
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            self.fields
+                .insert(field.name().to_string(), Value::from(value));
+        }
+
+        fn record_bool(&mut self, field: &tracing::field::Field, value: bool) {
+            self.fields
+                .insert(field.name().to_string(), Value::from(value));
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.record_text(field, format!("{value:?}"));
+        }
+    }
+
+    /// The `mcp_connection` field of a `connection_span`, kept in its extensions.
+    #[derive(Default)]
+    struct ConnectionId(Option<u64>);
+
+    impl tracing::field::Visit for ConnectionId {
+        fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+            if field.name() == "mcp_connection" {
+                self.0 = Some(value);
             }
-            Ok(())
         }
 
-        pub(crate) async fn execute(
+        fn record_debug(&mut self, _field: &tracing::field::Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    /// Forwards events to the client of the connection they happened in, if
+    /// its level admits them. Events outside any connection reach no client.
+    struct ClientLogLayer;
+
+    impl<S> Layer<S> for ClientLogLayer
+    where
+        S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(
             &self,
-            name: &str,
-            arguments: Option<&serde_json::Map<String, serde_json::Value>>,
-        ) -> ToolOutput {
-            let request = ExecuteRequest {
-                params: ExecuteRequestParams {
-                    name: name.to_string(),
-                    arguments: arguments.cloned(),
-                },
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: Context<'_, S>,
+        ) {
+            let mut connection = ConnectionId::default();
+            attrs.record(&mut connection);
+            if let (Some(_), Some(span)) = (connection.0, ctx.span(id)) {
+                span.extensions_mut().insert(connection);
+            }
+        }
+
+        fn on_event(&self, event: &tracing::Event<'_>, ctx: Context<'_, S>) {
+            let Some(connection) = ctx.event_scope(event).and_then(|scope| {
+                scope
+                    .into_iter()
+                    .find_map(|span| span.extensions().get::<ConnectionId>().and_then(|id| id.0))
+            }) else {
+                return;
             };
-            match tokio::time::timeout(self.tool_timeout, self.execute_request(request)).await {
-                Ok(result) => match result {
-                    Ok(output) => output,
-                    Err(never) => match never {},
-                },
-                Err(_) => ToolOutput::error(format!(
-                    "tool '{}' timed out after {} ms",
-                    name,
-                    self.tool_timeout.as_millis()
-                )),
+            let level = *event.metadata().level();
+            // Collect senders first so nothing runs under the lock.
+            let targets: Vec<Arc<SendLog>> = CLIENT_SINKS
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|sink| sink.connection == connection && sink.level >= level)
+                .map(|sink| Arc::clone(&sink.send))
+                .collect();
+            if targets.is_empty() {
+                return;
+            }
+            let mut fields = EventFields::default();
+            event.record(&mut fields);
+            let params = json!({
+                "level": mcp_level(&level),
+                "logger": event.metadata().target(),
+                "data": fields.into_data(),
+            });
+            for send in targets {
+                send(params.clone());
             }
         }
+    }
 
-        async fn execute_request(
-            &self,
-            request: ExecuteRequest,
-        ) -> std::result::Result<ToolOutput, std::convert::Infallible> {
-            let name = request.params.name.clone();
-            match name.as_str() {
-                "list_keyspaces" => {
-                    let span = tracing::info_span!("tool", name = "list_keyspaces");
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("list_keyspaces failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::list_keyspaces_with(&session).await {
-                        Ok(list) => {
-                            let json = serde_json::to_string(&list).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("list_keyspaces failed: {}", err);
-                            Ok(ToolOutput::error(msg))
-                        }
-                    }
-                }
-                "list_tables" => {
-                    // extract keyspace from arguments
-                    let ks = request.params.arguments.as_ref().and_then(|m| {
-                        m.get("keyspace")
-                            .and_then(|v| v.as_str())
-                            .map(|s| s.to_string())
-                    });
-                    if ks.is_none() {
-                        let msg = "missing required argument 'keyspace'".to_string();
-                        return Ok(ToolOutput::error(msg));
-                    }
-                    let keyspace = ks.unwrap();
-                    let span = tracing::info_span!("tool", name = "list_tables", %keyspace);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("list_tables failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::list_tables_with(&session, &keyspace).await {
-                        Ok(list) => {
-                            let json = serde_json::to_string(&list).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("list_tables failed: {}", err);
-                            Ok(ToolOutput::error(msg))
-                        }
+    #[cfg(test)]
+    mod tests {
+        use super::{connection_span, parse_mcp_level, redact, ClientLogLayer, ClientLogs};
+        use crate::mcp::ToolExecutor;
+        use serde_json::json;
+        use std::sync::{Arc, Mutex};
+        use tracing::level_filters::LevelFilter;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        #[test]
+        fn mcp_levels_map_onto_tracing_levels() {
+            assert_eq!(parse_mcp_level("debug"), Some(LevelFilter::DEBUG));
+            assert_eq!(parse_mcp_level("notice"), Some(LevelFilter::INFO));
+            assert_eq!(parse_mcp_level("warning"), Some(LevelFilter::WARN));
+            assert_eq!(parse_mcp_level("emergency"), Some(LevelFilter::ERROR));
+            assert_eq!(parse_mcp_level("verbose"), None);
+        }
+
+        #[test]
+        fn redaction_masks_credentials() {
+            assert_eq!(
+                redact("Authorization: Bearer abc.def-123"),
+                "Authorization: Bearer <redacted>"
+            );
+            assert_eq!(
+                redact("connect user=scylla password=hunter2, ssl=true"),
+                "connect user=scylla password=<redacted>, ssl=true"
+            );
+            assert_eq!(
+                redact(r#"{"api_key": "k-123", "node": "10.0.0.1"}"#),
+                r#"{"api_key": <redacted>, "node": "10.0.0.1"}"#
+            );
+            assert_eq!(
+                redact("read 40 rows from app.users"),
+                "read 40 rows from app.users"
+            );
+        }
+
+        #[test]
+        fn events_reach_clients_at_or_above_their_level() {
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let sink = Arc::clone(&received);
+            let logs = ClientLogs::subscribe(7, move |params| sink.lock().unwrap().push(params));
+            let subscriber = tracing_subscriber::registry().with(ClientLogLayer);
+            tracing::subscriber::with_default(subscriber, || {
+                let _connection = connection_span(7).entered();
+                tracing::warn!("dropped before set_level 7f1c");
+                logs.set_level(LevelFilter::INFO);
+                tracing::debug!("too verbose 7f1c");
+                tracing::info!(keyspace = "app", token = "s3cret", "schema changed 7f1c");
+                tracing::error!("query failed with password=hunter2 7f1c");
+            });
+            drop(logs);
+
+            let received: Vec<_> = received
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|p| p.to_string().contains("7f1c"))
+                .cloned()
+                .collect();
+            assert_eq!(received.len(), 2, "{received:?}");
+            assert_eq!(received[0]["level"], "info");
+            assert_eq!(received[0]["logger"], "scylla_rust_mcp::logging::tests");
+            assert_eq!(
+                received[0]["data"],
+                json!({ "keyspace": "app", "token": "<redacted>", "message": "schema changed 7f1c" })
+            );
+            assert_eq!(received[1]["level"], "error");
+            assert_eq!(
+                received[1]["data"],
+                "query failed with password=<redacted> 7f1c"
+            );
+        }
+
+        #[test]
+        fn events_reach_only_their_own_connection() {
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let subscribe = |connection: u64| {
+                let sink = Arc::clone(&received);
+                let logs = ClientLogs::subscribe(connection, move |params| {
+                    sink.lock().unwrap().push((connection, params))
+                });
+                logs.set_level(LevelFilter::INFO);
+                logs
+            };
+            let (first, second) = (subscribe(21), subscribe(22));
+            let subscriber = tracing_subscriber::registry().with(ClientLogLayer);
+            tracing::subscriber::with_default(subscriber, || {
+                connection_span(21).in_scope(|| {
+                    let _tool = tracing::info_span!("tool", name = "select").entered();
+                    tracing::info!("read rows 9b2e");
+                });
+                connection_span(22).in_scope(|| tracing::info!("listed keyspaces 9b2e"));
+                tracing::info!("no connection 9b2e");
+            });
+            drop((first, second));
+
+            let mut received: Vec<_> = received
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, p)| p.to_string().contains("9b2e"))
+                .map(|(connection, p)| (*connection, p["data"].clone()))
+                .collect();
+            received.sort_by_key(|(connection, _)| *connection);
+            assert_eq!(
+                received,
+                [
+                    (21, json!("read rows 9b2e")),
+                    (22, json!("listed keyspaces 9b2e"))
+                ]
+            );
+        }
+
+        #[test]
+        fn tool_spans_do_not_follow_worker_threads_into_other_tasks() {
+            let dispatch =
+                tracing::Dispatch::new(tracing_subscriber::registry().with(ClientLogLayer));
+            let worker_dispatch = dispatch.clone();
+            // Connecting through the driver needs more than the default 2 MiB
+            // stack in debug builds.
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .thread_stack_size(8 << 20)
+                .enable_all()
+                .on_thread_start(move || {
+                    std::mem::forget(tracing::dispatcher::set_default(&worker_dispatch))
+                })
+                .build()
+                .unwrap();
+            let _default = tracing::dispatcher::set_default(&dispatch);
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let sink = Arc::clone(&received);
+            runtime.block_on(async move {
+                // A peer that accepts and never answers keeps both calls connecting.
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let uri = listener.local_addr().unwrap().to_string();
+                tokio::spawn(async move {
+                    let mut held = Vec::new();
+                    while let Ok((socket, _)) = listener.accept().await {
+                        held.push(socket);
                     }
+                });
+                let connect = std::time::Duration::from_secs(30);
+                let mut logs = Vec::new();
+                let mut calls = Vec::new();
+                for index in 0..2 {
+                    let executor = Arc::new(ToolExecutor::for_uri(uri.clone(), connect));
+                    let sink = Arc::clone(&sink);
+                    let feed = executor
+                        .client_logs(move |params| sink.lock().unwrap().push((index, params)));
+                    feed.set_level(LevelFilter::DEBUG);
+                    logs.push(feed);
+                    calls.push(tokio::spawn(async move {
+                        executor
+                            .execute_with("list_keyspaces", None, Default::default())
+                            .await
+                    }));
                 }
-                "describe_table" => {
-                    // extract keyspace and table from arguments
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let tb = args
-                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() || tb.is_none() {
-                        let msg =
-                            "missing required arguments 'keyspace' and/or 'table'".to_string();
-                        return Ok(ToolOutput::error(msg));
-                    }
-                    match self
-                        .get_schema(&ks.clone().unwrap(), &tb.clone().unwrap())
-                        .await
-                    {
-                        Ok(cols) => {
-                            let json = serde_json::to_string(&cols).unwrap_or_else(|_| "{}".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("describe_table failed: {}", err);
-                            Ok(ToolOutput::error(msg))
-                        }
+                for _ in 0..30 {
+                    for _ in 0..20 {
+                        tokio::spawn(async {
+                            tokio::task::yield_now().await;
+                            tracing::info!("unrelated task 4d1a");
+                        });
                     }
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
                 }
-                "sample_rows" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let tb = args
-                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let lm = args.and_then(|m| m.get("limit").and_then(|v| v.as_u64()));
-                    let filters = args
-                        .and_then(|m| m.get("filters"))
-                        .and_then(|v| v.as_object());
-                    let Some((keyspace, table, limit_u64)) =
-                        ks.zip(tb).zip(lm).map(|((a, b), c)| (a, b, c))
-                    else {
-                        let msg = "missing required arguments 'keyspace', 'table', or 'limit'"
-                            .to_string();
-                        return Ok(ToolOutput::error(msg));
-                    };
-                    // Validate filter keys exist
-                    if let Some(f) = filters {
-                        match self.get_schema(&keyspace, &table).await {
-                            Ok(schema) => {
-                                let available: std::collections::HashSet<String> = schema
-                                    .columns
-                                    .iter()
-                                    .map(|c| c.column_name.clone())
-                                    .collect();
-                                for col in f.keys() {
-                                    if !available.contains(col) {
-                                        let msg = format!(
-                                            "invalid filter column '{}'; not in table columns",
-                                            col
-                                        );
-                                        return Ok(ToolOutput::error(msg));
-                                    }
-                                }
-                            }
-                            Err(err) => {
-                                let msg = format!("schema fetch failed: {}", err);
-                                return Ok(ToolOutput::error(msg));
-                            }
-                        }
-                    }
-                    let limit = (limit_u64 as u32).clamp(1, 500);
-                    let span = tracing::info_span!("tool", name = "sample_rows", %keyspace, %table, limit = limit as i64);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("sample_rows failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::sample_rows_with(&session, &keyspace, &table, limit, filters)
-                        .await
-                    {
-                        Ok(rows) => {
-                            let json = serde_json::to_string(&rows).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("sample_rows failed: {}", err);
-                            Ok(ToolOutput::error(msg))
-                        }
-                    }
+                for call in calls {
+                    call.abort();
                 }
-                "select" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let tb = args
-                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let cols =
-                        args.and_then(|m| m.get("columns").and_then(|v| v.as_array()).cloned());
-                    let lm = args.and_then(|m| m.get("limit").and_then(|v| v.as_u64()));
-                    let filters = args
-                        .and_then(|m| m.get("filters"))
-                        .and_then(|v| v.as_object());
-                    let order_by = args
-                        .and_then(|m| m.get("order_by").or_else(|| m.get("orderBy")))
-                        .and_then(|v| v.as_array().cloned());
-                    if ks.is_none() || tb.is_none() || cols.is_none() || lm.is_none() {
-                        let msg =
-                            "missing required arguments 'keyspace', 'table', 'columns', or 'limit'"
-                                .to_string();
-                        return Ok(ToolOutput::error(msg));
-                    }
-                    let keyspace = ks.unwrap();
-                    let table = tb.unwrap();
-                    let columns: Vec<String> = cols
-                        .unwrap()
-                        .iter()
-                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                        .collect();
-                    let limit = (lm.unwrap() as u32).clamp(1, 500);
-                    // Validate requested columns and filter keys exist
-                    match self.get_schema(&keyspace, &table).await {
-                        Ok(schema) => {
-                            let available: std::collections::HashSet<String> = schema
-                                .columns
-                                .iter()
-                                .map(|c| c.column_name.clone())
-                                .collect();
-                            for c in columns.iter() {
-                                if !available.contains(c) {
-                                    let msg = format!(
-                                        "invalid column '{}' in select; not in table columns",
-                                        c
-                                    );
-                                    return Ok(ToolOutput::error(msg));
-                                }
-                            }
-                            if let Some(f) = &filters {
-                                for col in f.keys() {
-                                    if !available.contains(col) {
-                                        let msg = format!(
-                                            "invalid filter column '{}'; not in table columns",
-                                            col
-                                        );
-                                        return Ok(ToolOutput::error(msg));
-                                    }
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            let msg = format!("schema fetch failed: {}", err);
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    }
-                    let order_tuples: Option<Vec<(String, String)>> = order_by.map(|arr| {
-                        arr.into_iter()
-                            .filter_map(|item| item.as_object().cloned())
-                            .filter_map(|m| {
-                                let col = m.get("column").and_then(|v| v.as_str())?;
-                                let dir =
-                                    m.get("direction").and_then(|v| v.as_str()).unwrap_or("asc");
-                                Some((col.to_string(), dir.to_string()))
-                            })
-                            .collect()
-                    });
-                    // Enforce order_by only on clustering keys
-                    if let Some(ref ords) = order_tuples {
-                        match self.get_schema(&keyspace, &table).await {
-                            Ok(schema) => {
-                                let allowed: std::collections::HashSet<String> =
-                                    schema.clustering_keys.iter().cloned().collect();
-                                for (col, _) in ords.iter() {
-                                    if !allowed.contains(col) {
-                                        let msg = format!(
-                                            "invalid order_by column '{}'; only clustering keys are allowed: {:?}",
-                                            col, schema.clustering_keys
-                                        );
-                                        return Ok(ToolOutput::error(msg));
-                                    }
-                                }
-                            }
-                            Err(err) => {
-                                let msg = format!("schema fetch failed: {}", err);
-                                return Ok(ToolOutput::error(msg));
-                            }
-                        }
-                    }
-                    let span = tracing::info_span!("tool", name = "select", %keyspace, %table, limit = limit as i64);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("select failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::select_columns_with(
-                        &session,
-                        &keyspace,
-                        &table,
-                        &columns,
-                        limit,
-                        filters,
-                        order_tuples.as_ref(),
-                    )
-                    .await
-                    {
-                        Ok(rows) => {
-                            let json = serde_json::to_string(&rows).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("select failed: {}", err);
-                            Ok(ToolOutput::error(msg))
-                        }
+                drop(logs);
+            });
+
+            let leaked: Vec<_> = received
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(_, p)| p.to_string().contains("4d1a"))
+                .cloned()
+                .collect();
+            assert!(leaked.is_empty(), "{leaked:?}");
+        }
+    }
+}
+
+pub mod mcp {
+    use anyhow::{Context, Result};
+    use base64::Engine;
+    use rust_mcp_schema::schema_utils::{ClientMessage, MessageFromServer};
+    use rust_mcp_sdk::error::SdkResult;
+    use std::sync::Arc;
+    use tokio::sync::OnceCell;
+    use tracing::{debug, error, info, Instrument};
+
+    const MAX_SERVER_CURSORS: usize = 1024;
+
+    pub async fn run_stdio_server() -> SdkResult<()> {
+        crate::logging::init_tracing();
+
+        info!("starting MCP stdio server (rust-mcp-sdk)");
+        use rust_mcp_sdk::mcp_server::server_runtime_core;
+        use rust_mcp_sdk::{MCPServer, StdioTransport, TransportOptions};
+
+        let in_flight = Arc::new(SdkInFlight::default());
+        let transport = CancellableTransport {
+            inner: StdioTransport::new(TransportOptions::default())?,
+            in_flight: Arc::clone(&in_flight),
+        };
+        let handler = SdkHandler {
+            executor: ToolExecutor::from_env(),
+            in_flight,
+        };
+        handler.executor.warmup_connection();
+        let server = server_runtime_core::create_server(sdk_server_details(), transport, handler);
+        server.start().await
+    }
+
+    fn sdk_server_details() -> rust_mcp_schema::InitializeResult {
+        use rust_mcp_schema::{
+            Implementation, InitializeResult, ServerCapabilities, ServerCapabilitiesTools,
+            LATEST_PROTOCOL_VERSION,
+        };
+
+        InitializeResult {
+            server_info: Implementation {
+                name: "scylla-rust-mcp".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            capabilities: ServerCapabilities {
+                tools: Some(ServerCapabilitiesTools { list_changed: None }),
+                ..Default::default()
+            },
+            meta: None,
+            instructions: Some("Read-only ScyllaDB access for AI agents".to_string()),
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+        }
+    }
+
+    // Basic handler – extend as we implement tools
+    use std::collections::HashMap as StdHashMap;
+    use tokio::sync::RwLock;
+
+    #[derive(Debug, Clone)]
+    pub struct ToolOutput {
+        pub text: String,
+        pub is_error: bool,
+    }
+
+    impl ToolOutput {
+        fn ok(text: String) -> Self {
+            Self {
+                text,
+                is_error: false,
+            }
+        }
+
+        fn error(text: String) -> Self {
+            Self {
+                text,
+                is_error: true,
+            }
+        }
+
+        /// The result as `structuredContent`, for tools that declare an output schema.
+        pub fn structured_content(&self, tool: &str) -> Option<serde_json::Value> {
+            if self.is_error || crate::server::tool_output_schema(tool).is_none() {
+                return None;
+            }
+            serde_json::from_str::<serde_json::Value>(&self.text)
+                .ok()
+                .filter(serde_json::Value::is_object)
+        }
+
+        fn text_content(text: String, _annotations: Option<serde_json::Value>) -> Self {
+            let trimmed = text.trim_start();
+            let looks_like_json = trimmed.starts_with('{') || trimmed.starts_with('[');
+            let is_error = !looks_like_json
+                && (text.starts_with("missing required")
+                    || text.starts_with("invalid ")
+                    || text.starts_with("partition keys mismatch")
+                    || text.starts_with("schema fetch failed")
+                    || text.contains(" failed")
+                    || text.contains("not yet implemented"));
+            if is_error {
+                Self::error(text)
+            } else {
+                Self::ok(text)
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct ExecuteRequest {
+        params: ExecuteRequestParams,
+        progress: crate::db::Progress,
+    }
+
+    /// Per-call hooks supplied by the transport.
+    #[derive(Clone, Debug, Default)]
+    pub(crate) struct CallContext {
+        /// Cancelled when the client sends `notifications/cancelled` for the call.
+        pub(crate) cancel: tokio_util::sync::CancellationToken,
+        /// Receives `notifications/progress` updates when the client sent a `progressToken`.
+        pub(crate) progress: crate::db::Progress,
+    }
+
+    #[derive(Debug, Clone)]
+    struct ExecuteRequestParams {
+        name: String,
+        arguments: Option<serde_json::Map<String, serde_json::Value>>,
+    }
+
+    pub struct ToolExecutor {
+        session_state: Arc<SessionState>,
+        schema_cache: Arc<RwLock<StdHashMap<(String, String), crate::db::DescribeTable>>>,
+        paging_cursors: Arc<RwLock<StdHashMap<String, StoredPagingCursor>>>,
+        tool_timeout: std::time::Duration,
+        /// REST API client from `SCYLLA_REST_URL`, or why it is unavailable.
+        rest: std::result::Result<Arc<crate::rest::RestClient>, String>,
+        /// Prometheus endpoints from `SCYLLA_METRICS_URL`, or why they are unavailable.
+        metrics: std::result::Result<Arc<crate::rest::RestClient>, String>,
+        resource_subscriptions: Arc<RwLock<std::collections::BTreeSet<String>>>,
+        schema_watch: Arc<tokio::sync::Mutex<SchemaWatch>>,
+        /// Shared by every connection so their pollers reuse one result.
+        latest_schema: Arc<tokio::sync::Mutex<LatestSchema>>,
+        schema_poll_interval: std::time::Duration,
+        /// Routes log events from this executor's work to its client only.
+        connection: u64,
+    }
+
+    /// Schema state as of this connection's last `poll_schema_changes`.
+    #[derive(Debug, Default)]
+    struct SchemaWatch {
+        version: Option<String>,
+        fingerprints: Option<Arc<crate::db::SchemaFingerprints>>,
+    }
+
+    /// The cluster schema as last read by any connection.
+    #[derive(Debug, Default)]
+    struct LatestSchema {
+        checked_at: Option<std::time::Instant>,
+        version: Option<String>,
+        fingerprints: Option<Arc<crate::db::SchemaFingerprints>>,
+    }
+
+    const REST_NOT_CONFIGURED: &str = "SCYLLA_REST_URL is not set; REST tools need one base URL per node, e.g. http://10.0.0.1:10000,http://10.0.0.2:10000";
+    const METRICS_NOT_CONFIGURED: &str = "SCYLLA_METRICS_URL is not set; node_metrics needs one Prometheus endpoint per node, e.g. http://10.0.0.1:9180/metrics,http://10.0.0.2:9180/metrics";
+
+    const RESOURCE_PAGE_SIZE: usize = 500;
+
+    /// Most values a `completion/complete` response may carry.
+    const MAX_COMPLETION_VALUES: usize = 100;
+
+    /// Candidates starting with `prefix` (case-insensitively), as an MCP completion.
+    fn completion_result(mut candidates: Vec<String>, prefix: &str) -> serde_json::Value {
+        let prefix = prefix.to_lowercase();
+        candidates.retain(|c| c.to_lowercase().starts_with(&prefix));
+        candidates.dedup();
+        let total = candidates.len();
+        candidates.truncate(MAX_COMPLETION_VALUES);
+        serde_json::json!({
+            "completion": {
+                "values": candidates,
+                "total": total,
+                "hasMore": total > MAX_COMPLETION_VALUES,
+            }
+        })
+    }
+
+    /// Resource URIs whose content a schema change affects.
+    fn changed_resource_uris(changes: &crate::db::SchemaChanges) -> Vec<String> {
+        use crate::server::ResourceUri;
+        let tables = changes
+            .tables
+            .iter()
+            .map(|(keyspace, table)| ResourceUri::TableSchema {
+                keyspace: keyspace.clone(),
+                table: table.clone(),
+            });
+        let keyspaces = changes
+            .keyspaces
+            .iter()
+            .map(|keyspace| ResourceUri::KeyspaceDdl {
+                keyspace: keyspace.clone(),
+            });
+        tables.chain(keyspaces).map(|uri| uri.uri()).collect()
+    }
+
+    /// Why a resources or prompts request failed; transports map it to a JSON-RPC error.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum RequestError {
+        InvalidParams(String),
+        NotFound {
+            message: String,
+            data: Option<serde_json::Value>,
+        },
+        Failed(String),
+    }
+
+    impl RequestError {
+        pub(crate) fn code(&self) -> i64 {
+            match self {
+                Self::InvalidParams(_) => -32602,
+                Self::NotFound { .. } => -32002,
+                Self::Failed(_) => -32603,
+            }
+        }
+
+        pub(crate) fn message(&self) -> &str {
+            match self {
+                Self::InvalidParams(message) | Self::Failed(message) => message,
+                Self::NotFound { message, .. } => message,
+            }
+        }
+
+        pub(crate) fn data(&self) -> Option<&serde_json::Value> {
+            match self {
+                Self::NotFound { data, .. } => data.as_ref(),
+                _ => None,
+            }
+        }
+
+        fn from_schema_error(uri: &str, err: anyhow::Error) -> Self {
+            match err.downcast_ref::<crate::db::UnknownIdentifier>() {
+                Some(unknown) => Self::NotFound {
+                    message: format!("resource not found: {uri}: {unknown}"),
+                    data: serde_json::to_value(unknown).ok(),
+                },
+                None => Self::Failed(format!("failed to read {uri}: {err}")),
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct PagingCursorBinding {
+        keyspace: String,
+        table: String,
+        columns: Vec<String>,
+        page_size: i32,
+        filters_json: Option<String>,
+        order_by: Option<Vec<(String, String)>>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct StoredPagingCursor {
+        binding: PagingCursorBinding,
+        raw_cursor: String,
+    }
+
+    impl ToolExecutor {
+        fn new(session_state: Arc<SessionState>) -> Self {
+            Self {
+                session_state,
+                schema_cache: Arc::new(RwLock::new(StdHashMap::new())),
+                paging_cursors: Arc::new(RwLock::new(StdHashMap::new())),
+                tool_timeout: timeout_from_env("MCP_TOOL_TIMEOUT_MS", 30_000),
+                rest: Err(REST_NOT_CONFIGURED.to_string()),
+                metrics: Err(METRICS_NOT_CONFIGURED.to_string()),
+                resource_subscriptions: Arc::new(RwLock::new(std::collections::BTreeSet::new())),
+                schema_watch: Arc::new(tokio::sync::Mutex::new(SchemaWatch::default())),
+                latest_schema: Arc::new(tokio::sync::Mutex::new(LatestSchema::default())),
+                schema_poll_interval: timeout_from_env("SCYLLA_SCHEMA_POLL_MS", 5_000),
+                connection: crate::logging::next_connection_id(),
+            }
+        }
+
+        pub(crate) fn from_env() -> Self {
+            let session_config = SessionConfig::from_env();
+            let session_state = Arc::new(SessionState::new(session_config));
+            let mut executor = Self::new(session_state);
+            executor.rest = match crate::rest::RestClient::from_env() {
+                Ok(Some(client)) => Ok(Arc::new(client)),
+                Ok(None) => Err(REST_NOT_CONFIGURED.to_string()),
+                Err(err) => Err(format!("{err:#}")),
+            };
+            executor.metrics = match crate::rest::RestClient::from_env_var(
+                "SCYLLA_METRICS_URL",
+                crate::metrics::DEFAULT_METRICS_PORT,
+            ) {
+                Ok(Some(client)) => Ok(Arc::new(client)),
+                Ok(None) => Err(METRICS_NOT_CONFIGURED.to_string()),
+                Err(err) => Err(format!("{err:#}")),
+            };
+            executor
+        }
+
+        /// An executor for `uri` with no credentials or TLS, for tests that need
+        /// a connect to fail fast or hang.
+        #[cfg(test)]
+        pub(crate) fn for_uri(uri: String, connect_timeout: std::time::Duration) -> Self {
+            Self::new(Arc::new(SessionState::new(SessionConfig {
+                uri,
+                credentials: None,
+                ssl: None,
+                connect_timeout,
+            })))
+        }
+
+        /// A fresh executor for another client connection: the Scylla session,
+        /// schema cache, latest schema poll and REST clients are shared, while
+        /// paging cursors, resource subscriptions and schema-watch state start empty.
+        pub(crate) fn for_new_connection(&self) -> Self {
+            let mut executor = Self::new(Arc::clone(&self.session_state));
+            executor.schema_cache = Arc::clone(&self.schema_cache);
+            executor.latest_schema = Arc::clone(&self.latest_schema);
+            executor.tool_timeout = self.tool_timeout;
+            executor.schema_poll_interval = self.schema_poll_interval;
+            executor.rest = self.rest.clone();
+            executor.metrics = self.metrics.clone();
+            executor
+        }
+
+        pub(crate) fn warmup_connection(&self) {
+            let enabled = bool_from_env("SCYLLA_WARMUP_ON_START", false);
+            if !enabled {
+                return;
+            }
+            let session_state = Arc::clone(&self.session_state);
+            tokio::spawn(
+                async move {
+                    if let Err(err) = session_state.session().await {
+                        error!("failed to establish initial Scylla connection: {err}");
                     }
                 }
-                "paged_select" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let tb = args
-                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let cols =
-                        args.and_then(|m| m.get("columns").and_then(|v| v.as_array()).cloned());
-                    let page_size = args.and_then(|m| m.get("page_size").and_then(|v| v.as_u64()));
-                    let filters = args
-                        .and_then(|m| m.get("filters"))
-                        .and_then(|v| v.as_object());
-                    let order_by = args
-                        .and_then(|m| m.get("order_by").or_else(|| m.get("orderBy")))
-                        .and_then(|v| v.as_array().cloned());
-                    let cursor = args
-                        .and_then(|m| m.get("cursor").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() || tb.is_none() || cols.is_none() || page_size.is_none() {
-                        let msg = "missing required arguments 'keyspace', 'table', 'columns', or 'page_size'".to_string();
-                        return Ok(ToolOutput::error(msg));
+                .instrument(self.connection_span()),
+            );
+        }
+
+        /// Events inside this span reach this connection's `logging/setLevel` client.
+        pub(crate) fn connection_span(&self) -> tracing::Span {
+            crate::logging::connection_span(self.connection)
+        }
+
+        /// This connection's client log feed; see `crate::logging::ClientLogs`.
+        pub(crate) fn client_logs(
+            &self,
+            send: impl Fn(serde_json::Value) + Send + Sync + 'static,
+        ) -> crate::logging::ClientLogs {
+            crate::logging::ClientLogs::subscribe(self.connection, send)
+        }
+
+        async fn session(&self) -> Result<Arc<scylla::Session>> {
+            self.session_state.session().await
+        }
+
+        /// The role the session authenticates as, from `SCYLLA_USER`.
+        fn configured_user(&self) -> Option<&str> {
+            self.session_state
+                .config
+                .credentials
+                .as_ref()
+                .map(|(user, _)| user.as_str())
+        }
+
+        async fn access_summary(
+            &self,
+            session: &scylla::Session,
+        ) -> Result<crate::access::AccessSummary> {
+            crate::db::access_summary_with(session, self.configured_user()).await
+        }
+
+        async fn get_schema(
+            &self,
+            keyspace: &str,
+            table: &str,
+        ) -> anyhow::Result<crate::db::DescribeTable> {
+            let key = (keyspace.to_string(), table.to_string());
+            if let Some(found) = self.schema_cache.read().await.get(&key).cloned() {
+                return Ok(found);
+            }
+            // Use the shared session and add a small retry for transient connection errors
+            use tokio::time::{sleep, Duration};
+            let mut last_err: Option<anyhow::Error> = None;
+            for backoff_ms in [0u64, 50, 150] {
+                if backoff_ms > 0 {
+                    sleep(Duration::from_millis(backoff_ms)).await;
+                }
+                let session = match self.session().await {
+                    Ok(session) => session,
+                    Err(err) => {
+                        last_err = Some(err);
+                        continue;
                     }
-                    let keyspace = ks.unwrap();
-                    let table = tb.unwrap();
-                    let page_size = (page_size.unwrap() as i32).clamp(1, 500);
-                    let columns: Vec<String> = cols
-                        .unwrap()
-                        .iter()
-                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                        .collect();
-                    // Validate columns/filters and enforce order_by on clustering keys
-                    match self.get_schema(&keyspace, &table).await {
-                        Ok(schema) => {
-                            let available: std::collections::HashSet<String> = schema
-                                .columns
-                                .iter()
-                                .map(|c| c.column_name.clone())
-                                .collect();
-                            for c in columns.iter() {
-                                if !available.contains(c) {
-                                    return Ok(ToolOutput::text_content(
-                                        format!("invalid column '{}'", c),
-                                        None,
-                                    ));
-                                }
-                            }
-                            if let Some(f) = &filters {
-                                for col in f.keys() {
-                                    if !available.contains(col) {
-                                        return Ok(ToolOutput::text_content(
-                                            format!("invalid filter column '{}'", col),
-                                            None,
-                                        ));
-                                    }
-                                }
-                            }
-                            if let Some(arr) = &order_by {
-                                let allowed: std::collections::HashSet<String> =
-                                    schema.clustering_keys.iter().cloned().collect();
-                                for item in arr.iter().filter_map(|v| v.as_object()) {
-                                    let Some(col) = item.get("column").and_then(|v| v.as_str())
-                                    else {
-                                        continue;
-                                    };
-                                    if !allowed.contains(col) {
-                                        return Ok(ToolOutput::text_content(
-                                            format!("invalid order_by column '{}'", col),
-                                            None,
-                                        ));
-                                    }
-                                }
-                            }
-                        }
-                        Err(err) => {
-                            return Ok(ToolOutput::text_content(
-                                format!("schema fetch failed: {}", err),
-                                None,
-                            ));
-                        }
+                };
+                match crate::db::describe_table_with(&session, keyspace, table).await {
+                    Ok(schema) if schema.columns.is_empty() => {
+                        // Missing tables come back as empty schemas; never cache them.
+                        let unknown =
+                            crate::db::unknown_table_with(&session, keyspace, table).await;
+                        return Err(anyhow::Error::new(unknown));
                     }
-                    let order_tuples: Option<Vec<(String, String)>> = order_by.map(|arr| {
-                        arr.into_iter()
-                            .filter_map(|item| item.as_object().cloned())
-                            .filter_map(|m| {
-                                let col = m.get("column").and_then(|v| v.as_str())?;
-                                let dir =
-                                    m.get("direction").and_then(|v| v.as_str()).unwrap_or("asc");
-                                Some((col.to_string(), dir.to_string()))
-                            })
-                            .collect()
+                    Ok(schema) => {
+                        self.schema_cache
+                            .write()
+                            .await
+                            .insert(key.clone(), schema.clone());
+                        return Ok(schema);
+                    }
+                    Err(e) => {
+                        last_err = Some(e);
+                    }
+                }
+            }
+            let err = last_err.unwrap_or_else(|| anyhow::anyhow!("unknown schema error"));
+            Err(err)
+        }
+
+        /// `resources/list`: the DDL of each application keyspace followed by the
+        /// schema of each of its tables, `RESOURCE_PAGE_SIZE` entries per page.
+        pub(crate) async fn list_resources(
+            &self,
+            cursor: Option<&str>,
+        ) -> std::result::Result<serde_json::Value, RequestError> {
+            use crate::server::ResourceUri;
+            let offset = match cursor {
+                Some(cursor) => cursor.parse::<usize>().map_err(|_| {
+                    RequestError::InvalidParams(format!("invalid resources cursor '{cursor}'"))
+                })?,
+                None => 0,
+            };
+            let session = self
+                .session()
+                .await
+                .map_err(|err| RequestError::Failed(format!("failed to connect: {err}")))?;
+            let tables = crate::db::list_all_tables_with(&session)
+                .await
+                .map_err(|err| RequestError::Failed(format!("failed to list tables: {err}")))?;
+            // Tables arrive sorted, so each keyspace's DDL entry precedes its tables.
+            let mut uris = Vec::new();
+            let mut current: Option<String> = None;
+            for (keyspace, table) in tables {
+                if crate::lint::is_system_keyspace(&keyspace) {
+                    continue;
+                }
+                if current.as_deref() != Some(keyspace.as_str()) {
+                    current = Some(keyspace.clone());
+                    uris.push(ResourceUri::KeyspaceDdl {
+                        keyspace: keyspace.clone(),
                     });
-                    let cursor_binding = match Self::paging_cursor_binding(
-                        &keyspace,
-                        &table,
-                        &columns,
-                        page_size,
-                        filters,
-                        order_tuples.as_ref(),
-                    ) {
-                        Ok(binding) => binding,
-                        Err(err) => {
-                            let msg = format!("paged_select failed: {}", err);
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    let cursor_state = match cursor.as_deref() {
-                        Some(token) => {
-                            match self.take_paging_cursor(token, &cursor_binding).await {
-                                Ok(raw_cursor) => Some(raw_cursor),
-                                Err(err) => {
-                                    let msg = format!("invalid cursor: {}", err);
-                                    return Ok(ToolOutput::error(msg));
-                                }
-                            }
-                        }
-                        None => None,
-                    };
-                    let span = tracing::info_span!("tool", name = "paged_select", %keyspace, %table, page_size);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("paged_select failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::paged_select_with(
-                        &session,
-                        &keyspace,
-                        &table,
-                        &columns,
-                        page_size,
-                        filters,
-                        order_tuples.as_ref(),
-                        cursor_state.as_deref(),
-                    )
+                }
+                uris.push(ResourceUri::TableSchema { keyspace, table });
+            }
+            let total = uris.len();
+            let resources: Vec<serde_json::Value> = uris
+                .into_iter()
+                .skip(offset)
+                .take(RESOURCE_PAGE_SIZE)
+                .map(|uri| {
+                    serde_json::json!({
+                        "uri": uri.uri(),
+                        "name": uri.name(),
+                        "mimeType": uri.mime_type(),
+                    })
+                })
+                .collect();
+            let mut result = serde_json::json!({ "resources": resources });
+            if offset + RESOURCE_PAGE_SIZE < total {
+                result["nextCursor"] =
+                    serde_json::Value::from((offset + RESOURCE_PAGE_SIZE).to_string());
+            }
+            Ok(result)
+        }
+
+        /// Record a `resources/subscribe`, taking a schema baseline if there is none yet
+        /// so that a change right after subscribing is not missed.
+        pub(crate) async fn subscribe_resource(
+            &self,
+            uri: &str,
+        ) -> std::result::Result<(), RequestError> {
+            let parsed =
+                crate::server::ResourceUri::parse(uri).map_err(RequestError::InvalidParams)?;
+            self.resource_subscriptions
+                .write()
+                .await
+                .insert(parsed.uri());
+            if self.schema_watch.lock().await.fingerprints.is_none() {
+                if let Err(err) = self.poll_schema_changes().await {
+                    debug!("schema baseline failed: {err}");
+                }
+            }
+            Ok(())
+        }
+
+        pub(crate) async fn unsubscribe_resource(
+            &self,
+            uri: &str,
+        ) -> std::result::Result<(), RequestError> {
+            let parsed =
+                crate::server::ResourceUri::parse(uri).map_err(RequestError::InvalidParams)?;
+            self.resource_subscriptions
+                .write()
+                .await
+                .remove(&parsed.uri());
+            Ok(())
+        }
+
+        /// How often transports should call `poll_schema_changes` (`SCYLLA_SCHEMA_POLL_MS`).
+        pub(crate) fn schema_poll_interval(&self) -> std::time::Duration {
+            self.schema_poll_interval
+        }
+
+        /// Compare `schema_version` with the previous poll. When it moved, evict the
+        /// affected `schema_cache` entries and return the subscribed resource URIs that
+        /// changed. Does nothing until a session exists, so polling never forces a connect.
+        pub(crate) async fn poll_schema_changes(&self) -> Result<Vec<String>> {
+            let Some(session) = self.session_state.connected() else {
+                return Ok(Vec::new());
+            };
+            let (version, fingerprints) = self.latest_schema(&session).await?;
+            let mut watch = self.schema_watch.lock().await;
+            if watch.fingerprints.is_some() && watch.version == version {
+                return Ok(Vec::new());
+            }
+            let changes = watch
+                .fingerprints
+                .as_ref()
+                .map(|previous| previous.diff(&fingerprints));
+            watch.version = version;
+            watch.fingerprints = Some(fingerprints);
+            drop(watch);
+            let Some(changes) = changes else {
+                return Ok(Vec::new());
+            };
+            if !changes.tables.is_empty() {
+                self.schema_cache
+                    .write()
                     .await
-                    {
-                        Ok(mut obj) => {
-                            if let Err(err) =
-                                self.replace_next_cursor(&mut obj, &cursor_binding).await
-                            {
-                                let msg = format!("paged_select failed: {}", err);
-                                return Ok(ToolOutput::error(msg));
-                            }
-                            let json = serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("paged_select failed: {}", err),
-                            None,
-                        )),
+                    .retain(|key, _| !changes.tables.contains(key));
+            }
+            let subscribed = self.resource_subscriptions.read().await;
+            Ok(changed_resource_uris(&changes)
+                .into_iter()
+                .filter(|uri| subscribed.contains(uri))
+                .collect())
+        }
+
+        /// The current schema version and fingerprints. A read less than half a
+        /// poll interval old is reused, and fingerprints are only recomputed when
+        /// the version moved, so N connections do not cost N schema scans.
+        async fn latest_schema(
+            &self,
+            session: &scylla::Session,
+        ) -> Result<(Option<String>, Arc<crate::db::SchemaFingerprints>)> {
+            let mut latest = self.latest_schema.lock().await;
+            let fresh = latest
+                .checked_at
+                .is_some_and(|at| at.elapsed() < self.schema_poll_interval / 2);
+            if let (true, Some(fingerprints)) = (fresh, &latest.fingerprints) {
+                return Ok((latest.version.clone(), Arc::clone(fingerprints)));
+            }
+            let version = crate::db::schema_version_with(session).await?;
+            let fingerprints = match &latest.fingerprints {
+                Some(fingerprints) if latest.version == version => Arc::clone(fingerprints),
+                _ => Arc::new(crate::db::schema_fingerprints_with(session).await?),
+            };
+            latest.checked_at = Some(std::time::Instant::now());
+            latest.version = version.clone();
+            latest.fingerprints = Some(Arc::clone(&fingerprints));
+            Ok((version, fingerprints))
+        }
+
+        /// `completion/complete` for a prompt (`ref/prompt`) or resource template
+        /// (`ref/resource`) argument. `keyspace` completes from the cluster, `table` from
+        /// the keyspace in `context`, and `column` from the cached table schema; lookups
+        /// that fail complete to nothing rather than erroring.
+        pub(crate) async fn complete_argument(
+            &self,
+            reference: &serde_json::Value,
+            argument: &str,
+            value: &str,
+            context: &StdHashMap<String, String>,
+        ) -> std::result::Result<serde_json::Value, RequestError> {
+            let known = match reference.get("type").and_then(|v| v.as_str()) {
+                Some("ref/prompt") => {
+                    let name = reference.get("name").and_then(|v| v.as_str());
+                    crate::server::list_prompts()
+                        .iter()
+                        .any(|prompt| Some(prompt.name) == name)
+                }
+                Some("ref/resource") => {
+                    let uri = reference.get("uri").and_then(|v| v.as_str());
+                    crate::server::resource_templates()
+                        .iter()
+                        .any(|template| Some(template.uri_template) == uri)
+                }
+                _ => {
+                    return Err(RequestError::InvalidParams(
+                        "ref.type must be 'ref/prompt' or 'ref/resource'".to_string(),
+                    ))
+                }
+            };
+            if !known {
+                return Err(RequestError::InvalidParams(format!(
+                    "unknown completion reference {reference}"
+                )));
+            }
+            let keyspace = context.get("keyspace").map(|ks| ident_arg(ks));
+            let table = context.get("table").map(|t| ident_arg(t));
+            let candidates = match (argument, keyspace, table) {
+                ("keyspace", _, _) => match self.session().await {
+                    Ok(session) => crate::db::list_keyspaces_with(&session).await,
+                    Err(err) => Err(err),
+                },
+                ("table", Some(keyspace), _) => match self.session().await {
+                    Ok(session) => crate::db::list_tables_with(&session, &keyspace).await,
+                    Err(err) => Err(err),
+                },
+                ("column", Some(keyspace), Some(table)) => self
+                    .get_schema(&keyspace, &table)
+                    .await
+                    .map(|schema| Self::column_names(&schema)),
+                _ => Ok(Vec::new()),
+            };
+            let mut candidates = candidates.unwrap_or_else(|err| {
+                debug!(%argument, "completion lookup failed: {err}");
+                Vec::new()
+            });
+            if argument != "column" {
+                candidates.sort();
+            }
+            Ok(completion_result(candidates, value))
+        }
+
+        /// `prompts/get`: validate the arguments and render the playbook with live schema.
+        pub(crate) async fn get_prompt(
+            &self,
+            name: &str,
+            arguments: Option<&serde_json::Map<String, serde_json::Value>>,
+        ) -> std::result::Result<serde_json::Value, RequestError> {
+            let prompt = crate::server::list_prompts()
+                .into_iter()
+                .find(|prompt| prompt.name == name)
+                .ok_or_else(|| RequestError::InvalidParams(format!("unknown prompt '{name}'")))?;
+            let mut args = StdHashMap::new();
+            for argument in &prompt.arguments {
+                match arguments.and_then(|m| m.get(argument.name)) {
+                    Some(serde_json::Value::String(value)) => {
+                        args.insert(argument.name, value.clone());
+                    }
+                    Some(_) => {
+                        return Err(RequestError::InvalidParams(format!(
+                            "prompt argument '{}' must be a string",
+                            argument.name
+                        )));
                     }
+                    None if argument.required => {
+                        return Err(RequestError::InvalidParams(format!(
+                            "missing required prompt argument '{}'",
+                            argument.name
+                        )));
+                    }
+                    None => {}
                 }
-                "partition_rows" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let tb = args
-                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let part = args
-                        .and_then(|m| m.get("partition"))
-                        .and_then(|v| v.as_object());
-                    let limit = args
-                        .and_then(|m| m.get("limit").and_then(|v| v.as_u64()))
-                        .map(|n| (n as u32).clamp(1, 500));
-                    if ks.is_none() || tb.is_none() || part.is_none() || limit.is_none() {
-                        let msg =
-                            "missing required arguments 'keyspace','table','partition','limit'"
-                                .to_string();
+            }
+            let keyspace = ident_arg(&args["keyspace"]);
+            let lookup_error =
+                |err: anyhow::Error| match err.downcast_ref::<crate::db::UnknownIdentifier>() {
+                    Some(unknown) => RequestError::InvalidParams(unknown.to_string()),
+                    None => RequestError::Failed(format!("prompt '{name}' failed: {err}")),
+                };
+            let session = self
+                .session()
+                .await
+                .map_err(|err| RequestError::Failed(format!("failed to connect: {err}")))?;
+            let replication = crate::db::keyspace_replication_with(&session, &keyspace)
+                .await
+                .map_err(lookup_error)?;
+            if replication.is_empty() {
+                let unknown = crate::db::unknown_table_with(&session, &keyspace, "").await;
+                return Err(RequestError::InvalidParams(unknown.to_string()));
+            }
+
+            let text = if prompt.name == "explore_keyspace" {
+                const MAX_PROMPT_TABLES: usize = 50;
+                let mut names = crate::db::list_tables_with(&session, &keyspace)
+                    .await
+                    .map_err(lookup_error)?;
+                names.sort();
+                let omitted = names.len().saturating_sub(MAX_PROMPT_TABLES);
+                let mut tables = Vec::new();
+                for table in names.iter().take(MAX_PROMPT_TABLES) {
+                    tables.push(
+                        self.get_schema(&keyspace, table)
+                            .await
+                            .map_err(lookup_error)?,
+                    );
+                }
+                crate::prompts::explore_keyspace(&keyspace, &replication, &tables, omitted)
+            } else {
+                let table = ident_arg(&args["table"]);
+                let schema = self
+                    .get_schema(&keyspace, &table)
+                    .await
+                    .map_err(lookup_error)?;
+                match prompt.name {
+                    "explain_data_model" => {
+                        crate::prompts::explain_data_model(&schema, &replication)
+                    }
+                    "find_entity_rows" => crate::prompts::find_entity_rows(
+                        &schema,
+                        &args["entity_id"],
+                        args.get("column").map(|c| ident_arg(c)).as_deref(),
+                    )
+                    .map_err(RequestError::InvalidParams)?,
+                    _ => crate::prompts::investigate_slow_partition(
+                        &schema,
+                        &replication,
+                        args.get("partition_key").map(String::as_str),
+                    )
+                    .map_err(RequestError::InvalidParams)?,
+                }
+            };
+            Ok(serde_json::json!({
+                "description": prompt.description,
+                "messages": [{
+                    "role": "user",
+                    "content": { "type": "text", "text": text },
+                }],
+            }))
+        }
+
+        /// `resources/read` for `scylla://` URIs; table schemas come from the schema cache.
+        pub(crate) async fn read_resource(
+            &self,
+            uri: &str,
+        ) -> std::result::Result<serde_json::Value, RequestError> {
+            use crate::server::ResourceUri;
+            let parsed = ResourceUri::parse(uri).map_err(RequestError::InvalidParams)?;
+            let text = match &parsed {
+                ResourceUri::TableSchema { keyspace, table } => {
+                    let schema = self
+                        .get_schema(keyspace, table)
+                        .await
+                        .map_err(|err| RequestError::from_schema_error(uri, err))?;
+                    serde_json::to_string_pretty(&schema)
+                        .map_err(|err| RequestError::Failed(err.to_string()))?
+                }
+                ResourceUri::KeyspaceDdl { keyspace } => {
+                    let session = self
+                        .session()
+                        .await
+                        .map_err(|err| RequestError::Failed(format!("failed to connect: {err}")))?;
+                    crate::db::keyspace_ddl_with(&session, keyspace)
+                        .await
+                        .map_err(|err| RequestError::from_schema_error(uri, err))?
+                }
+            };
+            Ok(serde_json::json!({
+                "contents": [{
+                    "uri": parsed.uri(),
+                    "mimeType": parsed.mime_type(),
+                    "text": text,
+                }]
+            }))
+        }
+
+        /// Error output carrying the unknown identifier and suggestions as JSON.
+        fn identifier_error_output(
+            message: String,
+            unknown: &crate::db::UnknownIdentifier,
+        ) -> ToolOutput {
+            let mut payload = match serde_json::to_value(unknown) {
+                Ok(serde_json::Value::Object(map)) => map,
+                _ => serde_json::Map::new(),
+            };
+            payload.insert("error".into(), serde_json::Value::String(message));
+            let json = serde_json::to_string(&payload).unwrap_or_else(|_| "{}".into());
+            ToolOutput::error(json)
+        }
+
+        fn unknown_column_output(
+            message: String,
+            column: &str,
+            schema: &crate::db::DescribeTable,
+            candidates: &[String],
+        ) -> ToolOutput {
+            let unknown = crate::db::UnknownIdentifier {
+                kind: "column",
+                name: column.to_string(),
+                keyspace: Some(schema.keyspace.clone()),
+                table: Some(schema.table.clone()),
+                suggestions: crate::db::suggest_identifiers(
+                    column,
+                    candidates.iter().map(String::as_str),
+                ),
+            };
+            Self::identifier_error_output(message, &unknown)
+        }
+
+        fn schema_error_output(prefix: &str, err: anyhow::Error) -> ToolOutput {
+            let message = format!("{}: {}", prefix, err);
+            match err.downcast_ref::<crate::db::UnknownIdentifier>() {
+                Some(unknown) => Self::identifier_error_output(message, unknown),
+                None => ToolOutput::error(message),
+            }
+        }
+
+        fn column_names(schema: &crate::db::DescribeTable) -> Vec<String> {
+            schema
+                .columns
+                .iter()
+                .map(|c| c.column_name.clone())
+                .collect()
+        }
+
+        fn paging_cursor_binding(
+            keyspace: &str,
+            table: &str,
+            columns: &[String],
+            page_size: i32,
+            filters: Option<&serde_json::Map<String, serde_json::Value>>,
+            order_by: Option<&Vec<(String, String)>>,
+        ) -> Result<PagingCursorBinding> {
+            let filters_json = filters
+                .map(serde_json::to_string)
+                .transpose()
+                .context("failed to encode paging cursor filter binding")?;
+            Ok(PagingCursorBinding {
+                keyspace: keyspace.to_string(),
+                table: table.to_string(),
+                columns: columns.to_vec(),
+                page_size,
+                filters_json,
+                order_by: order_by.cloned(),
+            })
+        }
+
+        async fn store_paging_cursor(
+            &self,
+            binding: PagingCursorBinding,
+            raw_cursor: String,
+        ) -> Result<String> {
+            let mut random = [0_u8; 32];
+            openssl::rand::rand_bytes(&mut random).context("failed to generate cursor token")?;
+            let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(random);
+
+            let mut cursors = self.paging_cursors.write().await;
+            if cursors.len() >= MAX_SERVER_CURSORS {
+                if let Some(oldest) = cursors.keys().next().cloned() {
+                    cursors.remove(&oldest);
+                }
+            }
+            cursors.insert(
+                token.clone(),
+                StoredPagingCursor {
+                    binding,
+                    raw_cursor,
+                },
+            );
+            Ok(token)
+        }
+
+        async fn take_paging_cursor(
+            &self,
+            token: &str,
+            binding: &PagingCursorBinding,
+        ) -> Result<String> {
+            let stored = self
+                .paging_cursors
+                .write()
+                .await
+                .remove(token)
+                .ok_or_else(|| anyhow::anyhow!("unknown or expired paging cursor"))?;
+            if stored.binding != *binding {
+                anyhow::bail!("paging cursor does not match the current query");
+            }
+            Ok(stored.raw_cursor)
+        }
+
+        async fn replace_next_cursor(
+            &self,
+            obj: &mut serde_json::Map<String, serde_json::Value>,
+            binding: &PagingCursorBinding,
+        ) -> Result<()> {
+            if let Some(serde_json::Value::String(raw_cursor)) = obj.get("next_cursor").cloned() {
+                let token = self
+                    .store_paging_cursor(binding.clone(), raw_cursor)
+                    .await?;
+                obj.insert("next_cursor".into(), serde_json::Value::String(token));
+            }
+            Ok(())
+        }
+
+        /// Run a tool until it finishes, times out or `call.cancel` fires; the
+        /// latter two drop the tool future, abandoning any in-flight queries.
+        pub(crate) async fn execute_with(
+            &self,
+            name: &str,
+            arguments: Option<&serde_json::Map<String, serde_json::Value>>,
+            call: CallContext,
+        ) -> ToolOutput {
+            let request = ExecuteRequest {
+                params: ExecuteRequestParams {
+                    name: name.to_string(),
+                    arguments: arguments.cloned(),
+                },
+                progress: call.progress,
+            };
+            // Boxed: the dispatch future is too large for a worker thread's stack.
+            let run = tokio::time::timeout(
+                self.tool_timeout,
+                Box::pin(
+                    self.execute_request(request)
+                        .instrument(self.connection_span()),
+                ),
+            );
+            tokio::select! {
+                biased;
+                _ = call.cancel.cancelled() => {
+                    ToolOutput::error(format!("tool '{}' was cancelled", name))
+                }
+                result = run => match result {
+                    Ok(result) => match result {
+                        Ok(output) => output,
+                        Err(never) => match never {},
+                    },
+                    Err(_) => ToolOutput::error(format!(
+                        "tool '{}' timed out after {} ms",
+                        name,
+                        self.tool_timeout.as_millis()
+                    )),
+                },
+            }
+        }
+
+        /// Each arm runs inside its `tool` span via `instrument`, never an entered
+        /// guard: a guard held across `.await` stays on the worker thread's span
+        /// stack and tags unrelated tasks with this connection. Arms are boxed so
+        /// their futures do not all add to this function's poll frame.
+        async fn execute_request(
+            &self,
+            request: ExecuteRequest,
+        ) -> std::result::Result<ToolOutput, std::convert::Infallible> {
+            let name = request.params.name.clone();
+            match name.as_str() {
+                "list_keyspaces" => {
+                    let span = tracing::info_span!("tool", name = "list_keyspaces");
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_keyspaces failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            let accessible_only = request
+                                .params
+                                .arguments
+                                .as_ref()
+                                .and_then(|m| m.get("accessible_only"))
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false);
+                            match crate::db::list_keyspaces_with(&session).await {
+                                Ok(mut list) => {
+                                    if accessible_only {
+                                        match self.access_summary(&session).await {
+                                            Ok(access) => {
+                                                list.retain(|ks| access.can_select(ks, None))
+                                            }
+                                            Err(err) => {
+                                                let msg = format!("list_keyspaces failed: {}", err);
+                                                return Ok(ToolOutput::error(msg));
+                                            }
+                                        }
+                                    }
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("list_keyspaces failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_tables" => {
+                    // extract keyspace from arguments
+                    let ks =
+                        request.params.arguments.as_ref().and_then(|m| {
+                            m.get("keyspace").and_then(|v| v.as_str()).map(ident_arg)
+                        });
+                    if ks.is_none() {
+                        let msg = "missing required argument 'keyspace'".to_string();
                         return Ok(ToolOutput::error(msg));
                     }
                     let keyspace = ks.unwrap();
-                    let table = tb.unwrap();
-                    let partition = part.unwrap();
-                    let limit = limit.unwrap();
-                    // Validate exact partition key set against schema (no extras, no missing)
+                    let span = tracing::info_span!("tool", name = "list_tables", %keyspace);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_tables failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            let accessible_only = request
+                                .params
+                                .arguments
+                                .as_ref()
+                                .and_then(|m| m.get("accessible_only"))
+                                .and_then(|v| v.as_bool())
+                                .unwrap_or(false);
+                            match crate::db::list_tables_with(&session, &keyspace).await {
+                                Ok(mut list) => {
+                                    if accessible_only {
+                                        match self.access_summary(&session).await {
+                                            Ok(access) => list.retain(|tb| {
+                                                access.can_select(&keyspace, Some(tb))
+                                            }),
+                                            Err(err) => {
+                                                let msg = format!("list_tables failed: {}", err);
+                                                return Ok(ToolOutput::error(msg));
+                                            }
+                                        }
+                                    }
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("list_tables failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "describe_table" => {
+                    // extract keyspace and table from arguments
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() || tb.is_none() {
+                        let msg =
+                            "missing required arguments 'keyspace' and/or 'table'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    match self
+                        .get_schema(&ks.clone().unwrap(), &tb.clone().unwrap())
+                        .await
+                    {
+                        Ok(cols) => {
+                            let json = serde_json::to_string(&cols).unwrap_or_else(|_| "{}".into());
+                            Ok(ToolOutput::text_content(json, None))
+                        }
+                        Err(err) => Ok(Self::schema_error_output("describe_table failed", err)),
+                    }
+                }
+                "sample_rows" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let lm = args.and_then(|m| m.get("limit").and_then(|v| v.as_u64()));
+                    let filters = args
+                        .and_then(|m| m.get("filters"))
+                        .and_then(|v| v.as_object())
+                        .map(ident_keys);
+                    let filters = filters.as_ref();
+                    let Some((keyspace, table, limit_u64)) =
+                        ks.zip(tb).zip(lm).map(|((a, b), c)| (a, b, c))
+                    else {
+                        let msg = "missing required arguments 'keyspace', 'table', or 'limit'"
+                            .to_string();
+                        return Ok(ToolOutput::error(msg));
+                    };
+                    // Validate the table against the live schema and filter keys exist
                     match self.get_schema(&keyspace, &table).await {
                         Ok(schema) => {
-                            let pk: std::collections::HashSet<String> =
-                                schema.partition_keys.iter().cloned().collect();
-                            let provided: std::collections::HashSet<String> =
-                                partition.keys().cloned().collect();
-                            if pk != provided {
-                                let msg = format!(
-                                    "partition keys mismatch: expected {:?}",
-                                    schema.partition_keys
-                                );
-                                return Ok(ToolOutput::error(msg));
+                            let available: std::collections::HashSet<String> = schema
+                                .columns
+                                .iter()
+                                .map(|c| c.column_name.clone())
+                                .collect();
+                            for col in filters.into_iter().flat_map(|f| f.keys()) {
+                                if !available.contains(col) {
+                                    let msg = format!(
+                                        "invalid filter column '{}'; not in table columns",
+                                        col
+                                    );
+                                    return Ok(Self::unknown_column_output(
+                                        msg,
+                                        col,
+                                        &schema,
+                                        &Self::column_names(&schema),
+                                    ));
+                                }
                             }
                         }
                         Err(err) => {
-                            return Ok(ToolOutput::text_content(
-                                format!("schema fetch failed: {}", err),
-                                None,
-                            ));
+                            return Ok(Self::schema_error_output("schema fetch failed", err));
+                        }
+                    }
+                    let limit = (limit_u64 as u32).clamp(1, 500);
+                    let span = tracing::info_span!("tool", name = "sample_rows", %keyspace, %table, limit = limit as i64);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("sample_rows failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::sample_rows_with(
+                                &session, &keyspace, &table, limit, filters,
+                            )
+                            .await
+                            {
+                                Ok(rows) => {
+                                    let json = serde_json::to_string(&rows)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("sample_rows failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "select" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let cols =
+                        args.and_then(|m| m.get("columns").and_then(|v| v.as_array()).cloned());
+                    let lm = args.and_then(|m| m.get("limit").and_then(|v| v.as_u64()));
+                    let filters = args
+                        .and_then(|m| m.get("filters"))
+                        .and_then(|v| v.as_object())
+                        .map(ident_keys);
+                    let filters = filters.as_ref();
+                    let order_by = args
+                        .and_then(|m| m.get("order_by").or_else(|| m.get("orderBy")))
+                        .and_then(|v| v.as_array().cloned());
+                    if ks.is_none() || tb.is_none() || cols.is_none() || lm.is_none() {
+                        let msg =
+                            "missing required arguments 'keyspace', 'table', 'columns', or 'limit'"
+                                .to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let table = tb.unwrap();
+                    let columns: Vec<String> = cols
+                        .unwrap()
+                        .iter()
+                        .filter_map(|v| v.as_str().map(ident_arg))
+                        .collect();
+                    let limit = (lm.unwrap() as u32).clamp(1, 500);
+                    // Validate requested columns and filter keys exist
+                    match self.get_schema(&keyspace, &table).await {
+                        Ok(schema) => {
+                            let available: std::collections::HashSet<String> = schema
+                                .columns
+                                .iter()
+                                .map(|c| c.column_name.clone())
+                                .collect();
+                            for c in columns.iter() {
+                                if !available.contains(c) {
+                                    let msg = format!(
+                                        "invalid column '{}' in select; not in table columns",
+                                        c
+                                    );
+                                    return Ok(Self::unknown_column_output(
+                                        msg,
+                                        c,
+                                        &schema,
+                                        &Self::column_names(&schema),
+                                    ));
+                                }
+                            }
+                            if let Some(f) = &filters {
+                                for col in f.keys() {
+                                    if !available.contains(col) {
+                                        let msg = format!(
+                                            "invalid filter column '{}'; not in table columns",
+                                            col
+                                        );
+                                        return Ok(Self::unknown_column_output(
+                                            msg,
+                                            col,
+                                            &schema,
+                                            &Self::column_names(&schema),
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            return Ok(Self::schema_error_output("schema fetch failed", err));
+                        }
+                    }
+                    let order_tuples: Option<Vec<(String, String)>> = order_by.map(|arr| {
+                        arr.into_iter()
+                            .filter_map(|item| item.as_object().cloned())
+                            .filter_map(|m| {
+                                let col = m.get("column").and_then(|v| v.as_str())?;
+                                let dir =
+                                    m.get("direction").and_then(|v| v.as_str()).unwrap_or("asc");
+                                Some((ident_arg(col), dir.to_string()))
+                            })
+                            .collect()
+                    });
+                    // Enforce order_by only on clustering keys
+                    if let Some(ref ords) = order_tuples {
+                        match self.get_schema(&keyspace, &table).await {
+                            Ok(schema) => {
+                                let allowed: std::collections::HashSet<String> =
+                                    schema.clustering_keys.iter().cloned().collect();
+                                for (col, _) in ords.iter() {
+                                    if !allowed.contains(col) {
+                                        let msg = format!(
+                                            "invalid order_by column '{}'; only clustering keys are allowed: {:?}",
+                                            col, schema.clustering_keys
+                                        );
+                                        return Ok(Self::unknown_column_output(
+                                            msg,
+                                            col,
+                                            &schema,
+                                            &schema.clustering_keys,
+                                        ));
+                                    }
+                                }
+                            }
+                            Err(err) => {
+                                return Ok(Self::schema_error_output("schema fetch failed", err));
+                            }
+                        }
+                    }
+                    let span = tracing::info_span!("tool", name = "select", %keyspace, %table, limit = limit as i64);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("select failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::select_columns_with(
+                                &session,
+                                &keyspace,
+                                &table,
+                                &columns,
+                                limit,
+                                filters,
+                                order_tuples.as_ref(),
+                            )
+                            .await
+                            {
+                                Ok(rows) => {
+                                    let json = serde_json::to_string(&rows)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("select failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "paged_select" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let cols =
+                        args.and_then(|m| m.get("columns").and_then(|v| v.as_array()).cloned());
+                    let page_size = args.and_then(|m| m.get("page_size").and_then(|v| v.as_u64()));
+                    let filters = args
+                        .and_then(|m| m.get("filters"))
+                        .and_then(|v| v.as_object())
+                        .map(ident_keys);
+                    let filters = filters.as_ref();
+                    let order_by = args
+                        .and_then(|m| m.get("order_by").or_else(|| m.get("orderBy")))
+                        .and_then(|v| v.as_array().cloned());
+                    let cursor = args
+                        .and_then(|m| m.get("cursor").and_then(|v| v.as_str()))
+                        .map(|s| s.to_string());
+                    if ks.is_none() || tb.is_none() || cols.is_none() || page_size.is_none() {
+                        let msg = "missing required arguments 'keyspace', 'table', 'columns', or 'page_size'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let table = tb.unwrap();
+                    let page_size = (page_size.unwrap() as i32).clamp(1, 500);
+                    let columns: Vec<String> = cols
+                        .unwrap()
+                        .iter()
+                        .filter_map(|v| v.as_str().map(ident_arg))
+                        .collect();
+                    // Validate columns/filters and enforce order_by on clustering keys
+                    match self.get_schema(&keyspace, &table).await {
+                        Ok(schema) => {
+                            let available: std::collections::HashSet<String> = schema
+                                .columns
+                                .iter()
+                                .map(|c| c.column_name.clone())
+                                .collect();
+                            for c in columns.iter() {
+                                if !available.contains(c) {
+                                    return Ok(Self::unknown_column_output(
+                                        format!("invalid column '{}'", c),
+                                        c,
+                                        &schema,
+                                        &Self::column_names(&schema),
+                                    ));
+                                }
+                            }
+                            if let Some(f) = &filters {
+                                for col in f.keys() {
+                                    if !available.contains(col) {
+                                        return Ok(Self::unknown_column_output(
+                                            format!("invalid filter column '{}'", col),
+                                            col,
+                                            &schema,
+                                            &Self::column_names(&schema),
+                                        ));
+                                    }
+                                }
+                            }
+                            if let Some(arr) = &order_by {
+                                let allowed: std::collections::HashSet<String> =
+                                    schema.clustering_keys.iter().cloned().collect();
+                                for item in arr.iter().filter_map(|v| v.as_object()) {
+                                    let Some(col) = item.get("column").and_then(|v| v.as_str())
+                                    else {
+                                        continue;
+                                    };
+                                    let col = ident_arg(col);
+                                    if !allowed.contains(&col) {
+                                        return Ok(Self::unknown_column_output(
+                                            format!("invalid order_by column '{}'", col),
+                                            &col,
+                                            &schema,
+                                            &schema.clustering_keys,
+                                        ));
+                                    }
+                                }
+                            }
+                        }
+                        Err(err) => {
+                            return Ok(Self::schema_error_output("schema fetch failed", err));
+                        }
+                    }
+                    let order_tuples: Option<Vec<(String, String)>> = order_by.map(|arr| {
+                        arr.into_iter()
+                            .filter_map(|item| item.as_object().cloned())
+                            .filter_map(|m| {
+                                let col = m.get("column").and_then(|v| v.as_str())?;
+                                let dir =
+                                    m.get("direction").and_then(|v| v.as_str()).unwrap_or("asc");
+                                Some((ident_arg(col), dir.to_string()))
+                            })
+                            .collect()
+                    });
+                    let cursor_binding = match Self::paging_cursor_binding(
+                        &keyspace,
+                        &table,
+                        &columns,
+                        page_size,
+                        filters,
+                        order_tuples.as_ref(),
+                    ) {
+                        Ok(binding) => binding,
+                        Err(err) => {
+                            let msg = format!("paged_select failed: {}", err);
+                            return Ok(ToolOutput::error(msg));
+                        }
+                    };
+                    let cursor_state = match cursor.as_deref() {
+                        Some(token) => {
+                            match self.take_paging_cursor(token, &cursor_binding).await {
+                                Ok(raw_cursor) => Some(raw_cursor),
+                                Err(err) => {
+                                    let msg = format!("invalid cursor: {}", err);
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+                    let span = tracing::info_span!("tool", name = "paged_select", %keyspace, %table, page_size);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("paged_select failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::paged_select_with(
+                                &session,
+                                &keyspace,
+                                &table,
+                                &columns,
+                                page_size,
+                                filters,
+                                order_tuples.as_ref(),
+                                cursor_state.as_deref(),
+                            )
+                            .await
+                            {
+                                Ok(mut obj) => {
+                                    if let Err(err) =
+                                        self.replace_next_cursor(&mut obj, &cursor_binding).await
+                                    {
+                                        let msg = format!("paged_select failed: {}", err);
+                                        return Ok(ToolOutput::error(msg));
+                                    }
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => Ok(ToolOutput::text_content(
+                                    format!("paged_select failed: {}", err),
+                                    None,
+                                )),
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "partition_rows" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let part = args
+                        .and_then(|m| m.get("partition"))
+                        .and_then(|v| v.as_object())
+                        .map(ident_keys);
+                    let limit = args
+                        .and_then(|m| m.get("limit").and_then(|v| v.as_u64()))
+                        .map(|n| (n as u32).clamp(1, 500));
+                    if ks.is_none() || tb.is_none() || part.is_none() || limit.is_none() {
+                        let msg =
+                            "missing required arguments 'keyspace','table','partition','limit'"
+                                .to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let table = tb.unwrap();
+                    let partition = part.unwrap();
+                    let limit = limit.unwrap();
+                    // Validate exact partition key set against schema (no extras, no missing)
+                    match self.get_schema(&keyspace, &table).await {
+                        Ok(schema) => {
+                            let pk: std::collections::HashSet<String> =
+                                schema.partition_keys.iter().cloned().collect();
+                            let provided: std::collections::HashSet<String> =
+                                partition.keys().cloned().collect();
+                            if pk != provided {
+                                let msg = format!(
+                                    "partition keys mismatch: expected {:?}",
+                                    schema.partition_keys
+                                );
+                                return Ok(ToolOutput::error(msg));
+                            }
+                        }
+                        Err(err) => {
+                            return Ok(Self::schema_error_output("schema fetch failed", err));
+                        }
+                    }
+                    let span = tracing::info_span!("tool", name = "partition_rows", %keyspace, %table, limit = limit as i64);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("partition_rows failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::partition_rows_with(
+                                &session, &keyspace, &table, &partition, limit,
+                            )
+                            .await
+                            {
+                                Ok(rows) => {
+                                    let json = serde_json::to_string(&rows)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => Ok(ToolOutput::text_content(
+                                    format!("partition_rows failed: {}", err),
+                                    None,
+                                )),
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "cluster_topology" => {
+                    let include_tokens = request
+                        .params
+                        .arguments
+                        .as_ref()
+                        .and_then(|m| m.get("include_tokens").and_then(|v| v.as_bool()))
+                        .unwrap_or(true);
+                    let span = tracing::info_span!("tool", name = "cluster_topology");
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("cluster_topology failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::cluster_topology_with(&session).await {
+                                Ok(mut nodes) => {
+                                    if !include_tokens {
+                                        for node in &mut nodes {
+                                            node.remove("tokens");
+                                        }
+                                    }
+                                    let json = serde_json::to_string(&nodes)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("cluster_topology failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_indexes" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() || tb.is_none() {
+                        let msg =
+                            "missing required arguments 'keyspace' and/or 'table'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let table = tb.unwrap();
+                    let span =
+                        tracing::info_span!("tool", name = "list_indexes", %keyspace, %table);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_indexes failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::list_indexes_with(&session, &keyspace, &table).await {
+                                Ok(list) => {
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("list_indexes failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "keyspace_replication" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() {
+                        let msg = "missing required argument 'keyspace'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let span =
+                        tracing::info_span!("tool", name = "keyspace_replication", %keyspace);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg =
+                                        format!("keyspace_replication failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::keyspace_replication_with(&session, &keyspace).await {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("keyspace_replication failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_views" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() {
+                        let msg = "missing required argument 'keyspace'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let span = tracing::info_span!("tool", name = "list_views", %keyspace);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_views failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::list_views_with(&session, &keyspace).await {
+                                Ok(list) => {
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => Ok(ToolOutput::text_content(
+                                    format!("list_views failed: {}", err),
+                                    None,
+                                )),
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_udts" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() {
+                        let msg = "missing required argument 'keyspace'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let span = tracing::info_span!("tool", name = "list_udts", %keyspace);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_udts failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::list_udts_with(&session, &keyspace).await {
+                                Ok(list) => {
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => Ok(ToolOutput::text_content(
+                                    format!("list_udts failed: {}", err),
+                                    None,
+                                )),
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_functions" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() {
+                        let msg = "missing required argument 'keyspace'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let span = tracing::info_span!("tool", name = "list_functions", %keyspace);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_functions failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::list_functions_with(&session, &keyspace).await {
+                                Ok(list) => {
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => Ok(ToolOutput::text_content(
+                                    format!("list_functions failed: {}", err),
+                                    None,
+                                )),
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_aggregates" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() {
+                        let msg = "missing required argument 'keyspace'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let span = tracing::info_span!("tool", name = "list_aggregates", %keyspace);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_aggregates failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::list_aggregates_with(&session, &keyspace).await {
+                                Ok(list) => {
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => Ok(ToolOutput::text_content(
+                                    format!("list_aggregates failed: {}", err),
+                                    None,
+                                )),
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "size_estimates" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if ks.is_none() || tb.is_none() {
+                        let msg =
+                            "missing required arguments 'keyspace' and/or 'table'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let keyspace = ks.unwrap();
+                    let table = tb.unwrap();
+                    let target = args
+                        .and_then(|m| m.get("node").and_then(|v| v.as_str()))
+                        .map(|node| crate::db::NodeTarget::parse(Some(node)))
+                        .unwrap_or(crate::db::NodeTarget::AllNodes);
+                    let with_summary = args
+                        .and_then(|m| m.get("summary").and_then(|v| v.as_bool()))
+                        .unwrap_or(false);
+                    let span =
+                        tracing::info_span!("tool", name = "size_estimates", %keyspace, %table);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("size_estimates failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            let rows = if target == crate::db::NodeTarget::AllNodes {
+                                crate::db::size_estimates_with(&session, &keyspace, &table).await
+                            } else {
+                                crate::db::size_estimates_with_target(
+                                    &session, &keyspace, &table, &target,
+                                )
+                                .await
+                            };
+                            match rows {
+                                Ok(rows) if with_summary => {
+                                    let obj = serde_json::json!({
+                                        "summary": crate::db::size_estimates_summary(&rows),
+                                        "ranges": rows,
+                                    });
+                                    Ok(ToolOutput::ok(obj.to_string()))
+                                }
+                                Ok(rows) => {
+                                    let json = serde_json::to_string(&rows)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::ok(json))
+                                }
+                                Err(err) => {
+                                    Ok(ToolOutput::error(format!("size_estimates failed: {}", err)))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "table_stats" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let (Some(keyspace), Some(table)) = (ks, tb) else {
+                        let msg =
+                            "missing required arguments 'keyspace' and/or 'table'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    };
+                    if let Err(err) = self.get_schema(&keyspace, &table).await {
+                        return Ok(Self::schema_error_output("schema fetch failed", err));
+                    }
+                    let span = tracing::info_span!("tool", name = "table_stats", %keyspace, %table);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("table_stats failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::table_stats_with(&session, &keyspace, &table).await {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("table_stats failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "search_schema" => {
+                    let args = request.params.arguments.as_ref();
+                    let pattern = args
+                        .and_then(|m| m.get("pattern").and_then(|v| v.as_str()))
+                        .map(|s| s.to_string());
+                    let keyspace = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if pattern.is_none() {
+                        let msg = "missing required argument 'pattern'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let pat = pattern.unwrap();
+                    let mut options = crate::db::SearchSchemaOptions::default();
+                    if let Some(mode) = args.and_then(|m| m.get("mode")).and_then(|v| v.as_str()) {
+                        match crate::db::SearchMode::parse(mode) {
+                            Ok(mode) => options.mode = mode,
+                            Err(err) => return Ok(ToolOutput::error(err.to_string())),
+                        }
+                    }
+                    let string_list = |key: &str| {
+                        args.and_then(|m| m.get(key))
+                            .and_then(|v| v.as_array())
+                            .map(|arr| {
+                                arr.iter()
+                                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                                    .collect::<Vec<_>>()
+                            })
+                    };
+                    if let Some(fields) = string_list("fields").filter(|f| !f.is_empty()) {
+                        options.fields = fields;
+                    }
+                    if let Some(kinds) = string_list("kinds").filter(|k| !k.is_empty()) {
+                        options.kinds = kinds;
+                    }
+                    if let Err(err) = options.validate() {
+                        return Ok(ToolOutput::error(err.to_string()));
+                    }
+                    let span = tracing::info_span!("tool", name = "search_schema", %pat, keyspace = keyspace.as_deref().unwrap_or("<all>"));
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("search_schema failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::search_schema_with_options(
+                                &session,
+                                &pat,
+                                keyspace.as_deref(),
+                                &options,
+                            )
+                            .await
+                            {
+                                Ok(items) => {
+                                    let json = serde_json::to_string(&items)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::ok(json))
+                                }
+                                Err(err) => {
+                                    Ok(ToolOutput::error(format!("search_schema failed: {}", err)))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "lint_schema" => {
+                    let args = request.params.arguments.as_ref();
+                    let keyspace = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let span = tracing::info_span!(
+                        "tool",
+                        name = "lint_schema",
+                        keyspace = keyspace.as_deref().unwrap_or("<all>")
+                    );
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("lint_schema failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::lint_schema_with(
+                                &session,
+                                keyspace.as_deref(),
+                                &request.progress,
+                            )
+                            .await
+                            {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    Ok(Self::schema_error_output("lint_schema failed", err))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "whoami" => {
+                    let span = tracing::info_span!("tool", name = "whoami");
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("whoami failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match self.access_summary(&session).await {
+                                Ok(access) => {
+                                    let mut obj = serde_json::Map::new();
+                                    obj.insert("user".into(), serde_json::json!(access.user));
+                                    obj.insert(
+                                        "auth_keyspace".into(),
+                                        serde_json::json!(access.auth_keyspace),
+                                    );
+                                    // Whether list_keyspaces/list_tables accessible_only filtering applies.
+                                    obj.insert(
+                                        "restricted".into(),
+                                        serde_json::json!(access.restricted),
+                                    );
+                                    obj.insert(
+                                        "superuser".into(),
+                                        serde_json::json!(access.superuser),
+                                    );
+                                    obj.insert("roles".into(), serde_json::json!(access.roles));
+                                    obj.insert(
+                                        "select_access".into(),
+                                        serde_json::Value::Object(access.select_scope()),
+                                    );
+                                    if !access.notes.is_empty() {
+                                        obj.insert("notes".into(), serde_json::json!(access.notes));
+                                    }
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("whoami failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_roles" => {
+                    let span = tracing::info_span!("tool", name = "list_roles");
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_roles failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            let roles = match crate::db::list_roles_with(&session).await {
+                                Ok((_, roles)) => Ok(roles),
+                                Err(_) => crate::db::list_roles_statement_with(&session).await,
+                            };
+                            match roles {
+                                Ok(list) => {
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("list_roles failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "list_permissions" => {
+                    let role = request
+                        .params
+                        .arguments
+                        .as_ref()
+                        .and_then(|m| m.get("role").and_then(|v| v.as_str()))
+                        .map(|s| s.to_string())
+                        .or_else(|| self.configured_user().map(|s| s.to_string()));
+                    let Some(role) = role else {
+                        let msg = "missing required argument 'role' (no SCYLLA_USER configured)"
+                            .to_string();
+                        return Ok(ToolOutput::error(msg));
+                    };
+                    let span = tracing::info_span!("tool", name = "list_permissions", %role);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("list_permissions failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::permissions_for_role_with(&session, &role).await {
+                                Ok(list) => {
+                                    let json = serde_json::to_string(&list)
+                                        .unwrap_or_else(|_| "[]".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("list_permissions failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "large_data" => {
+                    let args = request.params.arguments.as_ref();
+                    let keyspace = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let table = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if table.is_some() && keyspace.is_none() {
+                        let msg = "missing required argument 'keyspace' for 'table'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let kinds: Vec<String> = args
+                        .and_then(|m| m.get("kinds"))
+                        .and_then(|v| v.as_array())
+                        .map(|arr| {
+                            arr.iter()
+                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    if let Some(kind) = kinds
+                        .iter()
+                        .find(|k| !["partition", "row", "cell"].contains(&k.as_str()))
+                    {
+                        let msg =
+                            format!("invalid kind '{}'; expected partition, row or cell", kind);
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let limit = args
+                        .and_then(|m| m.get("limit").and_then(|v| v.as_u64()))
+                        .unwrap_or(20)
+                        .clamp(1, 500) as usize;
+                    let span = tracing::info_span!(
+                        "tool",
+                        name = "large_data",
+                        keyspace = keyspace.as_deref().unwrap_or("<all>")
+                    );
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("large_data failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            let target =
+                                match args.and_then(|m| m.get("node").and_then(|v| v.as_str())) {
+                                    None => crate::db::NodeTarget::AllNodes,
+                                    node => crate::db::NodeTarget::parse(node),
+                                };
+                            match crate::db::large_data_with(
+                                &session,
+                                keyspace.as_deref(),
+                                table.as_deref(),
+                                &kinds,
+                                limit,
+                                &target,
+                            )
+                            .await
+                            {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("large_data failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "replication_report" => {
+                    let keyspace = request
+                        .params
+                        .arguments
+                        .as_ref()
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let span = tracing::info_span!(
+                        "tool",
+                        name = "replication_report",
+                        keyspace = keyspace.as_deref().unwrap_or("<all>")
+                    );
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg =
+                                        format!("replication_report failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::replication_report_with(&session, keyspace.as_deref())
+                                .await
+                            {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("replication_report failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "locate_partition" => {
+                    let args = request.params.arguments.as_ref();
+                    let ks = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let tb = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let part = args
+                        .and_then(|m| m.get("partition"))
+                        .and_then(|v| v.as_object())
+                        .map(ident_keys);
+                    let (Some(keyspace), Some(table), Some(partition)) = (ks, tb, part) else {
+                        let msg =
+                            "missing required arguments 'keyspace','table','partition'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    };
+                    // Partition keys are checked by locate_partition_with; this only
+                    // turns an unknown keyspace or table into suggestions.
+                    if let Err(err) = self.get_schema(&keyspace, &table).await {
+                        return Ok(Self::schema_error_output("schema fetch failed", err));
+                    }
+                    let span =
+                        tracing::info_span!("tool", name = "locate_partition", %keyspace, %table);
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("locate_partition failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::locate_partition_with(
+                                &session, &keyspace, &table, &partition,
+                            )
+                            .await
+                            {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("locate_partition failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "token_ring" => {
+                    let args = request.params.arguments.as_ref();
+                    let keyspace = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let table = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    if table.is_some() && keyspace.is_none() {
+                        let msg = "missing required argument 'keyspace' for 'table'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let include_tablets = args
+                        .and_then(|m| m.get("include_tablets").and_then(|v| v.as_bool()))
+                        .unwrap_or(false);
+                    let span = tracing::info_span!(
+                        "tool",
+                        name = "token_ring",
+                        keyspace = keyspace.as_deref().unwrap_or("<all>")
+                    );
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("token_ring failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::token_ring_with(
+                                &session,
+                                keyspace.as_deref(),
+                                table.as_deref(),
+                                include_tablets,
+                            )
+                            .await
+                            {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("token_ring failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "compaction_status" | "table_sstables" | "node_status" => {
+                    let tool = request.params.name.as_str();
+                    let args = request.params.arguments.as_ref();
+                    let keyspace = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let table = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let node = args.and_then(|m| m.get("node").and_then(|v| v.as_str()));
+                    let client = match &self.rest {
+                        Ok(client) => Arc::clone(client),
+                        Err(reason) => {
+                            return Ok(ToolOutput::error(format!("{tool} unavailable: {reason}")));
+                        }
+                    };
+                    let span = tracing::info_span!("tool", name = tool);
+                    Box::pin(
+                        async {
+                            let result = match tool {
+                                "compaction_status" => {
+                                    crate::rest::compaction_status(
+                                        &client,
+                                        node,
+                                        keyspace.as_deref(),
+                                        table.as_deref(),
+                                    )
+                                    .await
+                                }
+                                "table_sstables" => {
+                                    let (Some(keyspace), Some(table)) = (keyspace, table) else {
+                                        let msg =
+                                            "missing required arguments 'keyspace' and/or 'table'"
+                                                .to_string();
+                                        return Ok(ToolOutput::error(msg));
+                                    };
+                                    crate::rest::table_sstables(&client, node, &keyspace, &table)
+                                        .await
+                                }
+                                _ => crate::rest::node_status(&client, node).await,
+                            };
+                            match result {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    Ok(ToolOutput::error(format!("{tool} failed: {err:#}")))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "node_metrics" => {
+                    let args = request.params.arguments.as_ref();
+                    let Some(prefix) = args
+                        .and_then(|m| m.get("prefix").and_then(|v| v.as_str()))
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|s| s.trim().to_string())
+                    else {
+                        let msg = "missing required argument 'prefix'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    };
+                    let mut labels = std::collections::BTreeMap::new();
+                    if let Some(raw) = args.and_then(|m| m.get("labels")) {
+                        let Some(obj) = raw.as_object() else {
+                            let msg =
+                                "'labels' must be an object of label name to value".to_string();
+                            return Ok(ToolOutput::error(msg));
+                        };
+                        for (key, value) in obj {
+                            let Some(value) = value.as_str() else {
+                                let msg = format!("label '{key}' must be a string");
+                                return Ok(ToolOutput::error(msg));
+                            };
+                            labels.insert(key.clone(), value.to_string());
+                        }
+                    }
+                    let interval_ms = args
+                        .and_then(|m| m.get("interval_ms").and_then(|v| v.as_u64()))
+                        .unwrap_or(2_000)
+                        .min(20_000);
+                    let per_shard = args
+                        .and_then(|m| m.get("per_shard").and_then(|v| v.as_bool()))
+                        .unwrap_or(false);
+                    let limit = args
+                        .and_then(|m| m.get("limit").and_then(|v| v.as_u64()))
+                        .unwrap_or(200)
+                        .clamp(1, 1000) as usize;
+                    let node = args.and_then(|m| m.get("node").and_then(|v| v.as_str()));
+                    let client = match &self.metrics {
+                        Ok(client) => Arc::clone(client),
+                        Err(reason) => {
+                            return Ok(ToolOutput::error(format!(
+                                "node_metrics unavailable: {reason}"
+                            )));
+                        }
+                    };
+                    let span = tracing::info_span!("tool", name = "node_metrics", %prefix);
+                    Box::pin(
+                        async {
+                            let filter = crate::metrics::SeriesFilter { prefix, labels };
+                            match crate::metrics::node_metrics(
+                                &client,
+                                node,
+                                &filter,
+                                std::time::Duration::from_millis(interval_ms),
+                                per_shard,
+                                limit,
+                                &request.progress,
+                            )
+                            .await
+                            {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    Ok(ToolOutput::error(format!("node_metrics failed: {err:#}")))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "node_config" => {
+                    let args = request.params.arguments.as_ref();
+                    let name = args
+                        .and_then(|m| m.get("name").and_then(|v| v.as_str()))
+                        .map(|s| s.to_string());
+                    let sections: Vec<String> = args
+                        .and_then(|m| m.get("sections"))
+                        .and_then(|v| v.as_array())
+                        .map(|arr| {
+                            arr.iter()
+                                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    if let Some(section) = sections
+                        .iter()
+                        .find(|s| !crate::db::NODE_INFO_SECTIONS.contains(&s.as_str()))
+                    {
+                        let msg = format!(
+                            "invalid section '{}'; expected one of {}",
+                            section,
+                            crate::db::NODE_INFO_SECTIONS.join(", ")
+                        );
+                        return Ok(ToolOutput::error(msg));
+                    }
+                    let target = crate::db::NodeTarget::parse(
+                        args.and_then(|m| m.get("node").and_then(|v| v.as_str())),
+                    );
+                    let span = tracing::info_span!("tool", name = "node_config");
+                    Box::pin(
+                        async {
+                            let session = match self.session().await {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    let msg = format!("node_config failed to connect: {err}");
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            };
+                            match crate::db::node_config_with(
+                                &session,
+                                &sections,
+                                name.as_deref(),
+                                &target,
+                            )
+                            .await
+                            {
+                                Ok(obj) => {
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("node_config failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                "cdc_changes" => {
+                    let args = request.params.arguments.as_ref();
+                    let Some(keyspace) = args
+                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
+                        .map(ident_arg)
+                    else {
+                        let msg = "missing required argument 'keyspace'".to_string();
+                        return Ok(ToolOutput::error(msg));
+                    };
+                    let table = args
+                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
+                        .map(ident_arg);
+                    let session = match self.session().await {
+                        Ok(session) => session,
+                        Err(err) => {
+                            let msg = format!("cdc_changes failed to connect: {err}");
+                            return Ok(ToolOutput::error(msg));
+                        }
+                    };
+                    let Some(table) = table else {
+                        let span = tracing::info_span!("tool", name = "cdc_changes", %keyspace);
+                        return Box::pin(
+                            async {
+                                match crate::db::cdc_tables_with(&session, &keyspace).await {
+                                    Ok(list) => {
+                                        let json = serde_json::to_string(&list)
+                                            .unwrap_or_else(|_| "[]".into());
+                                        Ok(ToolOutput::text_content(json, None))
+                                    }
+                                    Err(err) => {
+                                        let msg = format!("cdc_changes failed: {}", err);
+                                        Ok(ToolOutput::error(msg))
+                                    }
+                                }
+                            }
+                            .instrument(span),
+                        )
+                        .await;
+                    };
+                    let timestamp_arg = |key: &str| {
+                        args.and_then(|m| m.get(key))
+                            .map(crate::cdc::parse_timestamp_ms)
+                            .transpose()
+                    };
+                    let until_ms = match timestamp_arg("until") {
+                        Ok(Some(ms)) => ms,
+                        Ok(None) => std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_millis() as i64)
+                            .unwrap_or_default(),
+                        Err(err) => {
+                            return Ok(ToolOutput::error(format!("invalid 'until': {err}")))
+                        }
+                    };
+                    let since_ms = match timestamp_arg("since") {
+                        Ok(Some(ms)) => ms,
+                        Ok(None) => until_ms - 3_600_000,
+                        Err(err) => {
+                            return Ok(ToolOutput::error(format!("invalid 'since': {err}")))
+                        }
+                    };
+                    let page_size = args
+                        .and_then(|m| m.get("page_size").and_then(|v| v.as_u64()))
+                        .unwrap_or(100)
+                        .clamp(1, 500) as i32;
+                    let cursor = args
+                        .and_then(|m| m.get("cursor").and_then(|v| v.as_str()))
+                        .map(|s| s.to_string());
+                    let cursor_binding = PagingCursorBinding {
+                        keyspace: keyspace.clone(),
+                        table: table.clone(),
+                        columns: vec!["cdc_changes".into()],
+                        page_size,
+                        filters_json: None,
+                        order_by: None,
+                    };
+                    let cursor_state = match cursor.as_deref() {
+                        Some(token) => {
+                            match self.take_paging_cursor(token, &cursor_binding).await {
+                                Ok(raw_cursor) => Some(raw_cursor),
+                                Err(err) => {
+                                    let msg = format!("invalid cursor: {}", err);
+                                    return Ok(ToolOutput::error(msg));
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+                    let span = tracing::info_span!("tool", name = "cdc_changes", %keyspace, %table, page_size);
+                    Box::pin(
+                        async {
+                            match crate::db::cdc_changes_with(
+                                &session,
+                                &keyspace,
+                                &table,
+                                since_ms,
+                                until_ms,
+                                page_size,
+                                cursor_state.as_deref(),
+                                &request.progress,
+                            )
+                            .await
+                            {
+                                Ok(mut obj) => {
+                                    if let Err(err) =
+                                        self.replace_next_cursor(&mut obj, &cursor_binding).await
+                                    {
+                                        let msg = format!("cdc_changes failed: {}", err);
+                                        return Ok(ToolOutput::error(msg));
+                                    }
+                                    let json =
+                                        serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
+                                    Ok(ToolOutput::text_content(json, None))
+                                }
+                                Err(err) => {
+                                    let msg = format!("cdc_changes failed: {}", err);
+                                    Ok(ToolOutput::error(msg))
+                                }
+                            }
+                        }
+                        .instrument(span),
+                    )
+                    .await
+                }
+                _ => {
+                    let msg = format!("tool '{}' is not yet implemented (read-only phase)", name);
+                    Ok(ToolOutput::error(msg))
+                }
+            }
+        }
+    }
+
+    /// Accept exact or CQL double-quoted identifiers from clients and return the exact name.
+    fn ident_arg(value: &str) -> String {
+        crate::db::CqlIdent::parse(value)
+            .map(|ident| ident.name().to_string())
+            .unwrap_or_else(|_| value.to_string())
+    }
+
+    fn ident_keys(
+        map: &serde_json::Map<String, serde_json::Value>,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        map.iter().map(|(k, v)| (ident_arg(k), v.clone())).collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            changed_resource_uris, completion_result, ident_arg, ident_keys, CallContext,
+            SessionConfig, SessionState, ToolExecutor,
+        };
+        use std::sync::Arc;
+
+        fn test_executor() -> ToolExecutor {
+            ToolExecutor::new(Arc::new(SessionState::new(SessionConfig {
+                uri: "127.0.0.1:9042".to_string(),
+                credentials: None,
+                ssl: None,
+                connect_timeout: std::time::Duration::from_millis(1),
+            })))
+        }
+
+        #[test]
+        fn structured_content_only_for_successful_object_results() {
+            let report = r#"{"summary":{"error":0},"findings":[]}"#;
+            let ok = super::ToolOutput::ok(report.to_string());
+            assert_eq!(
+                ok.structured_content("lint_schema"),
+                Some(serde_json::json!({"summary": {"error": 0}, "findings": []}))
+            );
+            // No output schema, so the result stays text only.
+            assert_eq!(ok.structured_content("list_keyspaces"), None);
+            let failed = super::ToolOutput::error("lint_schema failed: timeout".to_string());
+            assert_eq!(failed.structured_content("lint_schema"), None);
+        }
+
+        #[tokio::test]
+        async fn cancelling_a_call_abandons_the_pending_connection() {
+            // A peer that accepts and never answers keeps the driver handshake pending.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let uri = listener.local_addr().unwrap().to_string();
+            tokio::spawn(async move {
+                let mut held = Vec::new();
+                while let Ok((socket, _)) = listener.accept().await {
+                    held.push(socket);
+                }
+            });
+            let executor = ToolExecutor::new(Arc::new(SessionState::new(SessionConfig {
+                uri,
+                credentials: None,
+                ssl: None,
+                connect_timeout: std::time::Duration::from_secs(30),
+            })));
+            let call = CallContext::default();
+            let cancel = call.cancel.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                cancel.cancel();
+            });
+            let started = std::time::Instant::now();
+            let output = executor.execute_with("list_keyspaces", None, call).await;
+            assert!(output.is_error);
+            assert_eq!(output.text, "tool 'list_keyspaces' was cancelled");
+            assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        }
+
+        /// Inner SDK transport fed by the test, writing responses to `output`.
+        struct ScriptedTransport {
+            messages: std::sync::Mutex<
+                Option<tokio::sync::mpsc::UnboundedReceiver<super::ClientMessage>>,
+            >,
+            output: std::sync::Mutex<Option<tokio::io::DuplexStream>>,
+        }
+
+        #[async_trait::async_trait]
+        impl rust_mcp_sdk::Transport<super::ClientMessage, super::MessageFromServer> for ScriptedTransport {
+            async fn start(
+                &self,
+            ) -> rust_mcp_transport::error::TransportResult<(
+                std::pin::Pin<Box<dyn futures::Stream<Item = super::ClientMessage> + Send>>,
+                rust_mcp_sdk::MessageDispatcher<super::ClientMessage>,
+                rust_mcp_sdk::IOStream,
+            )>
+            where
+                rust_mcp_sdk::MessageDispatcher<super::ClientMessage>:
+                    rust_mcp_sdk::MCPDispatch<super::ClientMessage, super::MessageFromServer>,
+            {
+                let messages = self.messages.lock().unwrap().take().unwrap();
+                let messages = futures::stream::unfold(messages, |mut messages| async move {
+                    messages.recv().await.map(|message| (message, messages))
+                });
+                let output = self.output.lock().unwrap().take().unwrap();
+                let sender = rust_mcp_sdk::MessageDispatcher::new(
+                    Default::default(),
+                    tokio::sync::Mutex::new(Box::pin(output)),
+                    Default::default(),
+                    60_000,
+                );
+                let stderr = rust_mcp_sdk::IOStream::Writable(Box::pin(tokio::io::sink()));
+                Ok((Box::pin(messages), sender, stderr))
+            }
+
+            async fn shut_down(&self) -> rust_mcp_transport::error::TransportResult<()> {
+                Ok(())
+            }
+
+            async fn is_shut_down(&self) -> bool {
+                false
+            }
+        }
+
+        #[tokio::test]
+        async fn sdk_transport_cancels_the_named_tool_call() {
+            use rust_mcp_sdk::MCPServer;
+            use tokio::io::AsyncBufReadExt;
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let uri = listener.local_addr().unwrap().to_string();
+            tokio::spawn(async move {
+                let mut held = Vec::new();
+                while let Ok((socket, _)) = listener.accept().await {
+                    held.push(socket);
+                }
+            });
+            let (client, messages) = tokio::sync::mpsc::unbounded_channel();
+            let (output, responses) = tokio::io::duplex(64 * 1024);
+            let in_flight = Arc::new(super::SdkInFlight::default());
+            let transport = super::CancellableTransport {
+                inner: ScriptedTransport {
+                    messages: std::sync::Mutex::new(Some(messages)),
+                    output: std::sync::Mutex::new(Some(output)),
+                },
+                in_flight: Arc::clone(&in_flight),
+            };
+            let handler = super::SdkHandler {
+                executor: ToolExecutor::for_uri(uri, std::time::Duration::from_secs(30)),
+                in_flight,
+            };
+            let server = rust_mcp_sdk::mcp_server::server_runtime_core::create_server(
+                super::sdk_server_details(),
+                transport,
+                handler,
+            );
+            let send = move |message: serde_json::Value| {
+                client
+                    .send(serde_json::from_value(message).unwrap())
+                    .unwrap();
+            };
+            let started = std::time::Instant::now();
+            // Dropping `send` closes the client stream, which ends the runtime.
+            let script = async move {
+                send(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": 7,
+                    "method": "tools/call",
+                    "params": { "name": "list_keyspaces", "arguments": {} },
+                }));
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                send(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/cancelled",
+                    "params": { "requestId": 7 },
+                }));
+                drop(send);
+            };
+            let (served, ()) = tokio::join!(server.start(), script);
+            served.unwrap();
+            assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+            let mut line = String::new();
+            tokio::io::BufReader::new(responses)
+                .read_line(&mut line)
+                .await
+                .unwrap();
+            let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(response["id"], 7);
+            assert_eq!(response["result"]["isError"], true);
+            assert_eq!(
+                response["result"]["content"][0]["text"],
+                "tool 'list_keyspaces' was cancelled"
+            );
+        }
+
+        #[test]
+        fn schema_changes_map_to_table_and_keyspace_resources() {
+            let mut changes = crate::db::SchemaChanges::default();
+            changes.tables.insert(("app".into(), "users".into()));
+            changes.keyspaces.insert("app".into());
+            assert_eq!(
+                changed_resource_uris(&changes),
+                ["scylla://app/users/schema", "scylla://app/ddl"]
+            );
+        }
+
+        #[test]
+        fn completions_filter_by_prefix_and_cap_values() {
+            let names = vec!["Orders".to_string(), "order_items".into(), "users".into()];
+            let result = completion_result(names, "ord");
+            assert_eq!(
+                result["completion"]["values"],
+                serde_json::json!(["Orders", "order_items"])
+            );
+            assert_eq!(result["completion"]["hasMore"], false);
+
+            let many: Vec<String> = (0..150).map(|i| format!("t{i:03}")).collect();
+            let result = completion_result(many, "");
+            assert_eq!(
+                result["completion"]["values"].as_array().unwrap().len(),
+                100
+            );
+            assert_eq!(result["completion"]["total"], 150);
+            assert_eq!(result["completion"]["hasMore"], true);
+        }
+
+        #[tokio::test]
+        async fn completions_need_a_known_reference_and_context() {
+            let executor = test_executor();
+            let context = std::collections::HashMap::new();
+            let err = executor
+                .complete_argument(
+                    &serde_json::json!({ "type": "ref/tool" }),
+                    "keyspace",
+                    "",
+                    &context,
+                )
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), -32602);
+            let prompt = serde_json::json!({ "type": "ref/prompt", "name": "explain_data_model" });
+            let result = executor
+                .complete_argument(&prompt, "table", "us", &context)
+                .await
+                .expect("table without a keyspace completes to nothing");
+            assert_eq!(result["completion"]["values"], serde_json::json!([]));
+        }
+
+        #[tokio::test]
+        async fn prompt_arguments_are_validated_before_connecting() {
+            let executor = test_executor();
+            let err = executor.get_prompt("nope", None).await.unwrap_err();
+            assert_eq!(err.message(), "unknown prompt 'nope'");
+            let args = serde_json::json!({ "keyspace": "app", "table": 7 });
+            let err = executor
+                .get_prompt("explain_data_model", args.as_object())
+                .await
+                .unwrap_err();
+            assert_eq!(err.message(), "prompt argument 'table' must be a string");
+            let args = serde_json::json!({ "keyspace": "app", "table": "orders" });
+            let err = executor
+                .get_prompt("find_entity_rows", args.as_object())
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), -32602);
+            assert!(err.message().contains("'entity_id'"));
+        }
+
+        #[tokio::test]
+        async fn resource_reads_reject_unknown_uris_before_connecting() {
+            let executor = test_executor();
+            let err = executor
+                .read_resource("file:///etc/passwd")
+                .await
+                .expect_err("non-scylla URIs are rejected");
+            assert_eq!(err.code(), -32602);
+            let err = executor
+                .read_resource("scylla://app/users/rows")
+                .await
+                .expect_err("unknown resource kinds are rejected");
+            assert!(err.message().contains("scylla://{keyspace}/ddl"));
+            let err = executor
+                .list_resources(Some("not-a-number"))
+                .await
+                .expect_err("cursor must be an offset");
+            assert_eq!(err.code(), -32602);
+        }
+
+        #[tokio::test]
+        async fn paging_cursor_tokens_are_opaque_one_time_and_query_bound() {
+            let executor = test_executor();
+            let columns = vec!["id".to_string()];
+            let binding =
+                ToolExecutor::paging_cursor_binding("ks", "tbl", &columns, 100, None, None)
+                    .expect("binding should encode");
+            let token = executor
+                .store_paging_cursor(binding.clone(), "raw-scylla-state".to_string())
+                .await
+                .expect("token should store");
+
+            assert_ne!(token, "raw-scylla-state");
+
+            let wrong_binding =
+                ToolExecutor::paging_cursor_binding("ks", "tbl", &columns, 50, None, None)
+                    .expect("binding should encode");
+            let err = executor
+                .take_paging_cursor(&token, &wrong_binding)
+                .await
+                .expect_err("cursor should be bound to page size and query shape");
+            assert!(err.to_string().contains("does not match"));
+
+            let token = executor
+                .store_paging_cursor(binding.clone(), "raw-scylla-state".to_string())
+                .await
+                .expect("token should store");
+            let raw = executor
+                .take_paging_cursor(&token, &binding)
+                .await
+                .expect("matching binding should resolve cursor");
+            assert_eq!(raw, "raw-scylla-state");
+
+            let replay = executor
+                .take_paging_cursor(&token, &binding)
+                .await
+                .expect_err("cursor tokens should be one-time use");
+            assert!(replay.to_string().contains("unknown or expired"));
+        }
+
+        #[test]
+        fn client_identifiers_are_unquoted_to_exact_schema_names() {
+            assert_eq!(ident_arg("\"UserEvents\""), "UserEvents");
+            assert_eq!(ident_arg("UserEvents"), "UserEvents");
+            assert_eq!(ident_arg("\"a\"\"b\""), "a\"b");
+            let filters = serde_json::json!({ "\"Email\"": "x", "id": 1 });
+            let keys: Vec<String> = ident_keys(filters.as_object().expect("filters"))
+                .keys()
+                .cloned()
+                .collect();
+            assert_eq!(keys, ["Email", "id"]);
+        }
+
+        #[test]
+        fn unknown_identifiers_produce_structured_error_payloads() {
+            let schema = crate::db::DescribeTable {
+                keyspace: "app".into(),
+                table: "users".into(),
+                partition_keys: vec!["id".into()],
+                clustering_keys: Vec::new(),
+                columns: Vec::new(),
+            };
+            let output = ToolExecutor::unknown_column_output(
+                "invalid column 'emial' in select; not in table columns".into(),
+                "emial",
+                &schema,
+                &["id".into(), "email".into()],
+            );
+            assert!(output.is_error);
+            let payload: serde_json::Value =
+                serde_json::from_str(&output.text).expect("payload should be JSON");
+            assert_eq!(payload["kind"], "column");
+            assert_eq!(payload["table"], "users");
+            assert_eq!(payload["suggestions"], serde_json::json!(["email"]));
+            assert!(payload["error"]
+                .as_str()
+                .unwrap_or_default()
+                .starts_with("invalid column 'emial'"));
+
+            let unknown = crate::db::UnknownIdentifier {
+                kind: "keyspace",
+                name: "ap".into(),
+                keyspace: None,
+                table: None,
+                suggestions: vec!["app".into()],
+            };
+            let output = ToolExecutor::schema_error_output(
+                "describe_table failed",
+                anyhow::Error::new(unknown),
+            );
+            assert!(output.is_error);
+            let payload: serde_json::Value =
+                serde_json::from_str(&output.text).expect("payload should be JSON");
+            assert_eq!(payload["suggestions"], serde_json::json!(["app"]));
+            assert_eq!(
+                payload["error"],
+                "describe_table failed: unknown keyspace 'ap'; did you mean 'app'?"
+            );
+
+            let output = ToolExecutor::schema_error_output(
+                "schema fetch failed",
+                anyhow::anyhow!("connection refused"),
+            );
+            assert_eq!(output.text, "schema fetch failed: connection refused");
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct SessionConfig {
+        uri: String,
+        credentials: Option<(String, String)>,
+        ssl: Option<SslConfig>,
+        connect_timeout: std::time::Duration,
+    }
+
+    #[derive(Clone, Debug)]
+    struct SslConfig {
+        ca_bundle: Option<String>,
+        insecure: bool,
+    }
+
+    impl SessionConfig {
+        fn from_env() -> Self {
+            let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+            let credentials = match (std::env::var("SCYLLA_USER"), std::env::var("SCYLLA_PASS")) {
+                (Ok(user), Ok(pass)) => Some((user, pass)),
+                _ => None,
+            };
+            let ssl_enabled = std::env::var("SCYLLA_SSL")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+            let ssl = if ssl_enabled {
+                let ca_bundle = std::env::var("SCYLLA_CA_BUNDLE").ok();
+                let insecure = std::env::var("SCYLLA_SSL_INSECURE")
+                    .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                    .unwrap_or(false);
+                Some(SslConfig {
+                    ca_bundle,
+                    insecure,
+                })
+            } else {
+                None
+            };
+            Self {
+                uri,
+                credentials,
+                ssl,
+                connect_timeout: timeout_from_env("SCYLLA_CONNECT_TIMEOUT_MS", 10_000),
+            }
+        }
+    }
+
+    struct SessionState {
+        config: SessionConfig,
+        cell: OnceCell<Arc<ScyllaSession>>,
+    }
+
+    type ScyllaSession = scylla::Session;
+
+    impl SessionState {
+        fn new(config: SessionConfig) -> Self {
+            Self {
+                config,
+                cell: OnceCell::new(),
+            }
+        }
+
+        /// The session if one has already been established.
+        fn connected(&self) -> Option<Arc<ScyllaSession>> {
+            self.cell.get().cloned()
+        }
+
+        async fn session(&self) -> Result<Arc<ScyllaSession>> {
+            let session = self
+                .cell
+                .get_or_try_init(|| async {
+                    build_session(&self.config)
+                        .await
+                        .map(Arc::new)
+                        .with_context(|| "unable to initialize Scylla session")
+                })
+                .await?;
+            Ok(Arc::clone(session))
+        }
+    }
+
+    async fn build_session(config: &SessionConfig) -> Result<ScyllaSession> {
+        let mut builder = scylla::SessionBuilder::new();
+        builder = builder.known_node(config.uri.clone());
+        if let Some((ref user, ref pass)) = config.credentials {
+            builder = builder.user(user.clone(), pass.clone());
+        }
+        if let Some(ssl) = &config.ssl {
+            use openssl::ssl::{SslContext, SslMethod, SslVerifyMode};
+            let mut ctx =
+                SslContext::builder(SslMethod::tls()).context("failed to build SSL context")?;
+            if let Some(path) = &ssl.ca_bundle {
+                ctx.set_ca_file(path)
+                    .context("failed to load SCYLLA_CA_BUNDLE")?;
+            }
+            ctx.set_verify(if ssl.insecure {
+                SslVerifyMode::NONE
+            } else {
+                SslVerifyMode::PEER
+            });
+            builder = builder.ssl_context(Some(ctx.build()));
+        }
+        tokio::time::timeout(config.connect_timeout, builder.build())
+            .await
+            .with_context(|| {
+                format!(
+                    "timed out connecting to SCYLLA_URI after {} ms",
+                    config.connect_timeout.as_millis()
+                )
+            })?
+            .context("failed to connect to SCYLLA_URI")
+    }
+
+    fn bool_from_env(name: &str, default: bool) -> bool {
+        std::env::var(name)
+            .map(|value| matches!(value.as_str(), "1" | "true" | "TRUE" | "yes" | "on"))
+            .unwrap_or(default)
+    }
+
+    fn timeout_from_env(name: &str, default_ms: u64) -> std::time::Duration {
+        let millis = std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(default_ms);
+        std::time::Duration::from_millis(millis)
+    }
+
+    /// `tools/list` result for the rust-mcp-sdk transport. Its 2024-11-05 `Tool`
+    /// has no title, annotations or outputSchema, so the full definitions travel
+    /// as extra result fields, exactly as the other transports list them.
+    pub fn sdk_list_tools_result() -> rust_mcp_schema::Result {
+        let tools = crate::server::list_tools()
+            .iter()
+            .map(crate::server::tool_definition)
+            .collect();
+        let mut extra = serde_json::Map::new();
+        extra.insert("tools".to_string(), serde_json::Value::Array(tools));
+        rust_mcp_schema::Result {
+            meta: None,
+            extra: Some(extra),
+        }
+    }
+
+    /// `tools/call` result for the rust-mcp-sdk transport, carrying
+    /// `structuredContent` for tools that declare an output schema.
+    pub fn sdk_call_tool_result(name: &str, output: ToolOutput) -> rust_mcp_schema::Result {
+        let structured = output.structured_content(name);
+        let mut extra = serde_json::Map::new();
+        extra.insert(
+            "content".to_string(),
+            serde_json::json!([{ "type": "text", "text": output.text }]),
+        );
+        extra.insert("isError".to_string(), output.is_error.into());
+        if let Some(structured) = structured {
+            extra.insert("structuredContent".to_string(), structured);
+        }
+        rust_mcp_schema::Result {
+            meta: None,
+            extra: Some(extra),
+        }
+    }
+
+    /// Requests read by `CancellableTransport` that the SDK runtime has not yet
+    /// finished, with their cancel tokens keyed by JSON-encoded id.
+    #[derive(Default)]
+    struct SdkInFlight {
+        calls: std::sync::Mutex<SdkCalls>,
+    }
+
+    #[derive(Default)]
+    struct SdkCalls {
+        /// Ids not yet handed to the runtime, in arrival order.
+        queued: std::collections::VecDeque<String>,
+        cancels: StdHashMap<String, tokio_util::sync::CancellationToken>,
+    }
+
+    impl SdkInFlight {
+        fn key(id: &rust_mcp_schema::RequestId) -> String {
+            serde_json::to_string(id).unwrap_or_default()
+        }
+
+        fn arrived(&self, id: &rust_mcp_schema::RequestId) {
+            let key = Self::key(id);
+            let mut calls = self.calls.lock().unwrap();
+            calls.queued.push_back(key.clone());
+            calls.cancels.insert(key, Default::default());
+        }
+
+        /// The runtime handles one request at a time in arrival order, so the
+        /// request it is starting is the oldest queued one.
+        fn start_next(&self) -> Option<(String, tokio_util::sync::CancellationToken)> {
+            let mut calls = self.calls.lock().unwrap();
+            let key = calls.queued.pop_front()?;
+            let cancel = calls.cancels.get(&key).cloned().unwrap_or_default();
+            Some((key, cancel))
+        }
+
+        fn finished(&self, key: &str) {
+            self.calls.lock().unwrap().cancels.remove(key);
+        }
+
+        fn cancel(&self, id: &rust_mcp_schema::RequestId) {
+            if let Some(cancel) = self.calls.lock().unwrap().cancels.get(&Self::key(id)) {
+                cancel.cancel();
+            }
+        }
+    }
+
+    /// The SDK runtime awaits each request before reading the next message, so
+    /// a `notifications/cancelled` would only arrive after the call it names.
+    /// This transport reads ahead on its own task, registers every request with
+    /// `SdkInFlight` and applies cancellations there, forwarding all messages
+    /// to the runtime in order.
+    struct CancellableTransport<T> {
+        inner: T,
+        in_flight: Arc<SdkInFlight>,
+    }
+
+    #[async_trait::async_trait]
+    impl<T> rust_mcp_sdk::Transport<ClientMessage, MessageFromServer> for CancellableTransport<T>
+    where
+        T: rust_mcp_sdk::Transport<ClientMessage, MessageFromServer>,
+    {
+        async fn start(
+            &self,
+        ) -> rust_mcp_transport::error::TransportResult<(
+            std::pin::Pin<Box<dyn futures::Stream<Item = ClientMessage> + Send>>,
+            rust_mcp_sdk::MessageDispatcher<ClientMessage>,
+            rust_mcp_sdk::IOStream,
+        )>
+        where
+            rust_mcp_sdk::MessageDispatcher<ClientMessage>:
+                rust_mcp_sdk::MCPDispatch<ClientMessage, MessageFromServer>,
+        {
+            use futures::StreamExt;
+            use rust_mcp_schema::schema_utils::NotificationFromClient;
+            use rust_mcp_schema::ClientNotification;
+
+            let (mut messages, sender, error_io) = self.inner.start().await?;
+            let (forward, received) = tokio::sync::mpsc::unbounded_channel();
+            let in_flight = Arc::clone(&self.in_flight);
+            tokio::spawn(async move {
+                while let Some(message) = messages.next().await {
+                    match &message {
+                        ClientMessage::Request(request) => in_flight.arrived(&request.id),
+                        ClientMessage::Notification(notification) => {
+                            if let NotificationFromClient::ClientNotification(
+                                ClientNotification::CancelledNotification(cancelled),
+                            ) = &notification.notification
+                            {
+                                debug!(id = ?cancelled.params.request_id, "client cancelled request");
+                                in_flight.cancel(&cancelled.params.request_id);
+                            }
+                        }
+                        _ => {}
+                    }
+                    if forward.send(message).is_err() {
+                        break;
+                    }
+                }
+            });
+            let received = futures::stream::unfold(received, |mut received| async move {
+                received.recv().await.map(|message| (message, received))
+            });
+            Ok((Box::pin(received), sender, error_io))
+        }
+
+        async fn shut_down(&self) -> rust_mcp_transport::error::TransportResult<()> {
+            self.inner.shut_down().await
+        }
+
+        async fn is_shut_down(&self) -> bool {
+            self.inner.is_shut_down().await
+        }
+    }
+
+    /// rust-mcp-sdk handler. The core handler lets tools/list and tools/call
+    /// return raw results; the typed `ServerHandler` would force the 2024-11-05
+    /// `ListToolsResult` shape.
+    struct SdkHandler {
+        executor: ToolExecutor,
+        in_flight: Arc<SdkInFlight>,
+    }
+
+    impl SdkHandler {
+        async fn respond(
+            &self,
+            request: rust_mcp_schema::schema_utils::RequestFromClient,
+            runtime: &dyn rust_mcp_sdk::MCPServer,
+            cancel: tokio_util::sync::CancellationToken,
+        ) -> std::result::Result<
+            rust_mcp_schema::schema_utils::ResultFromServer,
+            rust_mcp_schema::RpcError,
+        > {
+            use rust_mcp_schema::schema_utils::{CallToolError, RequestFromClient};
+            use rust_mcp_schema::{CallToolResult, ClientRequest, RpcError};
+
+            let method = request.method().to_string();
+            let RequestFromClient::ClientRequest(request) = request else {
+                return Err(RpcError::method_not_found()
+                    .with_message(format!("No handler is implemented for '{method}'.")));
+            };
+            match request {
+                ClientRequest::InitializeRequest(_) => {
+                    Ok(runtime.get_server_info().to_owned().into())
+                }
+                ClientRequest::PingRequest(_) => Ok(rust_mcp_schema::Result::default().into()),
+                ClientRequest::ListToolsRequest(_) => {
+                    runtime.assert_server_request_capabilities(&method)?;
+                    Ok(sdk_list_tools_result().into())
+                }
+                ClientRequest::CallToolRequest(request) => {
+                    if let Err(err) = runtime.assert_server_request_capabilities(&method) {
+                        return Ok(CallToolResult::with_error(CallToolError::new(err)).into());
+                    }
+                    let name = request.params.name.as_str();
+                    let call = CallContext {
+                        cancel,
+                        ..Default::default()
+                    };
+                    let output = self
+                        .executor
+                        .execute_with(name, request.params.arguments.as_ref(), call)
+                        .await;
+                    Ok(sdk_call_tool_result(name, output).into())
+                }
+                _ => Err(RpcError::method_not_found()
+                    .with_message(format!("No handler is implemented for '{method}'."))),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl rust_mcp_sdk::mcp_server::ServerHandlerCore for SdkHandler {
+        async fn handle_request(
+            &self,
+            request: rust_mcp_schema::schema_utils::RequestFromClient,
+            runtime: &dyn rust_mcp_sdk::MCPServer,
+        ) -> std::result::Result<
+            rust_mcp_schema::schema_utils::ResultFromServer,
+            rust_mcp_schema::RpcError,
+        > {
+            let started = self.in_flight.start_next();
+            let cancel = started
+                .as_ref()
+                .map(|(_, cancel)| cancel.clone())
+                .unwrap_or_default();
+            let result = self.respond(request, runtime, cancel).await;
+            if let Some((key, _)) = started {
+                self.in_flight.finished(&key);
+            }
+            result
+        }
+
+        async fn handle_notification(
+            &self,
+            _notification: rust_mcp_schema::schema_utils::NotificationFromClient,
+            _runtime: &dyn rust_mcp_sdk::MCPServer,
+        ) -> std::result::Result<(), rust_mcp_schema::RpcError> {
+            // Cancellations are applied by `CancellableTransport` as they arrive.
+            Ok(())
+        }
+
+        async fn handle_error(
+            &self,
+            _error: rust_mcp_schema::RpcError,
+            _runtime: &dyn rust_mcp_sdk::MCPServer,
+        ) -> std::result::Result<(), rust_mcp_schema::RpcError> {
+            Ok(())
+        }
+    }
+}
+
+pub mod rmcp_server {
+    use crate::db::Progress;
+    use crate::logging::ClientLogs;
+    use crate::mcp::{CallContext, RequestError, ToolExecutor};
+    use crate::server;
+    use anyhow::Result;
+    use rmcp::{
+        model::*, service::RequestContext, transport::stdio, Peer, RoleServer, ServerHandler,
+        ServiceExt,
+    };
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tracing::{debug, info, Instrument};
+
+    #[derive(Clone)]
+    pub(crate) struct BridgeHandler {
+        executor: Arc<ToolExecutor>,
+        schema_watcher_started: Arc<AtomicBool>,
+        /// Created by the first `logging/setLevel` of this connection.
+        client_logs: Arc<std::sync::Mutex<Option<ClientLogs>>>,
+    }
+
+    impl BridgeHandler {
+        fn new() -> Self {
+            let executor = ToolExecutor::from_env();
+            executor.warmup_connection();
+            Self::with_executor(executor)
+        }
+
+        pub(crate) fn with_executor(executor: ToolExecutor) -> Self {
+            Self {
+                executor: Arc::new(executor),
+                schema_watcher_started: Arc::new(AtomicBool::new(false)),
+                client_logs: Arc::new(std::sync::Mutex::new(None)),
+            }
+        }
+
+        /// Poll for schema changes and notify the client until it goes away.
+        fn start_schema_watcher(&self, peer: Peer<RoleServer>) {
+            if self.schema_watcher_started.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let executor = Arc::clone(&self.executor);
+            let span = executor.connection_span();
+            tokio::spawn(
+                async move {
+                    loop {
+                        tokio::time::sleep(executor.schema_poll_interval()).await;
+                        if peer.is_transport_closed() {
+                            return;
+                        }
+                        let uris = match executor.poll_schema_changes().await {
+                            Ok(uris) => uris,
+                            Err(err) => {
+                                debug!("schema poll failed: {err}");
+                                continue;
+                            }
+                        };
+                        for uri in uris {
+                            let param = ResourceUpdatedNotificationParam::new(uri);
+                            if peer.notify_resource_updated(param).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+                .instrument(span),
+            );
+        }
+    }
+
+    impl ServerHandler for BridgeHandler {
+        fn get_info(&self) -> ServerInfo {
+            // Enable tools; other capabilities can be enabled later.
+            let mut info = ServerInfo::default();
+            info.instructions = Some("Read-only ScyllaDB access for AI agents".into());
+            info.capabilities = ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_prompts()
+                .enable_completions()
+                .enable_logging()
+                .build();
+            info
+        }
+
+        async fn list_resources(
+            &self,
+            request: Option<PaginatedRequestParams>,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<ListResourcesResult, rmcp::ErrorData> {
+            let cursor = request.and_then(|r| r.cursor);
+            let result = self
+                .executor
+                .list_resources(cursor.as_deref())
+                .await
+                .map_err(request_error)?;
+            from_json(result)
+        }
+
+        async fn list_resource_templates(
+            &self,
+            _request: Option<PaginatedRequestParams>,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<ListResourceTemplatesResult, rmcp::ErrorData> {
+            from_json(crate::codex_stdio::list_resource_templates_result())
+        }
+
+        async fn read_resource(
+            &self,
+            request: ReadResourceRequestParams,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<ReadResourceResult, rmcp::ErrorData> {
+            let result = self
+                .executor
+                .read_resource(&request.uri)
+                .await
+                .map_err(request_error)?;
+            from_json(result)
+        }
+
+        async fn complete(
+            &self,
+            request: CompleteRequestParams,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<CompleteResult, rmcp::ErrorData> {
+            let reference = serde_json::to_value(&request.r#ref)
+                .map_err(|err| rmcp::ErrorData::internal_error(err.to_string(), None))?;
+            let context = request
+                .context
+                .and_then(|context| context.arguments)
+                .unwrap_or_default();
+            let result = self
+                .executor
+                .complete_argument(
+                    &reference,
+                    &request.argument.name,
+                    &request.argument.value,
+                    &context.into_iter().collect(),
+                )
+                .await
+                .map_err(request_error)?;
+            from_json(result)
+        }
+
+        async fn list_prompts(
+            &self,
+            _request: Option<PaginatedRequestParams>,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<ListPromptsResult, rmcp::ErrorData> {
+            from_json(crate::codex_stdio::list_prompts_result())
+        }
+
+        async fn get_prompt(
+            &self,
+            request: GetPromptRequestParams,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<GetPromptResult, rmcp::ErrorData> {
+            let result = self
+                .executor
+                .get_prompt(&request.name, request.arguments.as_ref())
+                .await
+                .map_err(request_error)?;
+            from_json(result)
+        }
+
+        async fn subscribe(
+            &self,
+            request: SubscribeRequestParams,
+            ctx: RequestContext<RoleServer>,
+        ) -> Result<(), rmcp::ErrorData> {
+            self.executor
+                .subscribe_resource(&request.uri)
+                .await
+                .map_err(request_error)?;
+            self.start_schema_watcher(ctx.peer);
+            Ok(())
+        }
+
+        async fn unsubscribe(
+            &self,
+            request: UnsubscribeRequestParams,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<(), rmcp::ErrorData> {
+            self.executor
+                .unsubscribe_resource(&request.uri)
+                .await
+                .map_err(request_error)
+        }
+
+        async fn list_tools(
+            &self,
+            _request: Option<PaginatedRequestParams>,
+            _ctx: RequestContext<RoleServer>,
+        ) -> Result<ListToolsResult, rmcp::ErrorData> {
+            let items = server::list_tools()
+                .iter()
+                .map(|t| from_json::<Tool>(server::tool_definition(t)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(ListToolsResult::with_all_items(items))
+        }
+
+        async fn set_level(
+            &self,
+            request: SetLevelRequestParams,
+            ctx: RequestContext<RoleServer>,
+        ) -> Result<(), rmcp::ErrorData> {
+            let name = serde_json::to_value(request.level)
+                .ok()
+                .and_then(|value| value.as_str().map(ToOwned::to_owned))
+                .unwrap_or_default();
+            let level = crate::logging::parse_mcp_level(&name).ok_or_else(|| {
+                rmcp::ErrorData::invalid_params(format!("unknown logging level '{name}'"), None)
+            })?;
+            let mut client_logs = self.client_logs.lock().unwrap();
+            client_logs
+                .get_or_insert_with(|| forward_client_logs(&self.executor, ctx.peer.clone()))
+                .set_level(level);
+            Ok(())
+        }
+
+        async fn call_tool(
+            &self,
+            request: CallToolRequestParams,
+            ctx: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, rmcp::ErrorData> {
+            let call = CallContext {
+                progress: progress_reporter(&ctx),
+                cancel: ctx.ct,
+            };
+            let output = self
+                .executor
+                .execute_with(request.name.as_ref(), request.arguments.as_ref(), call)
+                .await;
+            if output.is_error {
+                Ok(CallToolResult::error(vec![Content::text(output.text)]))
+            } else {
+                let structured = output.structured_content(request.name.as_ref());
+                let mut result = CallToolResult::success(vec![Content::text(output.text)]);
+                result.structured_content = structured;
+                Ok(result)
+            }
+        }
+    }
+
+    /// Send this connection's log events to the peer, in order.
+    fn forward_client_logs(executor: &ToolExecutor, peer: Peer<RoleServer>) -> ClientLogs {
+        let (messages, mut queue) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(params) = queue.recv().await {
+                let Ok(params) = from_json::<LoggingMessageNotificationParam>(params) else {
+                    continue;
+                };
+                if peer.notify_logging_message(params).await.is_err() {
+                    return;
+                }
+            }
+        });
+        executor.client_logs(move |params| {
+            let _ = messages.send(params);
+        })
+    }
+
+    /// Forward progress to the peer, in order, if the request carried a `progressToken`.
+    fn progress_reporter(ctx: &RequestContext<RoleServer>) -> Progress {
+        let Some(token) = ctx.meta.get_progress_token() else {
+            return Progress::default();
+        };
+        let (updates, mut queue) = tokio::sync::mpsc::unbounded_channel();
+        let peer = ctx.peer.clone();
+        tokio::spawn(async move {
+            while let Some(update) = queue.recv().await {
+                if peer.notify_progress(update).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Progress::new(move |done, total, message| {
+            let mut update =
+                ProgressNotificationParam::new(token.clone(), done as f64).with_message(message);
+            if let Some(total) = total {
+                update = update.with_total(total as f64);
+            }
+            let _ = updates.send(update);
+        })
+    }
+
+    fn request_error(err: RequestError) -> rmcp::ErrorData {
+        let data = err.data().cloned();
+        match err {
+            RequestError::InvalidParams(message) => rmcp::ErrorData::invalid_params(message, None),
+            RequestError::NotFound { message, .. } => {
+                rmcp::ErrorData::resource_not_found(message, data)
+            }
+            RequestError::Failed(message) => rmcp::ErrorData::internal_error(message, None),
+        }
+    }
+
+    /// Decode a result built as MCP JSON (shared with the content-length transport).
+    fn from_json<T: serde::de::DeserializeOwned>(
+        value: serde_json::Value,
+    ) -> Result<T, rmcp::ErrorData> {
+        serde_json::from_value(value)
+            .map_err(|err| rmcp::ErrorData::internal_error(err.to_string(), None))
+    }
+
+    pub async fn run_stdio_server() -> Result<()> {
+        crate::logging::init_tracing();
+        info!("starting MCP stdio server (rmcp, newline-delimited JSON)");
+
+        // RMCP 0.5 stdio uses newline-delimited JSON, not Content-Length framing.
+        let handler = BridgeHandler::new();
+        let service = handler.serve(stdio()).await?;
+        // Wait for client disconnect
+        service.waiting().await?;
+        Ok(())
+    }
+}
+
+pub mod codex_stdio {
+    use crate::db::Progress;
+    use crate::logging::ClientLogs;
+    use crate::mcp::{CallContext, RequestError, ToolExecutor};
+    use crate::server;
+    use anyhow::{anyhow, Context, Result};
+    use serde_json::{json, Map, Value};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio::io::{
+        AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+    };
+    use tokio_util::sync::CancellationToken;
+    use tracing::{debug, info, warn, Instrument};
+
+    const MAX_MCP_MESSAGE_BYTES: usize = 8 * 1024 * 1024;
+
+    /// Default cap on requests executing at once; `MCP_MAX_IN_FLIGHT` overrides it.
+    const DEFAULT_MAX_IN_FLIGHT: usize = 16;
+
+    fn max_in_flight_from_env() -> usize {
+        std::env::var("MCP_MAX_IN_FLIGHT")
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_MAX_IN_FLIGHT)
+    }
+
+    pub async fn run_stdio_server() -> Result<()> {
+        crate::logging::init_tracing();
+        info!("starting MCP stdio server (content-length)");
+
+        let handler = ToolExecutor::from_env();
+        handler.warmup_connection();
+        run_over(
+            tokio::io::stdin(),
+            tokio::io::stdout(),
+            handler,
+            max_in_flight_from_env(),
+        )
+        .await
+    }
+
+    /// Serve one client: a reader feeds a bounded queue, up to `max_in_flight`
+    /// requests execute concurrently, and a single writer emits responses in
+    /// completion order. Reading stalls while the queue and pool are full.
+    async fn run_over<R, W>(
+        reader: R,
+        mut writer: W,
+        handler: ToolExecutor,
+        max_in_flight: usize,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut reader = BufReader::new(reader);
+        let handler = Arc::new(handler);
+        let max_in_flight = max_in_flight.max(1);
+        // Responses and server-initiated notifications share one ordered writer.
+        let (outgoing, mut queue) = tokio::sync::mpsc::unbounded_channel::<Value>();
+        let (incoming, mut inbox) =
+            tokio::sync::mpsc::channel::<(Value, CancellationToken)>(max_in_flight);
+        let (stop, stopped) = tokio::sync::watch::channel(false);
+        // Cancellation tokens of queued and running requests, by JSON-encoded id.
+        let cancels = std::sync::Mutex::new(HashMap::<String, CancellationToken>::new());
+        let client_logs = {
+            let outgoing = outgoing.clone();
+            handler.client_logs(move |params| {
+                let _ = outgoing.send(json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/message",
+                    "params": params,
+                }));
+            })
+        };
+
+        // Cancellations are applied here rather than queued, so they still take
+        // effect while the pool is saturated.
+        let read = async {
+            let mut stopped = stopped.clone();
+            loop {
+                let message = tokio::select! {
+                    _ = stopped.wait_for(|stop| *stop) => break,
+                    message = read_message(&mut reader) => message?,
+                };
+                let Some(message) = message else {
+                    break;
+                };
+                if message.get("method").and_then(Value::as_str) == Some("notifications/cancelled")
+                {
+                    if let Some(id) = message.pointer("/params/requestId") {
+                        debug!(%id, "client cancelled request");
+                        if let Some(token) = cancels.lock().unwrap().get(&id.to_string()) {
+                            token.cancel();
+                        }
+                    }
+                    continue;
+                }
+                let cancel = CancellationToken::new();
+                if let Some(id) = message.get("id") {
+                    cancels
+                        .lock()
+                        .unwrap()
+                        .insert(id.to_string(), cancel.clone());
+                }
+                // Dispatch stops taking messages after `shutdown`, so a full
+                // queue must not keep the reader waiting past the stop.
+                tokio::select! {
+                    _ = stopped.wait_for(|stop| *stop) => break,
+                    sent = incoming.send((message, cancel)) => if sent.is_err() {
+                        break;
+                    },
+                }
+            }
+            drop(incoming);
+            Ok::<(), anyhow::Error>(())
+        };
+
+        let dispatch = async {
+            let mut running = tokio::task::JoinSet::new();
+            let mut shutdown_id = None;
+            let finished = |done: std::result::Result<
+                (Option<String>, Option<Value>),
+                tokio::task::JoinError,
+            >| match done {
+                Ok((key, response)) => {
+                    if let Some(key) = key {
+                        cancels.lock().unwrap().remove(&key);
+                    }
+                    if let Some(response) = response {
+                        let _ = outgoing.send(response);
+                    }
+                }
+                Err(err) => warn!("MCP request task failed: {err}"),
+            };
+            loop {
+                tokio::select! {
+                    Some(done) = running.join_next(), if !running.is_empty() => finished(done),
+                    message = inbox.recv(), if running.len() < max_in_flight => {
+                        let Some((message, cancel)) = message else {
+                            break;
+                        };
+                        // Answer shutdown only after everything before it has finished.
+                        if message.get("method").and_then(Value::as_str) == Some("shutdown") {
+                            shutdown_id = Some(message.get("id").cloned());
+                            break;
+                        }
+                        let key = message.get("id").map(Value::to_string);
+                        // The level belongs to this connection, so it is set here.
+                        if message.get("method").and_then(Value::as_str) == Some("logging/setLevel") {
+                            if let Some(key) = &key {
+                                cancels.lock().unwrap().remove(key);
+                            }
+                            if let Some(response) = set_level_response(&client_logs, &message) {
+                                let _ = outgoing.send(response);
+                            }
+                            continue;
+                        }
+                        let call = CallContext {
+                            progress: progress_reporter(&message, &outgoing),
+                            cancel: cancel.clone(),
+                        };
+                        let handler = Arc::clone(&handler);
+                        let span = handler.connection_span();
+                        // A cancelled request gets no response, as MCP requires.
+                        running.spawn(async move {
+                            let response = tokio::select! {
+                                biased;
+                                _ = cancel.cancelled() => None,
+                                response = handle_message(&handler, message, call) => response,
+                            };
+                            (key, response)
+                        }.instrument(span));
+                    }
+                }
+            }
+            while let Some(done) = running.join_next().await {
+                finished(done);
+            }
+            if let Some(Some(id)) = shutdown_id {
+                let _ = outgoing.send(ok_response(id, json!({})));
+            }
+            stop.send_replace(true);
+            Ok(())
+        };
+
+        let watch_schema = async {
+            let mut stopped = stopped.clone();
+            loop {
+                tokio::select! {
+                    _ = stopped.wait_for(|stop| *stop) => break,
+                    _ = tokio::time::sleep(handler.schema_poll_interval()) => {}
+                }
+                match handler.poll_schema_changes().await {
+                    Ok(uris) => {
+                        for uri in uris {
+                            let _ = outgoing.send(resource_updated_notification(&uri));
+                        }
+                    }
+                    Err(err) => debug!("schema poll failed: {err}"),
+                }
+            }
+            Ok(())
+        };
+
+        let write = async {
+            let mut stopped = stopped.clone();
+            loop {
+                tokio::select! {
+                    biased;
+                    Some(message) = queue.recv() => write_message(&mut writer, &message).await?,
+                    _ = stopped.wait_for(|stop| *stop) => {
+                        while let Ok(message) = queue.try_recv() {
+                            write_message(&mut writer, &message).await?;
+                        }
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        async { tokio::try_join!(read, dispatch, watch_schema, write) }
+            .instrument(handler.connection_span())
+            .await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    fn set_level_response(client_logs: &ClientLogs, message: &Value) -> Option<Value> {
+        let id = message.get("id").cloned()?;
+        let name = message
+            .pointer("/params/level")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        Some(match crate::logging::parse_mcp_level(name) {
+            Some(level) => {
+                client_logs.set_level(level);
+                ok_response(id, json!({}))
+            }
+            None => error_response(id, -32602, format!("unknown logging level '{name}'")),
+        })
+    }
+
+    /// Forward progress as `notifications/progress` if the request carried a `progressToken`.
+    fn progress_reporter(
+        message: &Value,
+        outgoing: &tokio::sync::mpsc::UnboundedSender<Value>,
+    ) -> Progress {
+        let Some(token) = message.pointer("/params/_meta/progressToken").cloned() else {
+            return Progress::default();
+        };
+        let outgoing = outgoing.clone();
+        Progress::new(move |done, total, message| {
+            let _ = outgoing.send(progress_notification(&token, done, total, message));
+        })
+    }
+
+    fn progress_notification(token: &Value, done: u64, total: Option<u64>, message: &str) -> Value {
+        let mut params = json!({
+            "progressToken": token,
+            "progress": done,
+            "message": message,
+        });
+        if let Some(total) = total {
+            params["total"] = json!(total);
+        }
+        json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/progress",
+            "params": params,
+        })
+    }
+
+    fn resource_updated_notification(uri: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/resources/updated",
+            "params": { "uri": uri },
+        })
+    }
+
+    async fn read_message<R>(reader: &mut BufReader<R>) -> Result<Option<Value>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut content_length = None;
+
+        loop {
+            let mut line = String::new();
+            let read = reader
+                .read_line(&mut line)
+                .await
+                .context("failed to read MCP header line")?;
+
+            if read == 0 {
+                if content_length.is_none() {
+                    return Ok(None);
+                }
+                return Err(anyhow!("unexpected EOF while reading MCP headers"));
+            }
+
+            if line == "\r\n" {
+                break;
+            }
+
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("invalid MCP header: {line:?}"))?;
+
+            if name.eq_ignore_ascii_case("content-length") {
+                let parsed = value
+                    .trim()
+                    .parse::<usize>()
+                    .with_context(|| format!("invalid Content-Length header: {value:?}"))?;
+                if parsed > MAX_MCP_MESSAGE_BYTES {
+                    return Err(anyhow!(
+                        "MCP message body exceeds maximum Content-Length of {} bytes",
+                        MAX_MCP_MESSAGE_BYTES
+                    ));
+                }
+                content_length = Some(parsed);
+            }
+        }
+
+        let len = content_length.ok_or_else(|| anyhow!("missing Content-Length header"))?;
+        let mut body = vec![0; len];
+        reader
+            .read_exact(&mut body)
+            .await
+            .context("failed to read MCP message body")?;
+
+        let value =
+            serde_json::from_slice(&body).context("failed to parse MCP JSON message body")?;
+        Ok(Some(value))
+    }
+
+    async fn write_message<W>(writer: &mut W, message: &Value) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let body = serde_json::to_vec(message).context("failed to encode MCP response")?;
+        let header = format!("Content-Length: {}\r\n\r\n", body.len());
+        writer
+            .write_all(header.as_bytes())
+            .await
+            .context("failed to write MCP response header")?;
+        writer
+            .write_all(&body)
+            .await
+            .context("failed to write MCP response body")?;
+        writer
+            .flush()
+            .await
+            .context("failed to flush MCP response")?;
+        Ok(())
+    }
+
+    async fn handle_message(
+        handler: &ToolExecutor,
+        message: Value,
+        call: CallContext,
+    ) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let method = message.get("method").and_then(Value::as_str)?;
+        let params = message
+            .get("params")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+
+        debug!(%method, "received MCP message");
+
+        match method {
+            "initialize" => id.map(|id| ok_response(id, initialize_result(&params))),
+            "notifications/initialized" => None,
+            "ping" => id.map(|id| ok_response(id, json!({}))),
+            "tools/list" => id.map(|id| ok_response(id, list_tools_result())),
+            "resources/list" => {
+                let id = id?;
+                let cursor = params.get("cursor").and_then(Value::as_str);
+                Some(request_response(id, handler.list_resources(cursor).await))
+            }
+            "resources/templates/list" => {
+                id.map(|id| ok_response(id, list_resource_templates_result()))
+            }
+            "resources/read" => {
+                let id = id?;
+                let Some(uri) = params.get("uri").and_then(Value::as_str) else {
+                    return Some(error_response(
+                        id,
+                        -32602,
+                        "missing string field 'uri'".to_string(),
+                    ));
+                };
+                Some(request_response(id, handler.read_resource(uri).await))
+            }
+            "prompts/list" => id.map(|id| ok_response(id, list_prompts_result())),
+            "prompts/get" => {
+                let id = id?;
+                let Some(name) = params.get("name").and_then(Value::as_str) else {
+                    return Some(error_response(
+                        id,
+                        -32602,
+                        "missing string field 'name'".to_string(),
+                    ));
+                };
+                let arguments = params.get("arguments").and_then(Value::as_object);
+                Some(request_response(
+                    id,
+                    handler.get_prompt(name, arguments).await,
+                ))
+            }
+            "completion/complete" => {
+                let id = id?;
+                let reference = params.get("ref").cloned().unwrap_or(Value::Null);
+                let argument = params.get("argument");
+                let name = argument.and_then(|a| a.get("name")).and_then(Value::as_str);
+                let value = argument
+                    .and_then(|a| a.get("value"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let Some(name) = name else {
+                    return Some(error_response(
+                        id,
+                        -32602,
+                        "missing string field 'argument.name'".to_string(),
+                    ));
+                };
+                let context = params
+                    .get("context")
+                    .and_then(|c| c.get("arguments"))
+                    .and_then(Value::as_object)
+                    .map(|args| {
+                        args.iter()
+                            .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(request_response(
+                    id,
+                    handler
+                        .complete_argument(&reference, name, value, &context)
+                        .await,
+                ))
+            }
+            "resources/subscribe" | "resources/unsubscribe" => {
+                let id = id?;
+                let Some(uri) = params.get("uri").and_then(Value::as_str) else {
+                    return Some(error_response(
+                        id,
+                        -32602,
+                        "missing string field 'uri'".to_string(),
+                    ));
+                };
+                let result = if method == "resources/subscribe" {
+                    handler.subscribe_resource(uri).await
+                } else {
+                    handler.unsubscribe_resource(uri).await
+                };
+                Some(request_response(id, result.map(|()| json!({}))))
+            }
+            "tools/call" => {
+                let id = id?;
+                let result = call_tool_result(handler, params, call).await;
+                Some(match result {
+                    Ok(result) => ok_response(id, result),
+                    Err(err) => {
+                        error_response(id, -32602, format!("invalid tools/call request: {err}"))
+                    }
+                })
+            }
+            "shutdown" => id.map(|id| ok_response(id, json!({}))),
+            _ if id.is_some() => {
+                id.map(|id| error_response(id, -32601, format!("method not found: {method}")))
+            }
+            _ => None,
+        }
+    }
+
+    fn initialize_result(params: &Map<String, Value>) -> Value {
+        let protocol_version = params
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .unwrap_or("2024-11-05");
+        let server_info = server::server_info();
+
+        json!({
+            "protocolVersion": protocol_version,
+            "capabilities": {
+                "tools": {
+                    "listChanged": false,
+                },
+                "resources": {
+                    "subscribe": true,
+                    "listChanged": false,
+                },
+                "prompts": {
+                    "listChanged": false,
+                },
+                "completions": {},
+                "logging": {},
+            },
+            "serverInfo": {
+                "name": server_info.name,
+                "version": server_info.version,
+            },
+            "instructions": server_info.instructions,
+        })
+    }
+
+    fn list_tools_result() -> Value {
+        let tools = server::list_tools()
+            .iter()
+            .map(server::tool_definition)
+            .collect::<Vec<_>>();
+
+        json!({ "tools": tools })
+    }
+
+    pub(crate) fn list_prompts_result() -> Value {
+        let prompts = server::list_prompts()
+            .into_iter()
+            .map(|prompt| {
+                let arguments = prompt
+                    .arguments
+                    .iter()
+                    .map(|argument| {
+                        json!({
+                            "name": argument.name,
+                            "description": argument.description,
+                            "required": argument.required,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                json!({
+                    "name": prompt.name,
+                    "description": prompt.description,
+                    "arguments": arguments,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({ "prompts": prompts })
+    }
+
+    pub(crate) fn list_resource_templates_result() -> Value {
+        let templates = server::resource_templates()
+            .into_iter()
+            .map(|template| {
+                json!({
+                    "uriTemplate": template.uri_template,
+                    "name": template.name,
+                    "description": template.description,
+                    "mimeType": template.mime_type,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({ "resourceTemplates": templates })
+    }
+
+    fn request_response(id: Value, result: std::result::Result<Value, RequestError>) -> Value {
+        match result {
+            Ok(result) => ok_response(id, result),
+            Err(err) => {
+                let mut response = error_response(id, err.code(), err.message().to_string());
+                if let Some(data) = err.data() {
+                    response["error"]["data"] = data.clone();
+                }
+                response
+            }
+        }
+    }
+
+    async fn call_tool_result(
+        handler: &ToolExecutor,
+        params: Map<String, Value>,
+        call: CallContext,
+    ) -> Result<Value> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("missing string field 'name'"))?;
+        let arguments = params.get("arguments").and_then(Value::as_object).cloned();
+        let output = handler.execute_with(name, arguments.as_ref(), call).await;
+
+        let mut result = json!({
+            "content": [
+                {
+                    "type": "text",
+                    "text": output.text,
+                }
+            ],
+            "isError": output.is_error,
+        });
+        if let Some(structured) = output.structured_content(name) {
+            result["structuredContent"] = structured;
+        }
+        Ok(result)
+    }
+
+    fn ok_response(id: Value, result: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result,
+        })
+    }
+
+    fn error_response(id: Value, code: i64, message: String) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": code,
+                "message": message,
+            }
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            initialize_result, list_prompts_result, list_resource_templates_result,
+            list_tools_result, progress_reporter, read_message, run_over, MAX_MCP_MESSAGE_BYTES,
+        };
+        use crate::mcp::ToolExecutor;
+        use serde_json::json;
+        use tokio::io::{AsyncWriteExt, BufReader};
+
+        #[test]
+        fn initialize_reflects_client_protocol_version() {
+            let params = json!({ "protocolVersion": "2024-11-05" });
+            let result = initialize_result(params.as_object().expect("initialize params"));
+            assert_eq!(result["protocolVersion"], "2024-11-05");
+            assert_eq!(result["serverInfo"]["name"], "scylla-rust-mcp");
+            assert_eq!(result["capabilities"]["tools"]["listChanged"], false);
+            assert_eq!(result["capabilities"]["resources"]["subscribe"], true);
+            assert_eq!(result["capabilities"]["prompts"]["listChanged"], false);
+            assert_eq!(result["capabilities"]["completions"], json!({}));
+            assert_eq!(result["capabilities"]["logging"], json!({}));
+        }
+
+        #[tokio::test]
+        async fn set_level_accepts_mcp_levels_only() {
+            let mut input = frame(json!({
+                "jsonrpc": "2.0", "id": 1, "method": "logging/setLevel",
+                "params": { "level": "warning" },
+            }));
+            input.extend(frame(json!({
+                "jsonrpc": "2.0", "id": 2, "method": "logging/setLevel",
+                "params": { "level": "verbose" },
+            })));
+            let responses = serve(input, 4).await;
+            assert_eq!(responses.len(), 2);
+            assert_eq!(responses[0]["result"], json!({}));
+            assert_eq!(responses[1]["error"]["code"], -32602);
+        }
+
+        #[test]
+        fn resource_templates_cover_schema_and_ddl() {
+            let result = list_resource_templates_result();
+            let templates: Vec<&str> = result["resourceTemplates"]
+                .as_array()
+                .expect("templates array")
+                .iter()
+                .filter_map(|t| t["uriTemplate"].as_str())
+                .collect();
+            assert_eq!(
+                templates,
+                [
+                    "scylla://{keyspace}/{table}/schema",
+                    "scylla://{keyspace}/ddl"
+                ]
+            );
+        }
+
+        #[test]
+        fn tools_list_includes_json_schema() {
+            let result = list_tools_result();
+            let tools = result["tools"].as_array().expect("tools array");
+            assert!(tools.iter().any(|tool| tool["name"] == "list_keyspaces"));
+            let list_tables = tools
+                .iter()
+                .find(|tool| tool["name"] == "list_tables")
+                .expect("list_tables tool");
+            assert_eq!(list_tables["inputSchema"]["required"], json!(["keyspace"]));
+            assert_eq!(list_tables["title"], "List Tables");
+            assert_eq!(list_tables["annotations"]["readOnlyHint"], true);
+            assert_eq!(list_tables["annotations"]["idempotentHint"], true);
+            assert_eq!(list_tables["annotations"]["openWorldHint"], false);
+            assert!(list_tables.get("outputSchema").is_none());
+
+            let lint = tools
+                .iter()
+                .find(|tool| tool["name"] == "lint_schema")
+                .expect("lint_schema tool");
+            assert_eq!(lint["outputSchema"]["type"], "object");
+            assert_eq!(
+                lint["outputSchema"]["required"],
+                json!(["summary", "findings"])
+            );
+        }
+
+        fn frame(message: serde_json::Value) -> Vec<u8> {
+            let body = serde_json::to_vec(&message).expect("encode");
+            let mut framed = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+            framed.extend(body);
+            framed
+        }
+
+        #[test]
+        fn prompts_list_declares_arguments() {
+            let result = list_prompts_result();
+            let prompts = result["prompts"].as_array().expect("prompts array");
+            let find = prompts
+                .iter()
+                .find(|p| p["name"] == "find_entity_rows")
+                .expect("find_entity_rows prompt");
+            let required: Vec<&str> = find["arguments"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|a| a["required"] == true)
+                .filter_map(|a| a["name"].as_str())
+                .collect();
+            assert_eq!(required, ["keyspace", "table", "entity_id"]);
+        }
+
+        #[tokio::test]
+        async fn subscriptions_are_acknowledged_and_the_server_exits_on_eof() {
+            let mut input = frame(json!({
+                "jsonrpc": "2.0", "id": 1, "method": "resources/subscribe",
+                "params": { "uri": "scylla://app/users/schema" },
+            }));
+            input.extend(frame(json!({
+                "jsonrpc": "2.0", "id": 2, "method": "resources/unsubscribe",
+                "params": { "uri": "scylla://app" },
+            })));
+            let responses = serve(input, 4).await;
+            assert_eq!(responses.len(), 2);
+            assert_eq!(responses[0]["id"], 1);
+            assert_eq!(responses[0]["result"], json!({}));
+            assert_eq!(responses[1]["id"], 2);
+            assert_eq!(responses[1]["error"]["code"], -32602);
+        }
+
+        /// Run the server over `input` and return its messages ordered by id.
+        async fn serve(input: Vec<u8>, max_in_flight: usize) -> Vec<serde_json::Value> {
+            let mut output = Vec::new();
+            run_over(
+                input.as_slice(),
+                &mut output,
+                ToolExecutor::from_env(),
+                max_in_flight,
+            )
+            .await
+            .expect("server loop");
+
+            let mut reader = BufReader::new(output.as_slice());
+            let mut responses = Vec::new();
+            while let Some(message) = read_message(&mut reader).await.unwrap() {
+                responses.push(message);
+            }
+            responses.sort_by_key(|message| message["id"].as_i64());
+            responses
+        }
+
+        #[tokio::test]
+        async fn requests_beyond_the_in_flight_limit_are_queued_not_dropped() {
+            let mut input = Vec::new();
+            for id in 1..=20 {
+                input.extend(frame(
+                    json!({ "jsonrpc": "2.0", "id": id, "method": "ping" }),
+                ));
+            }
+            input.extend(frame(json!({
+                "jsonrpc": "2.0", "method": "notifications/initialized",
+            })));
+            let responses = serve(input, 2).await;
+            let ids: Vec<i64> = responses.iter().filter_map(|r| r["id"].as_i64()).collect();
+            assert_eq!(ids, (1..=20).collect::<Vec<_>>());
+            assert!(responses.iter().all(|r| r["result"] == json!({})));
+        }
+
+        #[tokio::test]
+        async fn shutdown_waits_for_earlier_requests_and_stops_reading() {
+            let mut input = frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }));
+            input.extend(frame(json!({
+                "jsonrpc": "2.0", "id": 2, "method": "resources/subscribe",
+                "params": { "uri": "scylla://app/users/schema" },
+            })));
+            input.extend(frame(
+                json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+            ));
+            input.extend(frame(
+                json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" }),
+            ));
+
+            let mut output = Vec::new();
+            run_over(input.as_slice(), &mut output, ToolExecutor::from_env(), 4)
+                .await
+                .expect("server loop");
+            let mut reader = BufReader::new(output.as_slice());
+            let mut ids = Vec::new();
+            while let Some(message) = read_message(&mut reader).await.unwrap() {
+                ids.push(message["id"].as_i64().unwrap());
+            }
+            assert_eq!(ids.len(), 3);
+            assert_eq!(ids.last(), Some(&3));
+            assert!(!ids.contains(&4));
+        }
+
+        #[tokio::test]
+        async fn shutdown_returns_with_more_trailing_messages_than_the_queue_holds() {
+            let mut input = frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "shutdown" }));
+            for id in 2..=10 {
+                input.extend(frame(
+                    json!({ "jsonrpc": "2.0", "id": id, "method": "ping" }),
+                ));
+            }
+            let responses =
+                tokio::time::timeout(std::time::Duration::from_secs(5), serve(input, 2))
+                    .await
+                    .expect("server loop returns after shutdown");
+            assert_eq!(responses.len(), 1);
+            assert_eq!(responses[0]["id"], 1);
+        }
+
+        #[test]
+        fn cancelled_requests_get_no_response() {
+            // Tool calls run on worker threads; connecting through the driver
+            // needs more than the default 2 MiB stack in debug builds.
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .thread_stack_size(8 << 20)
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(cancelled_call_gets_no_response());
+        }
+
+        async fn cancelled_call_gets_no_response() {
+            // A peer that accepts and never answers keeps the call connecting.
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let uri = listener.local_addr().unwrap().to_string();
+            tokio::spawn(async move {
+                let mut held = Vec::new();
+                while let Ok((socket, _)) = listener.accept().await {
+                    held.push(socket);
+                }
+            });
+            let executor = ToolExecutor::for_uri(uri, std::time::Duration::from_secs(30));
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            let (server_read, server_write) = tokio::io::split(server);
+            let (client_read, mut client_write) = tokio::io::split(client);
+
+            let client = async move {
+                let mut reader = BufReader::new(client_read);
+                client_write
+                    .write_all(&frame(json!({
+                        "jsonrpc": "2.0", "id": "slow", "method": "tools/call",
+                        "params": { "name": "list_keyspaces", "arguments": {} },
+                    })))
+                    .await
+                    .unwrap();
+                let early = tokio::time::timeout(
+                    std::time::Duration::from_millis(300),
+                    read_message(&mut reader),
+                )
+                .await;
+                assert!(early.is_err(), "the call should still be running");
+
+                let mut input = frame(json!({
+                    "jsonrpc": "2.0", "method": "notifications/cancelled",
+                    "params": { "requestId": "slow", "reason": "user gave up" },
+                }));
+                input.extend(frame(
+                    json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+                ));
+                client_write.write_all(&input).await.unwrap();
+                client_write.shutdown().await.unwrap();
+                let mut responses = Vec::new();
+                while let Some(message) = read_message(&mut reader).await.unwrap() {
+                    responses.push(message);
+                }
+                responses
+            };
+            let (served, responses) =
+                tokio::time::timeout(std::time::Duration::from_secs(10), async {
+                    tokio::join!(run_over(server_read, server_write, executor, 1), client)
+                })
+                .await
+                .expect("the cancelled call freed its slot");
+            served.expect("server loop");
+            assert_eq!(responses.len(), 1, "{responses:?}");
+            assert_eq!(responses[0]["id"], 2);
+        }
+
+        #[test]
+        fn progress_is_reported_only_with_a_token() {
+            let (outgoing, mut queue) = tokio::sync::mpsc::unbounded_channel();
+            let without =
+                json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": {} });
+            progress_reporter(&without, &outgoing).step(1, None, "ignored");
+            assert!(queue.try_recv().is_err());
+
+            let with = json!({
+                "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": { "_meta": { "progressToken": "tok-1" } },
+            });
+            let progress = progress_reporter(&with, &outgoing);
+            progress.step(1, Some(2), "first scrape done");
+            progress.step(3, None, "read 40 rows");
+            let first = queue.try_recv().unwrap();
+            assert_eq!(first["method"], "notifications/progress");
+            assert_eq!(
+                first["params"],
+                json!({ "progressToken": "tok-1", "progress": 1, "total": 2, "message": "first scrape done" })
+            );
+            let second = queue.try_recv().unwrap();
+            assert_eq!(second["params"]["progress"], 3);
+            assert!(second["params"].get("total").is_none());
+        }
+
+        #[tokio::test]
+        async fn read_message_rejects_oversized_content_length() {
+            let input = format!("Content-Length: {}\r\n\r\n", MAX_MCP_MESSAGE_BYTES + 1);
+            let mut reader = BufReader::new(input.as_bytes());
+            let err = read_message(&mut reader)
+                .await
+                .expect_err("oversized message should be rejected before allocation");
+            assert!(err.to_string().contains("maximum Content-Length"));
+        }
+    }
+}
+
+pub mod http_server {
+    use crate::mcp::ToolExecutor;
+    use crate::rmcp_server::BridgeHandler;
+    use anyhow::{anyhow, bail, Context, Result};
+    use bytes::Bytes;
+    use http_body_util::{combinators::BoxBody, BodyExt, Full};
+    use hyper::{body::Incoming, Request, Response, StatusCode};
+    use hyper_util::rt::{TokioIo, TokioTimer};
+    use rmcp::transport::streamable_http_server::{
+        session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService,
+    };
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tracing::{debug, info, warn};
+
+    const DEFAULT_HTTP_ADDR: &str = "127.0.0.1:8080";
+    const DEFAULT_HTTP_PATH: &str = "/mcp";
+    const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+    const DEFAULT_MAX_CONNECTIONS: usize = 256;
+    const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+    /// Pause after a failed `accept` (e.g. out of file descriptors) so the
+    /// loop does not spin while the condition lasts.
+    const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+    type HttpResponse = Response<BoxBody<Bytes, Infallible>>;
+    type McpService = StreamableHttpService<BridgeHandler, LocalSessionManager>;
+
+    /// Certificate files for serving HTTPS; `client_ca` turns on mTLS.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct TlsFiles {
+        pub cert: String,
+        pub key: String,
+        pub client_ca: Option<String>,
+    }
+
+    /// Settings for `MCP_TRANSPORT=http`.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct HttpConfig {
+        pub addr: SocketAddr,
+        pub path: String,
+        pub bearer_token: Option<String>,
+        pub tls: Option<TlsFiles>,
+        /// Accepted `Host` headers; `None` keeps rmcp's loopback-only default
+        /// and an empty list disables the check.
+        pub allowed_hosts: Option<Vec<String>>,
+        /// Connections served at once; further clients wait in the listen
+        /// backlog until one closes.
+        pub max_connections: usize,
+        /// How long a client may take to send request headers, which also
+        /// closes keep-alive connections that sit idle between requests.
+        pub idle_timeout: Duration,
+        /// Serve plain HTTP on a non-loopback address, e.g. behind a proxy that
+        /// terminates TLS.
+        pub allow_plaintext: bool,
+    }
+
+    impl HttpConfig {
+        pub fn from_env() -> Result<Self> {
+            Self::from_lookup(|name| std::env::var(name).ok())
+        }
+
+        fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+            let var = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+            let addr = var("MCP_HTTP_ADDR").unwrap_or_else(|| DEFAULT_HTTP_ADDR.to_string());
+            let addr: SocketAddr = addr
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid MCP_HTTP_ADDR '{addr}', expected ip:port"))?;
+            let path = var("MCP_HTTP_PATH").unwrap_or_else(|| DEFAULT_HTTP_PATH.to_string());
+            if !path.starts_with('/') {
+                bail!("MCP_HTTP_PATH must start with '/', got '{path}'");
+            }
+            let tls = match (var("MCP_HTTP_TLS_CERT"), var("MCP_HTTP_TLS_KEY")) {
+                (Some(cert), Some(key)) => Some(TlsFiles {
+                    cert,
+                    key,
+                    client_ca: var("MCP_HTTP_CLIENT_CA"),
+                }),
+                (None, None) if var("MCP_HTTP_CLIENT_CA").is_some() => {
+                    bail!("MCP_HTTP_CLIENT_CA needs MCP_HTTP_TLS_CERT and MCP_HTTP_TLS_KEY")
+                }
+                (None, None) => None,
+                _ => bail!("MCP_HTTP_TLS_CERT and MCP_HTTP_TLS_KEY must be set together"),
+            };
+            let allowed_hosts = var("MCP_HTTP_ALLOWED_HOSTS").map(|raw| {
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|host| !host.is_empty() && *host != "*")
+                    .map(ToOwned::to_owned)
+                    .collect()
+            });
+            let max_connections = match var("MCP_HTTP_MAX_CONNECTIONS") {
+                Some(raw) => match raw.trim().parse::<usize>() {
+                    Ok(limit) if limit > 0 => limit,
+                    _ => bail!("MCP_HTTP_MAX_CONNECTIONS must be a positive integer, got '{raw}'"),
+                },
+                None => DEFAULT_MAX_CONNECTIONS,
+            };
+            let idle_timeout = match var("MCP_HTTP_IDLE_TIMEOUT_MS") {
+                Some(raw) => match raw.trim().parse::<u64>() {
+                    Ok(millis) if millis > 0 => Duration::from_millis(millis),
+                    _ => bail!("MCP_HTTP_IDLE_TIMEOUT_MS must be a positive integer, got '{raw}'"),
+                },
+                None => DEFAULT_IDLE_TIMEOUT,
+            };
+            let allow_plaintext = var("MCP_HTTP_ALLOW_PLAINTEXT")
+                .is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+            let config = Self {
+                addr,
+                path,
+                bearer_token: var("MCP_HTTP_BEARER_TOKEN"),
+                tls,
+                allowed_hosts,
+                max_connections,
+                idle_timeout,
+                allow_plaintext,
+            };
+            config.validate()?;
+            Ok(config)
+        }
+
+        fn client_auth_enabled(&self) -> bool {
+            self.bearer_token.is_some()
+                || self.tls.as_ref().is_some_and(|tls| tls.client_ca.is_some())
+        }
+
+        /// Refuse to expose cluster access beyond loopback without client auth,
+        /// or to send the bearer token there in cleartext unless explicitly allowed.
+        fn validate(&self) -> Result<()> {
+            if self.addr.ip().is_loopback() {
+                return Ok(());
+            }
+            if !self.client_auth_enabled() {
+                bail!(
+                    "refusing to listen on {} without client auth; set MCP_HTTP_BEARER_TOKEN or MCP_HTTP_CLIENT_CA (with MCP_HTTP_TLS_CERT/MCP_HTTP_TLS_KEY)",
+                    self.addr
+                );
+            }
+            if self.tls.is_none() && !self.allow_plaintext {
+                bail!(
+                    "refusing to listen on {} without TLS; set MCP_HTTP_TLS_CERT and MCP_HTTP_TLS_KEY, or MCP_HTTP_ALLOW_PLAINTEXT=true when a proxy terminates TLS",
+                    self.addr
+                );
+            }
+            Ok(())
+        }
+
+        /// Authenticated clients cannot be driven by DNS rebinding, so the
+        /// `Host` check only stays on by default for unauthenticated loopback.
+        fn server_config(&self) -> StreamableHttpServerConfig {
+            let config = StreamableHttpServerConfig::default();
+            match &self.allowed_hosts {
+                Some(hosts) if hosts.is_empty() => config.disable_allowed_hosts(),
+                Some(hosts) => config.with_allowed_hosts(hosts.clone()),
+                None if self.client_auth_enabled() => config.disable_allowed_hosts(),
+                None => config,
+            }
+        }
+    }
+
+    /// Compare without short-circuiting on the first differing byte.
+    fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        if a.len() != b.len() {
+            return false;
+        }
+        a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    /// Whether an `Authorization` header carries the expected bearer token.
+    fn bearer_matches(header: Option<&str>, expected: &str) -> bool {
+        let Some((scheme, token)) = header.and_then(|value| value.trim().split_once(' ')) else {
+            return false;
+        };
+        scheme.eq_ignore_ascii_case("bearer")
+            && constant_time_eq(token.trim().as_bytes(), expected.as_bytes())
+    }
+
+    fn plain_response(status: StatusCode, message: &'static str) -> HttpResponse {
+        let mut response = Response::new(Full::new(Bytes::from_static(message.as_bytes())).boxed());
+        *response.status_mut() = status;
+        response
+            .headers_mut()
+            .insert(hyper::header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        response
+    }
+
+    async fn route(
+        config: Arc<HttpConfig>,
+        service: McpService,
+        request: Request<Incoming>,
+    ) -> HttpResponse {
+        if request.uri().path() != config.path {
+            return plain_response(StatusCode::NOT_FOUND, "not found");
+        }
+        if let Some(expected) = &config.bearer_token {
+            let header = request
+                .headers()
+                .get(hyper::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok());
+            if !bearer_matches(header, expected) {
+                let mut response = plain_response(StatusCode::UNAUTHORIZED, "unauthorized");
+                response
+                    .headers_mut()
+                    .insert(hyper::header::WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+                return response;
+            }
+        }
+        service.handle(request).await
+    }
+
+    fn tls_acceptor(tls: &TlsFiles) -> Result<openssl::ssl::SslAcceptor> {
+        use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
+        let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())
+            .context("failed to build TLS acceptor")?;
+        builder
+            .set_certificate_chain_file(&tls.cert)
+            .context("failed to load MCP_HTTP_TLS_CERT")?;
+        builder
+            .set_private_key_file(&tls.key, SslFiletype::PEM)
+            .context("failed to load MCP_HTTP_TLS_KEY")?;
+        builder
+            .check_private_key()
+            .context("MCP_HTTP_TLS_KEY does not match MCP_HTTP_TLS_CERT")?;
+        if let Some(ca) = &tls.client_ca {
+            builder
+                .set_ca_file(ca)
+                .context("failed to load MCP_HTTP_CLIENT_CA")?;
+            builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
+        Ok(builder.build())
+    }
+
+    async fn serve_connection<S>(stream: S, config: Arc<HttpConfig>, service: McpService)
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        let idle_timeout = config.idle_timeout;
+        let handler = hyper::service::service_fn(move |request| {
+            let config = Arc::clone(&config);
+            let service = service.clone();
+            async move { Ok::<_, Infallible>(route(config, service, request).await) }
+        });
+        if let Err(err) = hyper::server::conn::http1::Builder::new()
+            .timer(TokioTimer::new())
+            .header_read_timeout(idle_timeout)
+            .serve_connection(TokioIo::new(stream), handler)
+            .await
+        {
+            debug!("HTTP connection ended with error: {err}");
+        }
+    }
+
+    /// Accept connections on `listener` forever, at most `max_connections` at
+    /// a time. Every MCP session gets its own `ToolExecutor` forked from
+    /// `executor`, so they share one Scylla session and schema poll but not
+    /// cursors or subscriptions.
+    pub(crate) async fn serve(
+        listener: tokio::net::TcpListener,
+        config: HttpConfig,
+        executor: ToolExecutor,
+    ) -> Result<()> {
+        let acceptor = config.tls.as_ref().map(tls_acceptor).transpose()?;
+        let executor = Arc::new(executor);
+        let service: McpService = StreamableHttpService::new(
+            move || Ok(BridgeHandler::with_executor(executor.for_new_connection())),
+            Arc::new(LocalSessionManager::default()),
+            config.server_config(),
+        );
+        let slots = Arc::new(tokio::sync::Semaphore::new(config.max_connections));
+        let config = Arc::new(config);
+        loop {
+            let permit = Arc::clone(&slots)
+                .acquire_owned()
+                .await
+                .context("connection limit closed")?;
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    warn!("failed to accept HTTP connection: {err}");
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            };
+            let config = Arc::clone(&config);
+            let service = service.clone();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let _permit = permit;
+                let Some(acceptor) = acceptor else {
+                    serve_connection(stream, config, service).await;
+                    return;
+                };
+                let ssl = match openssl::ssl::Ssl::new(acceptor.context()) {
+                    Ok(ssl) => ssl,
+                    Err(err) => {
+                        warn!("failed to create TLS session for {peer}: {err}");
+                        return;
+                    }
+                };
+                let Ok(mut stream) = tokio_openssl::SslStream::new(ssl, stream) else {
+                    return;
+                };
+                let handshake = std::pin::Pin::new(&mut stream).accept();
+                match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, handshake).await {
+                    Ok(Ok(())) => serve_connection(stream, config, service).await,
+                    Ok(Err(err)) => debug!("TLS handshake with {peer} failed: {err}"),
+                    Err(_) => debug!("TLS handshake with {peer} timed out"),
+                }
+            });
+        }
+    }
+
+    pub async fn run_http_server() -> Result<()> {
+        crate::logging::init_tracing();
+        let config = HttpConfig::from_env()?;
+        let listener = tokio::net::TcpListener::bind(config.addr)
+            .await
+            .map_err(|err| anyhow!("failed to bind MCP_HTTP_ADDR {}: {err}", config.addr))?;
+        info!(
+            "starting MCP Streamable HTTP server on {}://{}{}",
+            if config.tls.is_some() {
+                "https"
+            } else {
+                "http"
+            },
+            config.addr,
+            config.path
+        );
+        let executor = ToolExecutor::from_env();
+        executor.warmup_connection();
+        serve(listener, config, executor).await
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{bearer_matches, serve, HttpConfig};
+        use crate::mcp::ToolExecutor;
+        use std::collections::HashMap;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        fn config_from(vars: &[(&str, &str)]) -> anyhow::Result<HttpConfig> {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            HttpConfig::from_lookup(|name| vars.get(name).cloned())
+        }
+
+        #[test]
+        fn config_defaults_to_loopback_without_auth() {
+            let config = config_from(&[]).unwrap();
+            assert_eq!(config.addr.to_string(), "127.0.0.1:8080");
+            assert_eq!(config.path, "/mcp");
+            assert!(config.bearer_token.is_none());
+            assert!(!config.server_config().allowed_hosts.is_empty());
+            assert_eq!(config.max_connections, 256);
+            assert_eq!(config.idle_timeout, std::time::Duration::from_secs(30));
+        }
+
+        #[test]
+        fn config_reads_connection_limit() {
+            let config = config_from(&[("MCP_HTTP_MAX_CONNECTIONS", "8")]).unwrap();
+            assert_eq!(config.max_connections, 8);
+            assert!(config_from(&[("MCP_HTTP_MAX_CONNECTIONS", "0")]).is_err());
+            assert!(config_from(&[("MCP_HTTP_MAX_CONNECTIONS", "many")]).is_err());
+            assert!(config_from(&[("MCP_HTTP_IDLE_TIMEOUT_MS", "0")]).is_err());
+        }
+
+        #[test]
+        fn config_refuses_public_bind_without_auth() {
+            let err = config_from(&[("MCP_HTTP_ADDR", "0.0.0.0:8080")]).unwrap_err();
+            assert!(err.to_string().contains("without client auth"));
+
+            let err = config_from(&[
+                ("MCP_HTTP_ADDR", "0.0.0.0:8080"),
+                ("MCP_HTTP_BEARER_TOKEN", "s3cret"),
+            ])
+            .unwrap_err();
+            assert!(err.to_string().contains("without TLS"));
+
+            let config = config_from(&[
+                ("MCP_HTTP_ADDR", "0.0.0.0:8080"),
+                ("MCP_HTTP_BEARER_TOKEN", "s3cret"),
+                ("MCP_HTTP_ALLOW_PLAINTEXT", "true"),
+            ])
+            .unwrap();
+            assert!(config.server_config().allowed_hosts.is_empty());
+
+            let config = config_from(&[
+                ("MCP_HTTP_ADDR", "0.0.0.0:8443"),
+                ("MCP_HTTP_TLS_CERT", "/etc/mcp/cert.pem"),
+                ("MCP_HTTP_TLS_KEY", "/etc/mcp/key.pem"),
+                ("MCP_HTTP_CLIENT_CA", "/etc/mcp/ca.pem"),
+                (
+                    "MCP_HTTP_ALLOWED_HOSTS",
+                    "mcp.example.com, mcp.example.com:8443",
+                ),
+            ])
+            .unwrap();
+            assert_eq!(
+                config.server_config().allowed_hosts,
+                vec!["mcp.example.com", "mcp.example.com:8443"]
+            );
+        }
+
+        #[test]
+        fn config_rejects_partial_tls() {
+            assert!(config_from(&[("MCP_HTTP_TLS_CERT", "/c.pem")]).is_err());
+            assert!(config_from(&[("MCP_HTTP_CLIENT_CA", "/ca.pem")]).is_err());
+            assert!(config_from(&[("MCP_HTTP_PATH", "mcp")]).is_err());
+        }
+
+        #[test]
+        fn bearer_check_requires_exact_token() {
+            assert!(bearer_matches(Some("Bearer s3cret"), "s3cret"));
+            assert!(bearer_matches(Some("bearer  s3cret "), "s3cret"));
+            assert!(!bearer_matches(Some("Bearer s3cre"), "s3cret"));
+            assert!(!bearer_matches(Some("Basic s3cret"), "s3cret"));
+            assert!(!bearer_matches(Some("s3cret"), "s3cret"));
+            assert!(!bearer_matches(None, "s3cret"));
+        }
+
+        async fn post(port: u16, path: &str, auth: Option<&str>, body: &str) -> String {
+            let mut socket = tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .unwrap();
+            let auth = auth
+                .map(|token| format!("Authorization: Bearer {token}\r\n"))
+                .unwrap_or_default();
+            let request = format!(
+                "POST {path} HTTP/1.1\r\nHost: 127.0.0.1:{port}\r\n{auth}Content-Type: application/json\r\nAccept: application/json, text/event-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(request.as_bytes()).await.unwrap();
+            let mut response = Vec::new();
+            let _ = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                socket.read_to_end(&mut response),
+            )
+            .await;
+            String::from_utf8_lossy(&response).into_owned()
+        }
+
+        #[tokio::test]
+        async fn http_transport_checks_bearer_token_before_mcp() {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let config = config_from(&[("MCP_HTTP_BEARER_TOKEN", "s3cret")]).unwrap();
+            tokio::spawn(serve(listener, config, ToolExecutor::from_env()));
+
+            let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18","capabilities":{},"clientInfo":{"name":"test","version":"0"}}}"#;
+
+            let response = post(port, "/mcp", None, initialize).await;
+            assert!(response.starts_with("HTTP/1.1 401"), "{response}");
+            assert!(response.contains("www-authenticate: Bearer"), "{response}");
+
+            let response = post(port, "/other", Some("s3cret"), initialize).await;
+            assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+
+            let response = post(port, "/mcp", Some("s3cret"), initialize).await;
+            assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+            assert!(
+                response.to_ascii_lowercase().contains("mcp-session-id"),
+                "{response}"
+            );
+            assert!(response.contains("\"serverInfo\""), "{response}");
+        }
+
+        #[tokio::test]
+        async fn http_transport_holds_clients_beyond_the_connection_limit() {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let config = config_from(&[("MCP_HTTP_MAX_CONNECTIONS", "1")]).unwrap();
+            tokio::spawn(serve(listener, config, ToolExecutor::from_env()));
+
+            let idle = tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            let waiting = tokio::spawn(async move { post(port, "/other", None, "{}").await });
+            tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+            assert!(!waiting.is_finished());
+
+            drop(idle);
+            let response = waiting.await.unwrap();
+            assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+        }
+
+        #[tokio::test]
+        async fn http_transport_closes_idle_connections() {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let config = config_from(&[
+                ("MCP_HTTP_MAX_CONNECTIONS", "1"),
+                ("MCP_HTTP_IDLE_TIMEOUT_MS", "200"),
+            ])
+            .unwrap();
+            tokio::spawn(serve(listener, config, ToolExecutor::from_env()));
+
+            let mut idle = tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .unwrap();
+            let response = post(port, "/other", None, "{}").await;
+            assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+            let mut buf = Vec::new();
+            let closed = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                idle.read_to_end(&mut buf),
+            )
+            .await;
+            assert!(closed.is_ok(), "idle connection was not closed");
+        }
+    }
+}
+
+pub mod db {
+    use anyhow::Result;
+    use base64::Engine;
+    use scylla::query::Query;
+    use scylla::routing::Shard;
+    use scylla::statement::{PagingState, PagingStateResponse};
+    use scylla::transport::execution_profile::ExecutionProfileHandle;
+    use scylla::transport::load_balancing::{FallbackPlan, LoadBalancingPolicy, RoutingInfo};
+    use scylla::transport::{ClusterData, ExecutionProfile, NodeRef};
+    use scylla::SessionBuilder;
+    use scylla_cql::frame::response::result::CqlValue;
+    use scylla_cql::frame::response::result::Row;
+    use scylla_cql::frame::value::CqlTimestamp;
+    use serde::Serialize;
+    use serde_json::{Map, Value};
+    use std::env;
+    use tracing::info;
+
+    const MAX_PAGING_CURSOR_BYTES: usize = 16 * 1024;
+    const MAX_SEARCH_SCHEMA_PATTERN_BYTES: usize = 128;
+    const MAX_SEARCH_SCHEMA_RESULTS: usize = 500;
+    const MAX_SEARCH_SCHEMA_REGEX_BYTES: usize = 256 * 1024;
+    const MAX_SEARCH_SCHEMA_REGEX_NESTING: u32 = 16;
+    const TABLETS_PAGE_SIZE: i32 = 1000;
+
+    type ProgressFn = dyn Fn(u64, Option<u64>, &str) + Send + Sync;
+
+    /// Reports how far a multi-step operation has got; does nothing unless the
+    /// caller asked for progress.
+    #[derive(Clone, Default)]
+    pub struct Progress {
+        report: Option<std::sync::Arc<ProgressFn>>,
+    }
+
+    impl Progress {
+        pub fn new(report: impl Fn(u64, Option<u64>, &str) + Send + Sync + 'static) -> Self {
+            Self {
+                report: Some(std::sync::Arc::new(report)),
+            }
+        }
+
+        /// `done` steps out of `total` (if known) have finished.
+        pub fn step(&self, done: u64, total: Option<u64>, message: &str) {
+            if let Some(report) = &self.report {
+                report(done, total, message);
+            }
+        }
+    }
+
+    impl std::fmt::Debug for Progress {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Progress")
+                .field("enabled", &self.report.is_some())
+                .finish()
+        }
+    }
+
+    pub async fn list_keyspaces() -> Result<Vec<String>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, "connecting to scylla");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        let result = session
+            .query_unpaged("SELECT keyspace_name FROM system_schema.keyspaces", &[])
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String,)>()? {
+            let (name,) = row?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    pub async fn list_keyspaces_with(session: &scylla::Session) -> Result<Vec<String>> {
+        let result = session
+            .query_unpaged("SELECT keyspace_name FROM system_schema.keyspaces", &[])
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String,)>()? {
+            let (name,) = row?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    pub async fn list_tables(keyspace: &str) -> Result<Vec<String>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, %keyspace, "listing tables");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        let result = session
+            .query_unpaged(
+                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ?",
+                (keyspace.to_string(),),
+            )
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String,)>()? {
+            let (name,) = row?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    pub async fn list_tables_with(
+        session: &scylla::Session,
+        keyspace: &str,
+    ) -> Result<Vec<String>> {
+        let result = session
+            .query_unpaged(
+                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ?",
+                (keyspace.to_string(),),
+            )
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String,)>()? {
+            let (name,) = row?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    #[derive(Debug, Serialize, Clone)]
+    pub struct ColumnMeta {
+        pub column_name: String,
+        pub kind: String,
+        pub position: i32,
+        pub r#type: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub clustering_order: Option<String>,
+    }
+
+    #[derive(Debug, Serialize, Clone)]
+    pub struct DescribeTable {
+        pub keyspace: String,
+        pub table: String,
+        pub partition_keys: Vec<String>,
+        pub clustering_keys: Vec<String>,
+        pub columns: Vec<ColumnMeta>,
+    }
+
+    pub async fn describe_table(keyspace: &str, table: &str) -> Result<DescribeTable> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, %keyspace, %table, "describe table");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        describe_table_with(&session, keyspace, table).await
+    }
+
+    pub async fn describe_table_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+    ) -> Result<DescribeTable> {
+        // Reuse logic by calling the existing function for now
+        // (In a later pass, this can be deduplicated to avoid double sessions.)
+        // For now, re-implement against provided session to avoid new connections.
+        let result = session
+            .query_unpaged(
+                "SELECT column_name, kind, position, type, clustering_order FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ?",
+                (keyspace.to_string(), table.to_string()),
+            )
+            .await?;
+        let specs = result.col_specs().to_owned();
+        let mut idx_name = None;
+        let mut idx_kind = None;
+        let mut idx_pos = None;
+        let mut idx_type = None;
+        let mut idx_order = None;
+        for (i, spec) in specs.iter().enumerate() {
+            match spec.name.as_str() {
+                "column_name" => idx_name = Some(i),
+                "kind" => idx_kind = Some(i),
+                "position" => idx_pos = Some(i),
+                "type" => idx_type = Some(i),
+                "clustering_order" => idx_order = Some(i),
+                _ => {}
+            }
+        }
+        let rows = result.rows_or_empty();
+        let mut cols = Vec::new();
+        for row in rows.iter() {
+            let name = idx_name
+                .and_then(|i| row.columns.get(i))
+                .and_then(|o| o.as_ref())
+                .and_then(|v| match v {
+                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let kind = idx_kind
+                .and_then(|i| row.columns.get(i))
+                .and_then(|o| o.as_ref())
+                .and_then(|v| match v {
+                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let pos = idx_pos
+                .and_then(|i| row.columns.get(i))
+                .and_then(|o| o.as_ref())
+                .and_then(|v| match v {
+                    CqlValue::Int(i) => Some(*i),
+                    _ => None,
+                })
+                .unwrap_or(0);
+            let ty = idx_type
+                .and_then(|i| row.columns.get(i))
+                .and_then(|o| o.as_ref())
+                .and_then(|v| match v {
+                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let order = idx_order
+                .and_then(|i| row.columns.get(i))
+                .and_then(|o| o.as_ref())
+                .and_then(|v| match v {
+                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
+                    _ => None,
+                });
+            cols.push(ColumnMeta {
+                column_name: name,
+                kind,
+                position: pos,
+                r#type: ty,
+                clustering_order: order,
+            });
+        }
+        let mut part: Vec<(i32, String)> = cols
+            .iter()
+            .filter(|c| c.kind == "partition_key")
+            .map(|c| (c.position, c.column_name.clone()))
+            .collect();
+        part.sort_by_key(|(pos, _)| *pos);
+        let partition_keys: Vec<String> = part.into_iter().map(|(_, n)| n).collect();
+        let mut clust: Vec<(i32, String)> = cols
+            .iter()
+            .filter(|c| c.kind == "clustering")
+            .map(|c| (c.position, c.column_name.clone()))
+            .collect();
+        clust.sort_by_key(|(pos, _)| *pos);
+        let clustering_keys: Vec<String> = clust.into_iter().map(|(_, n)| n).collect();
+        Ok(DescribeTable {
+            keyspace: keyspace.to_string(),
+            table: table.to_string(),
+            partition_keys,
+            clustering_keys,
+            columns: cols,
+        })
+    }
+
+    fn cql_value_to_json(v: &CqlValue) -> Value {
+        match v {
+            CqlValue::Boolean(b) => Value::Bool(*b),
+            CqlValue::Int(i) => Value::from(*i),
+            CqlValue::BigInt(i) => Value::from(*i),
+            CqlValue::Float(f) => Value::from(*f),
+            CqlValue::Double(f) => Value::from(*f),
+            CqlValue::Ascii(s) | CqlValue::Text(s) => Value::from(s.clone()),
+            CqlValue::Uuid(u) => Value::from(u.to_string()),
+            CqlValue::Timeuuid(u) => Value::from(u.to_string()),
+            CqlValue::Inet(ip) => Value::from(ip.to_string()),
+            CqlValue::Blob(bytes) => {
+                use base64::engine::general_purpose::STANDARD as B64;
+                use base64::Engine;
+                Value::from(B64.encode(bytes))
+            }
+            CqlValue::List(items) => Value::Array(items.iter().map(cql_value_to_json).collect()),
+            CqlValue::Set(items) => Value::Array(items.iter().map(cql_value_to_json).collect()),
+            CqlValue::Map(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| (cql_map_key_to_string(k), cql_value_to_json(v)))
+                    .collect(),
+            ),
+            CqlValue::Tuple(values) => Value::Array(
+                values
+                    .iter()
+                    .map(|opt| opt.as_ref().map(cql_value_to_json).unwrap_or(Value::Null))
+                    .collect(),
+            ),
+            _ => Value::from(format!("{:?}", v)),
+        }
+    }
+
+    fn cql_map_key_to_string(k: &CqlValue) -> String {
+        match k {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => s.clone(),
+            CqlValue::Uuid(u) => u.to_string(),
+            CqlValue::Int(i) => i.to_string(),
+            CqlValue::BigInt(i) => i.to_string(),
+            CqlValue::Inet(ip) => ip.to_string(),
+            _ => format!("{:?}", k),
+        }
+    }
+
+    pub async fn sample_rows(
+        keyspace: &str,
+        table: &str,
+        limit: u32,
+        filters: Option<&Map<String, Value>>,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, %keyspace, %table, %limit, "sample rows");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
+        let cql = build_select_all_query(keyspace, table, &where_clause, limit)?;
+        let prepared = session.prepare(cql).await?;
+        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
+        let specs = result.col_specs().to_owned();
+        let rows: Vec<Row> = result.rows()?;
+        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    pub async fn sample_rows_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+        limit: u32,
+        filters: Option<&Map<String, Value>>,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
+        let cql = build_select_all_query(keyspace, table, &where_clause, limit)?;
+        let prepared = session.prepare(cql).await?;
+        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
+        let specs = result.col_specs().to_owned();
+        let rows: Vec<Row> = result.rows()?;
+        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    /// CQL keywords that must be double-quoted when used as identifiers.
+    const CQL_RESERVED_KEYWORDS: &[&str] = &[
+        "add",
+        "allow",
+        "alter",
+        "and",
+        "apply",
+        "asc",
+        "authorize",
+        "batch",
+        "begin",
+        "by",
+        "columnfamily",
+        "create",
+        "default",
+        "delete",
+        "desc",
+        "describe",
+        "drop",
+        "entries",
+        "execute",
+        "from",
+        "full",
+        "grant",
+        "if",
+        "in",
+        "index",
+        "infinity",
+        "insert",
+        "into",
+        "is",
+        "keyspace",
+        "limit",
+        "materialized",
+        "mbean",
+        "mbeans",
+        "modify",
+        "nan",
+        "norecursive",
+        "not",
+        "null",
+        "of",
+        "on",
+        "or",
+        "order",
+        "primary",
+        "rename",
+        "replace",
+        "revoke",
+        "schema",
+        "select",
+        "set",
+        "table",
+        "to",
+        "token",
+        "truncate",
+        "unlogged",
+        "unset",
+        "update",
+        "use",
+        "using",
+        "view",
+        "where",
+        "with",
+    ];
+
+    const MAX_IDENT_BYTES: usize = 256;
+
+    /// An exact (case-sensitive) CQL identifier, rendered bare when CQL would
+    /// read it back unchanged and double-quoted with `"` escaped otherwise.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct CqlIdent {
+        name: String,
+    }
+
+    impl CqlIdent {
+        /// Wrap an exact schema name as stored in `system_schema`.
+        pub fn new(name: &str) -> Result<Self> {
+            if name.is_empty() {
+                anyhow::bail!("identifier must not be empty");
+            }
+            if name.len() > MAX_IDENT_BYTES {
+                anyhow::bail!("identifier is too long");
+            }
+            if name.chars().any(char::is_control) {
+                anyhow::bail!("identifier must not contain control characters");
+            }
+            Ok(Self {
+                name: name.to_string(),
+            })
+        }
+
+        /// Keyspace and table names are limited to `[A-Za-z0-9_]` even when quoted.
+        pub fn schema_object(name: &str) -> Result<Self> {
+            let ident = Self::new(name)?;
+            if !name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_') {
+                anyhow::bail!("keyspace and table names may only contain letters, digits and '_'");
+            }
+            Ok(ident)
+        }
+
+        /// Parse client input: either an exact name or a CQL double-quoted
+        /// identifier such as `"Order ""Items"""`.
+        pub fn parse(input: &str) -> Result<Self> {
+            let Some(inner) = input
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+            else {
+                return Self::new(input);
+            };
+            let mut name = String::with_capacity(inner.len());
+            let mut chars = inner.chars().peekable();
+            while let Some(c) = chars.next() {
+                if c == '"' && chars.next_if_eq(&'"').is_none() {
+                    anyhow::bail!("unescaped '\"' inside quoted identifier");
+                }
+                name.push(c);
+            }
+            Self::new(&name)
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn needs_quotes(&self) -> bool {
+            let bytes = self.name.as_bytes();
+            let plain = bytes[0].is_ascii_lowercase()
+                && bytes
+                    .iter()
+                    .all(|&c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'_');
+            !plain || CQL_RESERVED_KEYWORDS.contains(&self.name.as_str())
+        }
+
+        pub fn to_cql(&self) -> String {
+            if self.needs_quotes() {
+                format!("\"{}\"", self.name.replace('"', "\"\""))
+            } else {
+                self.name.clone()
+            }
+        }
+
+        /// `name` as CQL for generated DDL and examples, left as-is when it is
+        /// not a valid identifier (e.g. empty) rather than failing the render.
+        pub fn quote_or_raw(name: &str) -> String {
+            Self::new(name)
+                .map(|ident| ident.to_cql())
+                .unwrap_or_else(|_| name.to_string())
+        }
+    }
+
+    impl std::fmt::Display for CqlIdent {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(&self.to_cql())
+        }
+    }
+
+    fn table_ref(keyspace: &str, table: &str) -> Result<String> {
+        let keyspace = CqlIdent::schema_object(keyspace)
+            .map_err(|_| anyhow::anyhow!("invalid keyspace identifier"))?;
+        let table = CqlIdent::schema_object(table)
+            .map_err(|_| anyhow::anyhow!("invalid table identifier"))?;
+        Ok(format!("{}.{}", keyspace, table))
+    }
+
+    fn build_column_list(columns: &[String]) -> Result<String> {
+        if columns.is_empty() {
+            anyhow::bail!("columns must not be empty");
+        }
+        let idents = columns
+            .iter()
+            .map(|c| {
+                CqlIdent::new(c)
+                    .map(|ident| ident.to_cql())
+                    .map_err(|_| anyhow::anyhow!("invalid column name"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(idents.join(", "))
+    }
+
+    fn build_select_all_query(
+        keyspace: &str,
+        table: &str,
+        where_clause: &str,
+        limit: u32,
+    ) -> Result<String> {
+        Ok(format!(
+            "SELECT * FROM {}{} LIMIT {}",
+            table_ref(keyspace, table)?,
+            where_clause,
+            limit
+        ))
+    }
+
+    fn build_select_columns_query(
+        keyspace: &str,
+        table: &str,
+        col_list: &str,
+        where_clause: &str,
+        order_clause: &str,
+        limit: Option<u32>,
+    ) -> Result<String> {
+        let limit_clause = limit
+            .map(|limit| format!(" LIMIT {}", limit))
+            .unwrap_or_default();
+        Ok(format!(
+            "SELECT {} FROM {}{}{}{}",
+            col_list,
+            table_ref(keyspace, table)?,
+            where_clause,
+            order_clause,
+            limit_clause
+        ))
+    }
+
+    fn decode_paging_cursor(token: &str) -> Result<Vec<u8>> {
+        let max_encoded = MAX_PAGING_CURSOR_BYTES.div_ceil(3) * 4;
+        if token.len() > max_encoded {
+            anyhow::bail!("paging cursor is too large");
+        }
+        let bytes = base64::engine::general_purpose::STANDARD.decode(token.as_bytes())?;
+        if bytes.len() > MAX_PAGING_CURSOR_BYTES {
+            anyhow::bail!("paging cursor is too large");
+        }
+        Ok(bytes)
+    }
+
+    fn validate_search_schema_pattern(pattern: &str) -> Result<()> {
+        if pattern.trim().is_empty() {
+            anyhow::bail!("search pattern must not be empty");
+        }
+        if pattern.len() > MAX_SEARCH_SCHEMA_PATTERN_BYTES {
+            anyhow::bail!("search pattern is too large");
+        }
+        Ok(())
+    }
+
+    fn push_schema_result(out: &mut Vec<Map<String, Value>>, item: Map<String, Value>) -> bool {
+        out.push(item);
+        out.len() < MAX_SEARCH_SCHEMA_RESULTS
+    }
+
+    fn build_filters_clause_prepared(
+        filters: Option<&Map<String, Value>>,
+    ) -> Result<(String, Vec<CqlValue>)> {
+        let mut clause = String::new();
+        let mut values: Vec<CqlValue> = Vec::new();
+        if let Some(map) = filters {
+            let mut first = true;
+            for (k, v) in map.iter() {
+                let ident = CqlIdent::new(k)
+                    .map_err(|_| anyhow::anyhow!("invalid column name in filters"))?;
+                clause.push_str(if first { " WHERE " } else { " AND " });
+                first = false;
+                clause.push_str(&ident.to_cql());
+                clause.push_str(" = ?");
+                let cv = match v {
+                    Value::String(s) => CqlValue::Text(s.clone()),
+                    Value::Bool(b) => CqlValue::Boolean(*b),
+                    Value::Number(n) => {
+                        if let Some(i) = n.as_i64() {
+                            CqlValue::BigInt(i)
+                        } else if let Some(f) = n.as_f64() {
+                            CqlValue::Double(f)
+                        } else {
+                            anyhow::bail!("unsupported numeric value")
+                        }
+                    }
+                    _ => anyhow::bail!("unsupported filter value type"),
+                };
+                values.push(cv);
+            }
+        }
+        Ok((clause, values))
+    }
+
+    pub async fn select_columns(
+        keyspace: &str,
+        table: &str,
+        columns: &[String],
+        limit: u32,
+        filters: Option<&Map<String, Value>>,
+        order_by: Option<&Vec<(String, String)>>,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, %keyspace, %table, %limit, cols=?columns, "select columns");
+        let col_list = build_column_list(columns)?;
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
+        let order_clause = build_order_by_clause(order_by)?;
+        let cql = build_select_columns_query(
+            keyspace,
+            table,
+            &col_list,
+            &where_clause,
+            &order_clause,
+            Some(limit),
+        )?;
+        let prepared = session.prepare(cql).await?;
+        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
+        let specs = result.col_specs().to_owned();
+        let rows: Vec<Row> = result.rows()?;
+        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    pub async fn select_columns_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+        columns: &[String],
+        limit: u32,
+        filters: Option<&Map<String, Value>>,
+        order_by: Option<&Vec<(String, String)>>,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let col_list = build_column_list(columns)?;
+        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
+        let order_clause = build_order_by_clause(order_by)?;
+        let cql = build_select_columns_query(
+            keyspace,
+            table,
+            &col_list,
+            &where_clause,
+            &order_clause,
+            Some(limit),
+        )?;
+        let prepared = session.prepare(cql).await?;
+        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
+        let specs = result.col_specs().to_owned();
+        let rows: Vec<Row> = result.rows()?;
+        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    /// Fetch rows by full partition key using a strictly prepared statement.
+    /// Requires the provided `partition` map to contain exactly the set of partition key columns.
+    pub async fn partition_rows_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+        partition: &Map<String, Value>,
+        limit: u32,
+    ) -> Result<Vec<Map<String, Value>>> {
+        // Describe table to determine partition key order
+        let meta = crate::db::describe_table_with(session, keyspace, table).await?;
+        let expected: std::collections::HashSet<String> =
+            meta.partition_keys.iter().cloned().collect();
+        let provided: std::collections::HashSet<String> = partition.keys().cloned().collect();
+        if expected != provided {
+            anyhow::bail!(
+                "partition keys mismatch: expected {:?}",
+                meta.partition_keys
+            );
+        }
+        // Build WHERE clause in schema PK order and bind values accordingly
+        let mut where_clause = String::from(" WHERE ");
+        let mut first = true;
+        let mut bind_values: Vec<CqlValue> = Vec::with_capacity(meta.partition_keys.len());
+        for pk in meta.partition_keys.iter() {
+            if !first {
+                where_clause.push_str(" AND ");
+            } else {
+                first = false;
+            }
+            where_clause.push_str(&CqlIdent::new(pk)?.to_cql());
+            where_clause.push_str(" = ?");
+            let v = partition.get(pk).expect("checked equality above");
+            let cv = match v {
+                Value::String(s) => CqlValue::Text(s.clone()),
+                Value::Bool(b) => CqlValue::Boolean(*b),
+                Value::Number(n) => {
+                    if let Some(i) = n.as_i64() {
+                        CqlValue::BigInt(i)
+                    } else if let Some(f) = n.as_f64() {
+                        CqlValue::Double(f)
+                    } else {
+                        anyhow::bail!("unsupported numeric value")
+                    }
+                }
+                _ => anyhow::bail!("unsupported partition value type"),
+            };
+            bind_values.push(cv);
+        }
+        let cql = build_select_all_query(keyspace, table, &where_clause, limit)?;
+        let prepared = session.prepare(cql).await?;
+        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
+        let specs = result.col_specs().to_owned();
+        let rows: Vec<Row> = result.rows()?;
+        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    /// Convert a JSON argument to a CQL value of column type `cql_type`, so the
+    /// driver serializes it exactly as the server does for token computation.
+    pub fn json_to_typed_cql(value: &Value, cql_type: &str) -> Result<CqlValue> {
+        let ty = cql_type.trim().to_ascii_lowercase();
+        let mismatch = || anyhow::anyhow!("value {} does not match CQL type '{}'", value, ty);
+        let int = || value.as_i64().ok_or_else(mismatch);
+        Ok(match ty.as_str() {
+            "text" | "varchar" => CqlValue::Text(value.as_str().ok_or_else(mismatch)?.to_string()),
+            "ascii" => CqlValue::Ascii(value.as_str().ok_or_else(mismatch)?.to_string()),
+            "boolean" => CqlValue::Boolean(value.as_bool().ok_or_else(mismatch)?),
+            "tinyint" => CqlValue::TinyInt(i8::try_from(int()?).map_err(|_| mismatch())?),
+            "smallint" => CqlValue::SmallInt(i16::try_from(int()?).map_err(|_| mismatch())?),
+            "int" => CqlValue::Int(i32::try_from(int()?).map_err(|_| mismatch())?),
+            "bigint" => CqlValue::BigInt(int()?),
+            "float" => CqlValue::Float(value.as_f64().ok_or_else(mismatch)? as f32),
+            "double" => CqlValue::Double(value.as_f64().ok_or_else(mismatch)?),
+            "uuid" | "timeuuid" => {
+                let parsed: scylla_cql::frame::value::CqlTimeuuid = value
+                    .as_str()
+                    .ok_or_else(mismatch)?
+                    .parse()
+                    .map_err(|_| mismatch())?;
+                if ty == "uuid" {
+                    CqlValue::Uuid(parsed.into())
+                } else {
+                    CqlValue::Timeuuid(parsed)
+                }
+            }
+            "timestamp" => CqlValue::Timestamp(CqlTimestamp(
+                crate::cdc::parse_timestamp_ms(value).map_err(|_| mismatch())?,
+            )),
+            "inet" => CqlValue::Inet(
+                value
+                    .as_str()
+                    .ok_or_else(mismatch)?
+                    .parse()
+                    .map_err(|_| mismatch())?,
+            ),
+            "blob" => {
+                let text = value.as_str().ok_or_else(mismatch)?;
+                let bytes = match text.strip_prefix("0x") {
+                    // Byte-offset slicing below needs ASCII; anything else is not hex anyway.
+                    Some(hex) if hex.is_ascii() && hex.len() % 2 == 0 => (0..hex.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                        .collect::<std::result::Result<Vec<u8>, _>>()
+                        .map_err(|_| mismatch())?,
+                    Some(_) => return Err(mismatch()),
+                    None => base64::engine::general_purpose::STANDARD
+                        .decode(text)
+                        .map_err(|_| mismatch())?,
+                };
+                CqlValue::Blob(bytes)
+            }
+            _ => anyhow::bail!("unsupported partition key type '{}'", ty),
+        })
+    }
+
+    /// Token of a full partition key and the replicas owning it, grouped by datacenter.
+    /// Values are typed per the schema and hashed by the driver with the table's partitioner.
+    pub async fn locate_partition_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+        partition: &Map<String, Value>,
+    ) -> Result<Map<String, Value>> {
+        let meta = describe_table_with(session, keyspace, table).await?;
+        let expected: std::collections::HashSet<String> =
+            meta.partition_keys.iter().cloned().collect();
+        let provided: std::collections::HashSet<String> = partition.keys().cloned().collect();
+        if expected != provided {
+            anyhow::bail!(
+                "partition keys mismatch: expected {:?}",
+                meta.partition_keys
+            );
+        }
+        let mut where_clause = String::from(" WHERE ");
+        let mut bind_values: Vec<CqlValue> = Vec::with_capacity(meta.partition_keys.len());
+        for (i, pk) in meta.partition_keys.iter().enumerate() {
+            if i > 0 {
+                where_clause.push_str(" AND ");
+            }
+            where_clause.push_str(&CqlIdent::new(pk)?.to_cql());
+            where_clause.push_str(" = ?");
+            let column = meta
+                .columns
+                .iter()
+                .find(|c| &c.column_name == pk)
+                .ok_or_else(|| anyhow::anyhow!("partition key column '{}' not found", pk))?;
+            let value = partition.get(pk).expect("checked equality above");
+            bind_values.push(
+                json_to_typed_cql(value, &column.r#type)
+                    .map_err(|err| anyhow::anyhow!("invalid value for '{}': {}", pk, err))?,
+            );
+        }
+        let cql = build_select_all_query(keyspace, table, &where_clause, 1)?;
+        let prepared = session.prepare(cql).await?;
+        let token = prepared
+            .calculate_token(&bind_values)?
+            .ok_or_else(|| anyhow::anyhow!("could not compute a token for this partition key"))?;
+
+        let mut cluster = session.get_cluster_data();
+        if !cluster.get_keyspace_info().contains_key(keyspace) {
+            session.refresh_metadata().await?;
+            cluster = session.get_cluster_data();
+        }
+        let mut datacenters: std::collections::BTreeMap<String, Vec<Value>> =
+            std::collections::BTreeMap::new();
+        let replicas = cluster.get_token_endpoints(keyspace, table, token);
+        for (node, shard) in replicas.iter() {
+            datacenters
+                .entry(node.datacenter.clone().unwrap_or_else(|| "unknown".into()))
+                .or_default()
+                .push(serde_json::json!({
+                    "host_id": node.host_id.to_string(),
+                    "address": node.address.to_string(),
+                    "rack": node.rack,
+                    "shard": shard,
+                    "up": !node.is_down(),
+                }));
+        }
+
+        let mut out = Map::new();
+        out.insert("keyspace".into(), Value::from(keyspace));
+        out.insert("table".into(), Value::from(table));
+        out.insert("partition".into(), Value::Object(partition.clone()));
+        out.insert("token".into(), Value::from(token.value()));
+        out.insert(
+            "replication".into(),
+            keyspace_replication_with(session, keyspace)
+                .await?
+                .remove("replication")
+                .unwrap_or(Value::Null),
+        );
+        out.insert("replica_count".into(), Value::from(replicas.len()));
+        out.insert("datacenters".into(), serde_json::json!(datacenters));
+        Ok(out)
+    }
+
+    /// Wrapper that creates a session, then calls `partition_rows_with`.
+    pub async fn partition_rows(
+        keyspace: &str,
+        table: &str,
+        partition: &Map<String, Value>,
+        limit: u32,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, %keyspace, %table, limit = limit as i64, "partition rows");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        partition_rows_with(&session, keyspace, table, partition, limit).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn paged_select_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+        columns: &[String],
+        page_size: i32,
+        filters: Option<&Map<String, Value>>,
+        order_by: Option<&Vec<(String, String)>>,
+        cursor: Option<&str>,
+    ) -> Result<Map<String, Value>> {
+        let col_list = build_column_list(columns)?;
+        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
+        let order_clause = build_order_by_clause(order_by)?;
+        let cql = build_select_columns_query(
+            keyspace,
+            table,
+            &col_list,
+            &where_clause,
+            &order_clause,
+            None,
+        )?;
+        let prepared = session
+            .prepare(Query::new(cql).with_page_size(page_size))
+            .await?;
+        let paging_state = match cursor {
+            Some(tok) => {
+                let bytes = decode_paging_cursor(tok)?;
+                PagingState::new_from_raw_bytes(bytes)
+            }
+            None => PagingState::start(),
+        };
+        let (result, paging_resp) = session
+            .execute_single_page(&prepared, &bind_values[..], paging_state)
+            .await?;
+        let specs = result.col_specs().to_owned();
+        let rows: Vec<Row> = result.rows_or_empty();
+        let mut items: Vec<Value> = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            items.push(Value::Object(m));
+        }
+        let next_cursor = match paging_resp {
+            PagingStateResponse::HasMorePages { state } => state
+                .as_bytes_slice()
+                .map(|arc| {
+                    let slice: &[u8] = arc.as_ref();
+                    Value::String(base64::engine::general_purpose::STANDARD.encode(slice))
+                })
+                .unwrap_or(Value::Null),
+            PagingStateResponse::NoMorePages => Value::Null,
+        };
+        let mut out = Map::new();
+        out.insert("items".into(), Value::Array(items));
+        out.insert("next_cursor".into(), next_cursor);
+        Ok(out)
+    }
+
+    fn build_order_by_clause(order_by: Option<&Vec<(String, String)>>) -> Result<String> {
+        if let Some(list) = order_by {
+            if list.is_empty() {
+                return Ok(String::new());
+            }
+            let mut clause = String::from(" ORDER BY ");
+            for (i, (col, dir)) in list.iter().enumerate() {
+                let ident = CqlIdent::new(col)
+                    .map_err(|_| anyhow::anyhow!("invalid column name in order_by"))?;
+                let d = match dir.to_ascii_lowercase().as_str() {
+                    "asc" => "ASC",
+                    "desc" => "DESC",
+                    _ => "ASC",
+                };
+                if i > 0 {
+                    clause.push_str(", ");
+                }
+                clause.push_str(&ident.to_cql());
+                clause.push(' ');
+                clause.push_str(d);
+            }
+            Ok(clause)
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    pub async fn cluster_topology() -> Result<Vec<Map<String, Value>>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, "cluster topology");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+
+        // Collect rows from system.local
+        let mut out: Vec<Map<String, Value>> = Vec::new();
+        let result_local = session
+            .query_unpaged(
+                "SELECT host_id, data_center, rack, rpc_address FROM system.local",
+                &[],
+            )
+            .await?;
+        let specs_local = result_local.col_specs().to_owned();
+        let rows_local: Vec<Row> = result_local.rows_or_empty();
+        for row in rows_local.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs_local.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            m.insert("source".into(), Value::from("local"));
+            out.push(m);
+        }
+
+        // Collect rows from system.peers (best-effort, may be empty)
+        let result_peers = session
+            .query_unpaged(
+                "SELECT host_id, data_center, rack, rpc_address FROM system.peers",
+                &[],
+            )
+            .await?;
+        let specs_peers = result_peers.col_specs().to_owned();
+        let rows_peers: Vec<Row> = result_peers.rows_or_empty();
+        for row in rows_peers.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs_peers.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            m.insert("source".into(), Value::from("peers"));
+            out.push(m);
+        }
+
+        Ok(out)
+    }
+
+    /// Upper bound on the per-node `cluster_topology` ping.
+    const TOPOLOGY_PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+    /// Nodes from `system.local`/`system.peers` merged with the driver's view:
+    /// versions, tokens and their count, shard counts, connection state and a
+    /// bounded ping latency, plus warnings for nodes that are down, unreachable
+    /// or lagging.
+    pub async fn cluster_topology_with(
+        session: &scylla::Session,
+    ) -> Result<Vec<Map<String, Value>>> {
+        cluster_topology_with_options(session, true).await
+    }
+
+    /// `cluster_topology_with`, pinging each node only when `ping` is set; callers
+    /// that just count nodes per datacenter skip the extra round trips.
+    pub async fn cluster_topology_with_options(
+        session: &scylla::Session,
+        ping: bool,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let mut out: Vec<Map<String, Value>> = Vec::new();
+        for (source, cql) in [
+            (
+                "local",
+                "SELECT host_id, data_center, rack, rpc_address, release_version, schema_version, tokens FROM system.local",
+            ),
+            (
+                "peers",
+                "SELECT host_id, data_center, rack, rpc_address, release_version, schema_version, tokens FROM system.peers",
+            ),
+        ] {
+            let result = session.query_unpaged(cql, &[]).await?;
+            for mut m in result_rows_as_maps(result) {
+                let tokens = m
+                    .get("tokens")
+                    .and_then(|t| t.as_array().map(Vec::len))
+                    .unwrap_or(0);
+                m.insert("token_count".into(), Value::from(tokens));
+                m.insert("source".into(), Value::from(source));
+                out.push(m);
+            }
+        }
+
+        let cluster = session.get_cluster_data();
+        let pings = cluster.get_nodes_info().iter().map(|node| async move {
+            let host_id = node.host_id.to_string();
+            if !ping {
+                return (node, host_id, None);
+            }
+            let query = node_query("SELECT now() FROM system.local", &host_id);
+            let started = std::time::Instant::now();
+            let ping = match tokio::time::timeout(
+                TOPOLOGY_PING_TIMEOUT,
+                session.query_unpaged(query, &[]),
+            )
+            .await
+            {
+                Ok(Ok(_)) => Ok(started.elapsed().as_secs_f64() * 1000.0),
+                Ok(Err(err)) => Err(err.to_string()),
+                Err(_) => Err(format!(
+                    "timed out after {} ms",
+                    TOPOLOGY_PING_TIMEOUT.as_millis()
+                )),
+            };
+            (node, host_id, Some(ping))
+        });
+        for (node, host_id, ping) in futures::future::join_all(pings).await {
+            let Some(m) = out
+                .iter_mut()
+                .find(|m| m.get("host_id").and_then(Value::as_str) == Some(host_id.as_str()))
+            else {
+                continue;
+            };
+            m.insert("address".into(), Value::from(node.address.to_string()));
+            m.insert(
+                "shard_count".into(),
+                node.sharder()
+                    .map(|s| Value::from(s.nr_shards.get()))
+                    .unwrap_or(Value::Null),
+            );
+            m.insert("connected".into(), Value::Bool(node.is_enabled()));
+            m.insert("up".into(), Value::Bool(!node.is_down()));
+            match ping {
+                Some(Ok(ms)) => {
+                    m.insert("ping_ms".into(), Value::from((ms * 100.0).round() / 100.0));
+                }
+                Some(Err(err)) => {
+                    m.insert("ping_ms".into(), Value::Null);
+                    m.insert("ping_error".into(), Value::from(err));
+                }
+                None => {}
+            }
+        }
+        annotate_topology_warnings(&mut out);
+        Ok(out)
+    }
+
+    /// Flag nodes that are down, failed the ping, or report a release or schema
+    /// version other than the one most nodes agree on.
+    fn annotate_topology_warnings(nodes: &mut [Map<String, Value>]) {
+        use std::collections::HashMap;
+
+        let majority = |key: &str, nodes: &[Map<String, Value>]| {
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for n in nodes {
+                if let Some(v) = n.get(key).and_then(Value::as_str) {
+                    *counts.entry(v.to_string()).or_default() += 1;
+                }
+            }
+            counts
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .map(|(v, _)| v)
+        };
+        let schema = majority("schema_version", nodes);
+        let release = majority("release_version", nodes);
+        for n in nodes.iter_mut() {
+            let mut warnings = Vec::new();
+            if n.get("up").and_then(Value::as_bool) == Some(false) {
+                warnings.push("driver marks node down".to_string());
+            }
+            if n.get("connected").and_then(Value::as_bool) == Some(false) {
+                warnings.push("driver holds no connection pool to node".to_string());
+            }
+            if let Some(err) = n.get("ping_error").and_then(Value::as_str) {
+                warnings.push(format!("ping failed: {}", err));
+            }
+            let differs = |key: &str, expected: &Option<String>| {
+                let v = n.get(key).and_then(Value::as_str)?;
+                let expected = expected.as_deref()?;
+                (v != expected).then(|| format!("{} {} differs from majority {}", key, v, expected))
+            };
+            warnings.extend(differs("schema_version", &schema));
+            warnings.extend(differs("release_version", &release));
+            if !warnings.is_empty() {
+                n.insert("warnings".into(), serde_json::json!(warnings));
+            }
+        }
+    }
+
+    pub async fn list_indexes(keyspace: &str, table: &str) -> Result<Vec<String>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, %keyspace, %table, "list indexes");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        list_indexes_with(&session, keyspace, table).await
+    }
+
+    pub async fn list_indexes_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+    ) -> Result<Vec<String>> {
+        let prepared = session
+            .prepare("SELECT index_name FROM system_schema.indexes WHERE keyspace_name = ? AND table_name = ?")
+            .await?;
+        let result = session
+            .execute_unpaged(&prepared, &(keyspace.to_string(), table.to_string()))
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String,)>()? {
+            let (name,) = row?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    pub async fn keyspace_replication(keyspace: &str) -> Result<Map<String, Value>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        info!(%uri, %keyspace, "keyspace replication");
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        keyspace_replication_with(&session, keyspace).await
+    }
+
+    pub async fn keyspace_replication_with(
+        session: &scylla::Session,
+        keyspace: &str,
+    ) -> Result<Map<String, Value>> {
+        let prepared = session
+            .prepare("SELECT replication, durable_writes FROM system_schema.keyspaces WHERE keyspace_name = ?")
+            .await?;
+        let result = session
+            .execute_unpaged(&prepared, &(keyspace.to_string(),))
+            .await?;
+        let rows = result.rows_or_empty();
+        let mut out = Map::new();
+        if let Some(row) = rows.first() {
+            if let Some(Some(CqlValue::Map(entries))) = row.columns.first().map(|o| o.as_ref()) {
+                let obj: Map<String, Value> = entries
+                    .iter()
+                    .map(|(k, v)| (cql_map_key_to_string(k), cql_value_to_json(v)))
+                    .collect();
+                out.insert("replication".into(), Value::Object(obj));
+            }
+            if let Some(Some(CqlValue::Boolean(b))) = row.columns.get(1).map(|o| o.as_ref()) {
+                out.insert("durable_writes".into(), Value::Bool(*b));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Hashes of each table's column definitions and of each keyspace's replication,
+    /// table set, table options, views, indexes and UDTs, used to tell which
+    /// resources a `schema_version` change touched.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct SchemaFingerprints {
+        pub tables: std::collections::BTreeMap<(String, String), u64>,
+        pub keyspaces: std::collections::BTreeMap<String, u64>,
+    }
+
+    /// Tables and keyspaces whose definitions differ between two fingerprints.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct SchemaChanges {
+        pub tables: std::collections::BTreeSet<(String, String)>,
+        pub keyspaces: std::collections::BTreeSet<String>,
+    }
+
+    impl SchemaFingerprints {
+        /// Changed, created and dropped tables; a keyspace counts as changed when its own
+        /// definition or any of its tables did.
+        pub fn diff(&self, newer: &Self) -> SchemaChanges {
+            let mut changes = SchemaChanges::default();
+            for key in self.tables.keys().chain(newer.tables.keys()) {
+                if self.tables.get(key) != newer.tables.get(key) {
+                    changes.tables.insert(key.clone());
+                    changes.keyspaces.insert(key.0.clone());
+                }
+            }
+            for key in self.keyspaces.keys().chain(newer.keyspaces.keys()) {
+                if self.keyspaces.get(key) != newer.keyspaces.get(key) {
+                    changes.keyspaces.insert(key.clone());
+                }
+            }
+            changes
+        }
+    }
+
+    /// The coordinator's `system.local.schema_version`.
+    pub async fn schema_version_with(session: &scylla::Session) -> Result<Option<String>> {
+        let result = session
+            .query_unpaged("SELECT schema_version FROM system.local", &[])
+            .await?;
+        Ok(result_rows_as_maps(result)
+            .into_iter()
+            .next()
+            .and_then(|row| row.get("schema_version").map(|v| v.to_string())))
+    }
+
+    pub async fn schema_fingerprints_with(session: &scylla::Session) -> Result<SchemaFingerprints> {
+        use std::hash::{Hash, Hasher};
+        let columns = session
+            .query_unpaged(
+                "SELECT keyspace_name, table_name, column_name, kind, position, type, clustering_order FROM system_schema.columns",
+                &[],
+            )
+            .await?;
+        let mut tables: std::collections::BTreeMap<(String, String), Vec<String>> =
+            std::collections::BTreeMap::new();
+        for row in result_rows_as_maps(columns) {
+            let name = |key: &str| {
+                row.get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            tables
+                .entry((name("keyspace_name"), name("table_name")))
+                .or_default()
+                .push(Value::Object(row.clone()).to_string());
+        }
+        // Table options (compaction, TTL, CDC extensions), views, indexes and UDTs
+        // only show up in the keyspace DDL, so they count toward the keyspace.
+        let mut definitions: std::collections::BTreeMap<String, Vec<String>> =
+            std::collections::BTreeMap::new();
+        for source in ["tables", "views", "indexes", "types"] {
+            let result = session
+                .query_unpaged(format!("SELECT * FROM system_schema.{source}"), &[])
+                .await?;
+            for row in result_rows_as_maps(result) {
+                let keyspace = row
+                    .get("keyspace_name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+                definitions
+                    .entry(keyspace)
+                    .or_default()
+                    .push(format!("{source}:{}", Value::Object(row)));
+            }
+        }
+        let keyspaces = session
+            .query_unpaged(
+                "SELECT keyspace_name, replication, durable_writes FROM system_schema.keyspaces",
+                &[],
+            )
+            .await?;
+        let hash = |parts: &[String]| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            parts.hash(&mut hasher);
+            hasher.finish()
+        };
+        let mut fingerprints = SchemaFingerprints::default();
+        for row in result_rows_as_maps(keyspaces) {
+            let keyspace = row
+                .get("keyspace_name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let mut parts = vec![Value::Object(row.clone()).to_string()];
+            parts.extend(
+                tables
+                    .keys()
+                    .filter(|(ks, _)| *ks == keyspace)
+                    .map(|(_, table)| table.clone()),
+            );
+            if let Some(mut rows) = definitions.remove(&keyspace) {
+                rows.sort();
+                parts.extend(rows);
+            }
+            fingerprints.keyspaces.insert(keyspace, hash(&parts));
+        }
+        for (key, mut rows) in tables {
+            rows.sort();
+            fingerprints.tables.insert(key, hash(&rows));
+        }
+        Ok(fingerprints)
+    }
+
+    /// Every `(keyspace, table)` pair in `system_schema.tables`.
+    pub async fn list_all_tables_with(session: &scylla::Session) -> Result<Vec<(String, String)>> {
+        let result = session
+            .query_unpaged(
+                "SELECT keyspace_name, table_name FROM system_schema.tables",
+                &[],
+            )
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String, String)>()? {
+            names.push(row?);
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// CQL that recreates a keyspace: the server's own `DESCRIBE KEYSPACE` output where
+    /// supported, otherwise a reconstruction of the keyspace and its tables' keys and columns.
+    pub async fn keyspace_ddl_with(session: &scylla::Session, keyspace: &str) -> Result<String> {
+        let ident = CqlIdent::schema_object(keyspace)
+            .map_err(|_| anyhow::anyhow!("invalid keyspace identifier"))?;
+        let keyspaces = list_keyspaces_with(session).await?;
+        if !keyspaces.iter().any(|ks| ks == keyspace) {
+            return Err(anyhow::Error::new(UnknownIdentifier {
+                kind: "keyspace",
+                name: keyspace.to_string(),
+                keyspace: None,
+                table: None,
+                suggestions: suggest_identifiers(keyspace, keyspaces.iter().map(String::as_str)),
+            }));
+        }
+
+        let described = session
+            .query_unpaged(format!("DESCRIBE KEYSPACE {ident}"), &[])
+            .await;
+        if let Ok(result) = described {
+            let statements: Vec<String> = result_rows_as_maps(result)
+                .into_iter()
+                .filter_map(|row| {
+                    row.get("create_statement")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                })
+                .collect();
+            if !statements.is_empty() {
+                return Ok(statements.join("\n\n") + "\n");
+            }
+        }
+
+        let replication = keyspace_replication_with(session, keyspace).await?;
+        let mut statements = vec![render_keyspace_ddl(keyspace, &replication)];
+        for table in list_tables_with(session, keyspace).await? {
+            let schema = describe_table_with(session, keyspace, &table).await?;
+            statements.push(render_table_ddl(&schema));
+        }
+        Ok(format!(
+            "-- Reconstructed from system_schema: table options, indexes and views are omitted.\n{}\n",
+            statements.join("\n\n")
+        ))
+    }
+
+    /// `CREATE KEYSPACE` from a `keyspace_replication_with` result.
+    fn render_keyspace_ddl(keyspace: &str, replication: &Map<String, Value>) -> String {
+        let options: Vec<String> = replication
+            .get("replication")
+            .and_then(Value::as_object)
+            .map(|map| {
+                map.iter()
+                    .map(|(k, v)| {
+                        let v = v
+                            .as_str()
+                            .map(str::to_string)
+                            .unwrap_or_else(|| v.to_string());
+                        format!("{}: {}", cql_string_literal(k), cql_string_literal(&v))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let name = CqlIdent::new(keyspace)
+            .map(|ident| ident.to_cql())
+            .unwrap_or_else(|_| keyspace.to_string());
+        let mut ddl = format!(
+            "CREATE KEYSPACE {name} WITH replication = {{{}}}",
+            options.join(", ")
+        );
+        if let Some(durable) = replication.get("durable_writes").and_then(Value::as_bool) {
+            ddl.push_str(&format!(" AND durable_writes = {durable}"));
+        }
+        ddl.push(';');
+        ddl
+    }
+
+    /// `CREATE TABLE` with columns, primary key and clustering order.
+    fn render_table_ddl(schema: &DescribeTable) -> String {
+        let mut columns = schema.columns.clone();
+        let rank = |kind: &str| match kind {
+            "partition_key" => 0,
+            "clustering" => 1,
+            "static" => 2,
+            _ => 3,
+        };
+        columns.sort_by(|a, b| {
+            rank(&a.kind)
+                .cmp(&rank(&b.kind))
+                .then(a.position.cmp(&b.position))
+                .then(a.column_name.cmp(&b.column_name))
+        });
+        let mut lines: Vec<String> = columns
+            .iter()
+            .map(|c| {
+                let suffix = if c.kind == "static" { " static" } else { "" };
+                format!(
+                    "    {} {}{}",
+                    CqlIdent::quote_or_raw(&c.column_name),
+                    c.r#type,
+                    suffix
+                )
+            })
+            .collect();
+        let partition: Vec<String> = schema
+            .partition_keys
+            .iter()
+            .map(|k| CqlIdent::quote_or_raw(k))
+            .collect();
+        let partition = if partition.len() == 1 {
+            partition[0].clone()
+        } else {
+            format!("({})", partition.join(", "))
+        };
+        let mut key = vec![partition];
+        key.extend(
+            schema
+                .clustering_keys
+                .iter()
+                .map(|k| CqlIdent::quote_or_raw(k)),
+        );
+        lines.push(format!("    PRIMARY KEY ({})", key.join(", ")));
+        let mut ddl = format!(
+            "CREATE TABLE {}.{} (\n{}\n)",
+            CqlIdent::quote_or_raw(&schema.keyspace),
+            CqlIdent::quote_or_raw(&schema.table),
+            lines.join(",\n")
+        );
+        let order: Vec<String> = schema
+            .clustering_keys
+            .iter()
+            .filter_map(|key| {
+                columns.iter().find(|c| &c.column_name == key).map(|c| {
+                    let dir = match c.clustering_order.as_deref() {
+                        Some(o) if o.eq_ignore_ascii_case("desc") => "DESC",
+                        _ => "ASC",
+                    };
+                    format!("{} {dir}", CqlIdent::quote_or_raw(key))
+                })
+            })
+            .collect();
+        if order.iter().any(|o| o.ends_with("DESC")) {
+            ddl.push_str(&format!(" WITH CLUSTERING ORDER BY ({})", order.join(", ")));
+        }
+        ddl.push(';');
+        ddl
+    }
+
+    pub async fn list_views_with(session: &scylla::Session, keyspace: &str) -> Result<Vec<String>> {
+        let prepared = session
+            .prepare("SELECT view_name FROM system_schema.views WHERE keyspace_name = ?")
+            .await?;
+        let result = session
+            .execute_unpaged(&prepared, &(keyspace.to_string(),))
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String,)>()? {
+            let (name,) = row?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    pub async fn list_udts_with(session: &scylla::Session, keyspace: &str) -> Result<Vec<String>> {
+        let prepared = session
+            .prepare("SELECT type_name FROM system_schema.types WHERE keyspace_name = ?")
+            .await?;
+        let result = session
+            .execute_unpaged(&prepared, &(keyspace.to_string(),))
+            .await?;
+        let mut names = Vec::new();
+        for row in result.rows_typed::<(String,)>()? {
+            let (name,) = row?;
+            names.push(name);
+        }
+        Ok(names)
+    }
+
+    pub async fn list_functions_with(
+        session: &scylla::Session,
+        keyspace: &str,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let prepared = session
+            .prepare("SELECT function_name, argument_types, return_type FROM system_schema.functions WHERE keyspace_name = ?")
+            .await?;
+        let result = session
+            .execute_unpaged(&prepared, &(keyspace.to_string(),))
+            .await?;
+        let specs = result.col_specs().to_owned();
+        let rows = result.rows_or_empty();
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    pub async fn list_aggregates_with(
+        session: &scylla::Session,
+        keyspace: &str,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let prepared = session
+            .prepare("SELECT aggregate_name, argument_types, return_type FROM system_schema.aggregates WHERE keyspace_name = ?")
+            .await?;
+        let result = session
+            .execute_unpaged(&prepared, &(keyspace.to_string(),))
+            .await?;
+        let specs = result.col_specs().to_owned();
+        let rows = result.rows_or_empty();
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let name = spec.name.clone();
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(name, json);
+            }
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    /// Routes every request to one node with no fallback, so node-local system
+    /// tables are read from that node instead of whichever coordinator is picked.
+    #[derive(Debug)]
+    struct SingleNodePolicy {
+        host_id: String,
+    }
+
+    impl LoadBalancingPolicy for SingleNodePolicy {
+        fn pick<'a>(
+            &'a self,
+            _query: &'a RoutingInfo,
+            cluster: &'a ClusterData,
+        ) -> Option<(NodeRef<'a>, Option<Shard>)> {
+            cluster
+                .get_nodes_info()
+                .iter()
+                .find(|node| node.host_id.to_string() == self.host_id)
+                .map(|node| (node, None))
+        }
+
+        fn fallback<'a>(
+            &'a self,
+            _query: &'a RoutingInfo,
+            _cluster: &'a ClusterData,
+        ) -> FallbackPlan<'a> {
+            Box::new(std::iter::empty())
+        }
+
+        fn name(&self) -> String {
+            "SingleNodePolicy".into()
+        }
+    }
+
+    fn single_node_profile(host_id: &str) -> ExecutionProfileHandle {
+        ExecutionProfile::builder()
+            .load_balancing_policy(std::sync::Arc::new(SingleNodePolicy {
+                host_id: host_id.to_string(),
+            }))
+            .build()
+            .into_handle()
+    }
+
+    /// An unprepared statement pinned to the node with `host_id`.
+    fn node_query(cql: &str, host_id: &str) -> Query {
+        let mut query = Query::new(cql);
+        query.set_execution_profile_handle(Some(single_node_profile(host_id)));
+        query
+    }
+
+    /// Which node(s) a query against node-local system tables is sent to.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub enum NodeTarget {
+        /// Whichever coordinator the driver's load-balancing policy picks.
+        #[default]
+        Coordinator,
+        /// One node, by host id or address (`10.0.0.1` or `10.0.0.1:9042`).
+        Node(String),
+        /// Every known node, queried concurrently.
+        AllNodes,
+    }
+
+    impl NodeTarget {
+        /// `None`/empty selects the coordinator, `"all"` every node, anything else one node.
+        pub fn parse(value: Option<&str>) -> Self {
+            match value.map(str::trim) {
+                None | Some("") => Self::Coordinator,
+                Some(v) if v.eq_ignore_ascii_case("all") => Self::AllNodes,
+                Some(v) => Self::Node(v.to_string()),
+            }
+        }
+    }
+
+    fn node_matches(node: &scylla::transport::Node, wanted: &str) -> bool {
+        node.host_id.to_string() == wanted
+            || node.address.to_string() == wanted
+            || node.address.ip().to_string() == wanted
+    }
+
+    /// `host_id`, `address` and `datacenter` of a node, attached to targeted results.
+    fn node_label(node: &scylla::transport::Node) -> Map<String, Value> {
+        let mut m = Map::new();
+        m.insert("node".into(), Value::from(node.address.to_string()));
+        m.insert("host_id".into(), Value::from(node.host_id.to_string()));
+        m.insert(
+            "datacenter".into(),
+            node.datacenter
+                .clone()
+                .map(Value::from)
+                .unwrap_or(Value::Null),
+        );
+        m
+    }
+
+    /// Execute `cql` on the targeted node(s). The coordinator yields one result
+    /// without a label; node targets yield one labelled result per node, and a
+    /// failing node does not fail the others.
+    pub(crate) async fn query_targeted<V>(
+        session: &scylla::Session,
+        cql: &str,
+        values: &V,
+        target: &NodeTarget,
+    ) -> Result<Vec<(Option<Map<String, Value>>, Result<scylla::QueryResult>)>>
+    where
+        V: scylla::serialize::row::SerializeRow + Sync,
+    {
+        let prepared = session.prepare(cql).await?;
+        let cluster = session.get_cluster_data();
+        let nodes: Vec<_> = match target {
+            NodeTarget::Coordinator => {
+                let result = execute_all_pages(session, &prepared, values).await?;
+                return Ok(vec![(None, Ok(result))]);
+            }
+            NodeTarget::AllNodes => cluster.get_nodes_info().iter().collect(),
+            NodeTarget::Node(wanted) => {
+                let found: Vec<_> = cluster
+                    .get_nodes_info()
+                    .iter()
+                    .filter(|n| node_matches(n, wanted))
+                    .collect();
+                if found.is_empty() {
+                    let known: Vec<String> = cluster
+                        .get_nodes_info()
+                        .iter()
+                        .map(|n| format!("{} ({})", n.address, n.host_id))
+                        .collect();
+                    anyhow::bail!(
+                        "unknown node '{}'; known nodes: {}",
+                        wanted,
+                        known.join(", ")
+                    );
+                }
+                found
+            }
+        };
+        let requests = nodes.into_iter().map(|node| {
+            let mut statement = prepared.clone();
+            statement
+                .set_execution_profile_handle(Some(single_node_profile(&node.host_id.to_string())));
+            async move {
+                let result = execute_all_pages(session, &statement, values).await;
+                (Some(node_label(node)), result)
+            }
+        });
+        Ok(futures::future::join_all(requests).await)
+    }
+
+    /// Execute `statement` page by page and gather every row into the first page's result.
+    async fn execute_all_pages<V>(
+        session: &scylla::Session,
+        statement: &scylla::prepared_statement::PreparedStatement,
+        values: &V,
+    ) -> Result<scylla::QueryResult>
+    where
+        V: scylla::serialize::row::SerializeRow + Sync,
+    {
+        let (mut result, mut paging) = session
+            .execute_single_page(statement, values, PagingState::start())
+            .await?;
+        while let PagingStateResponse::HasMorePages { state } = paging {
+            let (page, next) = session
+                .execute_single_page(statement, values, state)
+                .await?;
+            if let (Some(rows), Some(more)) = (result.rows.as_mut(), page.rows) {
+                rows.extend(more);
+            }
+            paging = next;
+        }
+        Ok(result)
+    }
+
+    /// Token-range size estimates from every node's `system.size_estimates`,
+    /// falling back to the coordinator when no node can be reached directly.
+    pub async fn size_estimates_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let rows =
+            size_estimates_with_target(session, keyspace, table, &NodeTarget::AllNodes).await?;
+        if !rows.is_empty() && rows.iter().all(|row| row.contains_key("error")) {
+            return size_estimates_with_target(session, keyspace, table, &NodeTarget::Coordinator)
+                .await;
+        }
+        Ok(rows)
+    }
+
+    /// Size estimates read from `target`. Each node only records its own primary
+    /// ranges, so targeted rows carry the node they came from, and a node that
+    /// fails contributes a single row with its `error`.
+    pub async fn size_estimates_with_target(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+        target: &NodeTarget,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let values = (keyspace.to_string(), table.to_string());
+        let mut out = Vec::new();
+        for (node, result) in query_targeted(
+            session,
+            "SELECT range_start, range_end, mean_partition_size, partitions_count FROM system.size_estimates WHERE keyspace_name = ? AND table_name = ?",
+            &values,
+            target,
+        )
+        .await?
+        {
+            match (node, result) {
+                (node, Ok(result)) => {
+                    for mut m in result_rows_as_maps(result) {
+                        if let Some(node) = &node {
+                            m.extend(node.clone());
                         }
+                        out.push(m);
                     }
-                    let span = tracing::info_span!("tool", name = "partition_rows", %keyspace, %table, limit = limit as i64);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("partition_rows failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::partition_rows_with(
-                        &session, &keyspace, &table, partition, limit,
+                }
+                (Some(mut node), Err(err)) => {
+                    node.insert("error".into(), Value::from(err.to_string()));
+                    out.push(node);
+                }
+                (None, Err(err)) => return Err(err),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Size-estimate rows keyed by token range, skipping failed nodes. Replicas
+    /// report the same range, so each range counts once, taking the replica
+    /// with the most partitions.
+    fn dedup_ranges<'a>(
+        rows: impl IntoIterator<Item = &'a Map<String, Value>>,
+    ) -> std::collections::BTreeMap<(String, String), (i64, i64)> {
+        let mut ranges = std::collections::BTreeMap::new();
+        for row in rows {
+            if row.contains_key("error") {
+                continue;
+            }
+            let bound = |key: &str| row.get(key).and_then(Value::as_str).map(str::to_string);
+            let (Some(start), Some(end)) = (bound("range_start"), bound("range_end")) else {
+                continue;
+            };
+            let mean = row
+                .get("mean_partition_size")
+                .and_then(Value::as_i64)
+                .unwrap_or(0);
+            let count = row
+                .get("partitions_count")
+                .and_then(Value::as_i64)
+                .unwrap_or(0);
+            let slot = ranges.entry((start, end)).or_insert((mean, count));
+            if count > slot.1 {
+                *slot = (mean, count);
+            }
+        }
+        ranges
+    }
+
+    /// Partition count and estimated bytes of `(mean_partition_size, partitions_count)` pairs.
+    fn range_totals(sizes: &[(i64, i64)]) -> (i64, i64) {
+        let partitions = sizes.iter().map(|(_, count)| count).sum();
+        let bytes = sizes
+            .iter()
+            .map(|(mean, count)| *mean as i128 * *count as i128)
+            .sum::<i128>() as i64;
+        (partitions, bytes)
+    }
+
+    /// Cluster-wide totals of `size_estimates_with` rows, plus per-node totals.
+    /// Ranges are deduplicated the same way `table_stats_summary` does.
+    pub fn size_estimates_summary(rows: &[Map<String, Value>]) -> Map<String, Value> {
+        use std::collections::BTreeMap;
+
+        let node_of = |row: &Map<String, Value>| {
+            row.get("node")
+                .and_then(Value::as_str)
+                .unwrap_or("coordinator")
+                .to_string()
+        };
+        let mut per_node: BTreeMap<String, Vec<&Map<String, Value>>> = BTreeMap::new();
+        let mut errors = Vec::new();
+        for row in rows {
+            let node = node_of(row);
+            if let Some(err) = row.get("error") {
+                errors.push(serde_json::json!({ "node": node, "error": err }));
+                continue;
+            }
+            per_node.entry(node).or_default().push(row);
+        }
+        let totals = |ranges: Vec<(i64, i64)>| {
+            let (partitions, bytes) = range_totals(&ranges);
+            serde_json::json!({
+                "ranges": ranges.len(),
+                "partitions_count": partitions,
+                "mean_partition_size": weighted_mean_partition_size(&ranges),
+                "estimated_bytes": bytes,
+            })
+        };
+        let mut out = match totals(dedup_ranges(rows).into_values().collect()) {
+            Value::Object(m) => m,
+            _ => Map::new(),
+        };
+        out.insert(
+            "nodes".into(),
+            Value::Object(
+                per_node
+                    .into_iter()
+                    .map(|(node, rows)| (node, totals(dedup_ranges(rows).into_values().collect())))
+                    .collect(),
+            ),
+        );
+        if !errors.is_empty() {
+            out.insert("errors".into(), Value::Array(errors));
+        }
+        out
+    }
+
+    /// Fold size-estimate rows (possibly from several nodes) into one table-level
+    /// estimate. Ranges reported by more than one node are counted once, and
+    /// per-DC stored bytes multiply the single-copy estimate by that DC's RF.
+    pub fn table_stats_summary(
+        rows: &[Map<String, Value>],
+        replication: &Map<String, Value>,
+    ) -> Map<String, Value> {
+        use std::collections::BTreeSet;
+
+        let mut nodes = BTreeSet::new();
+        let mut failed = BTreeSet::new();
+        for row in rows {
+            let node = row.get("node").and_then(Value::as_str).map(str::to_string);
+            if row.contains_key("error") {
+                failed.extend(node);
+            } else {
+                nodes.extend(node);
+            }
+        }
+        let ranges = dedup_ranges(rows);
+
+        let sizes: Vec<(i64, i64)> = ranges.values().copied().collect();
+        let (partitions, bytes) = range_totals(&sizes);
+        let coverage: f64 = ranges
+            .keys()
+            .filter_map(|(start, end)| {
+                let (start, end) = (start.parse::<i64>().ok()?, end.parse::<i64>().ok()?);
+                let span = i128::from(end) - i128::from(start);
+                Some(if span > 0 { span } else { span + (1i128 << 64) } as f64)
+            })
+            .sum::<f64>()
+            / 18_446_744_073_709_551_616.0;
+
+        let mut per_dc = Map::new();
+        for (dc, rf) in crate::lint::replication_factors(replication) {
+            let stored = bytes.saturating_mul(rf as i64);
+            let scope = if dc == "*" { "cluster".to_string() } else { dc };
+            per_dc.insert(
+                scope,
+                serde_json::json!({
+                    "rf": rf,
+                    "estimated_bytes": stored,
+                    "estimated_size": crate::lint::human_bytes(stored),
+                }),
+            );
+        }
+
+        let (confidence, mut notes) = if ranges.is_empty() {
+            (
+                "none",
+                vec![
+                    "no size estimates recorded yet; they appear after data is flushed to SSTables"
+                        .to_string(),
+                ],
+            )
+        } else if coverage >= 0.99 && failed.is_empty() {
+            ("high", Vec::new())
+        } else if coverage >= 0.5 {
+            (
+                "medium",
+                vec![format!(
+                    "estimates cover {:.0}% of the token ring",
+                    coverage * 100.0
+                )],
+            )
+        } else {
+            (
+                "low",
+                vec![format!(
+                    "estimates cover only {:.0}% of the token ring",
+                    coverage * 100.0
+                )],
+            )
+        };
+        if !failed.is_empty() {
+            notes.push(format!(
+                "no estimates from unreachable node(s): {}",
+                failed.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        notes.push(
+            "derived from SSTable statistics: memtable data is excluded and sizes are uncompressed"
+                .to_string(),
+        );
+
+        let mut out = Map::new();
+        out.insert("estimated_partitions".into(), Value::from(partitions));
+        out.insert(
+            "mean_partition_size".into(),
+            serde_json::json!(weighted_mean_partition_size(&sizes)),
+        );
+        out.insert(
+            "mean_partition_size_human".into(),
+            serde_json::json!(weighted_mean_partition_size(&sizes).map(crate::lint::human_bytes)),
+        );
+        out.insert("estimated_bytes_single_copy".into(), Value::from(bytes));
+        out.insert(
+            "estimated_size_single_copy".into(),
+            Value::from(crate::lint::human_bytes(bytes)),
+        );
+        out.insert("per_datacenter".into(), Value::Object(per_dc));
+        out.insert("ranges".into(), Value::from(ranges.len()));
+        out.insert(
+            "ring_coverage_pct".into(),
+            Value::from((coverage * 10_000.0).round() / 100.0),
+        );
+        out.insert("nodes".into(), serde_json::json!(nodes));
+        out.insert("confidence".into(), Value::from(confidence));
+        out.insert("notes".into(), serde_json::json!(notes));
+        out
+    }
+
+    /// Estimated partition count and sizes for a table, aggregated across every
+    /// node's `system.size_estimates` by `size_estimates_with`.
+    pub async fn table_stats_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+    ) -> Result<Map<String, Value>> {
+        let rows = size_estimates_with(session, keyspace, table).await?;
+        let replication = keyspace_replication_with(session, keyspace)
+            .await?
+            .remove("replication")
+            .and_then(|v| v.as_object().cloned())
+            .unwrap_or_default();
+        let mut out = Map::new();
+        out.insert("keyspace".into(), Value::from(keyspace));
+        out.insert("table".into(), Value::from(table));
+        out.extend(table_stats_summary(&rows, &replication));
+        Ok(out)
+    }
+
+    /// Node-local tables read by `node_config`: section name, the Scylla query, and
+    /// the closest Cassandra equivalent (if any) used when the Scylla table is absent.
+    const NODE_INFO_SOURCES: [(&str, &str, Option<&str>); 4] = [
+        (
+            "config",
+            "SELECT name, value, source, type FROM system.config",
+            Some("SELECT name, value FROM system_views.settings"),
+        ),
+        (
+            "versions",
+            "SELECT * FROM system.versions",
+            Some("SELECT release_version, cql_version, native_protocol_version FROM system.local"),
+        ),
+        ("runtime_info", "SELECT * FROM system.runtime_info", None),
+        (
+            "clients",
+            "SELECT * FROM system.clients",
+            Some("SELECT * FROM system_views.clients"),
+        ),
+    ];
+
+    pub const NODE_INFO_SECTIONS: [&str; 4] = ["config", "versions", "runtime_info", "clients"];
+
+    /// Hide credentials in config values: whole values of password/secret options,
+    /// and `…password…: value` pairs inside map-valued options such as encryption settings.
+    fn redact_config_value(name: &str, value: Value) -> Value {
+        let lowered = name.to_ascii_lowercase();
+        if lowered.contains("password") || lowered.contains("secret") {
+            return Value::from("<redacted>");
+        }
+        static PAIR: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+        match value {
+            Value::String(text) => {
+                let pattern = PAIR.get_or_init(|| {
+                    regex::Regex::new(
+                        r#"(?i)("?[a-z_]*(?:password|secret)[a-z_]*"?\s*[:=]\s*)("[^"]*"|[^,}\s]+)"#,
                     )
-                    .await
-                    {
-                        Ok(rows) => {
-                            let json = serde_json::to_string(&rows).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("partition_rows failed: {}", err),
-                            None,
-                        )),
+                    .expect("static regex")
+                });
+                Value::from(
+                    pattern
+                        .replace_all(&text, "${1}\"<redacted>\"")
+                        .into_owned(),
+                )
+            }
+            other => other,
+        }
+    }
+
+    /// Apply the option-name filter (case-insensitive substring) and redaction to a section.
+    fn filter_node_rows(
+        section: &str,
+        rows: Vec<Map<String, Value>>,
+        name: Option<&str>,
+    ) -> Vec<Map<String, Value>> {
+        let needle = name.map(str::to_ascii_lowercase);
+        let matches = |row: &Map<String, Value>, keys: &[&str]| {
+            needle.as_deref().is_none_or(|needle| {
+                keys.iter().any(|key| {
+                    row.get(*key)
+                        .and_then(Value::as_str)
+                        .is_some_and(|v| v.to_ascii_lowercase().contains(needle))
+                })
+            })
+        };
+        rows.into_iter()
+            .filter(|row| match section {
+                "config" => matches(row, &["name"]),
+                "runtime_info" => matches(row, &["group", "item"]),
+                _ => true,
+            })
+            .map(|mut row| {
+                if section == "config" {
+                    let option = row
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    if let Some(value) = row.remove("value") {
+                        row.insert("value".into(), redact_config_value(&option, value));
                     }
                 }
-                "cluster_topology" => {
-                    let span = tracing::info_span!("tool", name = "cluster_topology");
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("cluster_topology failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::cluster_topology_with(&session).await {
-                        Ok(nodes) => {
-                            let json =
-                                serde_json::to_string(&nodes).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("cluster_topology failed: {}", err);
-                            Ok(ToolOutput::error(msg))
+                row
+            })
+            .collect()
+    }
+
+    /// Read `system.config`, `system.versions`, `system.runtime_info` and
+    /// `system.clients` from the targeted node(s), falling back to Cassandra's
+    /// `system_views` equivalents and reporting sections that do not exist.
+    pub async fn node_config_with(
+        session: &scylla::Session,
+        sections: &[String],
+        name: Option<&str>,
+        target: &NodeTarget,
+    ) -> Result<Map<String, Value>> {
+        let mut nodes: std::collections::BTreeMap<String, Map<String, Value>> =
+            std::collections::BTreeMap::new();
+        let mut unavailable = Map::new();
+        for (section, cql, fallback) in NODE_INFO_SOURCES
+            .iter()
+            .filter(|(section, _, _)| sections.is_empty() || sections.iter().any(|s| s == section))
+        {
+            let results = match query_targeted(session, cql, &(), target).await {
+                Ok(results) => results,
+                Err(err) => match fallback {
+                    Some(fallback) => match query_targeted(session, fallback, &(), target).await {
+                        Ok(results) => results,
+                        Err(_) => {
+                            unavailable.insert(section.to_string(), Value::from(err.to_string()));
+                            continue;
                         }
+                    },
+                    None => {
+                        unavailable.insert(section.to_string(), Value::from(err.to_string()));
+                        continue;
                     }
+                },
+            };
+            for (label, result) in results {
+                let key = label
+                    .as_ref()
+                    .and_then(|l| l.get("node"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("coordinator")
+                    .to_string();
+                let entry = nodes
+                    .entry(key)
+                    .or_insert_with(|| label.clone().unwrap_or_default());
+                let value = match result {
+                    Ok(result) => Value::Array(
+                        filter_node_rows(section, result_rows_as_maps(result), name)
+                            .into_iter()
+                            .map(Value::Object)
+                            .collect(),
+                    ),
+                    Err(err) => serde_json::json!({ "error": err.to_string() }),
+                };
+                entry.insert(section.to_string(), value);
+            }
+        }
+        let mut out = Map::new();
+        out.insert(
+            "nodes".into(),
+            Value::Array(nodes.into_values().map(Value::Object).collect()),
+        );
+        if !unavailable.is_empty() {
+            out.insert("unavailable".into(), Value::Object(unavailable));
+        }
+        Ok(out)
+    }
+
+    /// How `search_schema` compares the pattern against schema text.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub enum SearchMode {
+        /// Case-insensitive substring match.
+        #[default]
+        Substring,
+        /// Case-insensitive whole-string glob with `*` and `?`.
+        Glob,
+        /// Case-insensitive regular expression, bounded in compiled size and nesting.
+        Regex,
+        /// Case-insensitive edit-distance match against names and their `_`-separated words.
+        Fuzzy,
+    }
+
+    impl SearchMode {
+        pub fn parse(value: &str) -> Result<Self> {
+            match value.to_ascii_lowercase().as_str() {
+                "substring" => Ok(Self::Substring),
+                "glob" => Ok(Self::Glob),
+                "regex" => Ok(Self::Regex),
+                "fuzzy" => Ok(Self::Fuzzy),
+                _ => anyhow::bail!(
+                    "invalid search mode '{}'; expected substring, glob, regex or fuzzy",
+                    value
+                ),
+            }
+        }
+    }
+
+    /// Searchable schema fields, with the weight applied to a match in that field.
+    pub const SEARCH_SCHEMA_FIELDS: [(&str, u32); 5] = [
+        ("name", 100),
+        ("index_target", 70),
+        ("type", 60),
+        ("comment", 50),
+        ("body", 30),
+    ];
+
+    /// Schema object kinds `search_schema` can return, in tie-break order.
+    pub const SEARCH_SCHEMA_KINDS: [&str; 7] = [
+        "table",
+        "column",
+        "udt",
+        "view",
+        "index",
+        "function",
+        "aggregate",
+    ];
+
+    /// Mode, searched fields and kind filter for `search_schema_with_options`.
+    #[derive(Debug, Clone)]
+    pub struct SearchSchemaOptions {
+        pub mode: SearchMode,
+        pub fields: Vec<String>,
+        pub kinds: Vec<String>,
+    }
+
+    impl Default for SearchSchemaOptions {
+        fn default() -> Self {
+            Self {
+                mode: SearchMode::Substring,
+                fields: vec!["name".to_string()],
+                kinds: SEARCH_SCHEMA_KINDS.iter().map(|k| k.to_string()).collect(),
+            }
+        }
+    }
+
+    impl SearchSchemaOptions {
+        pub fn validate(&self) -> Result<()> {
+            for field in &self.fields {
+                if !SEARCH_SCHEMA_FIELDS.iter().any(|(name, _)| name == field) {
+                    anyhow::bail!("invalid search field '{}'", field);
                 }
-                "list_indexes" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let tb = args
-                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() || tb.is_none() {
-                        let msg =
-                            "missing required arguments 'keyspace' and/or 'table'".to_string();
-                        return Ok(ToolOutput::error(msg));
-                    }
-                    let keyspace = ks.unwrap();
-                    let table = tb.unwrap();
-                    let span =
-                        tracing::info_span!("tool", name = "list_indexes", %keyspace, %table);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("list_indexes failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
+            }
+            for kind in &self.kinds {
+                if !SEARCH_SCHEMA_KINDS.contains(&kind.as_str()) {
+                    anyhow::bail!("invalid search kind '{}'", kind);
+                }
+            }
+            Ok(())
+        }
+
+        fn wants_kind(&self, kind: &str) -> bool {
+            self.kinds.iter().any(|k| k == kind)
+        }
+
+        fn field_weight(&self, field: &str) -> Option<u32> {
+            if !self.fields.iter().any(|f| f == field) {
+                return None;
+            }
+            SEARCH_SCHEMA_FIELDS
+                .iter()
+                .find(|(name, _)| *name == field)
+                .map(|(_, weight)| *weight)
+        }
+    }
+
+    /// Case-insensitive Levenshtein distance.
+    pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+        let a: Vec<char> = a.to_lowercase().chars().collect();
+        let b: Vec<char> = b.to_lowercase().chars().collect();
+        let mut prev: Vec<usize> = (0..=b.len()).collect();
+        let mut cur = vec![0; b.len() + 1];
+        for (i, ca) in a.iter().enumerate() {
+            cur[0] = i + 1;
+            for (j, cb) in b.iter().enumerate() {
+                let cost = usize::from(ca != cb);
+                cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+            }
+            std::mem::swap(&mut prev, &mut cur);
+        }
+        prev[b.len()]
+    }
+
+    const MAX_IDENTIFIER_SUGGESTIONS: usize = 5;
+
+    /// Closest names to `input`: case-insensitive equals first, then by edit
+    /// distance, then names that contain (or are contained in) the input.
+    pub fn suggest_identifiers<'a, I>(input: &str, candidates: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let lower = input.to_lowercase();
+        let max_distance = (input.chars().count() / 3).max(2);
+        let mut scored: Vec<(usize, &str)> = candidates
+            .into_iter()
+            .filter(|c| *c != input)
+            .filter_map(|c| {
+                let cl = c.to_lowercase();
+                if cl == lower {
+                    return Some((0, c));
+                }
+                let distance = edit_distance(&lower, &cl);
+                if distance <= max_distance {
+                    Some((distance, c))
+                } else if !lower.is_empty() && (cl.contains(&lower) || lower.contains(&cl)) {
+                    Some((max_distance + 1, c))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        scored.sort();
+        scored.dedup_by(|a, b| a.1 == b.1);
+        scored
+            .into_iter()
+            .take(MAX_IDENTIFIER_SUGGESTIONS)
+            .map(|(_, c)| c.to_string())
+            .collect()
+    }
+
+    /// A keyspace, table or column name that does not exist, with the closest
+    /// existing names so agents can retry without another discovery call.
+    #[derive(Debug, Clone, Serialize)]
+    pub struct UnknownIdentifier {
+        pub kind: &'static str,
+        pub name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub keyspace: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub table: Option<String>,
+        pub suggestions: Vec<String>,
+    }
+
+    impl std::fmt::Display for UnknownIdentifier {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "unknown {} '{}'", self.kind, self.name)?;
+            if let Some(first) = self.suggestions.first() {
+                write!(f, "; did you mean '{}'?", first)?;
+            }
+            Ok(())
+        }
+    }
+
+    impl std::error::Error for UnknownIdentifier {}
+
+    /// Explain why `keyspace.table` has no columns: either the keyspace or the table is missing.
+    pub async fn unknown_table_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+    ) -> UnknownIdentifier {
+        let tables = list_tables_with(session, keyspace)
+            .await
+            .unwrap_or_default();
+        if tables.is_empty() {
+            let keyspaces = list_keyspaces_with(session).await.unwrap_or_default();
+            if !keyspaces.iter().any(|ks| ks == keyspace) {
+                return UnknownIdentifier {
+                    kind: "keyspace",
+                    name: keyspace.to_string(),
+                    keyspace: None,
+                    table: None,
+                    suggestions: suggest_identifiers(
+                        keyspace,
+                        keyspaces.iter().map(String::as_str),
+                    ),
+                };
+            }
+        }
+        UnknownIdentifier {
+            kind: "table",
+            name: table.to_string(),
+            keyspace: Some(keyspace.to_string()),
+            table: None,
+            suggestions: suggest_identifiers(table, tables.iter().map(String::as_str)),
+        }
+    }
+
+    /// Question words and query verbs dropped from natural-language searches such as
+    /// "where do we store email addresses".
+    const SEARCH_STOP_WORDS: &[&str] = &[
+        "a", "an", "and", "any", "are", "at", "be", "by", "do", "does", "for", "find", "from",
+        "have", "hold", "how", "i", "in", "is", "it", "keep", "kept", "of", "on", "or", "our",
+        "save", "saved", "show", "store", "stored", "the", "their", "there", "to", "we", "what",
+        "where", "which", "who", "with",
+    ];
+
+    /// Plural to singular, so "addresses" finds `address` and "emails" finds `email`.
+    fn search_term_stem(word: &str) -> String {
+        if word.len() > 4 && word.ends_with("ies") {
+            format!("{}y", &word[..word.len() - 3])
+        } else if ["sses", "xes", "ches", "shes"]
+            .iter()
+            .any(|suffix| word.ends_with(suffix))
+        {
+            word[..word.len() - 2].to_string()
+        } else if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+            word[..word.len() - 1].to_string()
+        } else {
+            word.to_string()
+        }
+    }
+
+    /// Search terms of a multi-word pattern, or `None` for a single word.
+    fn search_terms(pattern: &str) -> Option<Vec<String>> {
+        if !pattern.trim().contains(char::is_whitespace) {
+            return None;
+        }
+        let mut terms: Vec<String> = Vec::new();
+        for word in pattern
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|word| !word.is_empty() && !SEARCH_STOP_WORDS.contains(word))
+        {
+            let term = search_term_stem(word);
+            if !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        (!terms.is_empty()).then_some(terms)
+    }
+
+    enum SchemaMatcher {
+        Substring(String),
+        Pattern(regex::Regex, Option<String>),
+        Fuzzy(String),
+        /// Each word of a natural-language query, scored by how many of them match.
+        Terms(Vec<SchemaMatcher>),
+    }
+
+    impl SchemaMatcher {
+        fn new(mode: SearchMode, pattern: &str) -> Result<Self> {
+            validate_search_schema_pattern(pattern)?;
+            let build = |source: &str| {
+                regex::RegexBuilder::new(source)
+                    .case_insensitive(true)
+                    .size_limit(MAX_SEARCH_SCHEMA_REGEX_BYTES)
+                    .dfa_size_limit(MAX_SEARCH_SCHEMA_REGEX_BYTES)
+                    .nest_limit(MAX_SEARCH_SCHEMA_REGEX_NESTING)
+                    .build()
+                    .map_err(|err| anyhow::anyhow!("invalid search pattern: {}", err))
+            };
+            let terms = match mode {
+                SearchMode::Substring | SearchMode::Fuzzy => search_terms(pattern),
+                SearchMode::Regex | SearchMode::Glob => None,
+            };
+            if let Some(terms) = terms {
+                let matchers = terms
+                    .into_iter()
+                    .map(|term| match mode {
+                        SearchMode::Fuzzy => Self::Fuzzy(term),
+                        _ => Self::Substring(term),
+                    })
+                    .collect();
+                return Ok(Self::Terms(matchers));
+            }
+            Ok(match mode {
+                SearchMode::Substring => Self::Substring(pattern.to_lowercase()),
+                SearchMode::Fuzzy => Self::Fuzzy(pattern.trim().to_lowercase()),
+                SearchMode::Regex => Self::Pattern(build(pattern)?, None),
+                SearchMode::Glob => {
+                    let mut source = String::from("^");
+                    let mut literal = String::new();
+                    for c in pattern.chars() {
+                        match c {
+                            '*' => source.push_str(".*"),
+                            '?' => source.push('.'),
+                            _ => {
+                                literal.push(c);
+                                source.push_str(&regex::escape(&c.to_string()));
+                            }
                         }
+                    }
+                    source.push('$');
+                    Self::Pattern(build(&source)?, Some(literal.to_lowercase()))
+                }
+            })
+        }
+
+        /// Relevance of `text` on a 0-100 scale, or `None` when it does not match.
+        fn score(&self, text: &str) -> Option<u32> {
+            if text.is_empty() {
+                return None;
+            }
+            let lower = text.to_lowercase();
+            // Shorter texts are closer to what was asked for, so they rank first.
+            let slack = |matched: usize| (lower.len().saturating_sub(matched) as u32).min(20) / 2;
+            match self {
+                Self::Substring(pat) => {
+                    let at = lower.find(pat.as_str())?;
+                    let base = if lower == *pat {
+                        100
+                    } else if at == 0 {
+                        85
+                    } else if lower[..at].ends_with(|c: char| !c.is_alphanumeric()) {
+                        70
+                    } else {
+                        55
                     };
-                    match crate::db::list_indexes_with(&session, &keyspace, &table).await {
-                        Ok(list) => {
-                            let json = serde_json::to_string(&list).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("list_indexes failed: {}", err);
-                            Ok(ToolOutput::error(msg))
+                    Some(base - slack(pat.len()))
+                }
+                Self::Pattern(re, glob_literal) => {
+                    let found = re.find(text)?;
+                    if let Some(literal) = glob_literal {
+                        if lower == *literal {
+                            return Some(100);
                         }
+                        return Some(75 - slack(literal.len()));
                     }
+                    Some(60 + (40 * found.len() / text.len()) as u32)
                 }
-                "keyspace_replication" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() {
-                        let msg = "missing required argument 'keyspace'".to_string();
-                        return Ok(ToolOutput::error(msg));
+                Self::Fuzzy(pat) => {
+                    let max = (pat.chars().count() / 3).max(1);
+                    let best = std::iter::once(lower.as_str())
+                        .chain(lower.split(|c: char| !c.is_alphanumeric()))
+                        .filter(|word| !word.is_empty())
+                        .map(|word| edit_distance(pat, word))
+                        .min()?;
+                    if best > max {
+                        return None;
                     }
-                    let keyspace = ks.unwrap();
-                    let span =
-                        tracing::info_span!("tool", name = "keyspace_replication", %keyspace);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("keyspace_replication failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::keyspace_replication_with(&session, &keyspace).await {
-                        Ok(obj) => {
-                            let json = serde_json::to_string(&obj).unwrap_or_else(|_| "{}".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => {
-                            let msg = format!("keyspace_replication failed: {}", err);
-                            Ok(ToolOutput::error(msg))
-                        }
+                    let base = 90 - (60 * best / (max + 1)) as u32;
+                    Some(base.saturating_sub(slack(pat.len())))
+                }
+                Self::Terms(terms) => {
+                    // Averaging over every term ranks texts that match more terms first.
+                    let scores: Vec<u32> = terms.iter().filter_map(|t| t.score(text)).collect();
+                    if scores.is_empty() {
+                        return None;
                     }
+                    Some(scores.iter().sum::<u32>() / terms.len() as u32)
                 }
-                "list_views" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() {
-                        let msg = "missing required argument 'keyspace'".to_string();
-                        return Ok(ToolOutput::error(msg));
+            }
+        }
+    }
+
+    struct SchemaCandidate {
+        kind: &'static str,
+        keyspace: String,
+        table: Option<String>,
+        name: String,
+        fields: Vec<(&'static str, String)>,
+    }
+
+    /// Score a candidate across the requested fields, keeping the best field match.
+    fn rank_schema_candidate(
+        matcher: &SchemaMatcher,
+        options: &SearchSchemaOptions,
+        candidate: SchemaCandidate,
+    ) -> Option<(u32, Map<String, Value>)> {
+        let mut best: Option<(u32, &'static str, &str)> = None;
+        for (field, text) in candidate.fields.iter() {
+            let group = if *field == "keyspace" { "name" } else { field };
+            let Some(weight) = options.field_weight(group) else {
+                continue;
+            };
+            // Table hits via their keyspace name rank below direct name hits.
+            let weight = if *field == "keyspace" {
+                weight / 2
+            } else {
+                weight
+            };
+            let Some(score) = matcher.score(text) else {
+                continue;
+            };
+            let weighted = score * weight / 100;
+            if best.is_none_or(|(s, _, _)| weighted > s) {
+                best = Some((weighted, group, text.as_str()));
+            }
+        }
+        let (score, field, text) = best?;
+        let mut m = Map::new();
+        m.insert("kind".into(), Value::String(candidate.kind.into()));
+        m.insert("keyspace".into(), Value::String(candidate.keyspace.clone()));
+        if let Some(table) = &candidate.table {
+            m.insert("table".into(), Value::String(table.clone()));
+        }
+        m.insert("name".into(), Value::String(candidate.name.clone()));
+        m.insert("score".into(), Value::from(score));
+        m.insert("matched".into(), Value::String(field.into()));
+        if field != "name" {
+            let snippet: String = text.chars().take(200).collect();
+            m.insert("match".into(), Value::String(snippet));
+        }
+        Some((score, m))
+    }
+
+    /// Sort ranked hits by score, then kind, keyspace and name, and cap the list.
+    fn finish_schema_results(mut hits: Vec<(u32, Map<String, Value>)>) -> Vec<Map<String, Value>> {
+        let kind_rank = |m: &Map<String, Value>| {
+            let kind = m.get("kind").and_then(Value::as_str).unwrap_or("");
+            SEARCH_SCHEMA_KINDS
+                .iter()
+                .position(|k| *k == kind)
+                .unwrap_or(SEARCH_SCHEMA_KINDS.len())
+        };
+        let text = |m: &Map<String, Value>, key: &str| {
+            m.get(key).and_then(Value::as_str).unwrap_or("").to_string()
+        };
+        hits.sort_by(|(sa, a), (sb, b)| {
+            sb.cmp(sa)
+                .then_with(|| kind_rank(a).cmp(&kind_rank(b)))
+                .then_with(|| text(a, "keyspace").cmp(&text(b, "keyspace")))
+                .then_with(|| text(a, "table").cmp(&text(b, "table")))
+                .then_with(|| text(a, "name").cmp(&text(b, "name")))
+        });
+        let mut out = Vec::new();
+        for (_, item) in hits {
+            if !push_schema_result(&mut out, item) {
+                break;
+            }
+        }
+        out
+    }
+
+    pub async fn search_schema_with(
+        session: &scylla::Session,
+        pattern: &str,
+        keyspace: Option<&str>,
+    ) -> Result<Vec<Map<String, Value>>> {
+        search_schema_with_options(session, pattern, keyspace, &SearchSchemaOptions::default())
+            .await
+    }
+
+    /// Search schema objects and rank them by relevance to `pattern`.
+    pub async fn search_schema_with_options(
+        session: &scylla::Session,
+        pattern: &str,
+        keyspace: Option<&str>,
+        options: &SearchSchemaOptions,
+    ) -> Result<Vec<Map<String, Value>>> {
+        options.validate()?;
+        let matcher = SchemaMatcher::new(options.mode, pattern)?;
+        let mut hits: Vec<(u32, Map<String, Value>)> = Vec::new();
+        let mut consider = |candidate: SchemaCandidate| {
+            if let Some(hit) = rank_schema_candidate(&matcher, options, candidate) {
+                hits.push(hit);
+            }
+        };
+
+        if options.wants_kind("table") {
+            let q_tables = match keyspace {
+                Some(_) => {
+                    "SELECT keyspace_name, table_name, comment FROM system_schema.tables WHERE keyspace_name = ?"
+                }
+                None => "SELECT keyspace_name, table_name, comment FROM system_schema.tables",
+            };
+            let res = match keyspace {
+                Some(ks) => session.query_unpaged(q_tables, &(ks.to_string(),)).await?,
+                None => session.query_unpaged(q_tables, &[]).await?,
+            };
+            for row in res.rows_typed::<(String, String, Option<String>)>()? {
+                let (ks, tb, comment) = row?;
+                consider(SchemaCandidate {
+                    kind: "table",
+                    fields: vec![
+                        ("name", tb.clone()),
+                        ("keyspace", ks.clone()),
+                        ("comment", comment.unwrap_or_default()),
+                    ],
+                    keyspace: ks,
+                    table: None,
+                    name: tb,
+                });
+            }
+        }
+
+        if options.wants_kind("column") {
+            let q_cols = match keyspace {
+                Some(_) => {
+                    "SELECT keyspace_name, table_name, column_name, type FROM system_schema.columns WHERE keyspace_name = ?"
+                }
+                None => "SELECT keyspace_name, table_name, column_name, type FROM system_schema.columns",
+            };
+            let res = match keyspace {
+                Some(ks) => session.query_unpaged(q_cols, &(ks.to_string(),)).await?,
+                None => session.query_unpaged(q_cols, &[]).await?,
+            };
+            for row in res.rows_typed::<(String, String, String, String)>()? {
+                let (ks, tb, col, ty) = row?;
+                consider(SchemaCandidate {
+                    kind: "column",
+                    fields: vec![("name", col.clone()), ("type", ty)],
+                    keyspace: ks,
+                    table: Some(tb),
+                    name: col,
+                });
+            }
+        }
+
+        if options.wants_kind("udt") {
+            let q_types = match keyspace {
+                Some(_) => {
+                    "SELECT keyspace_name, type_name, field_names, field_types FROM system_schema.types WHERE keyspace_name = ?"
+                }
+                None => "SELECT keyspace_name, type_name, field_names, field_types FROM system_schema.types",
+            };
+            let res = match keyspace {
+                Some(ks) => session.query_unpaged(q_types, &(ks.to_string(),)).await?,
+                None => session.query_unpaged(q_types, &[]).await?,
+            };
+            for row in
+                res.rows_typed::<(String, String, Option<Vec<String>>, Option<Vec<String>>)>()?
+            {
+                let (ks, ty, field_names, field_types) = row?;
+                let described = field_names
+                    .unwrap_or_default()
+                    .into_iter()
+                    .zip(field_types.unwrap_or_default())
+                    .map(|(n, t)| format!("{n} {t}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                consider(SchemaCandidate {
+                    kind: "udt",
+                    fields: vec![("name", ty.clone()), ("type", described)],
+                    keyspace: ks,
+                    table: None,
+                    name: ty,
+                });
+            }
+        }
+
+        if options.wants_kind("view") {
+            let q_views = match keyspace {
+                Some(_) => {
+                    "SELECT keyspace_name, view_name, comment FROM system_schema.views WHERE keyspace_name = ?"
+                }
+                None => "SELECT keyspace_name, view_name, comment FROM system_schema.views",
+            };
+            let res = match keyspace {
+                Some(ks) => session.query_unpaged(q_views, &(ks.to_string(),)).await?,
+                None => session.query_unpaged(q_views, &[]).await?,
+            };
+            for row in res.rows_typed::<(String, String, Option<String>)>()? {
+                let (ks, v, comment) = row?;
+                consider(SchemaCandidate {
+                    kind: "view",
+                    fields: vec![
+                        ("name", v.clone()),
+                        ("comment", comment.unwrap_or_default()),
+                    ],
+                    keyspace: ks,
+                    table: None,
+                    name: v,
+                });
+            }
+        }
+
+        if options.wants_kind("index") {
+            let q_indexes = match keyspace {
+                Some(_) => {
+                    "SELECT keyspace_name, table_name, index_name, options FROM system_schema.indexes WHERE keyspace_name = ?"
+                }
+                None => "SELECT keyspace_name, table_name, index_name, options FROM system_schema.indexes",
+            };
+            let res = match keyspace {
+                Some(ks) => session.query_unpaged(q_indexes, &(ks.to_string(),)).await?,
+                None => session.query_unpaged(q_indexes, &[]).await?,
+            };
+            for row in res.rows_typed::<(
+                String,
+                String,
+                String,
+                Option<std::collections::HashMap<String, String>>,
+            )>()? {
+                let (ks, tb, idx, idx_options) = row?;
+                let target = idx_options
+                    .and_then(|mut o| o.remove("target"))
+                    .unwrap_or_default();
+                consider(SchemaCandidate {
+                    kind: "index",
+                    fields: vec![("name", idx.clone()), ("index_target", target)],
+                    keyspace: ks,
+                    table: Some(tb),
+                    name: idx,
+                });
+            }
+        }
+
+        if options.wants_kind("function") {
+            let q_funcs = match keyspace {
+                Some(_) => {
+                    "SELECT keyspace_name, function_name, return_type, body FROM system_schema.functions WHERE keyspace_name = ?"
+                }
+                None => "SELECT keyspace_name, function_name, return_type, body FROM system_schema.functions",
+            };
+            let res = match keyspace {
+                Some(ks) => session.query_unpaged(q_funcs, &(ks.to_string(),)).await?,
+                None => session.query_unpaged(q_funcs, &[]).await?,
+            };
+            for row in res.rows_typed::<(String, String, Option<String>, Option<String>)>()? {
+                let (ks, f, return_type, body) = row?;
+                consider(SchemaCandidate {
+                    kind: "function",
+                    fields: vec![
+                        ("name", f.clone()),
+                        ("type", return_type.unwrap_or_default()),
+                        ("body", body.unwrap_or_default()),
+                    ],
+                    keyspace: ks,
+                    table: None,
+                    name: f,
+                });
+            }
+        }
+
+        if options.wants_kind("aggregate") {
+            let q_aggs = match keyspace {
+                Some(_) => {
+                    "SELECT keyspace_name, aggregate_name, return_type FROM system_schema.aggregates WHERE keyspace_name = ?"
+                }
+                None => "SELECT keyspace_name, aggregate_name, return_type FROM system_schema.aggregates",
+            };
+            let res = match keyspace {
+                Some(ks) => session.query_unpaged(q_aggs, &(ks.to_string(),)).await?,
+                None => session.query_unpaged(q_aggs, &[]).await?,
+            };
+            for row in res.rows_typed::<(String, String, Option<String>)>()? {
+                let (ks, a, return_type) = row?;
+                consider(SchemaCandidate {
+                    kind: "aggregate",
+                    fields: vec![
+                        ("name", a.clone()),
+                        ("type", return_type.unwrap_or_default()),
+                    ],
+                    keyspace: ks,
+                    table: None,
+                    name: a,
+                });
+            }
+        }
+
+        Ok(finish_schema_results(hits))
+    }
+
+    /// Partition-count-weighted mean of `(mean_partition_size, partitions_count)` ranges.
+    fn weighted_mean_partition_size(ranges: &[(i64, i64)]) -> Option<i64> {
+        let total: i128 = ranges.iter().map(|(_, count)| *count as i128).sum();
+        if total <= 0 {
+            return None;
+        }
+        let weighted: i128 = ranges
+            .iter()
+            .map(|(mean, count)| *mean as i128 * *count as i128)
+            .sum();
+        Some((weighted / total) as i64)
+    }
+
+    /// Gather schema, topology, size estimates and `system.large_partitions`
+    /// for one keyspace (or every non-system keyspace) and run the lint rules.
+    pub async fn lint_schema_with(
+        session: &scylla::Session,
+        keyspace: Option<&str>,
+        progress: &Progress,
+    ) -> Result<Map<String, Value>> {
+        use crate::lint::{KeyspaceSnapshot, SchemaSnapshot, TableSnapshot, UdtSnapshot};
+        use std::collections::{BTreeMap, HashMap};
+
+        let mut snapshot = SchemaSnapshot::default();
+        for node in cluster_topology_with_options(session, false).await? {
+            if let Some(dc) = node.get("data_center").and_then(Value::as_str) {
+                *snapshot.dc_nodes.entry(dc.to_string()).or_default() += 1;
+            }
+        }
+
+        let all_keyspaces = list_keyspaces_with(session).await?;
+        let keyspaces: Vec<String> = match keyspace {
+            Some(ks) if all_keyspaces.iter().any(|k| k == ks) => vec![ks.to_string()],
+            Some(ks) => {
+                return Err(anyhow::Error::new(UnknownIdentifier {
+                    kind: "keyspace",
+                    name: ks.to_string(),
+                    keyspace: None,
+                    table: None,
+                    suggestions: suggest_identifiers(ks, all_keyspaces.iter().map(String::as_str)),
+                }));
+            }
+            None => all_keyspaces
+                .into_iter()
+                .filter(|ks| !crate::lint::is_system_keyspace(ks))
+                .collect(),
+        };
+
+        // Checks that could not read their source, so a clean report is not
+        // mistaken for a clean schema.
+        let mut notes = Vec::new();
+
+        // Large partition records are Scylla-only and node-local: read every node,
+        // count each partition once across its replicas, and treat a missing table
+        // as "none recorded".
+        let mut large: HashMap<(String, String), (usize, i64)> = HashMap::new();
+        let mut seen = std::collections::HashSet::new();
+        let results = match query_targeted(
+            session,
+            "SELECT keyspace_name, table_name, partition_key, partition_size FROM system.large_partitions",
+            &(),
+            &NodeTarget::AllNodes,
+        )
+        .await
+        {
+            Ok(results) => results,
+            Err(err) if is_missing_table_error(&err) => Vec::new(),
+            Err(err) => {
+                notes.push(skipped_check_note("system.large_partitions", None, &err));
+                Vec::new()
+            }
+        };
+        for (node, result) in results {
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    if !is_missing_table_error(&err) {
+                        notes.push(skipped_check_note(
+                            "system.large_partitions",
+                            node.as_ref(),
+                            &err,
+                        ));
                     }
-                    let keyspace = ks.unwrap();
-                    let span = tracing::info_span!("tool", name = "list_views", %keyspace);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("list_views failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
+                    continue;
+                }
+            };
+            for row in result.rows_typed_or_empty::<(String, String, Option<String>, Option<i64>)>()
+            {
+                let (ks, tb, key, size) = row?;
+                let entry = large.entry((ks.clone(), tb.clone())).or_insert((0, 0));
+                if seen.insert((ks, tb, key)) {
+                    entry.0 += 1;
+                }
+                entry.1 = entry.1.max(size.unwrap_or(0));
+            }
+        }
+
+        let total = keyspaces.len() as u64;
+        for (done, ks) in keyspaces.into_iter().enumerate() {
+            let replication = keyspace_replication_with(session, &ks)
+                .await?
+                .get("replication")
+                .and_then(Value::as_object)
+                .cloned()
+                .unwrap_or_default();
+            snapshot.keyspaces.push(KeyspaceSnapshot {
+                name: ks.clone(),
+                replication,
+            });
+
+            let mut tables: BTreeMap<String, TableSnapshot> = list_tables_with(session, &ks)
+                .await?
+                .into_iter()
+                .map(|tb| {
+                    let snapshot = TableSnapshot {
+                        keyspace: ks.clone(),
+                        table: tb.clone(),
+                        ..Default::default()
                     };
-                    match crate::db::list_views_with(&session, &keyspace).await {
-                        Ok(list) => {
-                            let json = serde_json::to_string(&list).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("list_views failed: {}", err),
-                            None,
-                        )),
+                    (tb, snapshot)
+                })
+                .collect();
+
+            let result = session
+                .query_unpaged(
+                    "SELECT table_name, column_name, kind, position, type FROM system_schema.columns WHERE keyspace_name = ?",
+                    (ks.clone(),),
+                )
+                .await?;
+            let mut keys: Vec<(String, String, i32, String, String)> = Vec::new();
+            for row in result.rows_typed::<(String, String, String, i32, String)>()? {
+                let (tb, col, kind, pos, ty) = row?;
+                keys.push((tb, kind, pos, col, ty));
+            }
+            keys.sort_by(|a, b| (&a.0, &a.1, a.2).cmp(&(&b.0, &b.1, b.2)));
+            for (tb, kind, _, col, ty) in keys {
+                let Some(table) = tables.get_mut(&tb) else {
+                    continue;
+                };
+                match kind.as_str() {
+                    "partition_key" => table.partition_keys.push((col, ty)),
+                    "clustering" => table.clustering_keys.push((col, ty)),
+                    _ => table.regular_columns.push((col, ty)),
+                }
+            }
+
+            let result = session
+                .query_unpaged(
+                    "SELECT table_name FROM system_schema.indexes WHERE keyspace_name = ?",
+                    (ks.clone(),),
+                )
+                .await?;
+            for row in result.rows_typed::<(String,)>()? {
+                let (tb,) = row?;
+                if let Some(table) = tables.get_mut(&tb) {
+                    table.index_count += 1;
+                }
+            }
+
+            // Each node only records its own primary ranges, so gather them from every node.
+            let mut ranges: HashMap<String, Vec<(i64, i64)>> = HashMap::new();
+            let source = format!("system.size_estimates for keyspace {ks}");
+            let results = match query_targeted(
+                session,
+                "SELECT table_name, mean_partition_size, partitions_count FROM system.size_estimates WHERE keyspace_name = ?",
+                &(ks.clone(),),
+                &NodeTarget::AllNodes,
+            )
+            .await
+            {
+                Ok(results) => results,
+                Err(err) => {
+                    notes.push(skipped_check_note(&source, None, &err));
+                    Vec::new()
+                }
+            };
+            for (node, result) in results {
+                let result = match result {
+                    Ok(result) => result,
+                    Err(err) => {
+                        notes.push(skipped_check_note(&source, node.as_ref(), &err));
+                        continue;
+                    }
+                };
+                for row in result.rows_typed_or_empty::<(String, Option<i64>, Option<i64>)>() {
+                    let (tb, mean, count) = row?;
+                    ranges
+                        .entry(tb)
+                        .or_default()
+                        .push((mean.unwrap_or(0), count.unwrap_or(0)));
+                }
+            }
+
+            for (tb, table) in tables.iter_mut() {
+                table.mean_partition_size =
+                    ranges.get(tb).and_then(|r| weighted_mean_partition_size(r));
+                if let Some((count, largest)) = large.get(&(ks.clone(), tb.clone())) {
+                    table.large_partitions = *count;
+                    table.largest_partition = Some(*largest);
+                }
+            }
+            snapshot.tables.extend(tables.into_values());
+
+            let result = session
+                .query_unpaged(
+                    "SELECT type_name, field_types FROM system_schema.types WHERE keyspace_name = ?",
+                    (ks.clone(),),
+                )
+                .await?;
+            for row in result.rows_typed::<(String, Option<Vec<String>>)>()? {
+                let (name, field_types) = row?;
+                snapshot.udts.push(UdtSnapshot {
+                    keyspace: ks.clone(),
+                    name,
+                    field_types: field_types.unwrap_or_default(),
+                });
+            }
+            progress.step(done as u64 + 1, Some(total), &format!("read keyspace {ks}"));
+        }
+
+        let mut report = crate::lint::report(crate::lint::lint(&snapshot));
+        if !notes.is_empty() {
+            report.insert("notes".into(), serde_json::json!(notes));
+        }
+        Ok(report)
+    }
+
+    /// `lint_schema` note for a partition-size source that could not be read,
+    /// on the whole cluster or on the labelled node.
+    fn skipped_check_note(
+        source: &str,
+        node: Option<&Map<String, Value>>,
+        err: &anyhow::Error,
+    ) -> String {
+        let node = node
+            .and_then(|n| n.get("node"))
+            .and_then(Value::as_str)
+            .map(|n| format!(" on {n}"))
+            .unwrap_or_default();
+        format!("partition size checks skipped: could not read {source}{node}: {err}")
+    }
+
+    fn result_rows_as_maps(result: scylla::QueryResult) -> Vec<Map<String, Value>> {
+        let specs = result.col_specs().to_owned();
+        let rows = result.rows_or_empty();
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let val = row.columns.get(i).and_then(|o| o.as_ref());
+                let json = match val {
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(spec.name.clone(), json);
+            }
+            out.push(m);
+        }
+        out
+    }
+
+    /// Role metadata lives in `system_auth_v2` on Scylla 6+ and `system_auth` before that.
+    const AUTH_KEYSPACES: [&str; 2] = ["system_auth_v2", "system_auth"];
+
+    /// CQL string literal for statements such as `LIST ROLES OF` that cannot bind values.
+    fn cql_string_literal(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    /// Read every role from the auth keyspace; password hashes are never selected.
+    pub async fn list_roles_with(
+        session: &scylla::Session,
+    ) -> Result<(String, Vec<crate::access::RoleRecord>)> {
+        let mut last_err = None;
+        for ks in AUTH_KEYSPACES {
+            let cql = format!("SELECT role, can_login, is_superuser, member_of FROM {ks}.roles");
+            match session.query_unpaged(cql, &[]).await {
+                Ok(result) => {
+                    let mut roles = Vec::new();
+                    for row in result
+                        .rows_typed::<(String, Option<bool>, Option<bool>, Option<Vec<String>>)>()?
+                    {
+                        let (role, can_login, is_superuser, member_of) = row?;
+                        roles.push(crate::access::RoleRecord {
+                            role,
+                            can_login: can_login.unwrap_or(false),
+                            is_superuser: is_superuser.unwrap_or(false),
+                            member_of: member_of.unwrap_or_default(),
+                        });
                     }
+                    roles.sort_by(|a, b| a.role.cmp(&b.role));
+                    return Ok((ks.to_string(), roles));
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err
+            .map(anyhow::Error::from)
+            .unwrap_or_else(|| anyhow::anyhow!("no auth keyspace found")))
+    }
+
+    /// Grants held directly by each of `roles`, read from `<auth_keyspace>.role_permissions`.
+    pub async fn role_permissions_with(
+        session: &scylla::Session,
+        auth_keyspace: &str,
+        roles: &[String],
+    ) -> Result<Vec<crate::access::PermissionGrant>> {
+        if !AUTH_KEYSPACES.contains(&auth_keyspace) {
+            anyhow::bail!("invalid auth keyspace '{}'", auth_keyspace);
+        }
+        let cql = format!(
+            "SELECT role, resource, permissions FROM {auth_keyspace}.role_permissions WHERE role = ?"
+        );
+        let prepared = session.prepare(cql).await?;
+        let mut out = Vec::new();
+        for role in roles {
+            let result = session.execute_unpaged(&prepared, (role.clone(),)).await?;
+            for row in result.rows_typed::<(String, String, Option<Vec<String>>)>()? {
+                let (role, resource, permissions) = row?;
+                out.push(crate::access::PermissionGrant {
+                    role,
+                    resource,
+                    permissions: permissions.unwrap_or_default(),
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    /// `LIST ALL PERMISSIONS OF <role>`: works for a role's own permissions even
+    /// when the auth tables are not readable. Includes inherited grants.
+    pub async fn list_permissions_of_with(
+        session: &scylla::Session,
+        role: &str,
+    ) -> Result<Vec<crate::access::PermissionGrant>> {
+        let cql = format!("LIST ALL PERMISSIONS OF {}", cql_string_literal(role));
+        let result = session.query_unpaged(cql, &[]).await?;
+        let mut grouped: std::collections::BTreeMap<(String, String), Vec<String>> =
+            std::collections::BTreeMap::new();
+        for row in result_rows_as_maps(result) {
+            let text = |key: &str| {
+                row.get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            grouped
+                .entry((text("role"), text("resource")))
+                .or_default()
+                .push(text("permission"));
+        }
+        Ok(grouped
+            .into_iter()
+            .map(
+                |((role, resource), permissions)| crate::access::PermissionGrant {
+                    role,
+                    resource,
+                    permissions,
+                },
+            )
+            .collect())
+    }
+
+    /// `LIST ROLES`: every role for superusers, otherwise the caller's own roles.
+    pub async fn list_roles_statement_with(
+        session: &scylla::Session,
+    ) -> Result<Vec<crate::access::RoleRecord>> {
+        let result = session.query_unpaged("LIST ROLES", &[]).await?;
+        Ok(result_rows_as_maps(result)
+            .into_iter()
+            .filter_map(|row| {
+                Some(crate::access::RoleRecord {
+                    role: row.get("role")?.as_str()?.to_string(),
+                    can_login: row.get("login").and_then(Value::as_bool).unwrap_or(false),
+                    is_superuser: row.get("super").and_then(Value::as_bool).unwrap_or(false),
+                    member_of: Vec::new(),
+                })
+            })
+            .collect())
+    }
+
+    /// Direct and inherited grants of `role`.
+    pub async fn permissions_for_role_with(
+        session: &scylla::Session,
+        role: &str,
+    ) -> Result<Vec<crate::access::PermissionGrant>> {
+        if let Ok((auth_keyspace, roles)) = list_roles_with(session).await {
+            let effective = crate::access::effective_roles(role, &roles);
+            if let Ok(grants) = role_permissions_with(session, &auth_keyspace, &effective).await {
+                return Ok(grants);
+            }
+        }
+        list_permissions_of_with(session, role).await
+    }
+
+    /// Resolve the effective roles, superuser status and grants of `user`,
+    /// degrading from the auth tables to `LIST ROLES`/`LIST PERMISSIONS`.
+    pub async fn access_summary_with(
+        session: &scylla::Session,
+        user: Option<&str>,
+    ) -> Result<crate::access::AccessSummary> {
+        let mut summary = crate::access::AccessSummary {
+            user: user.map(str::to_string),
+            ..Default::default()
+        };
+        let Some(user) = user else {
+            summary
+                .notes
+                .push("no SCYLLA_USER configured; connected anonymously".into());
+            return Ok(summary);
+        };
+
+        match list_roles_with(session).await {
+            Ok((auth_keyspace, roles)) => {
+                if !roles.iter().any(|r| r.role == user) {
+                    summary.notes.push(format!(
+                        "role '{}' not found in {}.roles; authorization may be disabled",
+                        user, auth_keyspace
+                    ));
+                    summary.auth_keyspace = Some(auth_keyspace);
+                    return Ok(summary);
                 }
-                "list_udts" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() {
-                        let msg = "missing required argument 'keyspace'".to_string();
-                        return Ok(ToolOutput::error(msg));
-                    }
-                    let keyspace = ks.unwrap();
-                    let span = tracing::info_span!("tool", name = "list_udts", %keyspace);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("list_udts failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
+                summary.roles = crate::access::effective_roles(user, &roles);
+                summary.superuser = roles
+                    .iter()
+                    .any(|r| r.is_superuser && summary.roles.contains(&r.role));
+                summary.grants =
+                    match role_permissions_with(session, &auth_keyspace, &summary.roles).await {
+                        Ok(grants) => grants,
+                        Err(_) => list_permissions_of_with(session, user).await?,
                     };
-                    match crate::db::list_udts_with(&session, &keyspace).await {
-                        Ok(list) => {
-                            let json = serde_json::to_string(&list).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("list_udts failed: {}", err),
-                            None,
-                        )),
+                summary.auth_keyspace = Some(auth_keyspace);
+                summary.restricted = true;
+            }
+            Err(_) => {
+                let cql = format!("LIST ROLES OF {}", cql_string_literal(user));
+                let Ok(result) = session.query_unpaged(cql, &[]).await else {
+                    summary.notes.push(
+                        "role metadata is not readable; treating access as unrestricted".into(),
+                    );
+                    return Ok(summary);
+                };
+                for row in result_rows_as_maps(result) {
+                    if let Some(role) = row.get("role").and_then(Value::as_str) {
+                        summary.roles.push(role.to_string());
+                    }
+                    if row.get("super").and_then(Value::as_bool) == Some(true) {
+                        summary.superuser = true;
                     }
                 }
-                "list_functions" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() {
-                        let msg = "missing required argument 'keyspace'".to_string();
-                        return Ok(ToolOutput::error(msg));
+                match list_permissions_of_with(session, user).await {
+                    Ok(grants) => {
+                        summary.grants = grants;
+                        summary.restricted = true;
                     }
-                    let keyspace = ks.unwrap();
-                    let span = tracing::info_span!("tool", name = "list_functions", %keyspace);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("list_functions failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::list_functions_with(&session, &keyspace).await {
-                        Ok(list) => {
-                            let json = serde_json::to_string(&list).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
+                    Err(_) => summary.notes.push(
+                        "permissions are not readable; treating access as unrestricted".into(),
+                    ),
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// `cdc` extension options of the tables in `keyspace` (or of one table).
+    async fn cdc_options_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: Option<&str>,
+    ) -> Result<Vec<(String, std::collections::BTreeMap<String, String>)>> {
+        let result = match table {
+            Some(table) => {
+                session
+                    .query_unpaged(
+                        "SELECT table_name, extensions FROM system_schema.tables WHERE keyspace_name = ? AND table_name = ?",
+                        (keyspace.to_string(), table.to_string()),
+                    )
+                    .await?
+            }
+            None => {
+                session
+                    .query_unpaged(
+                        "SELECT table_name, extensions FROM system_schema.tables WHERE keyspace_name = ?",
+                        (keyspace.to_string(),),
+                    )
+                    .await?
+            }
+        };
+        let mut out = Vec::new();
+        for row in result.rows_or_empty() {
+            let Some(Some(CqlValue::Text(name))) = row.columns.first() else {
+                continue;
+            };
+            let Some(Some(CqlValue::Map(extensions))) = row.columns.get(1) else {
+                continue;
+            };
+            for (key, value) in extensions {
+                if let (CqlValue::Text(key), CqlValue::Blob(bytes)) = (key, value) {
+                    if key == "cdc" {
+                        if let Some(options) = crate::cdc::parse_extension(bytes) {
+                            out.push((name.clone(), options));
                         }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("list_functions failed: {}", err),
-                            None,
-                        )),
                     }
                 }
-                "list_aggregates" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() {
-                        let msg = "missing required argument 'keyspace'".to_string();
-                        return Ok(ToolOutput::error(msg));
-                    }
-                    let keyspace = ks.unwrap();
-                    let span = tracing::info_span!("tool", name = "list_aggregates", %keyspace);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("list_aggregates failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::list_aggregates_with(&session, &keyspace).await {
-                        Ok(list) => {
-                            let json = serde_json::to_string(&list).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("list_aggregates failed: {}", err),
-                            None,
-                        )),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Tables of `keyspace` with CDC enabled, with their log table and options.
+    pub async fn cdc_tables_with(
+        session: &scylla::Session,
+        keyspace: &str,
+    ) -> Result<Vec<Map<String, Value>>> {
+        let mut out = Vec::new();
+        for (table, options) in cdc_options_with(session, keyspace, None).await? {
+            if !crate::cdc::is_enabled(&options) {
+                continue;
+            }
+            let mut m = Map::new();
+            m.insert(
+                "log_table".into(),
+                Value::from(crate::cdc::log_table_name(&table)),
+            );
+            m.insert("table".into(), Value::from(table));
+            m.insert("options".into(), serde_json::json!(options));
+            out.push(m);
+        }
+        Ok(out)
+    }
+
+    /// Start times (Unix ms) of every published CDC stream generation.
+    async fn cdc_generations_with(session: &scylla::Session) -> Result<Vec<i64>> {
+        let result = session
+            .query_unpaged(
+                "SELECT time FROM system_distributed.cdc_generation_timestamps WHERE key = 'timestamps'",
+                &[],
+            )
+            .await?;
+        let mut out = Vec::new();
+        for row in result.rows_or_empty() {
+            if let Some(Some(CqlValue::Timestamp(ts))) = row.columns.first() {
+                out.push(ts.0);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Stream ids of the generation starting at `start_ms`, sorted for stable chunking.
+    async fn cdc_streams_with(session: &scylla::Session, start_ms: i64) -> Result<Vec<Vec<u8>>> {
+        let prepared = session
+            .prepare(
+                "SELECT streams FROM system_distributed.cdc_streams_descriptions_v2 WHERE time = ?",
+            )
+            .await?;
+        let result = session
+            .execute_unpaged(&prepared, (CqlTimestamp(start_ms),))
+            .await?;
+        let mut out = Vec::new();
+        for row in result.rows_or_empty() {
+            if let Some(Some(CqlValue::Set(streams) | CqlValue::List(streams))) =
+                row.columns.first()
+            {
+                for stream in streams {
+                    if let CqlValue::Blob(bytes) = stream {
+                        out.push(bytes.clone());
                     }
                 }
-                "size_estimates" => {
-                    let args = request.params.arguments.as_ref();
-                    let ks = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let tb = args
-                        .and_then(|m| m.get("table").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if ks.is_none() || tb.is_none() {
-                        let msg =
-                            "missing required arguments 'keyspace' and/or 'table'".to_string();
-                        return Ok(ToolOutput::error(msg));
+            }
+        }
+        out.sort();
+        out.dedup();
+        Ok(out)
+    }
+
+    fn cdc_log_rows(result: scylla::QueryResult) -> Vec<Map<String, Value>> {
+        let specs = result.col_specs().to_owned();
+        let rows = result.rows_or_empty();
+        let mut out = Vec::with_capacity(rows.len());
+        for row in rows.iter() {
+            let mut m = Map::new();
+            for (i, spec) in specs.iter().enumerate() {
+                let json = match row.columns.get(i).and_then(|o| o.as_ref()) {
+                    // cdc$operation is a tinyint, which the generic conversion leaves opaque.
+                    Some(CqlValue::TinyInt(v)) => Value::from(*v),
+                    Some(c) => cql_value_to_json(c),
+                    None => Value::Null,
+                };
+                m.insert(spec.name.clone(), json);
+            }
+            out.push(m);
+        }
+        out
+    }
+
+    /// Read one page of decoded changes from the CDC log of `keyspace.table` for
+    /// writes between `since_ms` and `until_ms`, walking every stream of each
+    /// generation active in that window. `cursor` is a previous `next_cursor`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn cdc_changes_with(
+        session: &scylla::Session,
+        keyspace: &str,
+        table: &str,
+        since_ms: i64,
+        until_ms: i64,
+        page_size: i32,
+        cursor: Option<&str>,
+        progress: &Progress,
+    ) -> Result<Map<String, Value>> {
+        use crate::cdc::Cursor;
+
+        let position: Option<Cursor> = cursor
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|_| anyhow::anyhow!("invalid cdc cursor"))?;
+        let (since_ms, until_ms) = position
+            .as_ref()
+            .map(|p| (p.since_ms, p.until_ms))
+            .unwrap_or((since_ms, until_ms));
+        if since_ms > until_ms {
+            anyhow::bail!("invalid window: 'since' is after 'until'");
+        }
+        let options = cdc_options_with(session, keyspace, Some(table))
+            .await?
+            .into_iter()
+            .map(|(_, options)| options)
+            .find(crate::cdc::is_enabled)
+            .ok_or_else(|| anyhow::anyhow!("CDC is not enabled on {}.{}", keyspace, table))?;
+        let log_table = crate::cdc::log_table_name(table);
+        let generations = crate::cdc::generations_in_window(
+            &cdc_generations_with(session).await?,
+            since_ms,
+            until_ms,
+        );
+
+        let cql = format!(
+            "SELECT * FROM {} WHERE \"cdc$stream_id\" IN ? AND \"cdc$time\" >= minTimeuuid(?) AND \"cdc$time\" <= maxTimeuuid(?)",
+            table_ref(keyspace, &log_table)?
+        );
+        let mut prepared = session.prepare(cql).await?;
+        let mut rows: Vec<Map<String, Value>> = Vec::new();
+        let mut next: Option<Cursor> = None;
+        let mut queries = 0u64;
+        let resume_ms = position.as_ref().map(|p| p.generation_ms);
+        'generations: for generation in generations
+            .iter()
+            .filter(|g| resume_ms.is_none_or(|ms| g.start_ms >= ms))
+        {
+            let streams = cdc_streams_with(session, generation.start_ms).await?;
+            let (first_chunk, mut paging) = match &position {
+                Some(p) if p.generation_ms == generation.start_ms => {
+                    (p.chunk, p.paging_state.clone())
+                }
+                _ => (0, None),
+            };
+            for (chunk_index, chunk) in streams
+                .chunks(crate::cdc::STREAMS_PER_QUERY)
+                .enumerate()
+                .skip(first_chunk)
+            {
+                let remaining = page_size - rows.len() as i32;
+                if remaining <= 0 {
+                    next = Some(Cursor {
+                        since_ms,
+                        until_ms,
+                        generation_ms: generation.start_ms,
+                        chunk: chunk_index,
+                        paging_state: None,
+                    });
+                    break 'generations;
+                }
+                prepared.set_page_size(remaining);
+                let paging_state = match paging.take() {
+                    Some(tok) => PagingState::new_from_raw_bytes(decode_paging_cursor(&tok)?),
+                    None => PagingState::start(),
+                };
+                let (result, paging_resp) = session
+                    .execute_single_page(
+                        &prepared,
+                        (
+                            chunk.to_vec(),
+                            CqlTimestamp(since_ms),
+                            CqlTimestamp(until_ms),
+                        ),
+                        paging_state,
+                    )
+                    .await?;
+                rows.extend(cdc_log_rows(result));
+                queries += 1;
+                progress.step(
+                    queries,
+                    None,
+                    &format!("read {} of up to {} CDC log rows", rows.len(), page_size),
+                );
+                if let PagingStateResponse::HasMorePages { state } = paging_resp {
+                    next = Some(Cursor {
+                        since_ms,
+                        until_ms,
+                        generation_ms: generation.start_ms,
+                        chunk: chunk_index,
+                        paging_state: state.as_bytes_slice().map(|arc| {
+                            let slice: &[u8] = arc.as_ref();
+                            base64::engine::general_purpose::STANDARD.encode(slice)
+                        }),
+                    });
+                    break 'generations;
+                }
+            }
+        }
+
+        let mut out = Map::new();
+        out.insert("keyspace".into(), Value::from(keyspace));
+        out.insert("table".into(), Value::from(table));
+        out.insert("log_table".into(), Value::from(log_table));
+        out.insert(
+            "since".into(),
+            Value::from(crate::cdc::format_timestamp_ms(since_ms)),
+        );
+        out.insert(
+            "until".into(),
+            Value::from(crate::cdc::format_timestamp_ms(until_ms)),
+        );
+        out.insert("cdc_options".into(), serde_json::json!(options));
+        out.insert(
+            "generations".into(),
+            Value::Array(
+                generations
+                    .iter()
+                    .map(|g| {
+                        serde_json::json!({
+                            "start": crate::cdc::format_timestamp_ms(g.start_ms),
+                            "end": g.end_ms.map(crate::cdc::format_timestamp_ms),
+                        })
+                    })
+                    .collect(),
+            ),
+        );
+        out.insert(
+            "changes".into(),
+            Value::Array(crate::cdc::group_changes(rows)),
+        );
+        let next_cursor = match next {
+            Some(cursor) => Value::String(serde_json::to_string(&cursor)?),
+            None => Value::Null,
+        };
+        out.insert("next_cursor".into(), next_cursor);
+        Ok(out)
+    }
+
+    /// One row of `system.large_partitions`, `large_rows` or `large_cells` as seen by a node.
+    #[derive(Debug, Clone, PartialEq)]
+    struct LargeDataEntry {
+        kind: &'static str,
+        keyspace: String,
+        table: String,
+        size: i64,
+        partition_key: String,
+        clustering_key: Option<String>,
+        column: Option<String>,
+        rows: Option<i64>,
+        sstable: Option<String>,
+        compaction_time_ms: Option<i64>,
+        node: String,
+    }
+
+    const LARGE_DATA_TABLES: [(&str, &str, &str); 3] = [
+        ("partition", "large_partitions", "partition_size"),
+        ("row", "large_rows", "row_size"),
+        ("cell", "large_cells", "cell_size"),
+    ];
+
+    fn large_data_entries(
+        kind: &'static str,
+        size_column: &str,
+        node: &str,
+        result: scylla::QueryResult,
+    ) -> Vec<LargeDataEntry> {
+        let specs = result.col_specs().to_owned();
+        let index = |name: &str| specs.iter().position(|spec| spec.name == name);
+        let columns = [
+            "keyspace_name",
+            "table_name",
+            size_column,
+            "partition_key",
+            "clustering_key",
+            "column_name",
+            "rows",
+            "sstable_name",
+            "compaction_time",
+        ]
+        .map(index);
+        let mut out = Vec::new();
+        for row in result.rows_or_empty() {
+            let value = |i: usize| {
+                columns[i]
+                    .and_then(|pos| row.columns.get(pos))
+                    .and_then(|v| v.as_ref())
+            };
+            let text = |i: usize| match value(i) {
+                Some(CqlValue::Text(s) | CqlValue::Ascii(s)) => Some(s.clone()),
+                _ => None,
+            };
+            let number = |i: usize| match value(i) {
+                Some(CqlValue::BigInt(n)) => Some(*n),
+                Some(CqlValue::Int(n)) => Some(i64::from(*n)),
+                _ => None,
+            };
+            let (Some(keyspace), Some(table)) = (text(0), text(1)) else {
+                continue;
+            };
+            out.push(LargeDataEntry {
+                kind,
+                keyspace,
+                table,
+                size: number(2).unwrap_or(0),
+                partition_key: text(3).unwrap_or_default(),
+                clustering_key: text(4).filter(|s| !s.is_empty()),
+                column: text(5),
+                rows: number(6),
+                sstable: text(7),
+                compaction_time_ms: match value(8) {
+                    Some(CqlValue::Timestamp(ts)) => Some(ts.0),
+                    _ => None,
+                },
+                node: node.to_string(),
+            });
+        }
+        out
+    }
+
+    /// Split a recorded partition key into its columns. Scylla renders composite
+    /// keys as `:`-joined values, so values are only attributed when the counts match.
+    fn partition_key_values(partition_key: &str, columns: &[String]) -> Option<Map<String, Value>> {
+        let values: Vec<&str> = if columns.len() == 1 {
+            vec![partition_key]
+        } else {
+            partition_key.split(':').collect()
+        };
+        (!columns.is_empty() && values.len() == columns.len()).then(|| {
+            columns
+                .iter()
+                .zip(values)
+                .map(|(c, v)| (c.clone(), Value::from(v)))
+                .collect()
+        })
+    }
+
+    /// Ranked large-data entries of one table.
+    #[derive(Debug)]
+    struct LargeDataTable {
+        keyspace: String,
+        table: String,
+        max_size: i64,
+        counts: Map<String, Value>,
+        entries: Vec<Map<String, Value>>,
+    }
+
+    /// Merge the same partition/row/cell reported by several replicas or SSTables
+    /// and rank per table by size, largest first, keeping `limit` entries per table.
+    fn merge_large_data(entries: Vec<LargeDataEntry>, limit: usize) -> Vec<LargeDataTable> {
+        use std::collections::{BTreeMap, BTreeSet};
+
+        type Key = (&'static str, String, Option<String>, Option<String>);
+        struct Merged {
+            size: i64,
+            rows: Option<i64>,
+            compaction_time_ms: Option<i64>,
+            nodes: BTreeSet<String>,
+            sstables: BTreeSet<String>,
+        }
+        let mut tables: BTreeMap<(String, String), BTreeMap<Key, Merged>> = BTreeMap::new();
+        for e in entries {
+            let merged = tables
+                .entry((e.keyspace, e.table))
+                .or_default()
+                .entry((e.kind, e.partition_key, e.clustering_key, e.column))
+                .or_insert(Merged {
+                    size: 0,
+                    rows: None,
+                    compaction_time_ms: None,
+                    nodes: BTreeSet::new(),
+                    sstables: BTreeSet::new(),
+                });
+            merged.size = merged.size.max(e.size);
+            merged.rows = merged.rows.max(e.rows);
+            merged.compaction_time_ms = merged.compaction_time_ms.max(e.compaction_time_ms);
+            merged.nodes.insert(e.node);
+            merged.sstables.extend(e.sstable);
+        }
+
+        let mut out = Vec::new();
+        for ((keyspace, table), merged) in tables {
+            let mut counts = Map::new();
+            for (kind, _, _, _) in merged.keys() {
+                let key = format!("{}s", kind);
+                let n = counts.get(&key).and_then(Value::as_u64).unwrap_or(0);
+                counts.insert(key, Value::from(n + 1));
+            }
+            let mut ranked: Vec<(Key, Merged)> = merged.into_iter().collect();
+            ranked.sort_by(|a, b| b.1.size.cmp(&a.1.size).then_with(|| a.0.cmp(&b.0)));
+            let max_size = ranked.first().map(|(_, m)| m.size).unwrap_or(0);
+            let entries = ranked
+                .into_iter()
+                .take(limit)
+                .map(|((kind, partition_key, clustering_key, column), m)| {
+                    let mut item = Map::new();
+                    item.insert("kind".into(), Value::from(kind));
+                    item.insert("size".into(), Value::from(m.size));
+                    item.insert("partition_key".into(), Value::from(partition_key));
+                    if let Some(ck) = clustering_key {
+                        item.insert("clustering_key".into(), Value::from(ck));
                     }
-                    let keyspace = ks.unwrap();
-                    let table = tb.unwrap();
-                    let span =
-                        tracing::info_span!("tool", name = "size_estimates", %keyspace, %table);
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("size_estimates failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::size_estimates_with(&session, &keyspace, &table).await {
-                        Ok(obj) => {
-                            let json = serde_json::to_string(&obj).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("size_estimates failed: {}", err),
-                            None,
-                        )),
+                    if let Some(col) = column {
+                        item.insert("column".into(), Value::from(col));
                     }
-                }
-                "search_schema" => {
-                    let args = request.params.arguments.as_ref();
-                    let pattern = args
-                        .and_then(|m| m.get("pattern").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    let keyspace = args
-                        .and_then(|m| m.get("keyspace").and_then(|v| v.as_str()))
-                        .map(|s| s.to_string());
-                    if pattern.is_none() {
-                        let msg = "missing required argument 'pattern'".to_string();
-                        return Ok(ToolOutput::error(msg));
+                    if let Some(rows) = m.rows {
+                        item.insert("rows".into(), Value::from(rows));
                     }
-                    let pat = pattern.unwrap();
-                    let span = tracing::info_span!("tool", name = "search_schema", %pat, keyspace = keyspace.as_deref().unwrap_or("<all>"));
-                    let _g = span.enter();
-                    let session = match self.session().await {
-                        Ok(session) => session,
-                        Err(err) => {
-                            let msg = format!("search_schema failed to connect: {err}");
-                            return Ok(ToolOutput::error(msg));
-                        }
-                    };
-                    match crate::db::search_schema_with(&session, &pat, keyspace.as_deref()).await {
-                        Ok(items) => {
-                            let json =
-                                serde_json::to_string(&items).unwrap_or_else(|_| "[]".into());
-                            Ok(ToolOutput::text_content(json, None))
-                        }
-                        Err(err) => Ok(ToolOutput::text_content(
-                            format!("search_schema failed: {}", err),
-                            None,
-                        )),
+                    if let Some(ms) = m.compaction_time_ms {
+                        item.insert(
+                            "compaction_time".into(),
+                            Value::from(crate::cdc::format_timestamp_ms(ms)),
+                        );
+                    }
+                    item.insert("nodes".into(), serde_json::json!(m.nodes));
+                    item.insert("sstables".into(), Value::from(m.sstables.len()));
+                    item
+                })
+                .collect();
+            out.push(LargeDataTable {
+                keyspace,
+                table,
+                max_size,
+                counts,
+                entries,
+            });
+        }
+        out.sort_by_key(|t| std::cmp::Reverse(t.max_size));
+        out
+    }
+
+    /// Read `system.large_partitions`, `large_rows` and `large_cells` from every
+    /// node, merge duplicates across replicas and rank entries per table by size.
+    pub async fn large_data_with(
+        session: &scylla::Session,
+        keyspace: Option<&str>,
+        table: Option<&str>,
+        kinds: &[String],
+        limit: usize,
+        target: &NodeTarget,
+    ) -> Result<Map<String, Value>> {
+        let mut nodes = std::collections::BTreeSet::new();
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        for (kind, system_table, size_column) in LARGE_DATA_TABLES
+            .iter()
+            .filter(|(kind, _, _)| kinds.is_empty() || kinds.iter().any(|k| k == kind))
+        {
+            // (keyspace_name, table_name) is the partition key, so a table filter is a
+            // single-partition read; a keyspace alone still needs ALLOW FILTERING.
+            let (cql, values) = match (keyspace, table) {
+                (Some(ks), Some(tb)) => (
+                    format!(
+                        "SELECT * FROM system.{} WHERE keyspace_name = ? AND table_name = ?",
+                        system_table
+                    ),
+                    vec![ks.to_string(), tb.to_string()],
+                ),
+                (Some(ks), None) => (
+                    format!(
+                        "SELECT * FROM system.{} WHERE keyspace_name = ? ALLOW FILTERING",
+                        system_table
+                    ),
+                    vec![ks.to_string()],
+                ),
+                _ => (format!("SELECT * FROM system.{}", system_table), Vec::new()),
+            };
+            for (node, result) in query_targeted(session, &cql, &values, target).await? {
+                let address = node
+                    .as_ref()
+                    .and_then(|n| n.get("node"))
+                    .and_then(Value::as_str)
+                    .unwrap_or("coordinator")
+                    .to_string();
+                nodes.insert(address.clone());
+                match result {
+                    Ok(result) => {
+                        entries.extend(large_data_entries(kind, size_column, &address, result))
                     }
+                    Err(err) => errors.push(serde_json::json!({
+                        "node": address,
+                        "table": format!("system.{}", system_table),
+                        "error": err.to_string(),
+                    })),
                 }
-                _ => {
-                    let msg = format!("tool '{}' is not yet implemented (read-only phase)", name);
-                    Ok(ToolOutput::error(msg))
+            }
+        }
+
+        let mut tables = Vec::new();
+        for ranked in merge_large_data(entries, limit) {
+            let LargeDataTable {
+                keyspace: ks,
+                table: tb,
+                max_size,
+                counts,
+                entries: mut items,
+            } = ranked;
+            let schema = describe_table_with(session, &ks, &tb).await.ok();
+            let partition_keys = schema
+                .as_ref()
+                .map(|s| s.partition_keys.clone())
+                .unwrap_or_default();
+            for item in items.iter_mut() {
+                let values = item
+                    .get("partition_key")
+                    .and_then(Value::as_str)
+                    .and_then(|pk| partition_key_values(pk, &partition_keys));
+                if let Some(values) = values {
+                    item.insert("partition_key_values".into(), Value::Object(values));
                 }
             }
+            let mut m = Map::new();
+            m.insert("keyspace".into(), Value::from(ks));
+            m.insert("table".into(), Value::from(tb));
+            m.insert(
+                "partition_key_columns".into(),
+                serde_json::json!(partition_keys),
+            );
+            m.insert(
+                "clustering_key_columns".into(),
+                serde_json::json!(schema.map(|s| s.clustering_keys).unwrap_or_default()),
+            );
+            m.insert("max_size".into(), Value::from(max_size));
+            m.insert("counts".into(), Value::Object(counts));
+            m.insert(
+                "entries".into(),
+                Value::Array(items.into_iter().map(Value::Object).collect()),
+            );
+            tables.push(Value::Object(m));
         }
-    }
 
-    #[cfg(test)]
-    mod tests {
-        use super::{SessionConfig, SessionState, ToolExecutor};
-        use std::sync::Arc;
+        let mut out = Map::new();
+        out.insert("nodes_queried".into(), serde_json::json!(nodes));
+        out.insert("tables".into(), Value::Array(tables));
+        if !errors.is_empty() {
+            out.insert("errors".into(), Value::Array(errors));
+        }
+        Ok(out)
+    }
 
-        fn test_executor() -> ToolExecutor {
-            ToolExecutor::new(Arc::new(SessionState::new(SessionConfig {
-                uri: "127.0.0.1:9042".to_string(),
-                credentials: None,
-                ssl: None,
-                connect_timeout: std::time::Duration::from_millis(1),
-            })))
+    /// Nodes from `system.local` and `system.peers` with their vnode tokens.
+    async fn ring_nodes_with(session: &scylla::Session) -> Result<Vec<crate::ring::NodeInfo>> {
+        let mut out = Vec::new();
+        for cql in [
+            "SELECT host_id, data_center, rpc_address, tokens FROM system.local",
+            "SELECT host_id, data_center, rpc_address, tokens FROM system.peers",
+        ] {
+            let result = session.query_unpaged(cql, &[]).await?;
+            for row in result_rows_as_maps(result) {
+                let Some(host_id) = row.get("host_id").and_then(Value::as_str) else {
+                    continue;
+                };
+                let tokens = row
+                    .get("tokens")
+                    .and_then(Value::as_array)
+                    .map(|tokens| {
+                        tokens
+                            .iter()
+                            .filter_map(|t| t.as_str()?.parse::<i64>().ok())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                out.push(crate::ring::NodeInfo {
+                    host_id: host_id.to_string(),
+                    address: row
+                        .get("rpc_address")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    datacenter: row
+                        .get("data_center")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    tokens,
+                });
+            }
         }
+        Ok(out)
+    }
 
-        #[tokio::test]
-        async fn paging_cursor_tokens_are_opaque_one_time_and_query_bound() {
-            let executor = test_executor();
-            let columns = vec!["id".to_string()];
-            let binding =
-                ToolExecutor::paging_cursor_binding("ks", "tbl", &columns, 100, None, None)
-                    .expect("binding should encode");
-            let token = executor
-                .store_paging_cursor(binding.clone(), "raw-scylla-state".to_string())
-                .await
-                .expect("token should store");
+    /// Whether `err` says the queried table does not exist, as opposed to a
+    /// permission, timeout or connection failure.
+    fn is_missing_table_error(err: &anyhow::Error) -> bool {
+        let message = format!("{err:#}").to_lowercase();
+        message.contains("unconfigured table") || message.contains("does not exist")
+    }
 
-            assert_ne!(token, "raw-scylla-state");
+    /// Vnode token ownership per node and, where `system.tablets` exists, the
+    /// tablet placement of each table (optionally scoped to a keyspace/table).
+    pub async fn token_ring_with(
+        session: &scylla::Session,
+        keyspace: Option<&str>,
+        table: Option<&str>,
+        include_tablets: bool,
+    ) -> Result<Map<String, Value>> {
+        use std::collections::BTreeMap;
 
-            let wrong_binding =
-                ToolExecutor::paging_cursor_binding("ks", "tbl", &columns, 50, None, None)
-                    .expect("binding should encode");
-            let err = executor
-                .take_paging_cursor(&token, &wrong_binding)
-                .await
-                .expect_err("cursor should be bound to page size and query shape");
-            assert!(err.to_string().contains("does not match"));
+        let nodes = ring_nodes_with(session).await?;
+        let mut out = Map::new();
+        out.insert(
+            "vnodes".into(),
+            Value::Object(crate::ring::vnode_report(&nodes)),
+        );
+
+        // Tablets are Scylla 6+; older Scylla and Cassandra have no system.tablets.
+        let mut cql = "SELECT * FROM system.tablets".to_string();
+        let mut values = Vec::new();
+        if let Some(ks) = keyspace {
+            cql.push_str(" WHERE keyspace_name = ?");
+            values.push(ks.to_string());
+            if let Some(tb) = table {
+                cql.push_str(" AND table_name = ?");
+                values.push(tb.to_string());
+            }
+            cql.push_str(" ALLOW FILTERING");
+        }
+        let read = async {
+            let prepared = session
+                .prepare(Query::new(cql).with_page_size(TABLETS_PAGE_SIZE))
+                .await?;
+            execute_all_pages(session, &prepared, &values).await
+        };
+        let result = match read.await {
+            Ok(result) => result,
+            Err(err) if is_missing_table_error(&err) => {
+                out.insert("tablets".into(), Value::Null);
+                out.insert(
+                    "notes".into(),
+                    serde_json::json!([
+                        "system.tablets is not available; cluster uses vnodes only"
+                    ]),
+                );
+                return Ok(out);
+            }
+            Err(err) => return Err(err.context("failed to read system.tablets")),
+        };
+        let mut tables: BTreeMap<(String, String), Vec<crate::ring::Tablet>> = BTreeMap::new();
+        for row in result_rows_as_maps(result) {
+            let text = |key: &str| row.get(key).and_then(Value::as_str).map(str::to_string);
+            let (Some(ks), Some(tb)) = (text("keyspace_name"), text("table_name")) else {
+                continue;
+            };
+            let Some(last_token) = row.get("last_token").and_then(Value::as_i64) else {
+                continue;
+            };
+            let replicas = row
+                .get("replicas")
+                .and_then(Value::as_array)
+                .map(|list| {
+                    list.iter()
+                        .filter_map(|r| {
+                            let pair = r.as_array()?;
+                            let host = pair.first()?.as_str()?.to_string();
+                            Some((host, pair.get(1).and_then(Value::as_i64).unwrap_or(0)))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let in_transition = ["transition", "stage"]
+                .iter()
+                .any(|key| row.get(*key).is_some_and(|v| !v.is_null()));
+            tables
+                .entry((ks, tb))
+                .or_default()
+                .push(crate::ring::Tablet {
+                    last_token,
+                    replicas,
+                    in_transition,
+                });
+        }
+        let tablets: Vec<Value> = tables
+            .into_iter()
+            .map(|((ks, tb), tablets)| {
+                let mut m = Map::new();
+                m.insert("keyspace".into(), Value::from(ks));
+                m.insert("table".into(), Value::from(tb));
+                m.extend(crate::ring::tablet_report(
+                    &tablets,
+                    &nodes,
+                    include_tablets,
+                ));
+                Value::Object(m)
+            })
+            .collect();
+        out.insert("tablets".into(), Value::Array(tablets));
+        Ok(out)
+    }
 
-            let token = executor
-                .store_paging_cursor(binding.clone(), "raw-scylla-state".to_string())
-                .await
-                .expect("token should store");
-            let raw = executor
-                .take_paging_cursor(&token, &binding)
-                .await
-                .expect("matching binding should resolve cursor");
-            assert_eq!(raw, "raw-scylla-state");
+    /// Cross-reference keyspace replication with the datacenters and live nodes
+    /// of the topology (one keyspace, or every keyspace including system ones).
+    pub async fn replication_report_with(
+        session: &scylla::Session,
+        keyspace: Option<&str>,
+    ) -> Result<Map<String, Value>> {
+        use crate::replication::DcNodes;
 
-            let replay = executor
-                .take_paging_cursor(&token, &binding)
-                .await
-                .expect_err("cursor tokens should be one-time use");
-            assert!(replay.to_string().contains("unknown or expired"));
+        let mut dcs: std::collections::BTreeMap<String, DcNodes> =
+            std::collections::BTreeMap::new();
+        for node in cluster_topology_with_options(session, false).await? {
+            let Some(dc) = node.get("data_center").and_then(Value::as_str) else {
+                continue;
+            };
+            let entry = dcs.entry(dc.to_string()).or_default();
+            entry.total += 1;
+            if node.get("up").and_then(Value::as_bool) != Some(false) {
+                entry.up += 1;
+            }
         }
+        let names = match keyspace {
+            Some(ks) => vec![ks.to_string()],
+            None => list_keyspaces_with(session).await?,
+        };
+        let mut keyspaces = Vec::with_capacity(names.len());
+        for ks in names {
+            let replication = keyspace_replication_with(session, &ks)
+                .await?
+                .remove("replication")
+                .and_then(|v| v.as_object().cloned())
+                .unwrap_or_default();
+            keyspaces.push((ks, replication));
+        }
+        Ok(crate::replication::report(&keyspaces, &dcs))
     }
 
-    #[derive(Clone, Debug)]
-    struct SessionConfig {
-        uri: String,
-        credentials: Option<(String, String)>,
-        ssl: Option<SslConfig>,
-        connect_timeout: std::time::Duration,
+    // Non-session wrappers for integration tests
+    pub async fn list_views(keyspace: &str) -> Result<Vec<String>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        list_views_with(&session, keyspace).await
     }
 
-    #[derive(Clone, Debug)]
-    struct SslConfig {
-        ca_bundle: Option<String>,
-        insecure: bool,
+    pub async fn list_udts(keyspace: &str) -> Result<Vec<String>> {
+        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
+        let session = SessionBuilder::new().known_node(uri).build().await?;
+        list_udts_with(&session, keyspace).await
     }
 
-    impl SessionConfig {
-        fn from_env() -> Self {
-            let uri = std::env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-            let credentials = match (std::env::var("SCYLLA_USER"), std::env::var("SCYLLA_PASS")) {
-                (Ok(user), Ok(pass)) => Some((user, pass)),
-                _ => None,
-            };
-            let ssl_enabled = std::env::var("SCYLLA_SSL")
-                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-                .unwrap_or(false);
-            let ssl = if ssl_enabled {
-                let ca_bundle = std::env::var("SCYLLA_CA_BUNDLE").ok();
-                let insecure = std::env::var("SCYLLA_SSL_INSECURE")
-                    .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
-                    .unwrap_or(false);
-                Some(SslConfig {
-                    ca_bundle,
-                    insecure,
-                })
-            } else {
-                None
-            };
-            Self {
-                uri,
-                credentials,
-                ssl,
-                connect_timeout: timeout_from_env("SCYLLA_CONNECT_TIMEOUT_MS", 10_000),
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            annotate_topology_warnings, build_column_list, build_filters_clause_prepared,
+            build_order_by_clause, build_select_all_query, build_select_columns_query,
+            cql_value_to_json, decode_paging_cursor, edit_distance, filter_node_rows,
+            finish_schema_results, is_missing_table_error, json_to_typed_cql, merge_large_data,
+            partition_key_values, push_schema_result, rank_schema_candidate, render_keyspace_ddl,
+            render_table_ddl, size_estimates_summary, skipped_check_note, suggest_identifiers,
+            table_stats_summary, validate_search_schema_pattern, ColumnMeta, CqlIdent,
+            DescribeTable, LargeDataEntry, NodeTarget, SchemaCandidate, SchemaFingerprints,
+            SchemaMatcher, SearchMode, SearchSchemaOptions, UnknownIdentifier,
+            MAX_PAGING_CURSOR_BYTES, MAX_SEARCH_SCHEMA_PATTERN_BYTES, MAX_SEARCH_SCHEMA_RESULTS,
+        };
+        use base64::Engine;
+        use scylla_cql::frame::response::result::CqlValue;
+        use serde_json::{Map, Value};
+
+        #[test]
+        fn table_ref_rejects_cql_identifier_injection() {
+            let err = build_select_all_query("ks; DROP KEYSPACE prod", "users", "", 10)
+                .expect_err("keyspace syntax should not be interpolated into CQL");
+            assert!(err.to_string().contains("invalid keyspace identifier"));
+
+            let err =
+                build_select_columns_query("ks", "users WHERE id = 1", "id", "", "", Some(10))
+                    .expect_err("table syntax should not be interpolated into CQL");
+            assert!(err.to_string().contains("invalid table identifier"));
+        }
+
+        #[test]
+        fn query_builders_accept_plain_identifiers() {
+            let select_all = build_select_all_query("ks_1", "users_2", " WHERE id = ?", 25)
+                .expect("valid identifiers should build");
+            assert_eq!(
+                select_all,
+                "SELECT * FROM ks_1.users_2 WHERE id = ? LIMIT 25"
+            );
+
+            let select_cols =
+                build_select_columns_query("ks", "users", "id, name", "", " ORDER BY id ASC", None)
+                    .expect("valid identifiers should build");
+            assert_eq!(select_cols, "SELECT id, name FROM ks.users ORDER BY id ASC");
+        }
+
+        #[test]
+        fn identifiers_quote_mixed_case_reserved_and_special_names() {
+            let cql = |name: &str| CqlIdent::new(name).expect("valid identifier").to_cql();
+            assert_eq!(cql("email"), "email");
+            assert_eq!(cql("user_2"), "user_2");
+            assert_eq!(cql("Users"), "\"Users\"");
+            assert_eq!(cql("order"), "\"order\"");
+            assert_eq!(cql("2fa_secret"), "\"2fa_secret\"");
+            assert_eq!(cql("first name"), "\"first name\"");
+            assert_eq!(cql("say \"hi\""), "\"say \"\"hi\"\"\"");
+
+            assert!(CqlIdent::new("").is_err());
+            assert!(CqlIdent::new("bad\nname").is_err());
+            assert!(CqlIdent::new(&"a".repeat(300)).is_err());
+
+            assert_eq!(CqlIdent::quote_or_raw("Users"), "\"Users\"");
+            assert_eq!(CqlIdent::quote_or_raw(""), "");
+        }
+
+        #[test]
+        fn parse_accepts_quoted_client_identifiers() {
+            let parsed = CqlIdent::parse("\"Order \"\"Items\"\"\"").expect("quoted identifier");
+            assert_eq!(parsed.name(), "Order \"Items\"");
+            assert_eq!(CqlIdent::parse("Users").expect("bare").name(), "Users");
+            assert!(CqlIdent::parse("\"bad\"quote\"").is_err());
+            assert!(CqlIdent::parse("\"\"").is_err());
+        }
+
+        #[test]
+        fn table_ref_quotes_case_sensitive_names() {
+            let cql = build_select_all_query("Analytics", "DailyEvents", "", 5)
+                .expect("mixed-case identifiers should build");
+            assert_eq!(cql, "SELECT * FROM \"Analytics\".\"DailyEvents\" LIMIT 5");
+
+            let err = build_select_all_query("ks", "t\" WHERE 1=1 --", "", 5)
+                .expect_err("quotes are not legal in table names");
+            assert!(err.to_string().contains("invalid table identifier"));
+        }
+
+        #[test]
+        fn column_filter_and_order_identifiers_cannot_break_out_of_quotes() {
+            let injected = "id\" FROM ks.secrets; --".to_string();
+            let cols = build_column_list(&[injected.clone(), "Name".into()])
+                .expect("escaped column identifiers should build");
+            assert_eq!(cols, "\"id\"\" FROM ks.secrets; --\", \"Name\"");
+
+            let mut filters = Map::new();
+            filters.insert(injected.clone(), Value::from(1));
+            let (clause, values) =
+                build_filters_clause_prepared(Some(&filters)).expect("filter should build");
+            assert_eq!(clause, " WHERE \"id\"\" FROM ks.secrets; --\" = ?");
+            assert_eq!(values.len(), 1);
+
+            let order = vec![(injected, "desc; DROP TABLE x".to_string())];
+            let clause = build_order_by_clause(Some(&order)).expect("order_by should build");
+            assert_eq!(clause, " ORDER BY \"id\"\" FROM ks.secrets; --\" ASC");
+
+            assert!(build_column_list(&["ok\u{0}".into()]).is_err());
+            assert!(build_column_list(&[]).is_err());
+        }
+
+        #[test]
+        fn paging_cursor_rejects_oversized_tokens() {
+            let bytes = vec![0_u8; MAX_PAGING_CURSOR_BYTES + 1];
+            let token = base64::engine::general_purpose::STANDARD.encode(bytes);
+            let err =
+                decode_paging_cursor(&token).expect_err("oversized cursor should be rejected");
+            assert!(err.to_string().contains("too large"));
+        }
+
+        #[test]
+        fn search_schema_rejects_empty_or_oversized_patterns() {
+            let err = validate_search_schema_pattern("   ")
+                .expect_err("blank patterns should not scan the whole schema");
+            assert!(err.to_string().contains("must not be empty"));
+
+            let pattern = "a".repeat(MAX_SEARCH_SCHEMA_PATTERN_BYTES + 1);
+            let err = validate_search_schema_pattern(&pattern)
+                .expect_err("oversized patterns should be rejected");
+            assert!(err.to_string().contains("too large"));
+        }
+
+        #[test]
+        fn search_schema_result_limit_stops_at_cap() {
+            let mut out = Vec::new();
+            for _ in 0..(MAX_SEARCH_SCHEMA_RESULTS - 1) {
+                assert!(push_schema_result(&mut out, Map::new()));
+            }
+
+            let mut last = Map::new();
+            last.insert("kind".to_string(), Value::String("last".to_string()));
+            assert!(!push_schema_result(&mut out, last));
+            assert_eq!(out.len(), MAX_SEARCH_SCHEMA_RESULTS);
+        }
+
+        fn column(table: &str, name: &str, ty: &str) -> SchemaCandidate {
+            SchemaCandidate {
+                kind: "column",
+                keyspace: "app".into(),
+                table: Some(table.into()),
+                name: name.into(),
+                fields: vec![("name", name.into()), ("type", ty.into())],
             }
         }
-    }
 
-    struct SessionState {
-        config: SessionConfig,
-        cell: OnceCell<Arc<ScyllaSession>>,
-    }
+        fn ranked_names(
+            mode: SearchMode,
+            pattern: &str,
+            options: SearchSchemaOptions,
+            candidates: Vec<SchemaCandidate>,
+        ) -> Vec<String> {
+            let options = SearchSchemaOptions { mode, ..options };
+            let matcher = SchemaMatcher::new(mode, pattern).expect("pattern should compile");
+            let hits = candidates
+                .into_iter()
+                .filter_map(|c| rank_schema_candidate(&matcher, &options, c))
+                .collect();
+            finish_schema_results(hits)
+                .into_iter()
+                .map(|m| m["name"].as_str().unwrap_or_default().to_string())
+                .collect()
+        }
+
+        #[test]
+        fn substring_ranks_exact_then_prefix_then_contains() {
+            let names = ranked_names(
+                SearchMode::Substring,
+                "email",
+                SearchSchemaOptions::default(),
+                vec![
+                    column("users", "backup_email_sent", "boolean"),
+                    column("users", "email_verified", "boolean"),
+                    column("users", "email", "text"),
+                    column("users", "name", "text"),
+                ],
+            );
+            assert_eq!(names, ["email", "email_verified", "backup_email_sent"]);
+        }
+
+        #[test]
+        fn natural_language_queries_match_each_term() {
+            let names = ranked_names(
+                SearchMode::Substring,
+                "where do we store email addresses",
+                SearchSchemaOptions::default(),
+                vec![
+                    column("users", "user_id", "uuid"),
+                    column("users", "email", "text"),
+                    column("users", "billing_address", "text"),
+                    column("users", "email_address", "text"),
+                    column("users", "store_id", "uuid"),
+                ],
+            );
+            assert_eq!(names, ["email_address", "email", "billing_address"]);
+
+            let names = ranked_names(
+                SearchMode::Fuzzy,
+                "where do we keep emal adresses",
+                SearchSchemaOptions::default(),
+                vec![
+                    column("users", "phone", "text"),
+                    column("users", "primary_email", "text"),
+                ],
+            );
+            assert_eq!(names, ["primary_email"]);
+        }
+
+        #[test]
+        fn glob_and_regex_modes_match_whole_names_and_expressions() {
+            let candidates = || {
+                vec![
+                    column("users", "created_at", "timestamp"),
+                    column("users", "updated_at", "timestamp"),
+                    column("users", "created_by", "uuid"),
+                ]
+            };
+            let names = ranked_names(
+                SearchMode::Glob,
+                "*_at",
+                SearchSchemaOptions::default(),
+                candidates(),
+            );
+            assert_eq!(names, ["created_at", "updated_at"]);
+
+            let names = ranked_names(
+                SearchMode::Regex,
+                "^created_(at|by)$",
+                SearchSchemaOptions::default(),
+                candidates(),
+            );
+            assert_eq!(names, ["created_at", "created_by"]);
+        }
+
+        #[test]
+        fn regex_mode_rejects_invalid_and_oversized_programs() {
+            let err = SchemaMatcher::new(SearchMode::Regex, "(unclosed")
+                .err()
+                .expect("invalid regex should be rejected");
+            assert!(err.to_string().starts_with("invalid search pattern"));
+
+            let err = SchemaMatcher::new(SearchMode::Regex, "(((((((((((((((((a)))))))))))))))))")
+                .err()
+                .expect("deeply nested regex should be rejected");
+            assert!(err.to_string().starts_with("invalid search pattern"));
+
+            let err = SchemaMatcher::new(SearchMode::Regex, "\\w{1000}\\w{1000}\\w{1000}")
+                .err()
+                .expect("regex exceeding the compiled size limit should be rejected");
+            assert!(err.to_string().starts_with("invalid search pattern"));
+        }
+
+        #[test]
+        fn fuzzy_mode_tolerates_typos_in_name_segments() {
+            assert_eq!(edit_distance("emial", "email"), 2);
+            assert_eq!(edit_distance("Email", "email"), 0);
+            let names = ranked_names(
+                SearchMode::Fuzzy,
+                "emails",
+                SearchSchemaOptions::default(),
+                vec![
+                    column("users", "primary_email", "text"),
+                    column("users", "phone", "text"),
+                ],
+            );
+            assert_eq!(names, ["primary_email"]);
+        }
+
+        #[test]
+        fn non_name_fields_are_opt_in_and_ranked_below_name_hits() {
+            let candidates = || {
+                vec![
+                    column("users", "contact", "frozen<email_address>"),
+                    column("users", "email", "text"),
+                ]
+            };
+            let names = ranked_names(
+                SearchMode::Substring,
+                "email",
+                SearchSchemaOptions::default(),
+                candidates(),
+            );
+            assert_eq!(names, ["email"]);
 
-    type ScyllaSession = scylla::Session;
+            let options = SearchSchemaOptions {
+                fields: vec!["name".into(), "type".into()],
+                ..SearchSchemaOptions::default()
+            };
+            let names = ranked_names(SearchMode::Substring, "email", options, candidates());
+            assert_eq!(names, ["email", "contact"]);
+        }
 
-    impl SessionState {
-        fn new(config: SessionConfig) -> Self {
-            Self {
-                config,
-                cell: OnceCell::new(),
-            }
+        #[test]
+        fn suggestions_prefer_case_insensitive_then_nearest_names() {
+            let columns = ["email", "email_verified", "name", "Emails", "created_at"];
+            assert_eq!(
+                suggest_identifiers("EMAIL", columns),
+                ["email", "Emails", "email_verified"]
+            );
+            assert_eq!(suggest_identifiers("emial", columns), ["email"]);
+            assert_eq!(suggest_identifiers("created", columns), ["created_at"]);
+            assert!(suggest_identifiers("zzz", columns).is_empty());
+            assert!(
+                suggest_identifiers("name", columns).is_empty(),
+                "an existing name needs no suggestion for itself"
+            );
         }
 
-        async fn session(&self) -> Result<Arc<ScyllaSession>> {
-            let session = self
-                .cell
-                .get_or_try_init(|| async {
-                    build_session(&self.config)
-                        .await
-                        .map(Arc::new)
-                        .with_context(|| "unable to initialize Scylla session")
-                })
-                .await?;
-            Ok(Arc::clone(session))
+        #[test]
+        fn unknown_identifier_message_names_best_suggestion() {
+            let unknown = UnknownIdentifier {
+                kind: "table",
+                name: "user".into(),
+                keyspace: Some("app".into()),
+                table: None,
+                suggestions: vec!["users".into()],
+            };
+            assert_eq!(
+                unknown.to_string(),
+                "unknown table 'user'; did you mean 'users'?"
+            );
         }
-    }
 
-    async fn build_session(config: &SessionConfig) -> Result<ScyllaSession> {
-        let mut builder = scylla::SessionBuilder::new();
-        builder = builder.known_node(config.uri.clone());
-        if let Some((ref user, ref pass)) = config.credentials {
-            builder = builder.user(user.clone(), pass.clone());
+        #[test]
+        fn search_options_reject_unknown_fields_and_kinds() {
+            let options = SearchSchemaOptions {
+                fields: vec!["owner".into()],
+                ..SearchSchemaOptions::default()
+            };
+            assert!(options.validate().is_err());
+            let options = SearchSchemaOptions {
+                kinds: vec!["keyspace".into()],
+                ..SearchSchemaOptions::default()
+            };
+            assert!(options.validate().is_err());
+            assert!(SearchMode::parse("soundex").is_err());
         }
-        if let Some(ssl) = &config.ssl {
-            use openssl::ssl::{SslContext, SslMethod, SslVerifyMode};
-            let mut ctx =
-                SslContext::builder(SslMethod::tls()).context("failed to build SSL context")?;
-            if let Some(path) = &ssl.ca_bundle {
-                ctx.set_ca_file(path)
-                    .context("failed to load SCYLLA_CA_BUNDLE")?;
+
+        #[test]
+        fn json_to_typed_cql_follows_column_types() {
+            use scylla_cql::frame::response::result::CqlValue;
+            use serde_json::json;
+
+            assert_eq!(
+                json_to_typed_cql(&json!(7), "int").unwrap(),
+                CqlValue::Int(7)
+            );
+            assert_eq!(
+                json_to_typed_cql(&json!(7), "bigint").unwrap(),
+                CqlValue::BigInt(7)
+            );
+            assert_eq!(
+                json_to_typed_cql(&json!("a"), "varchar").unwrap(),
+                CqlValue::Text("a".into())
+            );
+            assert_eq!(
+                json_to_typed_cql(&json!("0x0aff"), "blob").unwrap(),
+                CqlValue::Blob(vec![0x0a, 0xff])
+            );
+            let uuid = "123e4567-e89b-42d3-a456-426614174000";
+            match json_to_typed_cql(&json!(uuid), "uuid").unwrap() {
+                CqlValue::Uuid(u) => assert_eq!(u.to_string(), uuid),
+                other => panic!("unexpected {other:?}"),
             }
-            ctx.set_verify(if ssl.insecure {
-                SslVerifyMode::NONE
-            } else {
-                SslVerifyMode::PEER
-            });
-            builder = builder.ssl_context(Some(ctx.build()));
+            assert!(json_to_typed_cql(&json!(70_000), "smallint").is_err());
+            assert!(json_to_typed_cql(&json!("7"), "int").is_err());
+            assert!(json_to_typed_cql(&json!([1]), "frozen<list<int>>").is_err());
+            // Multi-byte characters must not split on a char boundary.
+            assert!(json_to_typed_cql(&json!("0xaéb"), "blob").is_err());
+            assert!(json_to_typed_cql(&json!("0xé0"), "blob").is_err());
         }
-        tokio::time::timeout(config.connect_timeout, builder.build())
-            .await
-            .with_context(|| {
-                format!(
-                    "timed out connecting to SCYLLA_URI after {} ms",
-                    config.connect_timeout.as_millis()
-                )
-            })?
-            .context("failed to connect to SCYLLA_URI")
-    }
-
-    fn bool_from_env(name: &str, default: bool) -> bool {
-        std::env::var(name)
-            .map(|value| matches!(value.as_str(), "1" | "true" | "TRUE" | "yes" | "on"))
-            .unwrap_or(default)
-    }
 
-    fn timeout_from_env(name: &str, default_ms: u64) -> std::time::Duration {
-        let millis = std::env::var(name)
-            .ok()
-            .and_then(|value| value.parse::<u64>().ok())
-            .filter(|value| *value > 0)
-            .unwrap_or(default_ms);
-        std::time::Duration::from_millis(millis)
-    }
+        #[test]
+        fn node_target_parses_coordinator_single_and_all() {
+            assert_eq!(NodeTarget::parse(None), NodeTarget::Coordinator);
+            assert_eq!(NodeTarget::parse(Some(" ")), NodeTarget::Coordinator);
+            assert_eq!(NodeTarget::parse(Some("ALL")), NodeTarget::AllNodes);
+            assert_eq!(
+                NodeTarget::parse(Some("10.0.0.2")),
+                NodeTarget::Node("10.0.0.2".into())
+            );
+        }
 
-    #[async_trait::async_trait]
-    impl rust_mcp_sdk::mcp_server::ServerHandler for ToolExecutor {
-        async fn handle_list_tools_request(
-            &self,
-            _request: rust_mcp_schema::ListToolsRequest,
-            runtime: &dyn rust_mcp_sdk::MCPServer,
-        ) -> std::result::Result<rust_mcp_schema::ListToolsResult, rust_mcp_schema::RpcError>
-        {
-            // Ensure capability exists
-            runtime.assert_server_request_capabilities(&"tools/list".to_string())?;
+        #[test]
+        fn size_estimates_summary_aggregates_per_node() {
+            let row = |node: &str, start: i64, mean: i64, count: i64| {
+                let mut m = Map::new();
+                m.insert("node".into(), Value::from(node));
+                m.insert("range_start".into(), Value::from(start.to_string()));
+                m.insert("range_end".into(), Value::from((start + 100).to_string()));
+                m.insert("mean_partition_size".into(), Value::from(mean));
+                m.insert("partitions_count".into(), Value::from(count));
+                m
+            };
+            let mut failed = Map::new();
+            failed.insert("node".into(), Value::from("10.0.0.3:9042"));
+            failed.insert("error".into(), Value::from("timed out"));
+            let rows = [
+                row("10.0.0.1:9042", 0, 100, 10),
+                row("10.0.0.1:9042", 100, 400, 10),
+                row("10.0.0.2:9042", 200, 100, 20),
+                // A replica of the first range counts once, as in table_stats.
+                row("10.0.0.2:9042", 0, 100, 8),
+                failed,
+            ];
+            let summary = size_estimates_summary(&rows);
+            assert_eq!(summary["ranges"], 3);
+            assert_eq!(summary["partitions_count"], 40);
+            assert_eq!(summary["estimated_bytes"], 7000);
+            assert_eq!(summary["mean_partition_size"], 175);
+            assert_eq!(summary["nodes"]["10.0.0.1:9042"]["ranges"], 2);
+            assert_eq!(
+                summary["nodes"]["10.0.0.1:9042"]["mean_partition_size"],
+                250
+            );
+            assert_eq!(summary["nodes"]["10.0.0.2:9042"]["partitions_count"], 28);
+            assert_eq!(summary["errors"][0]["node"], "10.0.0.3:9042");
+            let stats = table_stats_summary(&rows, &Map::new());
+            assert_eq!(stats["estimated_partitions"], summary["partitions_count"]);
+        }
 
-            use rust_mcp_schema::{ListToolsResult, Tool, ToolInputSchema};
-            // Map internal tool list to MCP schema
-            let tools = crate::server::list_tools()
-                .into_iter()
-                .map(|t| {
-                    let schema = crate::server::tool_input_schema(t.name);
-                    let required = schema
-                        .get("required")
-                        .and_then(|value| value.as_array())
-                        .map(|values| {
-                            values
-                                .iter()
-                                .filter_map(|value| value.as_str().map(ToOwned::to_owned))
-                                .collect()
-                        })
-                        .unwrap_or_default();
-                    let props = schema
-                        .get("properties")
-                        .and_then(|value| value.as_object())
-                        .map(|map| {
-                            map.iter()
-                                .filter_map(|(key, value)| {
-                                    value.as_object().map(|value| (key.clone(), value.clone()))
-                                })
-                                .collect()
-                        });
-                    Tool {
-                        description: Some(t.description.to_string()),
-                        input_schema: ToolInputSchema::new(required, props),
-                        name: t.name.to_string(),
-                    }
+        #[test]
+        fn table_stats_dedupes_replicated_ranges_and_applies_rf() {
+            let row = |node: &str, start: i64, end: i64, mean: i64, count: i64| {
+                serde_json::json!({
+                    "node": node,
+                    "range_start": start.to_string(),
+                    "range_end": end.to_string(),
+                    "mean_partition_size": mean,
+                    "partitions_count": count,
                 })
-                .collect();
-
-            Ok(ListToolsResult {
-                tools,
-                meta: None,
-                next_cursor: None,
+                .as_object()
+                .cloned()
+                .unwrap()
+            };
+            let rows = vec![
+                row("a", i64::MIN, 0, 1024, 100),
+                row("b", i64::MIN, 0, 1024, 90),
+                row("b", 0, i64::MIN, 2048, 100),
+            ];
+            let replication = serde_json::json!({
+                "class": "NetworkTopologyStrategy",
+                "dc1": "3",
+                "dc2": "2",
             })
+            .as_object()
+            .cloned()
+            .unwrap();
+            let stats = table_stats_summary(&rows, &replication);
+            assert_eq!(stats["estimated_partitions"], 200);
+            assert_eq!(stats["estimated_bytes_single_copy"], 307_200);
+            assert_eq!(stats["mean_partition_size"], 1536);
+            assert_eq!(stats["per_datacenter"]["dc1"]["estimated_bytes"], 921_600);
+            assert_eq!(
+                stats["per_datacenter"]["dc2"]["estimated_size"],
+                "600.0 KiB"
+            );
+            assert_eq!(stats["ring_coverage_pct"], 100.0);
+            assert_eq!(stats["confidence"], "high");
+
+            let partial = table_stats_summary(&rows[..1], &replication);
+            assert_eq!(partial["confidence"], "medium");
+            assert_eq!(table_stats_summary(&[], &replication)["confidence"], "none");
         }
 
-        async fn handle_call_tool_request(
-            &self,
-            request: rust_mcp_schema::CallToolRequest,
-            runtime: &dyn rust_mcp_sdk::MCPServer,
-        ) -> std::result::Result<
-            rust_mcp_schema::CallToolResult,
-            rust_mcp_schema::schema_utils::CallToolError,
-        > {
-            runtime
-                .assert_server_request_capabilities(&"tools/call".to_string())
-                .map_err(rust_mcp_schema::schema_utils::CallToolError::new)?;
+        #[test]
+        fn node_config_rows_are_filtered_and_redacted() {
+            let row = |name: &str, value: &str| {
+                serde_json::json!({ "name": name, "value": value })
+                    .as_object()
+                    .cloned()
+                    .unwrap()
+            };
+            let rows = vec![
+                row("memtable_flush_threshold", "0.5"),
+                row("auth_superuser_salted_password", "$6$abc"),
+                row(
+                    "client_encryption_options",
+                    r#"{"enabled": "true", "truststore_password": "hunter2", "certificate": "/etc/c.pem"}"#,
+                ),
+                row(
+                    "object_storage_config",
+                    r#"{"endpoint": "s3.local", "aws_secret_access_key": "abc"}"#,
+                ),
+            ];
+            let filtered = filter_node_rows("config", rows.clone(), Some("MEMTABLE"));
+            assert_eq!(filtered.len(), 1);
+            assert_eq!(filtered[0]["value"], "0.5");
+
+            let all = filter_node_rows("config", rows, None);
+            assert_eq!(all[1]["value"], "<redacted>");
+            let encryption = all[2]["value"].as_str().unwrap();
+            assert!(!encryption.contains("hunter2"), "{encryption}");
+            assert!(encryption.contains(r#""truststore_password": "<redacted>""#));
+            assert!(encryption.contains("/etc/c.pem"));
+            let storage = all[3]["value"].as_str().unwrap();
+            assert!(!storage.contains("abc"), "{storage}");
+            assert!(storage.contains("s3.local"));
+        }
 
-            let output = self
-                .execute(
-                    request.params.name.as_str(),
-                    request.params.arguments.as_ref(),
-                )
-                .await;
+        #[test]
+        fn schema_fingerprint_diff_reports_touched_tables_and_keyspaces() {
+            let key = |ks: &str, t: &str| (ks.to_string(), t.to_string());
+            let mut before = SchemaFingerprints::default();
+            before.tables.insert(key("app", "users"), 1);
+            before.tables.insert(key("app", "events"), 2);
+            before.tables.insert(key("other", "t"), 3);
+            before.keyspaces.insert("app".into(), 10);
+            before.keyspaces.insert("other".into(), 11);
+            before.keyspaces.insert("quiet".into(), 12);
+
+            let mut after = before.clone();
+            after.tables.insert(key("app", "users"), 9);
+            after.tables.remove(&key("app", "events"));
+            after.tables.insert(key("new", "t"), 4);
+            after.keyspaces.insert("quiet".into(), 13);
+
+            let changes = before.diff(&after);
+            assert_eq!(
+                changes.tables.into_iter().collect::<Vec<_>>(),
+                vec![key("app", "events"), key("app", "users"), key("new", "t")]
+            );
+            assert_eq!(
+                changes.keyspaces.into_iter().collect::<Vec<_>>(),
+                vec!["app", "new", "quiet"]
+            );
+            assert_eq!(before.diff(&before), Default::default());
+        }
 
-            let mut result = rust_mcp_schema::CallToolResult::text_content(output.text, None);
-            result.is_error = Some(output.is_error);
-            Ok(result)
+        #[test]
+        fn ddl_is_reconstructed_from_schema() {
+            let column =
+                |name: &str, kind: &str, position: i32, ty: &str, order: Option<&str>| ColumnMeta {
+                    column_name: name.to_string(),
+                    kind: kind.to_string(),
+                    position,
+                    r#type: ty.to_string(),
+                    clustering_order: order.map(str::to_string),
+                };
+            let schema = DescribeTable {
+                keyspace: "app".into(),
+                table: "Events".into(),
+                partition_keys: vec!["tenant".into(), "day".into()],
+                clustering_keys: vec!["ts".into()],
+                columns: vec![
+                    column("payload", "regular", -1, "text", None),
+                    column("ts", "clustering", 0, "timestamp", Some("desc")),
+                    column("day", "partition_key", 1, "date", None),
+                    column("tenant", "partition_key", 0, "uuid", None),
+                    column("owner", "static", -1, "text", None),
+                ],
+            };
+            assert_eq!(
+                render_table_ddl(&schema),
+                "CREATE TABLE app.\"Events\" (\n    tenant uuid,\n    day date,\n    ts timestamp,\n    owner text static,\n    payload text,\n    PRIMARY KEY ((tenant, day), ts)\n) WITH CLUSTERING ORDER BY (ts DESC);"
+            );
+
+            let replication: Map<String, Value> = serde_json::from_value(serde_json::json!({
+                "replication": {
+                    "class": "org.apache.cassandra.locator.NetworkTopologyStrategy",
+                    "dc1": "3",
+                },
+                "durable_writes": true,
+            }))
+            .unwrap();
+            assert_eq!(
+                render_keyspace_ddl("app", &replication),
+                "CREATE KEYSPACE app WITH replication = {'class': 'org.apache.cassandra.locator.NetworkTopologyStrategy', 'dc1': '3'} AND durable_writes = true;"
+            );
         }
-    }
-}
 
-pub mod rmcp_server {
-    use crate::mcp::ToolExecutor;
-    use crate::server;
-    use anyhow::Result;
-    use rmcp::{
-        model::*, service::RequestContext, transport::stdio, RoleServer, ServerHandler, ServiceExt,
-    };
-    use std::sync::Arc;
-    use tracing::info;
+        #[test]
+        fn topology_warnings_flag_down_and_lagging_nodes() {
+            let node = |schema: &str, up: bool, ping_error: Option<&str>| {
+                let mut m = Map::new();
+                m.insert("schema_version".into(), Value::from(schema));
+                m.insert("release_version".into(), Value::from("6.2.0"));
+                m.insert("up".into(), Value::Bool(up));
+                m.insert("connected".into(), Value::Bool(true));
+                if let Some(err) = ping_error {
+                    m.insert("ping_error".into(), Value::from(err));
+                }
+                m
+            };
+            let mut nodes = vec![
+                node("v1", true, None),
+                node("v1", true, None),
+                node("v2", false, Some("timed out after 2000 ms")),
+            ];
+            annotate_topology_warnings(&mut nodes);
+            assert!(nodes[0].get("warnings").is_none());
+            let warnings: Vec<&str> = nodes[2]["warnings"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(Value::as_str)
+                .collect();
+            assert_eq!(
+                warnings,
+                [
+                    "driver marks node down",
+                    "ping failed: timed out after 2000 ms",
+                    "schema_version v2 differs from majority v1",
+                ]
+            );
+        }
 
-    #[derive(Clone)]
-    struct BridgeHandler {
-        executor: Arc<ToolExecutor>,
-    }
+        fn large_entry(
+            kind: &'static str,
+            table: &str,
+            key: &str,
+            size: i64,
+            node: &str,
+        ) -> LargeDataEntry {
+            LargeDataEntry {
+                kind,
+                keyspace: "app".into(),
+                table: table.into(),
+                size,
+                partition_key: key.into(),
+                clustering_key: None,
+                column: None,
+                rows: None,
+                sstable: Some(format!("{}-{}-Data.db", node, key)),
+                compaction_time_ms: None,
+                node: node.into(),
+            }
+        }
 
-    impl BridgeHandler {
-        fn new() -> Self {
-            let executor = Arc::new(ToolExecutor::from_env());
-            executor.warmup_connection();
-            Self { executor }
+        #[test]
+        fn large_data_merges_replicas_and_ranks_by_size() {
+            let ranked = merge_large_data(
+                vec![
+                    large_entry("partition", "events", "a", 200, "10.0.0.1"),
+                    large_entry("partition", "events", "a", 250, "10.0.0.2"),
+                    large_entry("partition", "events", "b", 900, "10.0.0.1"),
+                    large_entry("row", "events", "b", 100, "10.0.0.1"),
+                    large_entry("partition", "users", "c", 50, "10.0.0.3"),
+                ],
+                2,
+            );
+            assert_eq!(ranked.len(), 2);
+            let events = &ranked[0];
+            assert_eq!(events.table, "events");
+            assert_eq!(events.max_size, 900);
+            assert_eq!(events.counts["partitions"], 2);
+            assert_eq!(events.counts["rows"], 1);
+            assert_eq!(events.entries.len(), 2, "limit applies per table");
+            assert_eq!(events.entries[0]["partition_key"], "b");
+            let merged = &events.entries[1];
+            assert_eq!(merged["size"], 250);
+            assert_eq!(merged["nodes"], serde_json::json!(["10.0.0.1", "10.0.0.2"]));
+            assert_eq!(merged["sstables"], 2);
+            assert_eq!(ranked[1].table, "users");
         }
-    }
 
-    impl ServerHandler for BridgeHandler {
-        fn get_info(&self) -> ServerInfo {
-            // Enable tools; other capabilities can be enabled later.
-            let mut info = ServerInfo::default();
-            info.instructions = Some("Read-only ScyllaDB access for AI agents".into());
-            info.capabilities = ServerCapabilities::builder().enable_tools().build();
-            info
+        #[test]
+        fn partition_key_values_only_split_when_columns_match() {
+            let single = vec!["id".to_string()];
+            let composite = vec!["tenant".to_string(), "day".to_string()];
+            assert_eq!(partition_key_values("a:b", &single).unwrap()["id"], "a:b");
+            let values = partition_key_values("acme:2024-01-01", &composite).unwrap();
+            assert_eq!(values["tenant"], "acme");
+            assert_eq!(values["day"], "2024-01-01");
+            assert!(partition_key_values("acme", &composite).is_none());
+            assert!(partition_key_values("acme", &[]).is_none());
         }
 
-        // List tools using our existing static tool metadata.
-        // For now, we construct simple tool entries; input schemas will be basic objects.
-        // We can refine schemas as we migrate each tool.
-        async fn list_tools(
-            &self,
-            _request: Option<PaginatedRequestParams>,
-            _ctx: RequestContext<RoleServer>,
-        ) -> Result<ListToolsResult, rmcp::ErrorData> {
-            let items = server::list_tools()
-                .into_iter()
-                .map(|t| {
-                    Tool::new(
-                        t.name.to_string(),
-                        t.description.to_string(),
-                        Arc::new(
-                            crate::server::tool_input_schema(t.name)
-                                .as_object()
-                                .cloned()
-                                .unwrap_or_default(),
-                        ),
-                    )
-                })
-                .collect::<Vec<_>>();
-            Ok(ListToolsResult::with_all_items(items))
+        #[test]
+        fn inet_values_render_as_plain_addresses() {
+            let ip: std::net::IpAddr = "10.0.0.1".parse().unwrap();
+            assert_eq!(cql_value_to_json(&CqlValue::Inet(ip)), "10.0.0.1");
         }
 
-        async fn call_tool(
-            &self,
-            request: CallToolRequestParams,
-            _ctx: RequestContext<RoleServer>,
-        ) -> Result<CallToolResult, rmcp::ErrorData> {
-            let output = self
-                .executor
-                .execute(request.name.as_ref(), request.arguments.as_ref())
-                .await;
-            if output.is_error {
-                Ok(CallToolResult::error(vec![Content::text(output.text)]))
-            } else {
-                Ok(CallToolResult::success(vec![Content::text(output.text)]))
-            }
+        #[test]
+        fn only_missing_tables_fall_back_to_vnodes() {
+            let missing = anyhow::anyhow!("Database returned an error: unconfigured table tablets");
+            assert!(is_missing_table_error(&missing));
+            let denied = anyhow::anyhow!(
+                "Database returned an error: Unauthorized: User bob has no SELECT permission"
+            );
+            assert!(!is_missing_table_error(&denied));
+            assert!(!is_missing_table_error(&anyhow::anyhow!("request timeout")));
+        }
+
+        #[test]
+        fn skipped_lint_checks_name_the_source_node_and_cause() {
+            let mut node = Map::new();
+            node.insert("node".into(), Value::from("10.0.0.2:9042"));
+            let err = anyhow::anyhow!("Unauthorized: no SELECT permission");
+            assert_eq!(
+                skipped_check_note("system.large_partitions", Some(&node), &err),
+                "partition size checks skipped: could not read system.large_partitions on 10.0.0.2:9042: Unauthorized: no SELECT permission"
+            );
+            assert_eq!(
+                skipped_check_note("system.size_estimates for keyspace app", None, &err),
+                "partition size checks skipped: could not read system.size_estimates for keyspace app: Unauthorized: no SELECT permission"
+            );
         }
     }
+}
 
-    pub async fn run_stdio_server() -> Result<()> {
-        crate::logging::init_tracing();
-        info!("starting MCP stdio server (rmcp, newline-delimited JSON)");
+pub mod lint {
+    use serde::Serialize;
+    use serde_json::{Map, Value};
+    use std::collections::BTreeMap;
+
+    /// Mean partition size above which a table is flagged as a warning.
+    pub const LARGE_MEAN_PARTITION_BYTES: i64 = 10 * 1024 * 1024;
+    /// Mean partition size above which a table is flagged as an error.
+    pub const HUGE_MEAN_PARTITION_BYTES: i64 = 100 * 1024 * 1024;
+    /// Rationale of both `huge_partitions` findings (size estimates and large partition records).
+    const HUGE_PARTITIONS_RATIONALE: &str = "very large partitions concentrate load on a few replicas and make compaction, repair and reads slow; add a bucketing component to the partition key";
+    /// Secondary index count above which a table is flagged.
+    pub const MAX_SECONDARY_INDEXES: usize = 3;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Severity {
+        Info,
+        Warning,
+        Error,
+    }
 
-        // RMCP 0.5 stdio uses newline-delimited JSON, not Content-Length framing.
-        let handler = BridgeHandler::new();
-        let service = handler.serve(stdio()).await?;
-        // Wait for client disconnect
-        service.waiting().await?;
-        Ok(())
+    #[derive(Debug, Clone, Serialize)]
+    pub struct Finding {
+        pub rule: &'static str,
+        pub severity: Severity,
+        pub keyspace: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub table: Option<String>,
+        pub message: String,
+        pub rationale: &'static str,
     }
-}
 
-pub mod codex_stdio {
-    use crate::{mcp::ToolExecutor, server};
-    use anyhow::{anyhow, Context, Result};
-    use serde_json::{json, Map, Value};
-    use tokio::io::{
-        AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
-    };
-    use tracing::{debug, info};
+    #[derive(Debug, Clone, Default)]
+    pub struct KeyspaceSnapshot {
+        pub name: String,
+        pub replication: Map<String, Value>,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub struct TableSnapshot {
+        pub keyspace: String,
+        pub table: String,
+        pub partition_keys: Vec<(String, String)>,
+        pub clustering_keys: Vec<(String, String)>,
+        pub regular_columns: Vec<(String, String)>,
+        pub index_count: usize,
+        pub mean_partition_size: Option<i64>,
+        pub large_partitions: usize,
+        pub largest_partition: Option<i64>,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub struct UdtSnapshot {
+        pub keyspace: String,
+        pub name: String,
+        pub field_types: Vec<String>,
+    }
+
+    /// Everything the lint rules look at, gathered by `db::lint_schema_with`.
+    #[derive(Debug, Clone, Default)]
+    pub struct SchemaSnapshot {
+        pub dc_nodes: BTreeMap<String, usize>,
+        pub keyspaces: Vec<KeyspaceSnapshot>,
+        pub tables: Vec<TableSnapshot>,
+        pub udts: Vec<UdtSnapshot>,
+    }
 
-    const MAX_MCP_MESSAGE_BYTES: usize = 8 * 1024 * 1024;
+    /// Replication strategy class without the `org.apache.cassandra.locator.` prefix.
+    pub fn strategy_name(replication: &Map<String, Value>) -> &str {
+        replication
+            .get("class")
+            .and_then(Value::as_str)
+            .map(|class| class.rsplit('.').next().unwrap_or(class))
+            .unwrap_or("")
+    }
 
-    struct ServerState {
-        shutdown_requested: bool,
+    /// Parse a replication factor value such as `"3"` or the transient form `"3/1"`.
+    pub fn parse_rf(value: &Value) -> Option<usize> {
+        match value {
+            Value::String(s) => s.split('/').next()?.trim().parse().ok(),
+            Value::Number(n) => n.as_u64().map(|n| n as usize),
+            _ => None,
+        }
     }
 
-    impl ServerState {
-        fn new() -> Self {
-            Self {
-                shutdown_requested: false,
+    /// Per-DC replication factors. SimpleStrategy is reported under the `"*"` key.
+    pub fn replication_factors(replication: &Map<String, Value>) -> BTreeMap<String, usize> {
+        let mut out = BTreeMap::new();
+        if strategy_name(replication) == "SimpleStrategy" {
+            if let Some(rf) = replication.get("replication_factor").and_then(parse_rf) {
+                out.insert("*".to_string(), rf);
             }
+            return out;
         }
+        for (key, value) in replication {
+            if key == "class" {
+                continue;
+            }
+            if let Some(rf) = parse_rf(value) {
+                out.insert(key.clone(), rf);
+            }
+        }
+        out
     }
 
-    pub async fn run_stdio_server() -> Result<()> {
-        crate::logging::init_tracing();
-        info!("starting MCP stdio server (content-length)");
-
-        let handler = ToolExecutor::from_env();
-        handler.warmup_connection();
-        run_over(tokio::io::stdin(), tokio::io::stdout(), handler).await
+    fn is_unbounded_collection(ty: &str) -> bool {
+        let ty = ty.trim();
+        ty.starts_with("list<") || ty.starts_with("set<") || ty.starts_with("map<")
     }
 
-    async fn run_over<R, W>(reader: R, mut writer: W, handler: ToolExecutor) -> Result<()>
-    where
-        R: AsyncRead + Unpin,
-        W: AsyncWrite + Unpin,
-    {
-        let mut reader = BufReader::new(reader);
-        let mut state = ServerState::new();
+    fn is_time_type(ty: &str) -> bool {
+        matches!(ty.trim(), "timestamp" | "timeuuid" | "date" | "time")
+    }
 
-        loop {
-            let message = match read_message(&mut reader).await? {
-                Some(message) => message,
-                None => break,
-            };
+    fn type_tokens(ty: &str) -> impl Iterator<Item = &str> {
+        ty.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|token| !token.is_empty())
+    }
 
-            if let Some(response) = handle_message(&handler, &mut state, message).await {
-                write_message(&mut writer, &response).await?;
-            }
+    pub(crate) fn human_bytes(bytes: i64) -> String {
+        const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} {}", bytes, UNITS[0])
+        } else {
+            format!("{:.1} {}", value, UNITS[unit])
+        }
+    }
 
-            if state.shutdown_requested {
-                break;
-            }
+    fn finding(
+        rule: &'static str,
+        severity: Severity,
+        keyspace: &str,
+        table: Option<&str>,
+        message: String,
+        rationale: &'static str,
+    ) -> Finding {
+        Finding {
+            rule,
+            severity,
+            keyspace: keyspace.to_string(),
+            table: table.map(str::to_string),
+            message,
+            rationale,
         }
+    }
 
-        writer.flush().await?;
-        Ok(())
+    /// `simple_strategy_multi_dc`, shared with `replication_report`.
+    pub fn simple_strategy_multi_dc(
+        keyspace: &str,
+        strategy: &str,
+        datacenters: usize,
+    ) -> Option<Finding> {
+        (strategy == "SimpleStrategy" && datacenters > 1).then(|| {
+            finding(
+                "simple_strategy_multi_dc",
+                Severity::Error,
+                keyspace,
+                None,
+                format!(
+                    "keyspace uses SimpleStrategy but the cluster spans {} datacenters",
+                    datacenters
+                ),
+                "SimpleStrategy places replicas on the ring without regard to datacenters, so replicas can all land in one DC and LOCAL_* consistency levels become unreliable; use NetworkTopologyStrategy",
+            )
+        })
     }
 
-    async fn read_message<R>(reader: &mut BufReader<R>) -> Result<Option<Value>>
-    where
-        R: AsyncRead + Unpin,
-    {
-        let mut content_length = None;
+    /// `rf_exceeds_nodes` for datacenter `dc` (`*` for the whole cluster), shared
+    /// with `replication_report`.
+    pub fn rf_exceeds_nodes(keyspace: &str, dc: &str, rf: usize, nodes: usize) -> Option<Finding> {
+        (rf > nodes).then(|| {
+            let scope = if dc == "*" {
+                "the cluster".to_string()
+            } else {
+                format!("datacenter '{dc}'")
+            };
+            finding(
+                "rf_exceeds_nodes",
+                Severity::Error,
+                keyspace,
+                None,
+                format!("replication factor {rf} exceeds the {nodes} node(s) in {scope}"),
+                "a replica set larger than the node count cannot be placed, so writes and reads at QUORUM or ALL fail for this keyspace",
+            )
+        })
+    }
 
-        loop {
-            let mut line = String::new();
-            let read = reader
-                .read_line(&mut line)
-                .await
-                .context("failed to read MCP header line")?;
+    pub fn lint(snapshot: &SchemaSnapshot) -> Vec<Finding> {
+        let mut out = Vec::new();
+        let total_nodes: usize = snapshot.dc_nodes.values().sum();
+
+        for ks in &snapshot.keyspaces {
+            let strategy = strategy_name(&ks.replication);
+            let factors = replication_factors(&ks.replication);
+            out.extend(simple_strategy_multi_dc(
+                &ks.name,
+                strategy,
+                snapshot.dc_nodes.len(),
+            ));
+            if total_nodes > 0 {
+                for (dc, rf) in &factors {
+                    let nodes = if dc == "*" {
+                        total_nodes
+                    } else {
+                        snapshot.dc_nodes.get(dc).copied().unwrap_or(0)
+                    };
+                    out.extend(rf_exceeds_nodes(&ks.name, dc, *rf, nodes));
+                }
+            }
+        }
 
-            if read == 0 {
-                if content_length.is_none() {
-                    return Ok(None);
+        for table in &snapshot.tables {
+            let hot = table.large_partitions > 0
+                || table
+                    .mean_partition_size
+                    .is_some_and(|size| size > LARGE_MEAN_PARTITION_BYTES);
+            for (column, ty) in &table.regular_columns {
+                if is_unbounded_collection(ty) {
+                    let severity = if hot {
+                        Severity::Warning
+                    } else {
+                        Severity::Info
+                    };
+                    out.push(finding(
+                        "unbounded_collection",
+                        severity,
+                        &table.keyspace,
+                        Some(&table.table),
+                        format!("column '{column}' is a non-frozen collection ({ty})"),
+                        "non-frozen collections are read whole and grow without bound; in busy or large partitions they cause tombstones and latency spikes, so prefer frozen collections or clustering rows",
+                    ));
                 }
-                return Err(anyhow!("unexpected EOF while reading MCP headers"));
             }
 
-            if line == "\r\n" {
-                break;
+            if table.index_count > MAX_SECONDARY_INDEXES {
+                out.push(finding(
+                    "too_many_secondary_indexes",
+                    Severity::Warning,
+                    &table.keyspace,
+                    Some(&table.table),
+                    format!(
+                        "table has {} secondary indexes (more than {})",
+                        table.index_count, MAX_SECONDARY_INDEXES
+                    ),
+                    "each secondary index is an extra hidden table updated on every write; many indexes multiply write amplification and are usually a sign a query-specific table is needed",
+                ));
             }
 
-            let (name, value) = line
-                .split_once(':')
-                .ok_or_else(|| anyhow!("invalid MCP header: {line:?}"))?;
+            if table.clustering_keys.is_empty() {
+                if let Some((column, ty)) =
+                    table.partition_keys.iter().find(|(_, ty)| is_time_type(ty))
+                {
+                    out.push(finding(
+                        "time_series_without_clustering",
+                        Severity::Warning,
+                        &table.keyspace,
+                        Some(&table.table),
+                        format!(
+                            "partition key column '{column}' ({ty}) with no clustering key stores one event per partition"
+                        ),
+                        "time series modelled without a clustering key cannot be read as ordered ranges; bucket the partition key and cluster by time instead",
+                    ));
+                }
+            }
 
-            if name.eq_ignore_ascii_case("content-length") {
-                let parsed = value
-                    .trim()
-                    .parse::<usize>()
-                    .with_context(|| format!("invalid Content-Length header: {value:?}"))?;
-                if parsed > MAX_MCP_MESSAGE_BYTES {
-                    return Err(anyhow!(
-                        "MCP message body exceeds maximum Content-Length of {} bytes",
-                        MAX_MCP_MESSAGE_BYTES
+            if let Some(mean) = table.mean_partition_size {
+                let severity = if mean > HUGE_MEAN_PARTITION_BYTES {
+                    Some(Severity::Error)
+                } else if mean > LARGE_MEAN_PARTITION_BYTES {
+                    Some(Severity::Warning)
+                } else {
+                    None
+                };
+                if let Some(severity) = severity {
+                    out.push(finding(
+                        "huge_partitions",
+                        severity,
+                        &table.keyspace,
+                        Some(&table.table),
+                        format!("estimated mean partition size is {}", human_bytes(mean)),
+                        HUGE_PARTITIONS_RATIONALE,
                     ));
                 }
-                content_length = Some(parsed);
+            }
+            if table.large_partitions > 0 {
+                let largest = table
+                    .largest_partition
+                    .map(|size| format!(" (largest {})", human_bytes(size)))
+                    .unwrap_or_default();
+                out.push(finding(
+                    "huge_partitions",
+                    Severity::Warning,
+                    &table.keyspace,
+                    Some(&table.table),
+                    format!(
+                        "{} partition(s) recorded in system.large_partitions{}",
+                        table.large_partitions, largest
+                    ),
+                    HUGE_PARTITIONS_RATIONALE,
+                ));
             }
         }
 
-        let len = content_length.ok_or_else(|| anyhow!("missing Content-Length header"))?;
-        let mut body = vec![0; len];
-        reader
-            .read_exact(&mut body)
-            .await
-            .context("failed to read MCP message body")?;
-
-        let value =
-            serde_json::from_slice(&body).context("failed to parse MCP JSON message body")?;
-        Ok(Some(value))
-    }
-
-    async fn write_message<W>(writer: &mut W, message: &Value) -> Result<()>
-    where
-        W: AsyncWrite + Unpin,
-    {
-        let body = serde_json::to_vec(message).context("failed to encode MCP response")?;
-        let header = format!("Content-Length: {}\r\n\r\n", body.len());
-        writer
-            .write_all(header.as_bytes())
-            .await
-            .context("failed to write MCP response header")?;
-        writer
-            .write_all(&body)
-            .await
-            .context("failed to write MCP response body")?;
-        writer
-            .flush()
-            .await
-            .context("failed to flush MCP response")?;
-        Ok(())
-    }
-
-    async fn handle_message(
-        handler: &ToolExecutor,
-        state: &mut ServerState,
-        message: Value,
-    ) -> Option<Value> {
-        let id = message.get("id").cloned();
-        let method = message.get("method").and_then(Value::as_str)?;
-        let params = message
-            .get("params")
-            .and_then(Value::as_object)
-            .cloned()
-            .unwrap_or_default();
-
-        debug!(%method, "received MCP message");
-
-        match method {
-            "initialize" => id.map(|id| ok_response(id, initialize_result(&params))),
-            "notifications/initialized" | "notifications/cancelled" => None,
-            "ping" => id.map(|id| ok_response(id, json!({}))),
-            "tools/list" => id.map(|id| ok_response(id, list_tools_result())),
-            "tools/call" => {
-                let id = id?;
-                let result = call_tool_result(handler, params).await;
-                Some(match result {
-                    Ok(result) => ok_response(id, result),
-                    Err(err) => {
-                        error_response(id, -32602, format!("invalid tools/call request: {err}"))
-                    }
+        for udt in &snapshot.udts {
+            let used_by_tables = snapshot
+                .tables
+                .iter()
+                .filter(|t| t.keyspace == udt.keyspace)
+                .flat_map(|t| {
+                    t.partition_keys
+                        .iter()
+                        .chain(&t.clustering_keys)
+                        .chain(&t.regular_columns)
                 })
+                .any(|(_, ty)| type_tokens(ty).any(|token| token == udt.name));
+            let used_by_types = snapshot
+                .udts
+                .iter()
+                .filter(|other| other.keyspace == udt.keyspace && other.name != udt.name)
+                .flat_map(|other| other.field_types.iter())
+                .any(|ty| type_tokens(ty).any(|token| token == udt.name));
+            if !used_by_tables && !used_by_types {
+                out.push(finding(
+                    "unused_udt",
+                    Severity::Info,
+                    &udt.keyspace,
+                    None,
+                    format!("user-defined type '{}' is not referenced by any table or type", udt.name),
+                    "unused types add schema noise and are easy to mistake for live data structures; drop them once confirmed unused",
+                ));
             }
-            "shutdown" => {
-                state.shutdown_requested = true;
-                id.map(|id| ok_response(id, json!({})))
-            }
-            _ if id.is_some() => {
-                id.map(|id| error_response(id, -32601, format!("method not found: {method}")))
-            }
-            _ => None,
         }
-    }
-
-    fn initialize_result(params: &Map<String, Value>) -> Value {
-        let protocol_version = params
-            .get("protocolVersion")
-            .and_then(Value::as_str)
-            .filter(|value| !value.is_empty())
-            .unwrap_or("2024-11-05");
-        let server_info = server::server_info();
 
-        json!({
-            "protocolVersion": protocol_version,
-            "capabilities": {
-                "tools": {
-                    "listChanged": false,
-                }
-            },
-            "serverInfo": {
-                "name": server_info.name,
-                "version": server_info.version,
-            },
-            "instructions": server_info.instructions,
-        })
+        out.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then_with(|| a.keyspace.cmp(&b.keyspace))
+                .then_with(|| a.table.cmp(&b.table))
+        });
+        out
     }
 
-    fn list_tools_result() -> Value {
-        let tools = server::list_tools()
-            .into_iter()
-            .map(|tool| {
-                json!({
-                    "name": tool.name,
-                    "description": tool.description,
-                    "inputSchema": server::tool_input_schema(tool.name),
-                })
-            })
-            .collect::<Vec<_>>();
+    /// Render findings with per-severity counts for tool output.
+    pub fn report(findings: Vec<Finding>) -> Map<String, Value> {
+        let mut counts = Map::new();
+        for severity in ["error", "warning", "info"] {
+            counts.insert(severity.into(), Value::from(0));
+        }
+        for f in &findings {
+            let key = match f.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "info",
+            };
+            let n = counts.get(key).and_then(Value::as_u64).unwrap_or(0) + 1;
+            counts.insert(key.into(), Value::from(n));
+        }
+        let mut out = Map::new();
+        out.insert("summary".into(), Value::Object(counts));
+        out.insert(
+            "findings".into(),
+            serde_json::to_value(findings).unwrap_or_else(|_| Value::Array(Vec::new())),
+        );
+        out
+    }
 
-        json!({ "tools": tools })
+    /// Keyspaces that belong to the database itself rather than to applications.
+    pub fn is_system_keyspace(name: &str) -> bool {
+        matches!(
+            name,
+            "system"
+                | "system_schema"
+                | "system_auth"
+                | "system_auth_v2"
+                | "system_distributed"
+                | "system_distributed_everywhere"
+                | "system_traces"
+                | "system_replicated_keys"
+                | "system_views"
+                | "system_virtual_schema"
+                | "system_audit"
+        )
     }
 
-    async fn call_tool_result(handler: &ToolExecutor, params: Map<String, Value>) -> Result<Value> {
-        let name = params
-            .get("name")
-            .and_then(Value::as_str)
-            .ok_or_else(|| anyhow!("missing string field 'name'"))?;
-        let arguments = params.get("arguments").and_then(Value::as_object).cloned();
-        let output = handler.execute(name, arguments.as_ref()).await;
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            lint, replication_factors, report, KeyspaceSnapshot, SchemaSnapshot, Severity,
+            TableSnapshot, UdtSnapshot, HUGE_MEAN_PARTITION_BYTES,
+        };
+        use serde_json::{json, Map, Value};
 
-        Ok(json!({
-            "content": [
-                {
-                    "type": "text",
-                    "text": output.text,
-                }
-            ],
-            "isError": output.is_error,
-        }))
-    }
+        fn replication(value: Value) -> Map<String, Value> {
+            value.as_object().cloned().expect("replication object")
+        }
 
-    fn ok_response(id: Value, result: Value) -> Value {
-        json!({
-            "jsonrpc": "2.0",
-            "id": id,
-            "result": result,
-        })
-    }
+        fn col(name: &str, ty: &str) -> (String, String) {
+            (name.to_string(), ty.to_string())
+        }
 
-    fn error_response(id: Value, code: i64, message: String) -> Value {
-        json!({
-            "jsonrpc": "2.0",
-            "id": id,
-            "error": {
-                "code": code,
-                "message": message,
+        fn rules(snapshot: &SchemaSnapshot) -> Vec<(&'static str, Severity)> {
+            lint(snapshot)
+                .into_iter()
+                .map(|f| (f.rule, f.severity))
+                .collect()
+        }
+
+        #[test]
+        fn only_database_keyspaces_count_as_system() {
+            for name in ["system", "system_schema", "system_auth", "system_traces"] {
+                assert!(super::is_system_keyspace(name), "{name}");
             }
-        })
-    }
+            for name in ["systems_inventory", "system_of_record", "app"] {
+                assert!(!super::is_system_keyspace(name), "{name}");
+            }
+        }
 
-    #[cfg(test)]
-    mod tests {
-        use super::{initialize_result, list_tools_result, read_message, MAX_MCP_MESSAGE_BYTES};
-        use serde_json::json;
-        use tokio::io::BufReader;
+        #[test]
+        fn replication_factors_handle_both_strategies() {
+            let simple = replication(json!({
+                "class": "org.apache.cassandra.locator.SimpleStrategy",
+                "replication_factor": "3",
+            }));
+            assert_eq!(replication_factors(&simple).get("*"), Some(&3));
+
+            let nts = replication(json!({
+                "class": "org.apache.cassandra.locator.NetworkTopologyStrategy",
+                "dc1": "3",
+                "dc2": "2/1",
+            }));
+            let factors = replication_factors(&nts);
+            assert_eq!(factors.get("dc1"), Some(&3));
+            assert_eq!(factors.get("dc2"), Some(&2));
+        }
 
         #[test]
-        fn initialize_reflects_client_protocol_version() {
-            let params = json!({ "protocolVersion": "2024-11-05" });
-            let result = initialize_result(params.as_object().expect("initialize params"));
-            assert_eq!(result["protocolVersion"], "2024-11-05");
-            assert_eq!(result["serverInfo"]["name"], "scylla-rust-mcp");
-            assert_eq!(result["capabilities"]["tools"]["listChanged"], false);
+        fn flags_simple_strategy_and_rf_above_node_count() {
+            let mut snapshot = SchemaSnapshot::default();
+            snapshot.dc_nodes.insert("dc1".into(), 3);
+            snapshot.dc_nodes.insert("dc2".into(), 1);
+            snapshot.keyspaces.push(KeyspaceSnapshot {
+                name: "legacy".into(),
+                replication: replication(json!({
+                    "class": "org.apache.cassandra.locator.SimpleStrategy",
+                    "replication_factor": "3",
+                })),
+            });
+            snapshot.keyspaces.push(KeyspaceSnapshot {
+                name: "app".into(),
+                replication: replication(json!({
+                    "class": "org.apache.cassandra.locator.NetworkTopologyStrategy",
+                    "dc1": "3",
+                    "dc2": "3",
+                })),
+            });
+            let found = rules(&snapshot);
+            assert!(found.contains(&("simple_strategy_multi_dc", Severity::Error)));
+            let rf = lint(&snapshot)
+                .into_iter()
+                .filter(|f| f.rule == "rf_exceeds_nodes")
+                .collect::<Vec<_>>();
+            assert_eq!(rf.len(), 1);
+            assert_eq!(rf[0].keyspace, "app");
+            assert!(rf[0].message.contains("dc2"));
+        }
+
+        #[test]
+        fn flags_table_level_anti_patterns() {
+            let mut snapshot = SchemaSnapshot::default();
+            snapshot.tables.push(TableSnapshot {
+                keyspace: "app".into(),
+                table: "events".into(),
+                partition_keys: vec![col("ts", "timestamp")],
+                regular_columns: vec![
+                    col("tags", "set<text>"),
+                    col("frozen_tags", "frozen<set<text>>"),
+                ],
+                index_count: 4,
+                mean_partition_size: Some(HUGE_MEAN_PARTITION_BYTES + 1),
+                ..Default::default()
+            });
+            let found = rules(&snapshot);
+            assert_eq!(found[0], ("huge_partitions", Severity::Error));
+            assert!(found.contains(&("unbounded_collection", Severity::Warning)));
+            assert!(found.contains(&("too_many_secondary_indexes", Severity::Warning)));
+            assert!(found.contains(&("time_series_without_clustering", Severity::Warning)));
+            assert_eq!(
+                found
+                    .iter()
+                    .filter(|(rule, _)| *rule == "unbounded_collection")
+                    .count(),
+                1,
+                "frozen collections are bounded and should not be flagged"
+            );
         }
 
         #[test]
-        fn tools_list_includes_json_schema() {
-            let result = list_tools_result();
-            let tools = result["tools"].as_array().expect("tools array");
-            assert!(tools.iter().any(|tool| tool["name"] == "list_keyspaces"));
-            let list_tables = tools
-                .iter()
-                .find(|tool| tool["name"] == "list_tables")
-                .expect("list_tables tool");
-            assert_eq!(list_tables["inputSchema"]["required"], json!(["keyspace"]));
+        fn flags_only_unreferenced_udts() {
+            let mut snapshot = SchemaSnapshot::default();
+            snapshot.tables.push(TableSnapshot {
+                keyspace: "app".into(),
+                table: "users".into(),
+                partition_keys: vec![col("id", "uuid")],
+                regular_columns: vec![col("home", "frozen<address>")],
+                ..Default::default()
+            });
+            for (name, fields) in [
+                (
+                    "address",
+                    vec!["text".to_string(), "frozen<geo>".to_string()],
+                ),
+                ("geo", vec!["double".to_string()]),
+                ("orphan", vec!["text".to_string()]),
+            ] {
+                snapshot.udts.push(UdtSnapshot {
+                    keyspace: "app".into(),
+                    name: name.into(),
+                    field_types: fields,
+                });
+            }
+            let unused = lint(&snapshot)
+                .into_iter()
+                .filter(|f| f.rule == "unused_udt")
+                .map(|f| f.message)
+                .collect::<Vec<_>>();
+            assert_eq!(unused.len(), 1);
+            assert!(unused[0].contains("'orphan'"));
         }
 
-        #[tokio::test]
-        async fn read_message_rejects_oversized_content_length() {
-            let input = format!("Content-Length: {}\r\n\r\n", MAX_MCP_MESSAGE_BYTES + 1);
-            let mut reader = BufReader::new(input.as_bytes());
-            let err = read_message(&mut reader)
-                .await
-                .expect_err("oversized message should be rejected before allocation");
-            assert!(err.to_string().contains("maximum Content-Length"));
+        #[test]
+        fn report_counts_findings_by_severity() {
+            let mut snapshot = SchemaSnapshot::default();
+            snapshot.udts.push(UdtSnapshot {
+                keyspace: "app".into(),
+                name: "orphan".into(),
+                field_types: Vec::new(),
+            });
+            let out = report(lint(&snapshot));
+            assert_eq!(out["summary"]["info"], 1);
+            assert_eq!(out["summary"]["error"], 0);
+            assert_eq!(out["findings"][0]["rule"], "unused_udt");
+            assert_eq!(out["findings"][0]["severity"], "info");
         }
     }
 }
 
-pub mod db {
-    use anyhow::Result;
-    use base64::Engine;
-    use scylla::query::Query;
-    use scylla::statement::{PagingState, PagingStateResponse};
-    use scylla::SessionBuilder;
-    use scylla_cql::frame::response::result::CqlValue;
-    use scylla_cql::frame::response::result::Row;
+pub mod access {
     use serde::Serialize;
     use serde_json::{Map, Value};
-    use std::env;
-    use tracing::info;
-
-    const MAX_PAGING_CURSOR_BYTES: usize = 16 * 1024;
-    const MAX_SEARCH_SCHEMA_PATTERN_BYTES: usize = 128;
-    const MAX_SEARCH_SCHEMA_RESULTS: usize = 500;
-
-    pub async fn list_keyspaces() -> Result<Vec<String>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, "connecting to scylla");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        let result = session
-            .query_unpaged("SELECT keyspace_name FROM system_schema.keyspaces", &[])
-            .await?;
-        let mut names = Vec::new();
-        for row in result.rows_typed::<(String,)>()? {
-            let (name,) = row?;
-            names.push(name);
-        }
-        Ok(names)
+    use std::collections::{BTreeSet, HashMap};
+
+    /// Objects every authenticated role may read regardless of grants.
+    const ALWAYS_READABLE: &[(&str, Option<&str>)] = &[
+        ("system_schema", None),
+        ("system", Some("local")),
+        ("system", Some("peers")),
+        ("system", Some("peers_v2")),
+        ("system", Some("size_estimates")),
+    ];
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum Resource {
+        AllKeyspaces,
+        Keyspace(String),
+        Table(String, String),
+        Other(String),
     }
 
-    pub async fn list_keyspaces_with(session: &scylla::Session) -> Result<Vec<String>> {
-        let result = session
-            .query_unpaged("SELECT keyspace_name FROM system_schema.keyspaces", &[])
-            .await?;
-        let mut names = Vec::new();
-        for row in result.rows_typed::<(String,)>()? {
-            let (name,) = row?;
-            names.push(name);
+    /// Parse a resource from `role_permissions` (`data/ks/tb`) or from
+    /// `LIST PERMISSIONS` output (`<table ks.tb>`).
+    pub fn parse_resource(resource: &str) -> Resource {
+        let resource = resource.trim();
+        if let Some(rest) = resource.strip_prefix("data") {
+            let parts: Vec<&str> = rest.split('/').filter(|p| !p.is_empty()).collect();
+            return match parts.as_slice() {
+                [] => Resource::AllKeyspaces,
+                [ks] => Resource::Keyspace(ks.to_string()),
+                [ks, tb] => Resource::Table(ks.to_string(), tb.to_string()),
+                _ => Resource::Other(resource.to_string()),
+            };
         }
-        Ok(names)
-    }
-
-    pub async fn list_tables(keyspace: &str) -> Result<Vec<String>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, %keyspace, "listing tables");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        let result = session
-            .query_unpaged(
-                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ?",
-                (keyspace.to_string(),),
-            )
-            .await?;
-        let mut names = Vec::new();
-        for row in result.rows_typed::<(String,)>()? {
-            let (name,) = row?;
-            names.push(name);
+        let inner = resource
+            .strip_prefix('<')
+            .and_then(|r| r.strip_suffix('>'))
+            .unwrap_or(resource);
+        if inner == "all keyspaces" {
+            return Resource::AllKeyspaces;
         }
-        Ok(names)
-    }
-
-    pub async fn list_tables_with(
-        session: &scylla::Session,
-        keyspace: &str,
-    ) -> Result<Vec<String>> {
-        let result = session
-            .query_unpaged(
-                "SELECT table_name FROM system_schema.tables WHERE keyspace_name = ?",
-                (keyspace.to_string(),),
-            )
-            .await?;
-        let mut names = Vec::new();
-        for row in result.rows_typed::<(String,)>()? {
-            let (name,) = row?;
-            names.push(name);
+        if let Some(ks) = inner.strip_prefix("keyspace ") {
+            return Resource::Keyspace(ks.trim().to_string());
         }
-        Ok(names)
-    }
-
-    #[derive(Debug, Serialize, Clone)]
-    pub struct ColumnMeta {
-        pub column_name: String,
-        pub kind: String,
-        pub position: i32,
-        pub r#type: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub clustering_order: Option<String>,
+        if let Some((ks, tb)) = inner
+            .strip_prefix("table ")
+            .and_then(|name| name.trim().split_once('.'))
+        {
+            return Resource::Table(ks.to_string(), tb.to_string());
+        }
+        Resource::Other(resource.to_string())
     }
 
-    #[derive(Debug, Serialize, Clone)]
-    pub struct DescribeTable {
-        pub keyspace: String,
-        pub table: String,
-        pub partition_keys: Vec<String>,
-        pub clustering_keys: Vec<String>,
-        pub columns: Vec<ColumnMeta>,
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub struct RoleRecord {
+        pub role: String,
+        pub can_login: bool,
+        pub is_superuser: bool,
+        pub member_of: Vec<String>,
     }
 
-    pub async fn describe_table(keyspace: &str, table: &str) -> Result<DescribeTable> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, %keyspace, %table, "describe table");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        describe_table_with(&session, keyspace, table).await
+    #[derive(Debug, Clone, Serialize)]
+    pub struct PermissionGrant {
+        pub role: String,
+        pub resource: String,
+        pub permissions: Vec<String>,
     }
 
-    pub async fn describe_table_with(
-        session: &scylla::Session,
-        keyspace: &str,
-        table: &str,
-    ) -> Result<DescribeTable> {
-        // Reuse logic by calling the existing function for now
-        // (In a later pass, this can be deduplicated to avoid double sessions.)
-        // For now, re-implement against provided session to avoid new connections.
-        let result = session
-            .query_unpaged(
-                "SELECT column_name, kind, position, type, clustering_order FROM system_schema.columns WHERE keyspace_name = ? AND table_name = ?",
-                (keyspace.to_string(), table.to_string()),
-            )
-            .await?;
-        let specs = result.col_specs().to_owned();
-        let mut idx_name = None;
-        let mut idx_kind = None;
-        let mut idx_pos = None;
-        let mut idx_type = None;
-        let mut idx_order = None;
-        for (i, spec) in specs.iter().enumerate() {
-            match spec.name.as_str() {
-                "column_name" => idx_name = Some(i),
-                "kind" => idx_kind = Some(i),
-                "position" => idx_pos = Some(i),
-                "type" => idx_type = Some(i),
-                "clustering_order" => idx_order = Some(i),
-                _ => {}
-            }
-        }
-        let rows = result.rows_or_empty();
-        let mut cols = Vec::new();
-        for row in rows.iter() {
-            let name = idx_name
-                .and_then(|i| row.columns.get(i))
-                .and_then(|o| o.as_ref())
-                .and_then(|v| match v {
-                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
-                    _ => None,
-                })
-                .unwrap_or_default();
-            let kind = idx_kind
-                .and_then(|i| row.columns.get(i))
-                .and_then(|o| o.as_ref())
-                .and_then(|v| match v {
-                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
-                    _ => None,
-                })
-                .unwrap_or_default();
-            let pos = idx_pos
-                .and_then(|i| row.columns.get(i))
-                .and_then(|o| o.as_ref())
-                .and_then(|v| match v {
-                    CqlValue::Int(i) => Some(*i),
-                    _ => None,
-                })
-                .unwrap_or(0);
-            let ty = idx_type
-                .and_then(|i| row.columns.get(i))
-                .and_then(|o| o.as_ref())
-                .and_then(|v| match v {
-                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
-                    _ => None,
-                })
-                .unwrap_or_default();
-            let order = idx_order
-                .and_then(|i| row.columns.get(i))
-                .and_then(|o| o.as_ref())
-                .and_then(|v| match v {
-                    CqlValue::Ascii(s) | CqlValue::Text(s) => Some(s.clone()),
-                    _ => None,
-                });
-            cols.push(ColumnMeta {
-                column_name: name,
-                kind,
-                position: pos,
-                r#type: ty,
-                clustering_order: order,
-            });
+    impl PermissionGrant {
+        fn allows_select(&self) -> bool {
+            self.permissions
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case("SELECT") || p.eq_ignore_ascii_case("ALL"))
         }
-        let mut part: Vec<(i32, String)> = cols
-            .iter()
-            .filter(|c| c.kind == "partition_key")
-            .map(|c| (c.position, c.column_name.clone()))
-            .collect();
-        part.sort_by_key(|(pos, _)| *pos);
-        let partition_keys: Vec<String> = part.into_iter().map(|(_, n)| n).collect();
-        let mut clust: Vec<(i32, String)> = cols
-            .iter()
-            .filter(|c| c.kind == "clustering")
-            .map(|c| (c.position, c.column_name.clone()))
-            .collect();
-        clust.sort_by_key(|(pos, _)| *pos);
-        let clustering_keys: Vec<String> = clust.into_iter().map(|(_, n)| n).collect();
-        Ok(DescribeTable {
-            keyspace: keyspace.to_string(),
-            table: table.to_string(),
-            partition_keys,
-            clustering_keys,
-            columns: cols,
-        })
     }
 
-    fn cql_value_to_json(v: &CqlValue) -> Value {
-        match v {
-            CqlValue::Boolean(b) => Value::Bool(*b),
-            CqlValue::Int(i) => Value::from(*i),
-            CqlValue::BigInt(i) => Value::from(*i),
-            CqlValue::Float(f) => Value::from(*f),
-            CqlValue::Double(f) => Value::from(*f),
-            CqlValue::Ascii(s) | CqlValue::Text(s) => Value::from(s.clone()),
-            CqlValue::Uuid(u) => Value::from(u.to_string()),
-            CqlValue::Timeuuid(u) => Value::from(u.to_string()),
-            CqlValue::Blob(bytes) => {
-                use base64::engine::general_purpose::STANDARD as B64;
-                use base64::Engine;
-                Value::from(B64.encode(bytes))
-            }
-            CqlValue::List(items) => Value::Array(items.iter().map(cql_value_to_json).collect()),
-            CqlValue::Set(items) => Value::Array(items.iter().map(cql_value_to_json).collect()),
-            CqlValue::Map(entries) => Value::Object(
-                entries
-                    .iter()
-                    .map(|(k, v)| (cql_map_key_to_string(k), cql_value_to_json(v)))
-                    .collect(),
-            ),
-            CqlValue::Tuple(values) => Value::Array(
-                values
-                    .iter()
-                    .map(|opt| opt.as_ref().map(cql_value_to_json).unwrap_or(Value::Null))
-                    .collect(),
-            ),
-            _ => Value::from(format!("{:?}", v)),
+    /// The role itself plus every role it inherits through `member_of`.
+    pub fn effective_roles(role: &str, roles: &[RoleRecord]) -> Vec<String> {
+        let by_name: HashMap<&str, &RoleRecord> =
+            roles.iter().map(|r| (r.role.as_str(), r)).collect();
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![role.to_string()];
+        let mut out = Vec::new();
+        while let Some(next) = stack.pop() {
+            if !seen.insert(next.clone()) {
+                continue;
+            }
+            if let Some(record) = by_name.get(next.as_str()) {
+                stack.extend(record.member_of.iter().cloned());
+            }
+            out.push(next);
         }
+        out
     }
 
-    fn cql_map_key_to_string(k: &CqlValue) -> String {
-        match k {
-            CqlValue::Ascii(s) | CqlValue::Text(s) => s.clone(),
-            CqlValue::Uuid(u) => u.to_string(),
-            CqlValue::Int(i) => i.to_string(),
-            CqlValue::BigInt(i) => i.to_string(),
-            _ => format!("{:?}", k),
-        }
+    /// What the configured role can read, as far as the cluster lets us see.
+    #[derive(Debug, Clone, Default, Serialize)]
+    pub struct AccessSummary {
+        pub user: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub auth_keyspace: Option<String>,
+        /// `false` when no user is configured or role metadata could not be read,
+        /// in which case nothing is filtered.
+        pub restricted: bool,
+        pub superuser: bool,
+        pub roles: Vec<String>,
+        pub grants: Vec<PermissionGrant>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub notes: Vec<String>,
     }
 
-    pub async fn sample_rows(
-        keyspace: &str,
-        table: &str,
-        limit: u32,
-        filters: Option<&Map<String, Value>>,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, %keyspace, %table, %limit, "sample rows");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
-        let cql = build_select_all_query(keyspace, table, &where_clause, limit)?;
-        let prepared = session.prepare(cql).await?;
-        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
-        let specs = result.col_specs().to_owned();
-        let rows: Vec<Row> = result.rows()?;
-        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+    impl AccessSummary {
+        /// Whether SELECT is allowed on a table, or on anything in the keyspace when `table` is `None`.
+        pub fn can_select(&self, keyspace: &str, table: Option<&str>) -> bool {
+            if !self.restricted || self.superuser {
+                return true;
             }
-            out.push(m);
+            let always = ALWAYS_READABLE.iter().any(|(ks, tb)| {
+                *ks == keyspace
+                    && match (tb, table) {
+                        (None, _) => true,
+                        (Some(tb), Some(table)) => *tb == table,
+                        (Some(_), None) => true,
+                    }
+            });
+            if always {
+                return true;
+            }
+            self.grants.iter().filter(|g| g.allows_select()).any(|g| {
+                match parse_resource(&g.resource) {
+                    Resource::AllKeyspaces => true,
+                    Resource::Keyspace(ks) => ks == keyspace,
+                    Resource::Table(ks, tb) => {
+                        ks == keyspace && table.is_none_or(|table| tb == table)
+                    }
+                    Resource::Other(_) => false,
+                }
+            })
         }
-        Ok(out)
-    }
 
-    pub async fn sample_rows_with(
-        session: &scylla::Session,
-        keyspace: &str,
-        table: &str,
-        limit: u32,
-        filters: Option<&Map<String, Value>>,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
-        let cql = build_select_all_query(keyspace, table, &where_clause, limit)?;
-        let prepared = session.prepare(cql).await?;
-        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
-        let specs = result.col_specs().to_owned();
-        let rows: Vec<Row> = result.rows()?;
-        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+        /// Keyspaces and tables reachable with SELECT, for `whoami` output.
+        pub fn select_scope(&self) -> Map<String, Value> {
+            let mut all = !self.restricted || self.superuser;
+            let mut keyspaces = BTreeSet::new();
+            let mut tables = BTreeSet::new();
+            for grant in self.grants.iter().filter(|g| g.allows_select()) {
+                match parse_resource(&grant.resource) {
+                    Resource::AllKeyspaces => all = true,
+                    Resource::Keyspace(ks) => {
+                        keyspaces.insert(ks);
+                    }
+                    Resource::Table(ks, tb) => {
+                        tables.insert(format!("{ks}.{tb}"));
+                    }
+                    Resource::Other(_) => {}
+                }
             }
-            out.push(m);
+            let mut out = Map::new();
+            out.insert("all_keyspaces".into(), Value::Bool(all));
+            out.insert(
+                "keyspaces".into(),
+                Value::Array(keyspaces.into_iter().map(Value::String).collect()),
+            );
+            out.insert(
+                "tables".into(),
+                Value::Array(tables.into_iter().map(Value::String).collect()),
+            );
+            out
         }
-        Ok(out)
     }
 
-    fn sanitize_ident(ident: &str) -> bool {
-        let bytes = ident.as_bytes();
-        if bytes.is_empty() {
-            return false;
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            effective_roles, parse_resource, AccessSummary, PermissionGrant, Resource, RoleRecord,
+        };
+
+        fn grant(resource: &str, permissions: &[&str]) -> PermissionGrant {
+            PermissionGrant {
+                role: "reader".into(),
+                resource: resource.into(),
+                permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            }
         }
-        let first = bytes[0];
-        let is_letter = |c: u8| c.is_ascii_alphabetic() || c == b'_';
-        let is_alnum = |c: u8| c.is_ascii_alphanumeric() || c == b'_';
-        if !is_letter(first) {
-            return false;
+
+        #[test]
+        fn parses_table_and_list_permissions_resource_formats() {
+            assert_eq!(parse_resource("data"), Resource::AllKeyspaces);
+            assert_eq!(parse_resource("data/app"), Resource::Keyspace("app".into()));
+            assert_eq!(
+                parse_resource("data/app/users"),
+                Resource::Table("app".into(), "users".into())
+            );
+            assert_eq!(parse_resource("<all keyspaces>"), Resource::AllKeyspaces);
+            assert_eq!(
+                parse_resource("<keyspace app>"),
+                Resource::Keyspace("app".into())
+            );
+            assert_eq!(
+                parse_resource("<table app.users>"),
+                Resource::Table("app".into(), "users".into())
+            );
+            assert!(matches!(parse_resource("roles/admin"), Resource::Other(_)));
         }
-        bytes.iter().all(|&c| is_alnum(c))
-    }
 
-    fn table_ref(keyspace: &str, table: &str) -> Result<String> {
-        if !sanitize_ident(keyspace) {
-            anyhow::bail!("invalid keyspace identifier");
+        #[test]
+        fn effective_roles_follow_membership_without_looping() {
+            let roles = vec![
+                RoleRecord {
+                    role: "alice".into(),
+                    member_of: vec!["reader".into()],
+                    ..Default::default()
+                },
+                RoleRecord {
+                    role: "reader".into(),
+                    member_of: vec!["base".into(), "alice".into()],
+                    ..Default::default()
+                },
+                RoleRecord {
+                    role: "base".into(),
+                    ..Default::default()
+                },
+            ];
+            let mut found = effective_roles("alice", &roles);
+            found.sort();
+            assert_eq!(found, ["alice", "base", "reader"]);
         }
-        if !sanitize_ident(table) {
-            anyhow::bail!("invalid table identifier");
+
+        #[test]
+        fn can_select_honours_grant_scope() {
+            let access = AccessSummary {
+                user: Some("alice".into()),
+                restricted: true,
+                grants: vec![
+                    grant("data/app", &["SELECT"]),
+                    grant("data/billing/invoices", &["SELECT", "MODIFY"]),
+                    grant("data/audit", &["MODIFY"]),
+                ],
+                ..Default::default()
+            };
+            assert!(access.can_select("app", None));
+            assert!(access.can_select("app", Some("users")));
+            assert!(access.can_select("billing", None));
+            assert!(access.can_select("billing", Some("invoices")));
+            assert!(!access.can_select("billing", Some("payments")));
+            assert!(!access.can_select("audit", Some("events")));
+            assert!(access.can_select("system_schema", Some("tables")));
+            assert!(access.can_select("system", Some("local")));
+            assert!(!access.can_select("system", Some("large_partitions")));
+
+            let scope = access.select_scope();
+            assert_eq!(scope["all_keyspaces"], false);
+            assert_eq!(scope["keyspaces"], serde_json::json!(["app"]));
+            assert_eq!(scope["tables"], serde_json::json!(["billing.invoices"]));
         }
-        Ok(format!("{}.{}", keyspace, table))
-    }
 
-    fn build_select_all_query(
-        keyspace: &str,
-        table: &str,
-        where_clause: &str,
-        limit: u32,
-    ) -> Result<String> {
-        Ok(format!(
-            "SELECT * FROM {}{} LIMIT {}",
-            table_ref(keyspace, table)?,
-            where_clause,
-            limit
-        ))
+        #[test]
+        fn unrestricted_or_superuser_access_sees_everything() {
+            let anonymous = AccessSummary::default();
+            assert!(anonymous.can_select("anything", Some("at_all")));
+            let admin = AccessSummary {
+                restricted: true,
+                superuser: true,
+                ..Default::default()
+            };
+            assert!(admin.can_select("app", Some("users")));
+            assert_eq!(admin.select_scope()["all_keyspaces"], true);
+        }
     }
+}
 
-    fn build_select_columns_query(
-        keyspace: &str,
-        table: &str,
-        col_list: &str,
-        where_clause: &str,
-        order_clause: &str,
-        limit: Option<u32>,
-    ) -> Result<String> {
-        let limit_clause = limit
-            .map(|limit| format!(" LIMIT {}", limit))
-            .unwrap_or_default();
-        Ok(format!(
-            "SELECT {} FROM {}{}{}{}",
-            col_list,
-            table_ref(keyspace, table)?,
-            where_clause,
-            order_clause,
-            limit_clause
-        ))
+pub mod cdc {
+    use anyhow::Result;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{Map, Value};
+    use std::collections::BTreeMap;
+
+    /// Scylla names the log of table `t` `t_scylla_cdc_log`.
+    pub const LOG_TABLE_SUFFIX: &str = "_scylla_cdc_log";
+    /// Stream ids bound into one `IN` restriction when reading the log.
+    pub const STREAMS_PER_QUERY: usize = 64;
+
+    /// Offset between the UUID v1 epoch (1582-10-15) and the Unix epoch, in 100ns ticks.
+    const UUID_EPOCH_OFFSET: i64 = 0x01B2_1DD2_1381_4000;
+
+    pub fn log_table_name(table: &str) -> String {
+        format!("{}{}", table, LOG_TABLE_SUFFIX)
     }
 
-    fn decode_paging_cursor(token: &str) -> Result<Vec<u8>> {
-        let max_encoded = MAX_PAGING_CURSOR_BYTES.div_ceil(3) * 4;
-        if token.len() > max_encoded {
-            anyhow::bail!("paging cursor is too large");
+    /// Decode the `cdc` entry of `system_schema.tables.extensions`: a little-endian
+    /// `u32` count followed by `u32`-length-prefixed key and value strings.
+    pub fn parse_extension(bytes: &[u8]) -> Option<BTreeMap<String, String>> {
+        fn take_u32(buf: &mut &[u8]) -> Option<u32> {
+            let (head, rest) = buf.split_first_chunk::<4>()?;
+            *buf = rest;
+            Some(u32::from_le_bytes(*head))
         }
-        let bytes = base64::engine::general_purpose::STANDARD.decode(token.as_bytes())?;
-        if bytes.len() > MAX_PAGING_CURSOR_BYTES {
-            anyhow::bail!("paging cursor is too large");
+        fn take_string(buf: &mut &[u8]) -> Option<String> {
+            let len = take_u32(buf)? as usize;
+            if buf.len() < len {
+                return None;
+            }
+            let (head, rest) = buf.split_at(len);
+            *buf = rest;
+            String::from_utf8(head.to_vec()).ok()
         }
-        Ok(bytes)
+        let mut buf = bytes;
+        let count = take_u32(&mut buf)?;
+        let mut options = BTreeMap::new();
+        for _ in 0..count {
+            let key = take_string(&mut buf)?;
+            let value = take_string(&mut buf)?;
+            options.insert(key, value);
+        }
+        buf.is_empty().then_some(options)
     }
 
-    fn validate_search_schema_pattern(pattern: &str) -> Result<()> {
-        if pattern.trim().is_empty() {
-            anyhow::bail!("search pattern must not be empty");
+    pub fn is_enabled(options: &BTreeMap<String, String>) -> bool {
+        options
+            .get("enabled")
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    }
+
+    /// Name of a `cdc$operation` code.
+    pub fn operation_name(code: i64) -> &'static str {
+        match code {
+            0 => "pre_image",
+            1 => "update",
+            2 => "insert",
+            3 => "row_delete",
+            4 => "partition_delete",
+            5 => "range_delete_start_inclusive",
+            6 => "range_delete_start_exclusive",
+            7 => "range_delete_end_inclusive",
+            8 => "range_delete_end_exclusive",
+            9 => "post_image",
+            _ => "unknown",
         }
-        if pattern.len() > MAX_SEARCH_SCHEMA_PATTERN_BYTES {
-            anyhow::bail!("search pattern is too large");
+    }
+
+    /// Unix milliseconds encoded in a version 1 (time-based) UUID string.
+    pub fn timeuuid_unix_ms(uuid: &str) -> Option<i64> {
+        let hex: String = uuid.chars().filter(|c| *c != '-').collect();
+        if hex.len() != 32 || !hex.is_ascii() {
+            return None;
         }
-        Ok(())
+        let time_low = i64::from_str_radix(&hex[0..8], 16).ok()?;
+        let time_mid = i64::from_str_radix(&hex[8..12], 16).ok()?;
+        let time_hi_and_version = i64::from_str_radix(&hex[12..16], 16).ok()?;
+        if time_hi_and_version >> 12 != 1 {
+            return None;
+        }
+        let ticks = ((time_hi_and_version & 0x0fff) << 48) | (time_mid << 32) | time_low;
+        Some((ticks - UUID_EPOCH_OFFSET).div_euclid(10_000))
     }
 
-    fn push_schema_result(out: &mut Vec<Map<String, Value>>, item: Map<String, Value>) -> bool {
-        out.push(item);
-        out.len() < MAX_SEARCH_SCHEMA_RESULTS
+    fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
     }
 
-    fn build_filters_clause_prepared(
-        filters: Option<&Map<String, Value>>,
-    ) -> Result<(String, Vec<CqlValue>)> {
-        let mut clause = String::new();
-        let mut values: Vec<CqlValue> = Vec::new();
-        if let Some(map) = filters {
-            let mut first = true;
-            for (k, v) in map.iter() {
-                if !sanitize_ident(k) {
-                    anyhow::bail!("invalid column name in filters");
-                }
-                clause.push_str(if first { " WHERE " } else { " AND " });
-                first = false;
-                clause.push_str(k);
-                clause.push_str(" = ?");
-                let cv = match v {
-                    Value::String(s) => CqlValue::Text(s.clone()),
-                    Value::Bool(b) => CqlValue::Boolean(*b),
-                    Value::Number(n) => {
-                        if let Some(i) = n.as_i64() {
-                            CqlValue::BigInt(i)
-                        } else if let Some(f) = n.as_f64() {
-                            CqlValue::Double(f)
-                        } else {
-                            anyhow::bail!("unsupported numeric value")
-                        }
-                    }
-                    _ => anyhow::bail!("unsupported filter value type"),
-                };
-                values.push(cv);
-            }
-        }
-        Ok((clause, values))
+    fn civil_from_days(days: i64) -> (i64, i64, i64) {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        (year, month, day)
     }
 
-    pub async fn select_columns(
-        keyspace: &str,
-        table: &str,
-        columns: &[String],
-        limit: u32,
-        filters: Option<&Map<String, Value>>,
-        order_by: Option<&Vec<(String, String)>>,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, %keyspace, %table, %limit, cols=?columns, "select columns");
-        // sanitize column idents
-        if columns.is_empty() {
-            anyhow::bail!("columns must not be empty");
+    /// Parse a window bound given as Unix milliseconds or an RFC 3339 timestamp
+    /// (`2024-05-01T12:00:00Z`, fractional seconds and `±HH:MM` offsets allowed).
+    pub fn parse_timestamp_ms(value: &Value) -> Result<i64> {
+        if let Some(ms) = value.as_i64() {
+            return Ok(ms);
         }
-        for c in columns {
-            if !sanitize_ident(c) {
-                anyhow::bail!("invalid column name");
-            }
+        let Some(text) = value.as_str() else {
+            anyhow::bail!("timestamp must be Unix milliseconds or an RFC 3339 string");
+        };
+        let invalid = || anyhow::anyhow!("invalid timestamp '{}'", text);
+        let text = text.trim();
+        if let Ok(ms) = text.parse::<i64>() {
+            return Ok(ms);
         }
-        let col_list = columns.join(", ");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
-        let order_clause = build_order_by_clause(order_by)?;
-        let cql = build_select_columns_query(
-            keyspace,
-            table,
-            &col_list,
-            &where_clause,
-            &order_clause,
-            Some(limit),
-        )?;
-        let prepared = session.prepare(cql).await?;
-        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
-        let specs = result.col_specs().to_owned();
-        let rows: Vec<Row> = result.rows()?;
-        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+        let (date, rest) = text.split_once(['T', 't', ' ']).ok_or_else(invalid)?;
+        let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>());
+        let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) =
+            (date_parts.next(), date_parts.next(), date_parts.next())
+        else {
+            return Err(invalid());
+        };
+        let (clock, offset_minutes) = if let Some(clock) = rest.strip_suffix(['Z', 'z']) {
+            (clock, 0)
+        } else if let Some(pos) = rest.rfind(['+', '-']) {
+            let (clock, offset) = rest.split_at(pos);
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (h, m) = offset[1..].split_once(':').ok_or_else(invalid)?;
+            let h: i64 = h.parse().map_err(|_| invalid())?;
+            let m: i64 = m.parse().map_err(|_| invalid())?;
+            (clock, sign * (h * 60 + m))
+        } else {
+            (rest, 0)
+        };
+        let (hms, fraction) = clock.split_once('.').unwrap_or((clock, ""));
+        let mut clock_parts = hms.splitn(3, ':').map(|p| p.parse::<i64>());
+        let (Some(Ok(hour)), Some(Ok(minute)), second) =
+            (clock_parts.next(), clock_parts.next(), clock_parts.next())
+        else {
+            return Err(invalid());
+        };
+        let second = match second {
+            Some(Ok(s)) => s,
+            None => 0,
+            Some(Err(_)) => return Err(invalid()),
+        };
+        if !(1..=12).contains(&month)
+            || !(1..=31).contains(&day)
+            || hour > 23
+            || minute > 59
+            || second > 60
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        let millis = format!("{:0<3}", &fraction[..fraction.len().min(3)])
+            .parse::<i64>()
+            .unwrap_or(0);
+        let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60
+            - offset_minutes * 60
+            + second;
+        Ok(seconds * 1000 + millis)
+    }
+
+    /// Format Unix milliseconds as `YYYY-MM-DDTHH:MM:SS.mmmZ`.
+    pub fn format_timestamp_ms(ms: i64) -> String {
+        let days = ms.div_euclid(86_400_000);
+        let rem = ms.rem_euclid(86_400_000);
+        let (year, month, day) = civil_from_days(days);
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            rem / 3_600_000,
+            rem / 60_000 % 60,
+            rem / 1000 % 60,
+            rem % 1000
+        )
+    }
+
+    /// A CDC stream generation, active from `start_ms` until the next one starts.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+    pub struct Generation {
+        pub start_ms: i64,
+        pub end_ms: Option<i64>,
+    }
+
+    /// Generations whose active interval overlaps `[since_ms, until_ms]`, oldest first.
+    pub fn generations_in_window(starts: &[i64], since_ms: i64, until_ms: i64) -> Vec<Generation> {
+        let mut starts = starts.to_vec();
+        starts.sort_unstable();
+        starts.dedup();
+        let mut out = Vec::new();
+        for (i, start) in starts.iter().enumerate() {
+            let end_ms = starts.get(i + 1).copied();
+            if *start <= until_ms && end_ms.is_none_or(|end| end > since_ms) {
+                out.push(Generation {
+                    start_ms: *start,
+                    end_ms,
+                });
             }
-            out.push(m);
         }
-        Ok(out)
+        out
     }
 
-    pub async fn select_columns_with(
-        session: &scylla::Session,
-        keyspace: &str,
-        table: &str,
-        columns: &[String],
-        limit: u32,
-        filters: Option<&Map<String, Value>>,
-        order_by: Option<&Vec<(String, String)>>,
-    ) -> Result<Vec<Map<String, Value>>> {
-        if columns.is_empty() {
-            anyhow::bail!("columns must not be empty");
-        }
-        for c in columns {
-            if !sanitize_ident(c) {
-                anyhow::bail!("invalid column name");
+    /// Position in the generation/stream-chunk walk plus the driver paging state.
+    /// The window is pinned so a default `until` of "now" does not drift between pages.
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Cursor {
+        pub since_ms: i64,
+        pub until_ms: i64,
+        pub generation_ms: i64,
+        pub chunk: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub paging_state: Option<String>,
+    }
+
+    /// Fold log rows (already ordered by stream, `cdc$time`, `cdc$batch_seq_no`)
+    /// into one change per write: its deltas plus any pre- and post-image.
+    pub fn group_changes(rows: Vec<Map<String, Value>>) -> Vec<Value> {
+        let mut changes: Vec<Map<String, Value>> = Vec::new();
+        let mut current_key: Option<(Value, Value)> = None;
+        let mut complete = true;
+        for row in rows {
+            let stream = row.get("cdc$stream_id").cloned().unwrap_or(Value::Null);
+            let time = row.get("cdc$time").cloned().unwrap_or(Value::Null);
+            let key = (stream.clone(), time.clone());
+            if current_key.as_ref() != Some(&key) {
+                if !complete {
+                    if let Some(last) = changes.last_mut() {
+                        last.insert("partial".into(), Value::Bool(true));
+                    }
+                }
+                let mut change = Map::new();
+                if let Some(ms) = time.as_str().and_then(timeuuid_unix_ms) {
+                    change.insert("timestamp".into(), Value::from(format_timestamp_ms(ms)));
+                    change.insert("timestamp_ms".into(), Value::from(ms));
+                }
+                change.insert("time".into(), time);
+                change.insert("stream_id".into(), stream);
+                change.insert("operations".into(), Value::Array(Vec::new()));
+                changes.push(change);
+                current_key = Some(key);
+            }
+            complete = row.get("cdc$end_of_batch").and_then(Value::as_bool) == Some(true);
+            let Some(change) = changes.last_mut() else {
+                continue;
+            };
+            let code = row
+                .get("cdc$operation")
+                .and_then(Value::as_i64)
+                .unwrap_or(-1);
+            let mut columns = Map::new();
+            let mut deleted_columns = Vec::new();
+            let mut deleted_elements = Map::new();
+            for (name, value) in row.iter() {
+                if let Some(col) = name.strip_prefix("cdc$deleted_elements_") {
+                    if !value.is_null() {
+                        deleted_elements.insert(col.to_string(), value.clone());
+                    }
+                } else if let Some(col) = name.strip_prefix("cdc$deleted_") {
+                    if value.as_bool() == Some(true) {
+                        deleted_columns.push(Value::from(col));
+                    }
+                } else if !name.starts_with("cdc$") && !value.is_null() {
+                    columns.insert(name.clone(), value.clone());
+                }
+            }
+            match code {
+                0 => {
+                    change.insert("pre_image".into(), Value::Object(columns));
+                }
+                9 => {
+                    change.insert("post_image".into(), Value::Object(columns));
+                }
+                _ => {
+                    let mut op = Map::new();
+                    op.insert("operation".into(), Value::from(operation_name(code)));
+                    if let Some(seq) = row.get("cdc$batch_seq_no") {
+                        op.insert("batch_seq_no".into(), seq.clone());
+                    }
+                    if let Some(ttl) = row.get("cdc$ttl").filter(|v| !v.is_null()) {
+                        op.insert("ttl".into(), ttl.clone());
+                    }
+                    op.insert("columns".into(), Value::Object(columns));
+                    if !deleted_columns.is_empty() {
+                        op.insert("deleted_columns".into(), Value::Array(deleted_columns));
+                    }
+                    if !deleted_elements.is_empty() {
+                        op.insert("deleted_elements".into(), Value::Object(deleted_elements));
+                    }
+                    if let Some(Value::Array(ops)) = change.get_mut("operations") {
+                        ops.push(Value::Object(op));
+                    }
+                }
             }
         }
-        let col_list = columns.join(", ");
-        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
-        let order_clause = build_order_by_clause(order_by)?;
-        let cql = build_select_columns_query(
-            keyspace,
-            table,
-            &col_list,
-            &where_clause,
-            &order_clause,
-            Some(limit),
-        )?;
-        let prepared = session.prepare(cql).await?;
-        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
-        let specs = result.col_specs().to_owned();
-        let rows: Vec<Row> = result.rows()?;
-        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+        if !complete {
+            if let Some(last) = changes.last_mut() {
+                last.insert("partial".into(), Value::Bool(true));
             }
-            out.push(m);
         }
-        Ok(out)
+        changes.into_iter().map(Value::Object).collect()
     }
 
-    /// Fetch rows by full partition key using a strictly prepared statement.
-    /// Requires the provided `partition` map to contain exactly the set of partition key columns.
-    pub async fn partition_rows_with(
-        session: &scylla::Session,
-        keyspace: &str,
-        table: &str,
-        partition: &Map<String, Value>,
-        limit: u32,
-    ) -> Result<Vec<Map<String, Value>>> {
-        // Describe table to determine partition key order
-        let meta = crate::db::describe_table_with(session, keyspace, table).await?;
-        let expected: std::collections::HashSet<String> =
-            meta.partition_keys.iter().cloned().collect();
-        let provided: std::collections::HashSet<String> = partition.keys().cloned().collect();
-        if expected != provided {
-            anyhow::bail!(
-                "partition keys mismatch: expected {:?}",
-                meta.partition_keys
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            format_timestamp_ms, generations_in_window, group_changes, parse_extension,
+            parse_timestamp_ms, timeuuid_unix_ms, Generation,
+        };
+        use serde_json::{json, Map, Value};
+
+        fn encode_extension(entries: &[(&str, &str)]) -> Vec<u8> {
+            let mut out = (entries.len() as u32).to_le_bytes().to_vec();
+            for (k, v) in entries {
+                for s in [k, v] {
+                    out.extend((s.len() as u32).to_le_bytes());
+                    out.extend(s.as_bytes());
+                }
+            }
+            out
+        }
+
+        fn row(value: Value) -> Map<String, Value> {
+            value.as_object().cloned().unwrap()
+        }
+
+        #[test]
+        fn parses_cdc_extension_options() {
+            let bytes = encode_extension(&[("enabled", "true"), ("preimage", "full")]);
+            let options = parse_extension(&bytes).expect("valid extension");
+            assert_eq!(options["enabled"], "true");
+            assert_eq!(options["preimage"], "full");
+            assert!(super::is_enabled(&options));
+            assert!(parse_extension(&bytes[..bytes.len() - 1]).is_none());
+            assert!(parse_extension(&[]).is_none());
+        }
+
+        #[test]
+        fn timestamps_round_trip_through_rfc3339() {
+            assert_eq!(parse_timestamp_ms(&json!(0)).unwrap(), 0);
+            assert_eq!(
+                parse_timestamp_ms(&json!("2024-03-01T12:30:15.250Z")).unwrap(),
+                1_709_296_215_250
+            );
+            assert_eq!(
+                parse_timestamp_ms(&json!("2024-03-01T14:30:15.25+02:00")).unwrap(),
+                1_709_296_215_250
+            );
+            assert_eq!(
+                format_timestamp_ms(1_709_296_215_250),
+                "2024-03-01T12:30:15.250Z"
             );
+            assert!(parse_timestamp_ms(&json!("yesterday")).is_err());
+            assert!(parse_timestamp_ms(&json!("2024-13-01T00:00:00Z")).is_err());
         }
-        // Build WHERE clause in schema PK order and bind values accordingly
-        let mut where_clause = String::from(" WHERE ");
-        let mut first = true;
-        let mut bind_values: Vec<CqlValue> = Vec::with_capacity(meta.partition_keys.len());
-        for pk in meta.partition_keys.iter() {
-            if !first {
-                where_clause.push_str(" AND ");
+
+        #[test]
+        fn decodes_unix_ms_from_timeuuid() {
+            // Time-based UUID generated at 2024-03-01T12:30:15.250Z.
+            assert_eq!(
+                timeuuid_unix_ms("74c38b20-d7c7-11ee-8080-808080808080"),
+                Some(1_709_296_215_250)
+            );
+            assert_eq!(
+                timeuuid_unix_ms("123e4567-e89b-42d3-a456-426614174000"),
+                None
+            );
+        }
+
+        #[test]
+        fn selects_generations_overlapping_the_window() {
+            let gens = generations_in_window(&[300, 100, 200], 150, 250);
+            assert_eq!(
+                gens,
+                vec![
+                    Generation {
+                        start_ms: 100,
+                        end_ms: Some(200)
+                    },
+                    Generation {
+                        start_ms: 200,
+                        end_ms: Some(300)
+                    },
+                ]
+            );
+            let gens = generations_in_window(&[100, 200], 500, 600);
+            assert_eq!(gens.len(), 1);
+            assert_eq!(gens[0].end_ms, None);
+        }
+
+        #[test]
+        fn groups_log_rows_into_changes_with_images() {
+            let time = "74c38b20-d7c7-11ee-8080-808080808080";
+            let rows = vec![
+                row(json!({
+                    "cdc$stream_id": "AQ==", "cdc$time": time, "cdc$batch_seq_no": 0,
+                    "cdc$operation": 0, "cdc$end_of_batch": false,
+                    "id": 1, "name": "old", "tags": null,
+                })),
+                row(json!({
+                    "cdc$stream_id": "AQ==", "cdc$time": time, "cdc$batch_seq_no": 1,
+                    "cdc$operation": 1, "cdc$ttl": 60, "cdc$end_of_batch": false,
+                    "cdc$deleted_tags": true, "cdc$deleted_elements_tags": null,
+                    "id": 1, "name": "new", "tags": null,
+                })),
+                row(json!({
+                    "cdc$stream_id": "AQ==", "cdc$time": time, "cdc$batch_seq_no": 2,
+                    "cdc$operation": 9, "cdc$end_of_batch": true,
+                    "id": 1, "name": "new", "tags": null,
+                })),
+                row(json!({
+                    "cdc$stream_id": "Ag==", "cdc$time": time, "cdc$batch_seq_no": 0,
+                    "cdc$operation": 3, "cdc$end_of_batch": false, "id": 2,
+                })),
+            ];
+            let changes = group_changes(rows);
+            assert_eq!(changes.len(), 2);
+            let first = &changes[0];
+            assert_eq!(first["timestamp"], "2024-03-01T12:30:15.250Z");
+            assert_eq!(first["pre_image"], json!({"id": 1, "name": "old"}));
+            assert_eq!(first["post_image"], json!({"id": 1, "name": "new"}));
+            assert_eq!(first["operations"][0]["operation"], "update");
+            assert_eq!(first["operations"][0]["ttl"], 60);
+            assert_eq!(first["operations"][0]["deleted_columns"], json!(["tags"]));
+            assert!(first.get("partial").is_none());
+            assert_eq!(changes[1]["operations"][0]["operation"], "row_delete");
+            assert_eq!(changes[1]["partial"], true);
+        }
+    }
+}
+
+pub mod ring {
+    use serde_json::{json, Map, Value};
+    use std::collections::BTreeMap;
+
+    /// Relative deviation from an even share, in percent, that triggers an imbalance warning.
+    pub const IMBALANCE_THRESHOLD_PCT: f64 = 20.0;
+
+    /// Number of Murmur3 tokens on the ring (`i64::MIN..=i64::MAX`).
+    const RING_SIZE: f64 = 18_446_744_073_709_551_616.0;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct NodeInfo {
+        pub host_id: String,
+        pub address: Option<String>,
+        pub datacenter: Option<String>,
+        pub tokens: Vec<i64>,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Tablet {
+        pub last_token: i64,
+        /// `(host_id, shard)` pairs.
+        pub replicas: Vec<(String, i64)>,
+        pub in_transition: bool,
+    }
+
+    /// Fraction of the ring owned by each host: every token owns the range from
+    /// the previous token (exclusive) up to itself, wrapping at the ends.
+    pub fn ownership(tokens: &[(i64, &str)]) -> BTreeMap<String, f64> {
+        let mut sorted = tokens.to_vec();
+        sorted.sort_unstable();
+        let mut out: BTreeMap<String, f64> = BTreeMap::new();
+        for (i, (token, host)) in sorted.iter().enumerate() {
+            let span = if i == 0 {
+                let last = sorted[sorted.len() - 1].0;
+                i128::from(*token) - i128::from(last) + (1i128 << 64)
             } else {
-                first = false;
-            }
-            where_clause.push_str(pk);
-            where_clause.push_str(" = ?");
-            let v = partition.get(pk).expect("checked equality above");
-            let cv = match v {
-                Value::String(s) => CqlValue::Text(s.clone()),
-                Value::Bool(b) => CqlValue::Boolean(*b),
-                Value::Number(n) => {
-                    if let Some(i) = n.as_i64() {
-                        CqlValue::BigInt(i)
-                    } else if let Some(f) = n.as_f64() {
-                        CqlValue::Double(f)
-                    } else {
-                        anyhow::bail!("unsupported numeric value")
-                    }
-                }
-                _ => anyhow::bail!("unsupported partition value type"),
+                i128::from(*token) - i128::from(sorted[i - 1].0)
             };
-            bind_values.push(cv);
+            *out.entry(host.to_string()).or_default() += span as f64 / RING_SIZE;
         }
-        let cql = build_select_all_query(keyspace, table, &where_clause, limit)?;
-        let prepared = session.prepare(cql).await?;
-        let result = session.execute_unpaged(&prepared, &bind_values[..]).await?;
-        let specs = result.col_specs().to_owned();
-        let rows: Vec<Row> = result.rows()?;
-        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
-            }
-            out.push(m);
+        out
+    }
+
+    /// Warnings for shares (fractions summing to 1) that stray from an even split.
+    pub fn imbalance_warnings(
+        scope: &str,
+        shares: &BTreeMap<String, f64>,
+        label: impl Fn(&str) -> String,
+    ) -> Vec<String> {
+        if shares.len() < 2 {
+            return Vec::new();
         }
-        Ok(out)
+        let expected = 1.0 / shares.len() as f64;
+        shares
+            .iter()
+            .filter(|(_, share)| {
+                ((**share - expected).abs() / expected) * 100.0 > IMBALANCE_THRESHOLD_PCT
+            })
+            .map(|(host, share)| {
+                format!(
+                    "{} owns {:.1}% of {} (even share {:.1}%)",
+                    label(host),
+                    share * 100.0,
+                    scope,
+                    expected * 100.0
+                )
+            })
+            .collect()
     }
 
-    /// Wrapper that creates a session, then calls `partition_rows_with`.
-    pub async fn partition_rows(
-        keyspace: &str,
-        table: &str,
-        partition: &Map<String, Value>,
-        limit: u32,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, %keyspace, %table, limit = limit as i64, "partition rows");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        partition_rows_with(&session, keyspace, table, partition, limit).await
+    fn round_pct(fraction: f64) -> f64 {
+        (fraction * 10_000.0).round() / 100.0
     }
 
-    #[allow(clippy::too_many_arguments)]
-    pub async fn paged_select_with(
-        session: &scylla::Session,
-        keyspace: &str,
-        table: &str,
-        columns: &[String],
-        page_size: i32,
-        filters: Option<&Map<String, Value>>,
-        order_by: Option<&Vec<(String, String)>>,
-        cursor: Option<&str>,
-    ) -> Result<Map<String, Value>> {
-        if columns.is_empty() {
-            anyhow::bail!("columns must not be empty");
-        }
-        for c in columns {
-            if !sanitize_ident(c) {
-                anyhow::bail!("invalid column name");
-            }
+    fn node_label(nodes: &[NodeInfo], host: &str) -> String {
+        nodes
+            .iter()
+            .find(|n| n.host_id == host)
+            .and_then(|n| n.address.clone())
+            .unwrap_or_else(|| host.to_string())
+    }
+
+    /// Vnode token ownership across the whole ring and within each datacenter.
+    pub fn vnode_report(nodes: &[NodeInfo]) -> Map<String, Value> {
+        let all: Vec<(i64, &str)> = nodes
+            .iter()
+            .flat_map(|n| n.tokens.iter().map(|t| (*t, n.host_id.as_str())))
+            .collect();
+        let global = ownership(&all);
+        let mut by_dc: BTreeMap<&str, Vec<(i64, &str)>> = BTreeMap::new();
+        for n in nodes {
+            let dc = n.datacenter.as_deref().unwrap_or("unknown");
+            by_dc
+                .entry(dc)
+                .or_default()
+                .extend(n.tokens.iter().map(|t| (*t, n.host_id.as_str())));
         }
-        let col_list = columns.join(", ");
-        let (where_clause, bind_values) = build_filters_clause_prepared(filters)?;
-        let order_clause = build_order_by_clause(order_by)?;
-        let cql = build_select_columns_query(
-            keyspace,
-            table,
-            &col_list,
-            &where_clause,
-            &order_clause,
-            None,
-        )?;
-        let prepared = session
-            .prepare(Query::new(cql).with_page_size(page_size))
-            .await?;
-        let paging_state = match cursor {
-            Some(tok) => {
-                let bytes = decode_paging_cursor(tok)?;
-                PagingState::new_from_raw_bytes(bytes)
-            }
-            None => PagingState::start(),
-        };
-        let (result, paging_resp) = session
-            .execute_single_page(&prepared, &bind_values[..], paging_state)
-            .await?;
-        let specs = result.col_specs().to_owned();
-        let rows: Vec<Row> = result.rows_or_empty();
-        let mut items: Vec<Value> = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
-            }
-            items.push(Value::Object(m));
+        let mut warnings = Vec::new();
+        let mut dc_shares: BTreeMap<String, f64> = BTreeMap::new();
+        for (dc, tokens) in by_dc {
+            let shares = ownership(&tokens);
+            warnings.extend(imbalance_warnings(
+                &format!("datacenter {} token ranges", dc),
+                &shares,
+                |host| node_label(nodes, host),
+            ));
+            dc_shares.extend(shares);
         }
-        let next_cursor = match paging_resp {
-            PagingStateResponse::HasMorePages { state } => state
-                .as_bytes_slice()
-                .map(|arc| {
-                    let slice: &[u8] = arc.as_ref();
-                    Value::String(base64::engine::general_purpose::STANDARD.encode(slice))
-                })
-                .unwrap_or(Value::Null),
-            PagingStateResponse::NoMorePages => Value::Null,
-        };
+        let rows: Vec<Value> = nodes
+            .iter()
+            .map(|n| {
+                json!({
+                    "host_id": n.host_id,
+                    "address": n.address,
+                    "datacenter": n.datacenter,
+                    "tokens": n.tokens.len(),
+                    "ownership_pct": round_pct(global.get(&n.host_id).copied().unwrap_or(0.0)),
+                    "dc_ownership_pct": round_pct(dc_shares.get(&n.host_id).copied().unwrap_or(0.0)),
+                })
+            })
+            .collect();
         let mut out = Map::new();
-        out.insert("items".into(), Value::Array(items));
-        out.insert("next_cursor".into(), next_cursor);
-        Ok(out)
+        out.insert("nodes".into(), Value::Array(rows));
+        out.insert("warnings".into(), json!(warnings));
+        out
     }
 
-    fn build_order_by_clause(order_by: Option<&Vec<(String, String)>>) -> Result<String> {
-        if let Some(list) = order_by {
-            if list.is_empty() {
-                return Ok(String::new());
+    /// Tablet placement of one table: per-node replica counts, the share of the
+    /// table's replicated token space each node holds, and imbalance per datacenter.
+    pub fn tablet_report(
+        tablets: &[Tablet],
+        nodes: &[NodeInfo],
+        include_tablets: bool,
+    ) -> Map<String, Value> {
+        let mut sorted = tablets.to_vec();
+        sorted.sort_by_key(|t| t.last_token);
+        let mut replicas: BTreeMap<String, usize> = BTreeMap::new();
+        let mut weight: BTreeMap<String, f64> = BTreeMap::new();
+        let mut previous = i128::from(i64::MIN);
+        let mut listed = Vec::new();
+        for tablet in sorted.iter() {
+            let span = (i128::from(tablet.last_token) - previous).max(0) as f64 / RING_SIZE;
+            previous = i128::from(tablet.last_token);
+            for (host, _) in tablet.replicas.iter() {
+                *replicas.entry(host.clone()).or_default() += 1;
+                *weight.entry(host.clone()).or_default() += span;
             }
-            let mut clause = String::from(" ORDER BY ");
-            for (i, (col, dir)) in list.iter().enumerate() {
-                if !sanitize_ident(col) {
-                    anyhow::bail!("invalid column name in order_by");
-                }
-                let d = match dir.to_ascii_lowercase().as_str() {
-                    "asc" => "ASC",
-                    "desc" => "DESC",
-                    _ => "ASC",
-                };
-                if i > 0 {
-                    clause.push_str(", ");
-                }
-                clause.push_str(col);
-                clause.push(' ');
-                clause.push_str(d);
+            if include_tablets {
+                listed.push(json!({
+                    "last_token": tablet.last_token,
+                    "replicas": tablet
+                        .replicas
+                        .iter()
+                        .map(|(host, shard)| json!({
+                            "host_id": host,
+                            "address": nodes.iter().find(|n| &n.host_id == host).and_then(|n| n.address.clone()),
+                            "shard": shard,
+                        }))
+                        .collect::<Vec<_>>(),
+                    "in_transition": tablet.in_transition,
+                }));
             }
-            Ok(clause)
-        } else {
-            Ok(String::new())
         }
-    }
 
-    pub async fn cluster_topology() -> Result<Vec<Map<String, Value>>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, "cluster topology");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
+        // Nodes of every datacenter the table replicates to count, even with no tablets.
+        let dc_of = |host: &str| {
+            nodes
+                .iter()
+                .find(|n| n.host_id == host)
+                .and_then(|n| n.datacenter.clone())
+                .unwrap_or_else(|| "unknown".into())
+        };
+        let mut by_dc: BTreeMap<String, BTreeMap<String, f64>> = BTreeMap::new();
+        for (host, w) in weight.iter() {
+            by_dc
+                .entry(dc_of(host))
+                .or_default()
+                .insert(host.clone(), *w);
+        }
+        for n in nodes {
+            let dc = n.datacenter.clone().unwrap_or_else(|| "unknown".into());
+            if let Some(hosts) = by_dc.get_mut(&dc) {
+                hosts.entry(n.host_id.clone()).or_insert(0.0);
+            }
+        }
 
-        // Collect rows from system.local
-        let mut out: Vec<Map<String, Value>> = Vec::new();
-        let result_local = session
-            .query_unpaged(
-                "SELECT host_id, data_center, rack, rpc_address FROM system.local",
-                &[],
-            )
-            .await?;
-        let specs_local = result_local.col_specs().to_owned();
-        let rows_local: Vec<Row> = result_local.rows_or_empty();
-        for row in rows_local.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs_local.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+        let mut warnings = Vec::new();
+        let mut rows = Vec::new();
+        for (dc, hosts) in by_dc.iter() {
+            let total: f64 = hosts.values().sum();
+            let shares: BTreeMap<String, f64> = hosts
+                .iter()
+                .map(|(h, w)| (h.clone(), if total > 0.0 { w / total } else { 0.0 }))
+                .collect();
+            warnings.extend(imbalance_warnings(
+                &format!("datacenter {} tablet replicas", dc),
+                &shares,
+                |host| node_label(nodes, host),
+            ));
+            for (host, share) in shares {
+                rows.push(json!({
+                    "host_id": host,
+                    "address": nodes.iter().find(|n| n.host_id == host).and_then(|n| n.address.clone()),
+                    "datacenter": dc,
+                    "tablet_replicas": replicas.get(&host).copied().unwrap_or(0),
+                    "dc_ownership_pct": round_pct(share),
+                }));
             }
-            m.insert("source".into(), Value::from("local"));
-            out.push(m);
         }
 
-        // Collect rows from system.peers (best-effort, may be empty)
-        let result_peers = session
-            .query_unpaged(
-                "SELECT host_id, data_center, rack, rpc_address FROM system.peers",
-                &[],
-            )
-            .await?;
-        let specs_peers = result_peers.col_specs().to_owned();
-        let rows_peers: Vec<Row> = result_peers.rows_or_empty();
-        for row in rows_peers.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs_peers.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+        let mut out = Map::new();
+        out.insert("tablet_count".into(), Value::from(sorted.len()));
+        out.insert(
+            "in_transition".into(),
+            Value::from(sorted.iter().filter(|t| t.in_transition).count()),
+        );
+        out.insert("nodes".into(), Value::Array(rows));
+        out.insert("warnings".into(), json!(warnings));
+        if include_tablets {
+            out.insert("tablets".into(), Value::Array(listed));
+        }
+        out
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{ownership, tablet_report, vnode_report, NodeInfo, Tablet};
+
+        fn node(host: &str, dc: &str, tokens: &[i64]) -> NodeInfo {
+            NodeInfo {
+                host_id: host.into(),
+                address: Some(format!("10.0.0.{}", host.trim_start_matches('h'))),
+                datacenter: Some(dc.into()),
+                tokens: tokens.to_vec(),
             }
-            m.insert("source".into(), Value::from("peers"));
-            out.push(m);
         }
 
-        Ok(out)
+        #[test]
+        fn ownership_wraps_around_the_ring() {
+            let shares = ownership(&[(0, "a"), (i64::MIN, "b")]);
+            assert!((shares["a"] - 0.5).abs() < 1e-9);
+            assert!((shares["b"] - 0.5).abs() < 1e-9);
+            let single = ownership(&[(42, "a")]);
+            assert!((single["a"] - 1.0).abs() < 1e-9);
+        }
+
+        #[test]
+        fn vnode_report_flags_uneven_datacenter_ownership() {
+            let quarter = i64::MAX / 2;
+            let nodes = vec![
+                node("h1", "dc1", &[quarter]),
+                node("h2", "dc1", &[i64::MAX]),
+                node("h3", "dc2", &[0]),
+                node("h4", "dc2", &[i64::MIN]),
+            ];
+            let report = vnode_report(&nodes);
+            let rows = report["nodes"].as_array().unwrap();
+            assert_eq!(rows[2]["dc_ownership_pct"], 50.0);
+            assert_eq!(rows[0]["tokens"], 1);
+            let warnings = report["warnings"].as_array().unwrap();
+            assert_eq!(warnings.len(), 2, "{warnings:?}");
+            assert!(warnings[0].as_str().unwrap().contains("datacenter dc1"));
+        }
+
+        #[test]
+        fn tablet_report_counts_replicas_and_idle_nodes() {
+            let nodes = vec![
+                node("h1", "dc1", &[]),
+                node("h2", "dc1", &[]),
+                node("h3", "dc1", &[]),
+            ];
+            let tablets = vec![
+                Tablet {
+                    last_token: -1,
+                    replicas: vec![("h1".into(), 0), ("h2".into(), 1)],
+                    in_transition: false,
+                },
+                Tablet {
+                    last_token: i64::MAX,
+                    replicas: vec![("h1".into(), 1), ("h2".into(), 0)],
+                    in_transition: true,
+                },
+            ];
+            let report = tablet_report(&tablets, &nodes, true);
+            assert_eq!(report["tablet_count"], 2);
+            assert_eq!(report["in_transition"], 1);
+            let rows = report["nodes"].as_array().unwrap();
+            assert_eq!(rows.len(), 3);
+            assert_eq!(rows[0]["tablet_replicas"], 2);
+            assert_eq!(rows[0]["dc_ownership_pct"], 50.0);
+            assert_eq!(rows[2]["tablet_replicas"], 0);
+            let warnings = report["warnings"].as_array().unwrap();
+            assert_eq!(warnings.len(), 3, "{warnings:?}");
+            assert_eq!(report["tablets"][0]["replicas"][1]["shard"], 1);
+        }
     }
+}
 
-    pub async fn cluster_topology_with(
-        session: &scylla::Session,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let mut out: Vec<Map<String, Value>> = Vec::new();
-        let result_local = session
-            .query_unpaged(
-                "SELECT host_id, data_center, rack, rpc_address FROM system.local",
-                &[],
-            )
-            .await?;
-        let specs_local = result_local.col_specs().to_owned();
-        let rows_local: Vec<Row> = result_local.rows_or_empty();
-        for row in rows_local.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs_local.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+pub mod replication {
+    use crate::lint::{replication_factors, strategy_name, Finding, Severity};
+    use serde_json::{json, Map, Value};
+    use std::collections::BTreeMap;
+
+    /// System keyspaces whose data must survive node loss for the cluster to work.
+    const CRITICAL_SYSTEM_KEYSPACES: [&str; 4] = [
+        "system_auth",
+        "system_distributed",
+        "system_distributed_everywhere",
+        "system_traces",
+    ];
+
+    /// Node counts of one datacenter.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct DcNodes {
+        pub total: usize,
+        pub up: usize,
+    }
+
+    fn quorum(rf: usize) -> usize {
+        rf / 2 + 1
+    }
+
+    /// Replicas guaranteed reachable when every down node happens to hold one.
+    fn live_replicas(rf: usize, nodes: DcNodes) -> usize {
+        rf.min(nodes.total)
+            .saturating_sub(nodes.total - nodes.up.min(nodes.total))
+    }
+
+    fn finding(
+        rule: &'static str,
+        severity: Severity,
+        keyspace: &str,
+        message: String,
+        rationale: &'static str,
+    ) -> Finding {
+        Finding {
+            rule,
+            severity,
+            keyspace: keyspace.to_string(),
+            table: None,
+            message,
+            rationale,
+        }
+    }
+
+    /// Cross-reference one keyspace's replication with the datacenters that exist,
+    /// returning its report entry and any findings.
+    pub fn analyze_keyspace(
+        keyspace: &str,
+        replication: &Map<String, Value>,
+        dcs: &BTreeMap<String, DcNodes>,
+    ) -> (Map<String, Value>, Vec<Finding>) {
+        let strategy = strategy_name(replication);
+        let mut findings = Vec::new();
+        let mut entry = Map::new();
+        entry.insert("keyspace".into(), Value::from(keyspace));
+        entry.insert("strategy".into(), Value::from(strategy));
+        entry.insert("replication".into(), Value::Object(replication.clone()));
+
+        let cluster = dcs.values().fold(DcNodes::default(), |acc, n| DcNodes {
+            total: acc.total + n.total,
+            up: acc.up + n.up,
+        });
+        let factors: BTreeMap<String, usize> = match strategy {
+            "LocalStrategy" => {
+                entry.insert(
+                    "note".into(),
+                    Value::from("node-local data; not replicated"),
+                );
+                return (entry, findings);
             }
-            m.insert("source".into(), Value::from("local"));
-            out.push(m);
+            "EverywhereStrategy" => dcs.iter().map(|(dc, n)| (dc.clone(), n.total)).collect(),
+            _ => replication_factors(replication),
+        };
+
+        findings.extend(crate::lint::simple_strategy_multi_dc(
+            keyspace,
+            strategy,
+            dcs.len(),
+        ));
+
+        let mut per_dc = Vec::new();
+        let mut total_rf = 0;
+        let mut total_live = 0;
+        let mut each_quorum = true;
+        for (dc, rf) in factors.iter() {
+            let (label, nodes) = if dc == "*" {
+                ("cluster".to_string(), cluster)
+            } else {
+                (dc.clone(), dcs.get(dc).copied().unwrap_or_default())
+            };
+            total_rf += rf;
+            if *rf == 0 {
+                continue;
+            }
+            if nodes.total == 0 {
+                findings.push(finding(
+                    "rf_dc_without_nodes",
+                    Severity::Error,
+                    keyspace,
+                    format!("replication factor {} for datacenter '{}' which has no nodes", rf, dc),
+                    "replicas assigned to a datacenter with no nodes are never written, and consistency levels counting them (QUORUM, EACH_QUORUM, ALL) cannot be met",
+                ));
+            } else {
+                findings.extend(crate::lint::rf_exceeds_nodes(
+                    keyspace,
+                    dc,
+                    *rf,
+                    nodes.total,
+                ));
+            }
+            let live = live_replicas(*rf, nodes);
+            total_live += live;
+            let local_quorum = live >= quorum(*rf);
+            each_quorum &= local_quorum;
+            if !local_quorum && nodes.total > 0 && nodes.up < nodes.total {
+                findings.push(finding(
+                    "local_quorum_at_risk",
+                    Severity::Warning,
+                    keyspace,
+                    format!(
+                        "{} of {} node(s) down in {}; LOCAL_QUORUM ({} of {}) is not guaranteed",
+                        nodes.total - nodes.up,
+                        nodes.total,
+                        label,
+                        quorum(*rf),
+                        rf
+                    ),
+                    "if the down nodes hold replicas of a range, LOCAL_QUORUM requests for that range fail until they return",
+                ));
+            }
+            let mut satisfiable = vec!["ANY"];
+            if live >= 1 {
+                satisfiable.push("LOCAL_ONE");
+            }
+            if local_quorum {
+                satisfiable.push("LOCAL_QUORUM");
+            }
+            per_dc.push(json!({
+                "datacenter": label,
+                "rf": rf,
+                "nodes": nodes.total,
+                "nodes_up": nodes.up,
+                "guaranteed_live_replicas": live,
+                "satisfiable": satisfiable,
+            }));
         }
-        let result_peers = session
-            .query_unpaged(
-                "SELECT host_id, data_center, rack, rpc_address FROM system.peers",
-                &[],
-            )
-            .await?;
-        let specs_peers = result_peers.col_specs().to_owned();
-        let rows_peers: Vec<Row> = result_peers.rows_or_empty();
-        for row in rows_peers.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs_peers.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
+
+        if CRITICAL_SYSTEM_KEYSPACES.contains(&keyspace) && strategy != "EverywhereStrategy" {
+            let single_copy = factors.iter().filter(|(_, rf)| **rf == 1).any(|(dc, _)| {
+                let nodes = if dc == "*" {
+                    cluster.total
+                } else {
+                    dcs.get(dc).map_or(0, |n| n.total)
                 };
-                m.insert(name, json);
+                nodes > 1
+            });
+            if single_copy {
+                let severity = if keyspace == "system_auth" {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                };
+                findings.push(finding(
+                    "system_keyspace_rf",
+                    severity,
+                    keyspace,
+                    "system keyspace keeps a single replica in a multi-node datacenter".into(),
+                    "with RF 1 the loss of one node loses this data; for system_auth that blocks logins for the affected roles. Use RF 3 (or the DC node count if smaller) in every DC",
+                ));
             }
-            m.insert("source".into(), Value::from("peers"));
-            out.push(m);
         }
-        Ok(out)
+
+        if strategy == "NetworkTopologyStrategy" && !crate::lint::is_system_keyspace(keyspace) {
+            for dc in dcs.keys().filter(|dc| !factors.contains_key(*dc)) {
+                findings.push(finding(
+                    "dc_not_replicated",
+                    Severity::Info,
+                    keyspace,
+                    format!("datacenter '{}' holds no replicas of this keyspace", dc),
+                    "clients routed to this datacenter read remotely and LOCAL_* consistency levels fail there",
+                ));
+            }
+        }
+
+        let mut cluster_levels = vec!["ANY"];
+        for (name, needed) in [
+            ("ONE", 1),
+            ("TWO", 2),
+            ("THREE", 3),
+            ("QUORUM", quorum(total_rf)),
+            ("ALL", total_rf),
+        ] {
+            if total_rf >= needed && total_live >= needed {
+                cluster_levels.push(name);
+            }
+        }
+        if each_quorum && !per_dc.is_empty() {
+            cluster_levels.push("EACH_QUORUM");
+        }
+        entry.insert("datacenters".into(), Value::Array(per_dc));
+        entry.insert("satisfiable".into(), json!(cluster_levels));
+        (entry, findings)
     }
 
-    pub async fn list_indexes(keyspace: &str, table: &str) -> Result<Vec<String>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, %keyspace, %table, "list indexes");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        list_indexes_with(&session, keyspace, table).await
+    /// Analyze every keyspace against the datacenter node counts.
+    pub fn report(
+        keyspaces: &[(String, Map<String, Value>)],
+        dcs: &BTreeMap<String, DcNodes>,
+    ) -> Map<String, Value> {
+        let mut entries = Vec::new();
+        let mut findings = Vec::new();
+        for (keyspace, replication) in keyspaces {
+            let (entry, found) = analyze_keyspace(keyspace, replication, dcs);
+            entries.push(Value::Object(entry));
+            findings.extend(found);
+        }
+        findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        let mut out = crate::lint::report(findings);
+        out.insert(
+            "datacenters".into(),
+            Value::Object(
+                dcs.iter()
+                    .map(|(dc, n)| (dc.clone(), json!({ "nodes": n.total, "up": n.up })))
+                    .collect(),
+            ),
+        );
+        out.insert("keyspaces".into(), Value::Array(entries));
+        out
     }
 
-    pub async fn list_indexes_with(
-        session: &scylla::Session,
-        keyspace: &str,
-        table: &str,
-    ) -> Result<Vec<String>> {
-        let prepared = session
-            .prepare("SELECT index_name FROM system_schema.indexes WHERE keyspace_name = ? AND table_name = ?")
-            .await?;
-        let result = session
-            .execute_unpaged(&prepared, &(keyspace.to_string(), table.to_string()))
-            .await?;
-        let mut names = Vec::new();
-        for row in result.rows_typed::<(String,)>()? {
-            let (name,) = row?;
-            names.push(name);
+    #[cfg(test)]
+    mod tests {
+        use super::{analyze_keyspace, report, DcNodes};
+        use serde_json::{json, Map, Value};
+        use std::collections::BTreeMap;
+
+        fn replication(value: Value) -> Map<String, Value> {
+            value.as_object().cloned().unwrap()
+        }
+
+        fn dcs(entries: &[(&str, usize, usize)]) -> BTreeMap<String, DcNodes> {
+            entries
+                .iter()
+                .map(|(dc, total, up)| {
+                    (
+                        dc.to_string(),
+                        DcNodes {
+                            total: *total,
+                            up: *up,
+                        },
+                    )
+                })
+                .collect()
+        }
+
+        fn rules(findings: &[crate::lint::Finding]) -> Vec<&str> {
+            findings.iter().map(|f| f.rule).collect()
+        }
+
+        #[test]
+        fn flags_missing_dc_and_oversized_rf() {
+            let (entry, findings) = analyze_keyspace(
+                "app",
+                &replication(json!({
+                    "class": "org.apache.cassandra.locator.NetworkTopologyStrategy",
+                    "dc1": "5",
+                    "dc3": "3",
+                })),
+                &dcs(&[("dc1", 3, 3), ("dc2", 3, 3)]),
+            );
+            assert_eq!(
+                rules(&findings),
+                [
+                    "rf_exceeds_nodes",
+                    "rf_dc_without_nodes",
+                    "dc_not_replicated"
+                ]
+            );
+            assert_eq!(entry["strategy"], "NetworkTopologyStrategy");
+            assert_eq!(entry["satisfiable"], json!(["ANY", "ONE", "TWO", "THREE"]));
+        }
+
+        #[test]
+        fn computes_satisfiable_levels_with_down_nodes() {
+            let (entry, findings) = analyze_keyspace(
+                "app",
+                &replication(json!({
+                    "class": "NetworkTopologyStrategy",
+                    "dc1": "3",
+                    "dc2": "3",
+                })),
+                &dcs(&[("dc1", 3, 3), ("dc2", 3, 1)]),
+            );
+            assert_eq!(rules(&findings), ["local_quorum_at_risk"]);
+            let per_dc = entry["datacenters"].as_array().unwrap();
+            assert_eq!(
+                per_dc[0]["satisfiable"],
+                json!(["ANY", "LOCAL_ONE", "LOCAL_QUORUM"])
+            );
+            assert_eq!(per_dc[1]["guaranteed_live_replicas"], 1);
+            assert_eq!(per_dc[1]["satisfiable"], json!(["ANY", "LOCAL_ONE"]));
+            assert_eq!(
+                entry["satisfiable"],
+                json!(["ANY", "ONE", "TWO", "THREE", "QUORUM"])
+            );
+        }
+
+        #[test]
+        fn flags_simple_strategy_and_fragile_system_keyspaces() {
+            let (_, findings) = analyze_keyspace(
+                "system_auth",
+                &replication(json!({
+                    "class": "org.apache.cassandra.locator.SimpleStrategy",
+                    "replication_factor": "1",
+                })),
+                &dcs(&[("dc1", 3, 3), ("dc2", 3, 3)]),
+            );
+            assert_eq!(
+                rules(&findings),
+                ["simple_strategy_multi_dc", "system_keyspace_rf"]
+            );
+            assert_eq!(findings[1].severity, crate::lint::Severity::Error);
+
+            let (entry, findings) = analyze_keyspace(
+                "system",
+                &replication(json!({ "class": "org.apache.cassandra.locator.LocalStrategy" })),
+                &dcs(&[("dc1", 3, 3)]),
+            );
+            assert!(findings.is_empty());
+            assert!(entry.get("datacenters").is_none());
+        }
+
+        #[test]
+        fn report_orders_findings_by_severity() {
+            let out = report(
+                &[
+                    (
+                        "app".to_string(),
+                        replication(json!({ "class": "NetworkTopologyStrategy", "dc1": "1" })),
+                    ),
+                    (
+                        "bad".to_string(),
+                        replication(json!({ "class": "NetworkTopologyStrategy", "dc9": "3" })),
+                    ),
+                ],
+                &dcs(&[("dc1", 1, 1), ("dc2", 1, 1)]),
+            );
+            assert_eq!(out["summary"]["error"], 1);
+            assert_eq!(out["findings"][0]["rule"], "rf_dc_without_nodes");
+            assert_eq!(out["datacenters"]["dc2"]["nodes"], 1);
+            assert_eq!(out["keyspaces"].as_array().unwrap().len(), 2);
+        }
+    }
+}
+
+pub mod rest {
+    //! Read-only client for Scylla's REST API (port 10000 by default).
+    //!
+    //! Only plain-HTTP `GET`s are issued; the API binds to a management interface
+    //! and is not TLS-enabled by Scylla itself.
+    use anyhow::{bail, Context, Result};
+    use futures::future::join_all;
+    use serde_json::{json, Map, Value};
+    use std::time::Duration;
+
+    const DEFAULT_REST_PORT: u16 = 10_000;
+    /// Upper bound on a REST response body; REST answers here are small JSON documents.
+    const MAX_JSON_BYTES: usize = 8 * 1024 * 1024;
+    /// Upper bound on a Prometheus scrape; per-shard series make these large on big nodes.
+    const MAX_TEXT_BYTES: usize = 64 * 1024 * 1024;
+
+    /// One node's REST API base URL.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RestEndpoint {
+        pub host: String,
+        pub port: u16,
+        pub base_path: String,
+    }
+
+    impl RestEndpoint {
+        /// Accepts `http://host:port[/prefix]`, `host:port` or a bare `host`.
+        pub fn parse(url: &str) -> Result<Self> {
+            Self::parse_with_port(url, DEFAULT_REST_PORT)
+        }
+
+        /// Like [`RestEndpoint::parse`] with a different port for bare hosts.
+        pub fn parse_with_port(url: &str, default_port: u16) -> Result<Self> {
+            let url = url.trim();
+            if url.starts_with("https://") {
+                bail!("'{url}': https is not supported for the Scylla REST API");
+            }
+            let rest = url.strip_prefix("http://").unwrap_or(url);
+            let (authority, path) = match rest.find('/') {
+                Some(idx) => (&rest[..idx], &rest[idx..]),
+                None => (rest, ""),
+            };
+            if authority.is_empty() {
+                bail!("'{url}': missing host");
+            }
+            let (host, port) = match authority.strip_prefix('[') {
+                Some(bracketed) => {
+                    let (host, tail) = bracketed
+                        .split_once(']')
+                        .with_context(|| format!("'{url}': unterminated IPv6 address"))?;
+                    (host, tail.strip_prefix(':'))
+                }
+                None => match authority.rsplit_once(':') {
+                    Some((host, port)) => (host, Some(port)),
+                    None => (authority, None),
+                },
+            };
+            let port = match port {
+                Some(port) => port
+                    .parse::<u16>()
+                    .with_context(|| format!("'{url}': invalid port '{port}'"))?,
+                None => default_port,
+            };
+            Ok(Self {
+                host: host.to_string(),
+                port,
+                base_path: path.trim_end_matches('/').to_string(),
+            })
+        }
+
+        pub fn label(&self) -> String {
+            if self.host.contains(':') {
+                format!("[{}]:{}", self.host, self.port)
+            } else {
+                format!("{}:{}", self.host, self.port)
+            }
         }
-        Ok(names)
     }
 
-    pub async fn keyspace_replication(keyspace: &str) -> Result<Map<String, Value>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        info!(%uri, %keyspace, "keyspace replication");
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        keyspace_replication_with(&session, keyspace).await
+    /// HTTP endpoints for each node plus the per-request timeout.
+    #[derive(Debug, Clone)]
+    pub struct RestClient {
+        endpoints: Vec<RestEndpoint>,
+        timeout: Duration,
+        /// Environment variable the endpoints came from, for error messages.
+        source: String,
     }
 
-    pub async fn keyspace_replication_with(
-        session: &scylla::Session,
-        keyspace: &str,
-    ) -> Result<Map<String, Value>> {
-        let prepared = session
-            .prepare("SELECT replication, durable_writes FROM system_schema.keyspaces WHERE keyspace_name = ?")
-            .await?;
-        let result = session
-            .execute_unpaged(&prepared, &(keyspace.to_string(),))
-            .await?;
-        let rows = result.rows_or_empty();
-        let mut out = Map::new();
-        if let Some(row) = rows.first() {
-            if let Some(Some(CqlValue::Map(entries))) = row.columns.first().map(|o| o.as_ref()) {
-                let obj: Map<String, Value> = entries
-                    .iter()
-                    .map(|(k, v)| (cql_map_key_to_string(k), cql_value_to_json(v)))
-                    .collect();
-                out.insert("replication".into(), Value::Object(obj));
+    impl RestClient {
+        pub fn new(endpoints: Vec<RestEndpoint>, timeout: Duration) -> Self {
+            Self {
+                endpoints,
+                timeout,
+                source: "SCYLLA_REST_URL".to_string(),
             }
-            if let Some(Some(CqlValue::Boolean(b))) = row.columns.get(1).map(|o| o.as_ref()) {
-                out.insert("durable_writes".into(), Value::Bool(*b));
+        }
+
+        /// `SCYLLA_REST_URL` holds one comma-separated base URL per node;
+        /// `Ok(None)` when it is unset.
+        pub fn from_env() -> Result<Option<Self>> {
+            Self::from_env_var("SCYLLA_REST_URL", DEFAULT_REST_PORT)
+        }
+
+        /// Read comma-separated per-node URLs from `var`, using `default_port` for bare
+        /// hosts. The timeout comes from the matching `*_TIMEOUT_MS` variable, so
+        /// `SCYLLA_METRICS_URL` is governed by `SCYLLA_METRICS_TIMEOUT_MS`.
+        pub fn from_env_var(var: &str, default_port: u16) -> Result<Option<Self>> {
+            let Ok(raw) = std::env::var(var) else {
+                return Ok(None);
+            };
+            let endpoints = raw
+                .split(',')
+                .filter(|part| !part.trim().is_empty())
+                .map(|part| RestEndpoint::parse_with_port(part, default_port))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("invalid {var}"))?;
+            if endpoints.is_empty() {
+                return Ok(None);
             }
+            let timeout_var = format!("{}_TIMEOUT_MS", var.trim_end_matches("_URL"));
+            let millis = std::env::var(timeout_var)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(5_000);
+            Ok(Some(Self {
+                endpoints,
+                timeout: Duration::from_millis(millis),
+                source: var.to_string(),
+            }))
         }
-        Ok(out)
-    }
 
-    pub async fn list_views_with(session: &scylla::Session, keyspace: &str) -> Result<Vec<String>> {
-        let prepared = session
-            .prepare("SELECT view_name FROM system_schema.views WHERE keyspace_name = ?")
-            .await?;
-        let result = session
-            .execute_unpaged(&prepared, &(keyspace.to_string(),))
-            .await?;
-        let mut names = Vec::new();
-        for row in result.rows_typed::<(String,)>()? {
-            let (name,) = row?;
-            names.push(name);
+        /// Endpoints matching `node` (a host, `host:port`, or `all`/absent for every node).
+        pub fn select(&self, node: Option<&str>) -> Result<Vec<&RestEndpoint>> {
+            let node = node.map(str::trim).filter(|n| !n.is_empty());
+            match node {
+                None => Ok(self.endpoints.iter().collect()),
+                Some(n) if n.eq_ignore_ascii_case("all") => Ok(self.endpoints.iter().collect()),
+                Some(n) => {
+                    let found: Vec<&RestEndpoint> = self
+                        .endpoints
+                        .iter()
+                        .filter(|ep| ep.host == n || ep.label() == n)
+                        .collect();
+                    if found.is_empty() {
+                        let known: Vec<String> =
+                            self.endpoints.iter().map(|ep| ep.label()).collect();
+                        bail!(
+                            "node '{n}' is not configured in {} (known: {})",
+                            self.source,
+                            known.join(", ")
+                        );
+                    }
+                    Ok(found)
+                }
+            }
         }
-        Ok(names)
-    }
 
-    pub async fn list_udts_with(session: &scylla::Session, keyspace: &str) -> Result<Vec<String>> {
-        let prepared = session
-            .prepare("SELECT type_name FROM system_schema.types WHERE keyspace_name = ?")
-            .await?;
-        let result = session
-            .execute_unpaged(&prepared, &(keyspace.to_string(),))
-            .await?;
-        let mut names = Vec::new();
-        for row in result.rows_typed::<(String,)>()? {
-            let (name,) = row?;
-            names.push(name);
+        /// `GET` a path below the endpoint's base and decode the JSON body.
+        pub async fn get_json(&self, endpoint: &RestEndpoint, path: &str) -> Result<Value> {
+            let full = format!("{}{}", endpoint.base_path, path);
+            let body = self.get(endpoint, &full, MAX_JSON_BYTES).await?;
+            serde_json::from_str(&body).with_context(|| {
+                format!("GET {full} on {} returned invalid JSON", endpoint.label())
+            })
         }
-        Ok(names)
-    }
 
-    pub async fn list_functions_with(
-        session: &scylla::Session,
-        keyspace: &str,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let prepared = session
-            .prepare("SELECT function_name, argument_types, return_type FROM system_schema.functions WHERE keyspace_name = ?")
-            .await?;
-        let result = session
-            .execute_unpaged(&prepared, &(keyspace.to_string(),))
-            .await?;
-        let specs = result.col_specs().to_owned();
-        let rows = result.rows_or_empty();
-        let mut out = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
+        /// `GET` a plain-text document (such as a Prometheus scrape), using
+        /// `default_path` when the endpoint URL has no path of its own.
+        pub async fn get_text(
+            &self,
+            endpoint: &RestEndpoint,
+            default_path: &str,
+        ) -> Result<String> {
+            let full = if endpoint.base_path.is_empty() {
+                default_path.to_string()
+            } else {
+                endpoint.base_path.clone()
+            };
+            self.get(endpoint, &full, MAX_TEXT_BYTES).await
+        }
+
+        async fn get(
+            &self,
+            endpoint: &RestEndpoint,
+            full: &str,
+            max_bytes: usize,
+        ) -> Result<String> {
+            let request = http_get(endpoint, full, max_bytes);
+            let (status, body) = tokio::time::timeout(self.timeout, request)
+                .await
+                .with_context(|| {
+                    format!(
+                        "GET {full} on {} timed out after {} ms",
+                        endpoint.label(),
+                        self.timeout.as_millis()
+                    )
+                })??;
+            if !(200..300).contains(&status) {
+                let snippet: String = body.chars().take(200).collect();
+                bail!(
+                    "GET {full} on {} returned HTTP {status}: {snippet}",
+                    endpoint.label()
+                );
+            }
+            Ok(body)
+        }
+
+        /// Fetch several `(field, path)` pairs concurrently; failures become `{ "error": … }`.
+        async fn fetch_fields(
+            &self,
+            endpoint: &RestEndpoint,
+            fields: &[(&str, String)],
+        ) -> Map<String, Value> {
+            let results =
+                join_all(fields.iter().map(|(_, path)| self.get_json(endpoint, path))).await;
+            let mut out = Map::new();
+            out.insert("node".into(), Value::from(endpoint.label()));
+            for ((field, _), result) in fields.iter().zip(results) {
+                let value = match result {
+                    Ok(value) => value,
+                    Err(err) => json!({ "error": format!("{err:#}") }),
                 };
-                m.insert(name, json);
+                out.insert(field.to_string(), value);
             }
-            out.push(m);
+            out
         }
-        Ok(out)
     }
 
-    pub async fn list_aggregates_with(
-        session: &scylla::Session,
-        keyspace: &str,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let prepared = session
-            .prepare("SELECT aggregate_name, argument_types, return_type FROM system_schema.aggregates WHERE keyspace_name = ?")
-            .await?;
-        let result = session
-            .execute_unpaged(&prepared, &(keyspace.to_string(),))
-            .await?;
-        let specs = result.col_specs().to_owned();
-        let rows = result.rows_or_empty();
-        let mut out = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
+    async fn http_get(
+        endpoint: &RestEndpoint,
+        path: &str,
+        max_bytes: usize,
+    ) -> Result<(u16, String)> {
+        use http_body_util::{BodyExt, Empty, Limited};
+        use hyper::header::{ACCEPT, HOST};
+
+        let stream = tokio::net::TcpStream::connect((endpoint.host.as_str(), endpoint.port))
+            .await
+            .with_context(|| format!("failed to connect to {}", endpoint.label()))?;
+        let (mut sender, connection) =
+            hyper::client::conn::http1::handshake(hyper_util::rt::TokioIo::new(stream))
+                .await
+                .with_context(|| format!("HTTP handshake with {} failed", endpoint.label()))?;
+        let request = hyper::Request::get(path)
+            .header(HOST, endpoint.label())
+            .header(ACCEPT, "application/json")
+            .body(Empty::<bytes::Bytes>::new())?;
+        let exchange = async {
+            let response = sender.send_request(request).await?;
+            let status = response.status().as_u16();
+            let body = Limited::new(response.into_body(), max_bytes)
+                .collect()
+                .await
+                .map_err(|err| {
+                    if err.is::<http_body_util::LengthLimitError>() {
+                        anyhow::anyhow!(
+                            "response from {} exceeds {max_bytes} bytes",
+                            endpoint.label()
+                        )
+                    } else {
+                        anyhow::anyhow!("failed to read response from {}: {err}", endpoint.label())
+                    }
+                })?
+                .to_bytes();
+            Ok((status, String::from_utf8_lossy(&body).into_owned()))
+        };
+        // The connection future drives the socket; it only finishes early on an I/O error.
+        tokio::pin!(connection);
+        tokio::select! {
+            result = exchange => result,
+            Err(err) = &mut connection => {
+                Err(anyhow::Error::new(err).context(format!("connection to {} failed", endpoint.label())))
             }
-            out.push(m);
         }
-        Ok(out)
     }
 
-    pub async fn size_estimates_with(
-        session: &scylla::Session,
+    /// Percent-encode a path segment (`keyspace:table` names may be quoted identifiers).
+    fn encode_segment(segment: &str) -> String {
+        segment
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'.' | b':' => {
+                    (b as char).to_string()
+                }
+                other => format!("%{other:02X}"),
+            })
+            .collect()
+    }
+
+    /// Keep compactions touching `keyspace`/`table` and add a completion percentage.
+    pub fn summarize_compactions(
+        compactions: &Value,
+        keyspace: Option<&str>,
+        table: Option<&str>,
+    ) -> Vec<Value> {
+        let Some(items) = compactions.as_array() else {
+            return Vec::new();
+        };
+        items
+            .iter()
+            .filter(|c| keyspace.is_none_or(|ks| c.get("ks").and_then(Value::as_str) == Some(ks)))
+            .filter(|c| table.is_none_or(|cf| c.get("cf").and_then(Value::as_str) == Some(cf)))
+            .map(|c| {
+                let mut entry = c.as_object().cloned().unwrap_or_default();
+                let completed = c.get("completed").and_then(Value::as_f64);
+                let total = c.get("total").and_then(Value::as_f64);
+                if let (Some(completed), Some(total)) = (completed, total) {
+                    if total > 0.0 {
+                        let pct = (completed / total * 1000.0).round() / 10.0;
+                        entry.insert("progress_pct".into(), json!(pct));
+                    }
+                }
+                Value::Object(entry)
+            })
+            .collect()
+    }
+
+    /// Active compactions and pending/completed task counters per node.
+    pub async fn compaction_status(
+        client: &RestClient,
+        node: Option<&str>,
+        keyspace: Option<&str>,
+        table: Option<&str>,
+    ) -> Result<Value> {
+        let fields = [
+            ("compactions", "/compaction_manager/compactions".to_string()),
+            (
+                "pending_tasks",
+                "/compaction_manager/metrics/pending_tasks".to_string(),
+            ),
+            (
+                "completed_tasks",
+                "/compaction_manager/metrics/completed_tasks".to_string(),
+            ),
+        ];
+        let endpoints = client.select(node)?;
+        let nodes = join_all(endpoints.iter().map(|ep| client.fetch_fields(ep, &fields))).await;
+        let mut active_total = 0usize;
+        let nodes: Vec<Value> = nodes
+            .into_iter()
+            .map(|mut entry| {
+                if let Some(raw) = entry.get("compactions").cloned() {
+                    if raw.get("error").is_none() {
+                        let active = summarize_compactions(&raw, keyspace, table);
+                        active_total += active.len();
+                        entry.insert("compactions".into(), Value::Array(active));
+                    }
+                }
+                Value::Object(entry)
+            })
+            .collect();
+        Ok(json!({ "active_compactions": active_total, "nodes": nodes }))
+    }
+
+    /// SSTable counts, per-level layout and disk usage of one table per node.
+    pub async fn table_sstables(
+        client: &RestClient,
+        node: Option<&str>,
         keyspace: &str,
         table: &str,
-    ) -> Result<Vec<Map<String, Value>>> {
-        let prepared = session
-            .prepare("SELECT range_start, range_end, mean_partition_size, partitions_count FROM system.size_estimates WHERE keyspace_name = ? AND table_name = ?")
-            .await?;
-        let result = session
-            .execute_unpaged(&prepared, &(keyspace.to_string(), table.to_string()))
-            .await?;
-        let specs = result.col_specs().to_owned();
-        let rows = result.rows_or_empty();
-        let mut out: Vec<Map<String, Value>> = Vec::with_capacity(rows.len());
-        for row in rows.iter() {
-            let mut m = Map::new();
-            for (i, spec) in specs.iter().enumerate() {
-                let name = spec.name.clone();
-                let val = row.columns.get(i).and_then(|o| o.as_ref());
-                let json = match val {
-                    Some(c) => cql_value_to_json(c),
-                    None => Value::Null,
-                };
-                m.insert(name, json);
-            }
-            out.push(m);
+    ) -> Result<Value> {
+        let name = encode_segment(&format!("{keyspace}:{table}"));
+        let fields = [
+            (
+                "live_sstable_count",
+                format!("/column_family/metrics/live_ss_table_count/{name}"),
+            ),
+            (
+                "sstables_per_level",
+                format!("/column_family/sstables/per_level/{name}"),
+            ),
+            (
+                "live_disk_space_used",
+                format!("/column_family/metrics/live_disk_space_used/{name}"),
+            ),
+            (
+                "total_disk_space_used",
+                format!("/column_family/metrics/total_disk_space_used/{name}"),
+            ),
+            (
+                "pending_compactions",
+                format!("/column_family/metrics/pending_compactions/{name}"),
+            ),
+            (
+                "compaction_strategy",
+                format!("/column_family/compaction_strategy/{name}"),
+            ),
+        ];
+        let endpoints = client.select(node)?;
+        let nodes = join_all(endpoints.iter().map(|ep| client.fetch_fields(ep, &fields))).await;
+        let total_sstables: i64 = nodes
+            .iter()
+            .filter_map(|n| n.get("live_sstable_count").and_then(Value::as_i64))
+            .sum();
+        let live_bytes: i64 = nodes
+            .iter()
+            .filter_map(|n| n.get("live_disk_space_used").and_then(Value::as_i64))
+            .sum();
+        Ok(json!({
+            "keyspace": keyspace,
+            "table": table,
+            "live_sstable_count": total_sstables,
+            "live_disk_space_used": live_bytes,
+            "live_disk_space_used_human": crate::lint::human_bytes(live_bytes),
+            "nodes": nodes,
+        }))
+    }
+
+    /// Operation mode, version, load, client/gossip state and running repairs per node.
+    pub async fn node_status(client: &RestClient, node: Option<&str>) -> Result<Value> {
+        let fields = [
+            ("host_id", "/storage_service/hostid/local".to_string()),
+            (
+                "operation_mode",
+                "/storage_service/operation_mode".to_string(),
+            ),
+            (
+                "release_version",
+                "/storage_service/release_version".to_string(),
+            ),
+            (
+                "schema_version",
+                "/storage_service/schema_version".to_string(),
+            ),
+            ("load", "/storage_service/load".to_string()),
+            (
+                "native_transport",
+                "/storage_service/native_transport".to_string(),
+            ),
+            ("gossiping", "/storage_service/gossiping".to_string()),
+            (
+                "active_repairs",
+                "/storage_service/active_repair/".to_string(),
+            ),
+        ];
+        let endpoints = client.select(node)?;
+        let nodes = join_all(endpoints.iter().map(|ep| client.fetch_fields(ep, &fields))).await;
+        let warnings: Vec<String> = nodes
+            .iter()
+            .filter_map(|n| {
+                let label = n.get("node").and_then(Value::as_str).unwrap_or_default();
+                match n.get("operation_mode").and_then(Value::as_str) {
+                    Some(mode) if !mode.eq_ignore_ascii_case("NORMAL") => {
+                        Some(format!("{label} is in operation mode {mode}"))
+                    }
+                    Some(_) => None,
+                    None => Some(format!("{label} did not report an operation mode")),
+                }
+            })
+            .collect();
+        Ok(json!({ "nodes": nodes, "warnings": warnings }))
+    }
+
+    /// Canned HTTP server shared by the `rest` and `metrics` tests.
+    #[cfg(test)]
+    pub(crate) mod stand_in {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        /// Answer each request with `respond(path)` as `(content type, body)`, or
+        /// 404 for `None`, until the test ends. Returns the bound port.
+        pub(crate) async fn serve<F>(respond: F) -> u16
+        where
+            F: Fn(&str) -> Option<(&'static str, String)> + Send + Sync + 'static,
+        {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let respond = std::sync::Arc::new(respond);
+            tokio::spawn(async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let respond = std::sync::Arc::clone(&respond);
+                    tokio::spawn(async move {
+                        let mut buf = vec![0u8; 4096];
+                        let n = socket.read(&mut buf).await.unwrap_or(0);
+                        let request = String::from_utf8_lossy(&buf[..n]);
+                        let path = request.split_whitespace().nth(1).unwrap_or_default();
+                        let response = match respond(path) {
+                            Some((content_type, body)) => format!(
+                                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+                                content_type,
+                                body.len(),
+                                body
+                            ),
+                            None => "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found"
+                                .to_string(),
+                        };
+                        let _ = socket.write_all(response.as_bytes()).await;
+                    });
+                }
+            });
+            port
         }
-        Ok(out)
     }
 
-    pub async fn search_schema_with(
-        session: &scylla::Session,
-        pattern: &str,
-        keyspace: Option<&str>,
-    ) -> Result<Vec<Map<String, Value>>> {
-        validate_search_schema_pattern(pattern)?;
-        let pat_lower = pattern.to_lowercase();
-        // Helper to check if s contains pattern (case-insensitive)
-        let contains = |s: &str| s.to_lowercase().contains(&pat_lower);
-        let mut out: Vec<Map<String, Value>> = Vec::new();
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            compaction_status, node_status, summarize_compactions, table_sstables, RestClient,
+            RestEndpoint,
+        };
+        use serde_json::json;
+        use std::time::Duration;
+
+        /// Serve canned JSON bodies by request path until the test ends.
+        async fn stand_in(routes: Vec<(&'static str, &'static str)>) -> RestEndpoint {
+            let port = super::stand_in::serve(move |path| {
+                routes
+                    .iter()
+                    .find(|(p, _)| *p == path)
+                    .map(|(_, body)| ("application/json", body.to_string()))
+            })
+            .await;
+            RestEndpoint::parse(&format!("http://127.0.0.1:{port}")).unwrap()
+        }
+
+        #[test]
+        fn endpoints_parse_urls_and_bare_hosts() {
+            let ep = RestEndpoint::parse("http://10.0.0.1:10000/api/").unwrap();
+            assert_eq!(ep.host, "10.0.0.1");
+            assert_eq!(ep.port, 10000);
+            assert_eq!(ep.base_path, "/api");
+            assert_eq!(RestEndpoint::parse("node-a").unwrap().port, 10000);
+            assert_eq!(
+                RestEndpoint::parse("[::1]:10001").unwrap().label(),
+                "[::1]:10001"
+            );
+            assert!(RestEndpoint::parse("https://node-a:10000").is_err());
+            assert!(RestEndpoint::parse("node-a:port").is_err());
+        }
+
+        #[tokio::test]
+        async fn chunked_responses_are_decoded() {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let response = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n\"NOR\r\n4\r\nMAL\"\r\n0\r\n\r\n";
+                socket.write_all(response.as_bytes()).await.unwrap();
+            });
+            let ep = RestEndpoint::parse(&format!("127.0.0.1:{port}")).unwrap();
+            let client = RestClient::new(vec![ep.clone()], Duration::from_secs(5));
+            assert_eq!(
+                client
+                    .get_json(&ep, "/storage_service/operation_mode")
+                    .await
+                    .unwrap(),
+                json!("NORMAL")
+            );
+        }
+
+        #[test]
+        fn compactions_are_filtered_and_get_progress() {
+            let raw = json!([
+                {"ks": "app", "cf": "users", "task_type": "COMPACTION", "completed": 25, "total": 100},
+                {"ks": "app", "cf": "events", "task_type": "COMPACTION", "completed": 1, "total": 3},
+                {"ks": "other", "cf": "users", "task_type": "CLEANUP", "completed": 0, "total": 0},
+            ]);
+            let all = summarize_compactions(&raw, None, None);
+            assert_eq!(all.len(), 3);
+            assert_eq!(all[0]["progress_pct"], 25.0);
+            assert_eq!(all[1]["progress_pct"], 33.3);
+            assert!(all[2].get("progress_pct").is_none());
+            let users = summarize_compactions(&raw, Some("app"), Some("users"));
+            assert_eq!(users.len(), 1);
+        }
+
+        #[tokio::test]
+        async fn tools_read_from_a_rest_stand_in() {
+            let ep = stand_in(vec![
+                (
+                    "/compaction_manager/compactions",
+                    r#"[{"ks":"app","cf":"users","completed":5,"total":10}]"#,
+                ),
+                ("/compaction_manager/metrics/pending_tasks", "3"),
+                ("/compaction_manager/metrics/completed_tasks", "42"),
+                ("/column_family/metrics/live_ss_table_count/app:users", "7"),
+                ("/column_family/sstables/per_level/app:users", "[7]"),
+                (
+                    "/column_family/metrics/live_disk_space_used/app:users",
+                    "2048",
+                ),
+                ("/storage_service/operation_mode", "\"JOINING\""),
+                ("/storage_service/release_version", "\"3.0.8\""),
+                ("/storage_service/active_repair/", "[1]"),
+            ])
+            .await;
+            let label = ep.label();
+            let client = RestClient::new(vec![ep], Duration::from_secs(2));
+
+            let compactions = compaction_status(&client, None, Some("app"), None)
+                .await
+                .unwrap();
+            assert_eq!(compactions["active_compactions"], 1);
+            let node = &compactions["nodes"][0];
+            assert_eq!(node["node"], label.as_str());
+            assert_eq!(node["pending_tasks"], 3);
+            assert_eq!(node["compactions"][0]["progress_pct"], 50.0);
+
+            let sstables = table_sstables(&client, Some(&label), "app", "users")
+                .await
+                .unwrap();
+            assert_eq!(sstables["live_sstable_count"], 7);
+            assert_eq!(sstables["live_disk_space_used"], 2048);
+            let missing = sstables["nodes"][0]["compaction_strategy"]["error"]
+                .as_str()
+                .unwrap();
+            assert!(missing.contains("HTTP 404"), "{missing}");
+
+            let status = node_status(&client, None).await.unwrap();
+            assert_eq!(status["nodes"][0]["release_version"], "3.0.8");
+            assert_eq!(status["nodes"][0]["active_repairs"], json!([1]));
+            assert_eq!(
+                status["warnings"][0],
+                format!("{label} is in operation mode JOINING")
+            );
+
+            let err = node_status(&client, Some("10.9.9.9")).await.unwrap_err();
+            assert!(err.to_string().contains("not configured"), "{err}");
+        }
+    }
+}
+
+pub mod metrics {
+    //! Prometheus text-exposition parsing and two-scrape summaries for `node_metrics`.
+    use crate::rest::RestClient;
+    use anyhow::{bail, Result};
+    use futures::future::join_all;
+    use serde_json::{json, Map, Value};
+    use std::collections::{BTreeMap, HashMap};
+    use std::time::{Duration, Instant};
+
+    pub const DEFAULT_METRICS_PORT: u16 = 9180;
+    const DEFAULT_METRICS_PATH: &str = "/metrics";
+    const QUANTILES: [(&str, f64); 3] = [("p50", 0.5), ("p95", 0.95), ("p99", 0.99)];
+
+    type Labels = BTreeMap<String, String>;
+
+    /// One sample line: `name{labels} value`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Sample {
+        pub name: String,
+        pub labels: Labels,
+        pub value: f64,
+    }
+
+    /// Samples of one scrape plus the declared `# TYPE` of each metric family.
+    #[derive(Debug, Clone, Default)]
+    pub struct Exposition {
+        pub types: HashMap<String, String>,
+        pub samples: Vec<Sample>,
+    }
+
+    /// Which series to keep: a metric-name prefix and exact label matches.
+    #[derive(Debug, Clone, Default)]
+    pub struct SeriesFilter {
+        pub prefix: String,
+        pub labels: Labels,
+    }
 
-        // Tables
-        let q_tables = match keyspace {
-            Some(_) => {
-                "SELECT keyspace_name, table_name FROM system_schema.tables WHERE keyspace_name = ?"
+    impl SeriesFilter {
+        fn matches(&self, sample: &Sample) -> bool {
+            sample.name.starts_with(&self.prefix)
+                && self
+                    .labels
+                    .iter()
+                    .all(|(k, v)| sample.labels.get(k) == Some(v))
+        }
+    }
+
+    /// Parse the text exposition format, keeping only samples accepted by `filter`.
+    pub fn parse_exposition(text: &str, filter: &SeriesFilter) -> Exposition {
+        let mut out = Exposition::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let mut parts = rest.split_whitespace();
+                if let (Some(name), Some(kind)) = (parts.next(), parts.next()) {
+                    if name.starts_with(&filter.prefix) {
+                        out.types.insert(name.to_string(), kind.to_string());
+                    }
+                }
+                continue;
             }
-            None => "SELECT keyspace_name, table_name FROM system_schema.tables",
-        };
-        let res_tables = match keyspace {
-            Some(ks) => session.query_unpaged(q_tables, &(ks.to_string(),)).await?,
-            None => session.query_unpaged(q_tables, &[]).await?,
-        };
-        for row in res_tables.rows_typed::<(String, String)>()? {
-            let (ks, tb) = row?;
-            if contains(&ks) || contains(&tb) {
-                let mut m = Map::new();
-                m.insert("kind".into(), Value::String("table".into()));
-                m.insert("keyspace".into(), Value::String(ks));
-                m.insert("name".into(), Value::String(tb));
-                if !push_schema_result(&mut out, m) {
-                    return Ok(out);
+            if line.is_empty() || line.starts_with('#') || !line.starts_with(&filter.prefix) {
+                continue;
+            }
+            if let Some(sample) = parse_sample(line) {
+                if filter.matches(&sample) {
+                    out.samples.push(sample);
                 }
             }
         }
+        out
+    }
 
-        // Columns
-        let q_cols = match keyspace {
-            Some(_) => {
-                "SELECT keyspace_name, table_name, column_name FROM system_schema.columns WHERE keyspace_name = ?"
-            }
-            None => "SELECT keyspace_name, table_name, column_name FROM system_schema.columns",
-        };
-        let res_cols = match keyspace {
-            Some(ks) => session.query_unpaged(q_cols, &(ks.to_string(),)).await?,
-            None => session.query_unpaged(q_cols, &[]).await?,
+    fn parse_sample(line: &str) -> Option<Sample> {
+        let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+        let name = line[..name_end].to_string();
+        let mut rest = &line[name_end..];
+        let mut labels = Labels::new();
+        if let Some(body) = rest.strip_prefix('{') {
+            let (parsed, remainder) = parse_labels(body)?;
+            labels = parsed;
+            rest = remainder;
+        }
+        let value = rest.split_whitespace().next()?;
+        let value = match value {
+            "+Inf" => f64::INFINITY,
+            "-Inf" => f64::NEG_INFINITY,
+            other => other.parse::<f64>().ok()?,
         };
-        for row in res_cols.rows_typed::<(String, String, String)>()? {
-            let (ks, tb, col) = row?;
-            if contains(&col) {
-                let mut m = Map::new();
-                m.insert("kind".into(), Value::String("column".into()));
-                m.insert("keyspace".into(), Value::String(ks));
-                m.insert("table".into(), Value::String(tb));
-                m.insert("name".into(), Value::String(col));
-                if !push_schema_result(&mut out, m) {
-                    return Ok(out);
+        Some(Sample {
+            name,
+            labels,
+            value,
+        })
+    }
+
+    /// Parse `k="v",…}` and return the labels plus the text after the closing brace.
+    fn parse_labels(mut body: &str) -> Option<(Labels, &str)> {
+        let mut labels = Labels::new();
+        loop {
+            body = body.trim_start_matches([',', ' ']);
+            if let Some(rest) = body.strip_prefix('}') {
+                return Some((labels, rest));
+            }
+            let eq = body.find('=')?;
+            let key = body[..eq].trim().to_string();
+            let quoted = body[eq + 1..].trim_start().strip_prefix('"')?;
+            let mut chars = quoted.char_indices();
+            let mut value = String::new();
+            let end = loop {
+                let (idx, c) = chars.next()?;
+                match c {
+                    '"' => break idx,
+                    '\\' => match chars.next()?.1 {
+                        'n' => value.push('\n'),
+                        other => value.push(other),
+                    },
+                    other => value.push(other),
                 }
+            };
+            labels.insert(key, value);
+            body = &quoted[end + 1..];
+        }
+    }
+
+    /// Sum series that differ only in their `shard` label, except summary quantiles
+    /// which cannot be added up.
+    pub fn aggregate_shards(exposition: Exposition) -> Exposition {
+        let mut merged: BTreeMap<(String, Labels), f64> = BTreeMap::new();
+        let mut kept = Vec::new();
+        for mut sample in exposition.samples {
+            if family_type(&exposition.types, &sample.name) == "summary" {
+                kept.push(sample);
+                continue;
             }
+            sample.labels.remove("shard");
+            *merged.entry((sample.name, sample.labels)).or_default() += sample.value;
         }
+        kept.extend(merged.into_iter().map(|((name, labels), value)| Sample {
+            name,
+            labels,
+            value,
+        }));
+        Exposition {
+            types: exposition.types,
+            samples: kept,
+        }
+    }
 
-        // UDTs
-        let q_types = match keyspace {
-            Some(_) => {
-                "SELECT keyspace_name, type_name FROM system_schema.types WHERE keyspace_name = ?"
+    /// The declared type of the family a sample belongs to (`untyped` when undeclared).
+    fn family_type<'a>(types: &'a HashMap<String, String>, name: &str) -> &'a str {
+        if let Some(kind) = types.get(name) {
+            return kind;
+        }
+        for suffix in ["_bucket", "_sum", "_count"] {
+            if let Some(kind) = name.strip_suffix(suffix).and_then(|base| types.get(base)) {
+                return kind;
             }
-            None => "SELECT keyspace_name, type_name FROM system_schema.types",
-        };
-        let res_types = match keyspace {
-            Some(ks) => session.query_unpaged(q_types, &(ks.to_string(),)).await?,
-            None => session.query_unpaged(q_types, &[]).await?,
-        };
-        for row in res_types.rows_typed::<(String, String)>()? {
-            let (ks, ty) = row?;
-            if contains(&ty) {
-                let mut m = Map::new();
-                m.insert("kind".into(), Value::String("udt".into()));
-                m.insert("keyspace".into(), Value::String(ks));
-                m.insert("name".into(), Value::String(ty));
-                if !push_schema_result(&mut out, m) {
-                    return Ok(out);
+        }
+        "untyped"
+    }
+
+    /// `histogram_quantile` over cumulative `(le, count)` buckets sorted by bound.
+    pub fn histogram_quantile(q: f64, buckets: &[(f64, f64)]) -> Option<f64> {
+        let total = buckets.last()?.1;
+        if total <= 0.0 {
+            return None;
+        }
+        let rank = q * total;
+        let mut prev_bound = 0.0;
+        let mut prev_count = 0.0;
+        for &(bound, count) in buckets {
+            if count >= rank {
+                if bound.is_infinite() {
+                    return Some(prev_bound);
                 }
+                if count == prev_count {
+                    return Some(bound);
+                }
+                return Some(
+                    prev_bound + (bound - prev_bound) * (rank - prev_count) / (count - prev_count),
+                );
             }
+            prev_bound = bound;
+            prev_count = count;
         }
+        Some(prev_bound)
+    }
+
+    fn round3(value: f64) -> f64 {
+        (value * 1000.0).round() / 1000.0
+    }
+
+    fn labels_json(labels: &Labels) -> Value {
+        Value::Object(
+            labels
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::from(v.clone())))
+                .collect(),
+        )
+    }
+
+    /// Histogram buckets, sum and count grouped by base name and non-`le` labels.
+    #[derive(Default)]
+    struct Histogram {
+        buckets: Vec<(f64, f64)>,
+        sum: f64,
+        count: f64,
+    }
 
-        // Views
-        let q_views = match keyspace {
-            Some(_) => {
-                "SELECT keyspace_name, view_name FROM system_schema.views WHERE keyspace_name = ?"
+    fn histograms(exposition: &Exposition) -> BTreeMap<(String, Labels), Histogram> {
+        let mut out: BTreeMap<(String, Labels), Histogram> = BTreeMap::new();
+        for sample in &exposition.samples {
+            if family_type(&exposition.types, &sample.name) != "histogram" {
+                continue;
             }
-            None => "SELECT keyspace_name, view_name FROM system_schema.views",
-        };
-        let res_views = match keyspace {
-            Some(ks) => session.query_unpaged(q_views, &(ks.to_string(),)).await?,
-            None => session.query_unpaged(q_views, &[]).await?,
-        };
-        for row in res_views.rows_typed::<(String, String)>()? {
-            let (ks, v) = row?;
-            if contains(&v) {
-                let mut m = Map::new();
-                m.insert("kind".into(), Value::String("view".into()));
-                m.insert("keyspace".into(), Value::String(ks));
-                m.insert("name".into(), Value::String(v));
-                if !push_schema_result(&mut out, m) {
-                    return Ok(out);
-                }
+            let mut labels = sample.labels.clone();
+            if let Some(base) = sample.name.strip_suffix("_bucket") {
+                let Some(le) = labels.remove("le") else {
+                    continue;
+                };
+                let bound = match le.as_str() {
+                    "+Inf" => f64::INFINITY,
+                    other => match other.parse::<f64>() {
+                        Ok(bound) => bound,
+                        Err(_) => continue,
+                    },
+                };
+                out.entry((base.to_string(), labels))
+                    .or_default()
+                    .buckets
+                    .push((bound, sample.value));
+            } else if let Some(base) = sample.name.strip_suffix("_sum") {
+                out.entry((base.to_string(), labels)).or_default().sum = sample.value;
+            } else if let Some(base) = sample.name.strip_suffix("_count") {
+                out.entry((base.to_string(), labels)).or_default().count = sample.value;
             }
         }
+        for histogram in out.values_mut() {
+            histogram.buckets.sort_by(|a, b| a.0.total_cmp(&b.0));
+        }
+        out
+    }
+
+    /// Summarise the latest scrape, with per-second rates and windowed percentiles
+    /// when an earlier scrape taken `elapsed` before is available.
+    pub fn summarize(
+        first: Option<&Exposition>,
+        second: &Exposition,
+        elapsed: Duration,
+        limit: usize,
+    ) -> Value {
+        let secs = elapsed.as_secs_f64();
+        let previous: HashMap<(&str, &Labels), f64> = first
+            .map(|e| {
+                e.samples
+                    .iter()
+                    .map(|s| ((s.name.as_str(), &s.labels), s.value))
+                    .collect()
+            })
+            .unwrap_or_default();
 
-        // Functions
-        let q_funcs = match keyspace {
-            Some(_) => {
-                "SELECT keyspace_name, function_name FROM system_schema.functions WHERE keyspace_name = ?"
+        let mut series = Vec::new();
+        for sample in &second.samples {
+            let kind = family_type(&second.types, &sample.name);
+            if kind == "histogram" {
+                continue;
             }
-            None => "SELECT keyspace_name, function_name FROM system_schema.functions",
-        };
-        let res_funcs = match keyspace {
-            Some(ks) => session.query_unpaged(q_funcs, &(ks.to_string(),)).await?,
-            None => session.query_unpaged(q_funcs, &[]).await?,
-        };
-        for row in res_funcs.rows_typed::<(String, String)>()? {
-            let (ks, f) = row?;
-            if contains(&f) {
-                let mut m = Map::new();
-                m.insert("kind".into(), Value::String("function".into()));
-                m.insert("keyspace".into(), Value::String(ks));
-                m.insert("name".into(), Value::String(f));
-                if !push_schema_result(&mut out, m) {
-                    return Ok(out);
+            let mut entry = Map::new();
+            entry.insert("name".into(), Value::from(sample.name.clone()));
+            if !sample.labels.is_empty() {
+                entry.insert("labels".into(), labels_json(&sample.labels));
+            }
+            entry.insert("type".into(), Value::from(kind));
+            entry.insert("value".into(), json!(sample.value));
+            if kind == "counter" && secs > 0.0 {
+                if let Some(before) = previous.get(&(sample.name.as_str(), &sample.labels)) {
+                    if sample.value >= *before {
+                        entry.insert(
+                            "rate_per_sec".into(),
+                            json!(round3((sample.value - before) / secs)),
+                        );
+                    } else {
+                        entry.insert("counter_reset".into(), Value::Bool(true));
+                    }
                 }
             }
+            series.push(Value::Object(entry));
         }
 
-        // Aggregates
-        let q_aggs = match keyspace {
-            Some(_) => {
-                "SELECT keyspace_name, aggregate_name FROM system_schema.aggregates WHERE keyspace_name = ?"
+        let earlier = first.map(histograms).unwrap_or_default();
+        let mut summaries = Vec::new();
+        for (key, current) in histograms(second) {
+            let (name, labels) = &key;
+            let mut window = Histogram {
+                buckets: current.buckets.clone(),
+                sum: current.sum,
+                count: current.count,
+            };
+            let mut windowed = false;
+            if let Some(before) = earlier.get(&key) {
+                if current.count >= before.count && current.buckets.len() == before.buckets.len() {
+                    window.buckets = current
+                        .buckets
+                        .iter()
+                        .zip(&before.buckets)
+                        .map(|(now, then)| (now.0, now.1 - then.1))
+                        .collect();
+                    window.sum = current.sum - before.sum;
+                    window.count = current.count - before.count;
+                    windowed = true;
+                }
             }
-            None => "SELECT keyspace_name, aggregate_name FROM system_schema.aggregates",
-        };
-        let res_aggs = match keyspace {
-            Some(ks) => session.query_unpaged(q_aggs, &(ks.to_string(),)).await?,
-            None => session.query_unpaged(q_aggs, &[]).await?,
-        };
-        for row in res_aggs.rows_typed::<(String, String)>()? {
-            let (ks, a) = row?;
-            if contains(&a) {
-                let mut m = Map::new();
-                m.insert("kind".into(), Value::String("aggregate".into()));
-                m.insert("keyspace".into(), Value::String(ks));
-                m.insert("name".into(), Value::String(a));
-                if !push_schema_result(&mut out, m) {
-                    return Ok(out);
+            let mut entry = Map::new();
+            entry.insert("name".into(), Value::from(name.clone()));
+            if !labels.is_empty() {
+                entry.insert("labels".into(), labels_json(labels));
+            }
+            entry.insert("type".into(), Value::from("histogram"));
+            entry.insert(
+                "window".into(),
+                Value::from(if windowed {
+                    "between_scrapes"
+                } else {
+                    "since_start"
+                }),
+            );
+            entry.insert("count".into(), json!(window.count));
+            if windowed && secs > 0.0 {
+                entry.insert("rate_per_sec".into(), json!(round3(window.count / secs)));
+            }
+            if window.count > 0.0 {
+                entry.insert("mean".into(), json!(round3(window.sum / window.count)));
+            }
+            for (label, q) in QUANTILES {
+                if let Some(value) = histogram_quantile(q, &window.buckets) {
+                    entry.insert(label.into(), json!(round3(value)));
                 }
             }
+            summaries.push(Value::Object(entry));
         }
 
-        Ok(out)
+        let total = series.len() + summaries.len();
+        let mut out = Map::new();
+        summaries.truncate(limit);
+        series.truncate(limit.saturating_sub(summaries.len()));
+        out.insert("histograms".into(), Value::Array(summaries));
+        out.insert("series".into(), Value::Array(series));
+        if total > limit {
+            out.insert(
+                "truncated".into(),
+                json!({ "total_series": total, "limit": limit }),
+            );
+        }
+        out.into()
     }
 
-    // Non-session wrappers for integration tests
-    pub async fn list_views(keyspace: &str) -> Result<Vec<String>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        list_views_with(&session, keyspace).await
-    }
+    /// Scrape the selected nodes (twice, `interval` apart, when `interval` is non-zero)
+    /// and summarise the series matching `filter`.
+    pub async fn node_metrics(
+        client: &RestClient,
+        node: Option<&str>,
+        filter: &SeriesFilter,
+        interval: Duration,
+        per_shard: bool,
+        limit: usize,
+        progress: &crate::db::Progress,
+    ) -> Result<Value> {
+        if filter.prefix.is_empty() {
+            bail!("a metric name prefix is required");
+        }
+        let endpoints = client.select(node)?;
+        let scrape = || async {
+            let started = Instant::now();
+            let texts = join_all(
+                endpoints
+                    .iter()
+                    .map(|ep| client.get_text(ep, DEFAULT_METRICS_PATH)),
+            )
+            .await;
+            let parsed: Vec<Result<Exposition>> = texts
+                .into_iter()
+                .map(|text| {
+                    text.map(|text| {
+                        let exposition = parse_exposition(&text, filter);
+                        if per_shard {
+                            exposition
+                        } else {
+                            aggregate_shards(exposition)
+                        }
+                    })
+                })
+                .collect();
+            (started, parsed)
+        };
+        let (first_at, first) = if interval.is_zero() {
+            (None, None)
+        } else {
+            let (at, parsed) = scrape().await;
+            progress.step(
+                1,
+                Some(2),
+                &format!(
+                    "first scrape done, waiting {} ms for the second",
+                    interval.as_millis()
+                ),
+            );
+            tokio::time::sleep(interval).await;
+            (Some(at), Some(parsed))
+        };
+        let (second_at, second) = scrape().await;
+        if first_at.is_some() {
+            progress.step(2, Some(2), "second scrape done");
+        }
+        let elapsed = first_at
+            .map(|at| second_at.duration_since(at))
+            .unwrap_or_default();
 
-    pub async fn list_udts(keyspace: &str) -> Result<Vec<String>> {
-        let uri = env::var("SCYLLA_URI").unwrap_or_else(|_| "127.0.0.1:9042".to_string());
-        let session = SessionBuilder::new().known_node(uri).build().await?;
-        list_udts_with(&session, keyspace).await
+        let nodes: Vec<Value> = endpoints
+            .iter()
+            .enumerate()
+            .zip(second)
+            .map(|((idx, ep), current)| {
+                let mut entry = Map::new();
+                entry.insert("node".into(), Value::from(ep.label()));
+                match current {
+                    Ok(current) => {
+                        let earlier = first
+                            .as_ref()
+                            .and_then(|scrapes| scrapes[idx].as_ref().ok());
+                        let summary = summarize(earlier, &current, elapsed, limit);
+                        if let Value::Object(summary) = summary {
+                            entry.extend(summary);
+                        }
+                    }
+                    Err(err) => {
+                        entry.insert("error".into(), Value::from(format!("{err:#}")));
+                    }
+                }
+                Value::Object(entry)
+            })
+            .collect();
+        Ok(json!({
+            "prefix": filter.prefix,
+            "interval_ms": elapsed.as_millis() as u64,
+            "nodes": nodes,
+        }))
     }
 
     #[cfg(test)]
     mod tests {
         use super::{
-            build_select_all_query, build_select_columns_query, decode_paging_cursor,
-            push_schema_result, validate_search_schema_pattern, MAX_PAGING_CURSOR_BYTES,
-            MAX_SEARCH_SCHEMA_PATTERN_BYTES, MAX_SEARCH_SCHEMA_RESULTS,
+            aggregate_shards, histogram_quantile, node_metrics, parse_exposition, summarize,
+            SeriesFilter,
         };
-        use base64::Engine;
-        use serde_json::{Map, Value};
+        use crate::rest::{RestClient, RestEndpoint};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        const SCRAPE_1: &str = r#"# HELP scylla_storage_proxy_coordinator_read_latency Read latency histogram
+# TYPE scylla_storage_proxy_coordinator_read_latency histogram
+scylla_storage_proxy_coordinator_read_latency_bucket{scheduling_group_name="statement",shard="0",le="100.0"} 10
+scylla_storage_proxy_coordinator_read_latency_bucket{scheduling_group_name="statement",shard="0",le="1000.0"} 20
+scylla_storage_proxy_coordinator_read_latency_bucket{scheduling_group_name="statement",shard="0",le="+Inf"} 20
+scylla_storage_proxy_coordinator_read_latency_sum{scheduling_group_name="statement",shard="0"} 4000
+scylla_storage_proxy_coordinator_read_latency_count{scheduling_group_name="statement",shard="0"} 20
+# TYPE scylla_transport_requests_served counter
+scylla_transport_requests_served{shard="0"} 100
+scylla_transport_requests_served{shard="1"} 50
+# TYPE scylla_memory_free_memory gauge
+scylla_memory_free_memory{shard="0"} 1.5e9
+"#;
+
+        const SCRAPE_2: &str = r#"# TYPE scylla_storage_proxy_coordinator_read_latency histogram
+scylla_storage_proxy_coordinator_read_latency_bucket{scheduling_group_name="statement",shard="0",le="100.0"} 10
+scylla_storage_proxy_coordinator_read_latency_bucket{scheduling_group_name="statement",shard="0",le="1000.0"} 120
+scylla_storage_proxy_coordinator_read_latency_bucket{scheduling_group_name="statement",shard="0",le="+Inf"} 120
+scylla_storage_proxy_coordinator_read_latency_sum{scheduling_group_name="statement",shard="0"} 54000
+scylla_storage_proxy_coordinator_read_latency_count{scheduling_group_name="statement",shard="0"} 120
+# TYPE scylla_transport_requests_served counter
+scylla_transport_requests_served{shard="0"} 300
+scylla_transport_requests_served{shard="1"} 250
+"#;
+
+        fn filter(prefix: &str) -> SeriesFilter {
+            SeriesFilter {
+                prefix: prefix.to_string(),
+                ..SeriesFilter::default()
+            }
+        }
 
         #[test]
-        fn table_ref_rejects_cql_identifier_injection() {
-            let err = build_select_all_query("ks; DROP KEYSPACE prod", "users", "", 10)
-                .expect_err("keyspace syntax should not be interpolated into CQL");
-            assert!(err.to_string().contains("invalid keyspace identifier"));
-
-            let err =
-                build_select_columns_query("ks", "users WHERE id = 1", "id", "", "", Some(10))
-                    .expect_err("table syntax should not be interpolated into CQL");
-            assert!(err.to_string().contains("invalid table identifier"));
+        fn exposition_parses_labels_types_and_filters() {
+            let text = "# TYPE a_total counter\na_total{path=\"x\\\"y\",shard=\"0\"} 3 1700000000\nb_gauge 2\n";
+            let parsed = parse_exposition(text, &filter("a_"));
+            assert_eq!(parsed.samples.len(), 1);
+            assert_eq!(parsed.samples[0].labels["path"], "x\"y");
+            assert_eq!(parsed.samples[0].value, 3.0);
+            assert_eq!(parsed.types["a_total"], "counter");
+
+            let mut by_label = filter("scylla_transport");
+            by_label.labels.insert("shard".into(), "1".into());
+            let parsed = parse_exposition(SCRAPE_1, &by_label);
+            assert_eq!(parsed.samples.len(), 1);
+            assert_eq!(parsed.samples[0].value, 50.0);
         }
 
         #[test]
-        fn query_builders_accept_plain_identifiers() {
-            let select_all = build_select_all_query("ks_1", "users_2", " WHERE id = ?", 25)
-                .expect("valid identifiers should build");
+        fn histogram_quantiles_interpolate_within_buckets() {
+            let buckets = [(100.0, 10.0), (1000.0, 20.0), (f64::INFINITY, 20.0)];
+            assert_eq!(histogram_quantile(0.5, &buckets), Some(100.0));
+            assert_eq!(histogram_quantile(0.75, &buckets), Some(550.0));
+            assert_eq!(histogram_quantile(0.5, &[(1.0, 0.0)]), None);
             assert_eq!(
-                select_all,
-                "SELECT * FROM ks_1.users_2 WHERE id = ? LIMIT 25"
+                histogram_quantile(0.99, &[(1.0, 1.0), (f64::INFINITY, 100.0)]),
+                Some(1.0)
             );
+        }
 
-            let select_cols =
-                build_select_columns_query("ks", "users", "id, name", "", " ORDER BY id ASC", None)
-                    .expect("valid identifiers should build");
-            assert_eq!(select_cols, "SELECT id, name FROM ks.users ORDER BY id ASC");
+        #[test]
+        fn two_scrapes_give_rates_and_windowed_percentiles() {
+            let first = aggregate_shards(parse_exposition(SCRAPE_1, &filter("scylla_")));
+            let second = aggregate_shards(parse_exposition(SCRAPE_2, &filter("scylla_")));
+            let out = summarize(Some(&first), &second, Duration::from_secs(10), 100);
+
+            let served = &out["series"][0];
+            assert_eq!(served["name"], "scylla_transport_requests_served");
+            assert_eq!(served["value"], 550.0);
+            assert_eq!(served["rate_per_sec"], 40.0);
+
+            let latency = &out["histograms"][0];
+            assert_eq!(latency["window"], "between_scrapes");
+            assert_eq!(latency["count"], 100.0);
+            assert_eq!(latency["rate_per_sec"], 10.0);
+            assert_eq!(latency["mean"], 500.0);
+            assert_eq!(latency["p50"], 550.0);
+            assert_eq!(latency["labels"]["scheduling_group_name"], "statement");
+
+            let single = summarize(None, &first, Duration::ZERO, 1);
+            assert_eq!(single["histograms"][0]["window"], "since_start");
+            assert_eq!(single["series"].as_array().unwrap().len(), 0);
+            assert_eq!(single["truncated"]["total_series"], 3);
+        }
+
+        #[tokio::test]
+        async fn node_metrics_scrapes_a_stand_in_twice() {
+            let hits = Arc::new(AtomicUsize::new(0));
+            let served = Arc::clone(&hits);
+            let port = crate::rest::stand_in::serve(move |_| {
+                let body = if served.fetch_add(1, Ordering::SeqCst) == 0 {
+                    SCRAPE_1
+                } else {
+                    SCRAPE_2
+                };
+                Some(("text/plain", body.to_string()))
+            })
+            .await;
+            let ep = RestEndpoint::parse(&format!("127.0.0.1:{port}")).unwrap();
+            let client = RestClient::new(vec![ep], Duration::from_secs(2));
+            let steps = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let seen = Arc::clone(&steps);
+            let progress = crate::db::Progress::new(move |done, total, _| {
+                seen.lock().unwrap().push((done, total));
+            });
+            let out = node_metrics(
+                &client,
+                None,
+                &filter("scylla_transport"),
+                Duration::from_millis(50),
+                true,
+                10,
+                &progress,
+            )
+            .await
+            .unwrap();
+            assert_eq!(hits.load(Ordering::SeqCst), 2);
+            assert_eq!(*steps.lock().unwrap(), [(1, Some(2)), (2, Some(2))]);
+            let series = out["nodes"][0]["series"].as_array().unwrap();
+            assert_eq!(series.len(), 2);
+            assert!(series[0]["rate_per_sec"].as_f64().unwrap() > 0.0);
+            assert_eq!(series[0]["labels"]["shard"], "0");
+
+            let err = node_metrics(
+                &client,
+                None,
+                &filter(""),
+                Duration::ZERO,
+                false,
+                10,
+                &crate::db::Progress::default(),
+            )
+            .await
+            .unwrap_err();
+            assert!(err.to_string().contains("prefix"), "{err}");
+        }
+    }
+}
+
+pub mod prompts {
+    //! Rendering of the `prompts/get` playbooks from live schema.
+    use crate::db::{CqlIdent, DescribeTable};
+    use serde_json::{json, Map, Value};
+
+    fn column_role(schema: &DescribeTable, column: &str) -> &'static str {
+        if schema.partition_keys.iter().any(|k| k == column) {
+            "partition key"
+        } else if schema.clustering_keys.iter().any(|k| k == column) {
+            "clustering key"
+        } else if schema
+            .columns
+            .iter()
+            .any(|c| c.column_name == column && c.kind == "static")
+        {
+            "static"
+        } else {
+            "regular"
+        }
+    }
+
+    /// Markdown summary of a table's keys and columns.
+    pub fn schema_section(schema: &DescribeTable) -> String {
+        let mut out = format!(
+            "Table `{}.{}`\n- Partition key: {}\n",
+            CqlIdent::quote_or_raw(&schema.keyspace),
+            CqlIdent::quote_or_raw(&schema.table),
+            schema
+                .partition_keys
+                .iter()
+                .map(|k| CqlIdent::quote_or_raw(k))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if !schema.clustering_keys.is_empty() {
+            let clustering: Vec<String> = schema
+                .clustering_keys
+                .iter()
+                .map(|key| {
+                    let desc = schema.columns.iter().any(|c| {
+                        &c.column_name == key
+                            && c.clustering_order
+                                .as_deref()
+                                .is_some_and(|o| o.eq_ignore_ascii_case("desc"))
+                    });
+                    format!(
+                        "{}{}",
+                        CqlIdent::quote_or_raw(key),
+                        if desc { " DESC" } else { "" }
+                    )
+                })
+                .collect();
+            out.push_str(&format!("- Clustering key: {}\n", clustering.join(", ")));
+        }
+        out.push_str("- Columns:\n");
+        for column in &schema.columns {
+            out.push_str(&format!(
+                "  - {} {} ({})\n",
+                CqlIdent::quote_or_raw(&column.column_name),
+                column.r#type,
+                column_role(schema, &column.column_name)
+            ));
+        }
+        out
+    }
+
+    /// One-line summary of a `keyspace_replication_with` result.
+    pub fn replication_section(replication: &Map<String, Value>) -> String {
+        let options: Vec<String> = replication
+            .get("replication")
+            .and_then(Value::as_object)
+            .map(|map| {
+                map.iter()
+                    .map(|(k, v)| format!("{k}={}", v.as_str().unwrap_or(&v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut out = format!("Replication: {}", options.join(", "));
+        if let Some(durable) = replication.get("durable_writes").and_then(Value::as_bool) {
+            out.push_str(&format!("; durable_writes={durable}"));
+        }
+        out
+    }
+
+    pub fn explore_keyspace(
+        keyspace: &str,
+        replication: &Map<String, Value>,
+        tables: &[DescribeTable],
+        omitted: usize,
+    ) -> String {
+        let mut out = format!(
+            "Explore the ScyllaDB keyspace `{}` using this read-only MCP server.\n\n## Live schema\n{}\n\nTables:\n",
+            CqlIdent::quote_or_raw(keyspace),
+            replication_section(replication)
+        );
+        for table in tables {
+            out.push_str(&format!(
+                "- `{}`: partition key ({}), clustering key ({}), {} columns\n",
+                CqlIdent::quote_or_raw(&table.table),
+                table.partition_keys.join(", "),
+                table.clustering_keys.join(", "),
+                table.columns.len()
+            ));
+        }
+        if tables.is_empty() {
+            out.push_str("- (no tables)\n");
+        }
+        if omitted > 0 {
+            out.push_str(&format!(
+                "- … {omitted} more; use `list_tables` to see them\n"
+            ));
+        }
+        out.push_str(
+            "\n## Playbook\n\
+             1. Call `describe_table` on the tables that look central and `sample_rows` with a small limit (5) to see real values.\n\
+             2. Call `list_views` and `list_indexes` to find alternative access paths.\n\
+             3. Call `table_stats` on the largest-looking tables for partition counts and sizes.\n\
+             4. Call `lint_schema` for this keyspace to surface modelling risks.\n\
+             5. Summarise what each table stores, how the tables relate, and the queries the schema is designed for.\n",
+        );
+        out
+    }
+
+    pub fn explain_data_model(schema: &DescribeTable, replication: &Map<String, Value>) -> String {
+        format!(
+            "Explain the data model of `{}.{}` to someone new to the codebase.\n\n## Live schema\n{}\n{}\n\n## Playbook\n\
+             1. Say what one partition represents and what one row within it represents.\n\
+             2. Describe how rows are ordered inside a partition by the clustering key.\n\
+             3. List the queries this table serves efficiently (equality on the full partition key, ranges over a clustering-key prefix) and the ones it cannot serve without an index or view.\n\
+             4. Use `sample_rows` with limit 5 to illustrate with real values, and `table_stats` to judge partition sizes.\n\
+             5. Point out modelling risks such as unbounded partitions or low-cardinality partition keys.\n",
+            CqlIdent::quote_or_raw(&schema.keyspace),
+            CqlIdent::quote_or_raw(&schema.table),
+            schema_section(schema),
+            replication_section(replication)
+        )
+    }
+
+    pub fn find_entity_rows(
+        schema: &DescribeTable,
+        entity_id: &str,
+        column: Option<&str>,
+    ) -> Result<String, String> {
+        let column = match column {
+            Some(column) => column.to_string(),
+            None => schema
+                .partition_keys
+                .first()
+                .cloned()
+                .ok_or_else(|| "table has no partition key".to_string())?,
+        };
+        let Some(meta) = schema.columns.iter().find(|c| c.column_name == column) else {
+            return Err(format!(
+                "unknown column '{column}' in {}.{}",
+                schema.keyspace, schema.table
+            ));
+        };
+        let mut out = format!(
+            "Find the rows for entity `{entity_id}` in `{}.{}`, identified by column `{}` ({}).\n\n## Live schema\n{}\n## Playbook\n",
+            CqlIdent::quote_or_raw(&schema.keyspace),
+            CqlIdent::quote_or_raw(&schema.table),
+            CqlIdent::quote_or_raw(&column),
+            meta.r#type,
+            schema_section(schema)
+        );
+        let role = column_role(schema, &column);
+        if role == "partition key" && schema.partition_keys.len() == 1 {
+            let call = json!({
+                "keyspace": schema.keyspace,
+                "table": schema.table,
+                "partition": { column.clone(): entity_id },
+                "limit": 100,
+            });
+            out.push_str(&format!(
+                "1. `{}` is the whole partition key, so call `partition_rows` with:\n   {}\n   Convert the id to the column type `{}` if needed.\n\
+                 2. If the result is truncated, continue with `paged_select` filtered on the same key.\n",
+                CqlIdent::quote_or_raw(&column),
+                call,
+                meta.r#type
+            ));
+        } else if role == "partition key" {
+            let others: Vec<&str> = schema
+                .partition_keys
+                .iter()
+                .filter(|k| **k != column)
+                .map(String::as_str)
+                .collect();
+            out.push_str(&format!(
+                "1. `{}` is only part of the partition key; the other components are: {}.\n\
+                 2. Obtain those values (from the user or related tables) before querying; never scan the table.\n\
+                 3. Then call `partition_rows` with all partition key columns set.\n",
+                CqlIdent::quote_or_raw(&column),
+                others.join(", ")
+            ));
+        } else {
+            out.push_str(&format!(
+                "1. `{}` is a {role} column, so rows cannot be located by partition.\n\
+                 2. Call `list_indexes` and `list_views` for this table; if an index or view covers `{}`, query it with `select` and filters {{\"{}\": ...}}.\n\
+                 3. Otherwise explain that the lookup needs a full scan, which this server does not perform, and suggest an index, a view or a lookup table.\n",
+                CqlIdent::quote_or_raw(&column),
+                CqlIdent::quote_or_raw(&column),
+                column
+            ));
+        }
+        Ok(out)
+    }
+
+    pub fn investigate_slow_partition(
+        schema: &DescribeTable,
+        replication: &Map<String, Value>,
+        partition_key: Option<&str>,
+    ) -> Result<String, String> {
+        let partition = match partition_key {
+            Some(raw) => {
+                let value: Value = serde_json::from_str(raw)
+                    .map_err(|_| "partition_key must be a JSON object".to_string())?;
+                if !value.is_object() {
+                    return Err("partition_key must be a JSON object".to_string());
+                }
+                Some(value)
+            }
+            None => None,
+        };
+        let mut out = format!(
+            "Investigate why reads of a partition in `{}.{}` are slow.\n\n## Live schema\n{}\n{}\n\n## Playbook\n",
+            CqlIdent::quote_or_raw(&schema.keyspace),
+            CqlIdent::quote_or_raw(&schema.table),
+            schema_section(schema),
+            replication_section(replication)
+        );
+        match &partition {
+            Some(partition) => out.push_str(&format!(
+                "1. Call `locate_partition` with partition {partition} to get its token and replica nodes.\n"
+            )),
+            None => out.push_str(
+                "1. Ask for the partition key, or pick candidates from `large_data`, then call `locate_partition` to get token and replicas.\n",
+            ),
+        }
+        out.push_str(
+            "2. Call `large_data` for this table to see whether the partition is recorded as a large partition, row or cell.\n\
+             3. Call `table_stats` for mean and maximum partition sizes across the ring.\n\
+             4. Call `table_sstables` and `compaction_status` on the replicas: many SSTables or pending compactions mean read amplification.\n\
+             5. Call `node_metrics` with prefix `scylla_storage_proxy_coordinator_read_latency` on the replicas to compare latency across nodes.\n\
+             6. Read a few rows with `partition_rows` (limit 10) to judge row width and clustering layout.\n\
+             7. Conclude with the most likely cause (partition size, tombstones, SSTable count, a hot or unhealthy replica) and a remedy.\n",
+        );
+        Ok(out)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::{
+            explore_keyspace, find_entity_rows, investigate_slow_partition, replication_section,
+        };
+        use crate::db::{ColumnMeta, DescribeTable};
+        use serde_json::{json, Map, Value};
+
+        fn orders_table() -> DescribeTable {
+            let column = |name: &str, kind: &str, ty: &str| ColumnMeta {
+                column_name: name.to_string(),
+                kind: kind.to_string(),
+                position: 0,
+                r#type: ty.to_string(),
+                clustering_order: None,
+            };
+            DescribeTable {
+                keyspace: "app".into(),
+                table: "orders".into(),
+                partition_keys: vec!["customer_id".into()],
+                clustering_keys: vec!["order_id".into()],
+                columns: vec![
+                    column("customer_id", "partition_key", "uuid"),
+                    column("order_id", "clustering", "timeuuid"),
+                    column("status", "regular", "text"),
+                ],
+            }
+        }
+
+        fn replication() -> Map<String, Value> {
+            json!({
+                "replication": { "class": "NetworkTopologyStrategy", "dc1": "3" },
+                "durable_writes": true,
+            })
+            .as_object()
+            .cloned()
+            .unwrap()
         }
 
         #[test]
-        fn paging_cursor_rejects_oversized_tokens() {
-            let bytes = vec![0_u8; MAX_PAGING_CURSOR_BYTES + 1];
-            let token = base64::engine::general_purpose::STANDARD.encode(bytes);
-            let err =
-                decode_paging_cursor(&token).expect_err("oversized cursor should be rejected");
-            assert!(err.to_string().contains("too large"));
+        fn prompts_embed_live_schema_and_replication() {
+            assert_eq!(
+                replication_section(&replication()),
+                "Replication: class=NetworkTopologyStrategy, dc1=3; durable_writes=true"
+            );
+            let text = explore_keyspace("app", &replication(), &[orders_table()], 2);
+            assert!(text.contains(
+                "- `orders`: partition key (customer_id), clustering key (order_id), 3 columns"
+            ));
+            assert!(text.contains("2 more"));
         }
 
         #[test]
-        fn search_schema_rejects_empty_or_oversized_patterns() {
-            let err = validate_search_schema_pattern("   ")
-                .expect_err("blank patterns should not scan the whole schema");
-            assert!(err.to_string().contains("must not be empty"));
+        fn entity_lookup_depends_on_the_column_role() {
+            let by_key = find_entity_rows(&orders_table(), "42", None).unwrap();
+            assert!(by_key.contains("call `partition_rows`"));
+            assert!(by_key.contains(r#""partition":{"customer_id":"42"}"#));
 
-            let pattern = "a".repeat(MAX_SEARCH_SCHEMA_PATTERN_BYTES + 1);
-            let err = validate_search_schema_pattern(&pattern)
-                .expect_err("oversized patterns should be rejected");
-            assert!(err.to_string().contains("too large"));
+            let by_status = find_entity_rows(&orders_table(), "open", Some("status")).unwrap();
+            assert!(by_status.contains("is a regular column"));
+            assert!(by_status.contains("does not perform"));
+
+            assert!(find_entity_rows(&orders_table(), "x", Some("missing")).is_err());
         }
 
         #[test]
-        fn search_schema_result_limit_stops_at_cap() {
-            let mut out = Vec::new();
-            for _ in 0..(MAX_SEARCH_SCHEMA_RESULTS - 1) {
-                assert!(push_schema_result(&mut out, Map::new()));
-            }
-
-            let mut last = Map::new();
-            last.insert("kind".to_string(), Value::String("last".to_string()));
-            assert!(!push_schema_result(&mut out, last));
-            assert_eq!(out.len(), MAX_SEARCH_SCHEMA_RESULTS);
+        fn slow_partition_requires_an_object_key() {
+            let text = investigate_slow_partition(
+                &orders_table(),
+                &replication(),
+                Some(r#"{"customer_id":"6f1c"}"#),
+            )
+            .unwrap();
+            assert!(text.contains(r#"`locate_partition` with partition {"customer_id":"6f1c"}"#));
+            assert!(
+                investigate_slow_partition(&orders_table(), &replication(), Some("[1]")).is_err()
+            );
         }
     }
 }
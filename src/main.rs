@@ -2,6 +2,12 @@
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // MCP_TRANSPORT=http serves MCP Streamable HTTP instead of stdio.
+    if std::env::var("MCP_TRANSPORT").as_deref() == Ok("http") {
+        scylla_rust_mcp::http_server::run_http_server().await?;
+        return Ok(());
+    }
+
     // Default to Content-Length framing for Codex and other MCP clients.
     // Set MCP_FRAMING=newline to use the legacy newline-delimited transport.
     match std::env::var("MCP_FRAMING").as_deref() {
@@ -22,3 +22,90 @@ fn tools_include_core_read_only_actions() {
         assert!(names.contains(&e), "missing tool {e}");
     }
 }
+
+#[test]
+fn resource_uris_round_trip_and_reject_other_shapes() {
+    use scylla_rust_mcp::server::{resource_templates, ResourceUri};
+
+    let schema = ResourceUri::parse("scylla://app/users/schema").expect("schema uri");
+    assert_eq!(
+        schema,
+        ResourceUri::TableSchema {
+            keyspace: "app".into(),
+            table: "users".into(),
+        }
+    );
+    assert_eq!(schema.uri(), "scylla://app/users/schema");
+    assert_eq!(schema.mime_type(), "application/json");
+
+    let ddl = ResourceUri::parse("scylla://app/ddl").expect("ddl uri");
+    assert_eq!(ddl.uri(), "scylla://app/ddl");
+
+    let quoted = ResourceUri::TableSchema {
+        keyspace: "MyKs".into(),
+        table: "Order Items".into(),
+    };
+    assert_eq!(quoted.uri(), "scylla://MyKs/Order%20Items/schema");
+    assert_eq!(ResourceUri::parse(&quoted.uri()), Ok(quoted));
+
+    for bad in [
+        "scylla://app",
+        "scylla://app/users",
+        "scylla://app/users/schema/extra",
+        "scylla://app;drop/ddl",
+        "scylla://app%2/ddl",
+        "scylla://app%+1/ddl",
+        "scylla://%FF/ddl",
+        "http://app/ddl",
+    ] {
+        assert!(ResourceUri::parse(bad).is_err(), "{bad} should be rejected");
+    }
+    assert_eq!(resource_templates().len(), 2);
+}
+
+#[test]
+fn tool_definitions_carry_titles_hints_and_examples() {
+    use scylla_rust_mcp::server::{tool_definition, tool_output_schema};
+
+    for tool in list_tools() {
+        let definition = tool_definition(&tool);
+        assert!(!tool.title.is_empty(), "{} has no title", tool.name);
+        assert!(
+            tool.description.contains("Example: {"),
+            "{} has no example",
+            tool.name
+        );
+        assert_eq!(definition["annotations"]["readOnlyHint"], true);
+        assert_eq!(definition["annotations"]["destructiveHint"], false);
+        assert_eq!(definition["annotations"]["idempotentHint"], true);
+        assert_eq!(
+            definition.get("outputSchema").is_some(),
+            tool_output_schema(tool.name).is_some()
+        );
+
+        // The rmcp bridge decodes the same definition.
+        let decoded: rmcp::model::Tool =
+            serde_json::from_value(definition).expect("rmcp tool definition");
+        assert_eq!(decoded.title.as_deref(), Some(tool.title));
+        let hints = decoded.annotations.expect("annotations");
+        assert_eq!(hints.read_only_hint, Some(true));
+        assert_eq!(hints.open_world_hint, Some(false));
+    }
+
+    // The rust-mcp-sdk transport lists the very same definitions.
+    let listed = serde_json::to_value(scylla_rust_mcp::mcp::sdk_list_tools_result())
+        .expect("sdk tools/list result");
+    let expected: Vec<_> = list_tools().iter().map(tool_definition).collect();
+    assert_eq!(listed["tools"], serde_json::Value::Array(expected));
+    let lint = listed["tools"]
+        .as_array()
+        .and_then(|tools| tools.iter().find(|tool| tool["name"] == "lint_schema"))
+        .expect("lint_schema listed");
+    assert!(lint["title"].is_string());
+    assert_eq!(lint["annotations"]["readOnlyHint"], true);
+    assert!(lint["outputSchema"].is_object());
+    // Older SDK clients still decode it as a 2024-11-05 tools/list result.
+    let decoded: rust_mcp_schema::ListToolsResult =
+        serde_json::from_value(listed).expect("2024-11-05 tools/list result");
+    assert_eq!(decoded.tools.len(), list_tools().len());
+}